@@ -0,0 +1,2 @@
+-- Free-text booking details (remittance information, bank memo) carried over from imports
+ALTER TABLE transactions ADD COLUMN memo TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,4 @@
+-- Transaction id assigned by the bank (e.g. OFX FITID), used to skip re-imported bookings
+ALTER TABLE transactions ADD COLUMN external_id TEXT;
+
+CREATE INDEX idx_transactions_external_id ON transactions (external_id);
@@ -0,0 +1 @@
+ALTER TABLE parties DROP COLUMN IF EXISTS bic;
@@ -0,0 +1,2 @@
+-- BIC of the party's bank, as reported by SEPA statements
+ALTER TABLE parties ADD COLUMN bic TEXT NOT NULL DEFAULT '';
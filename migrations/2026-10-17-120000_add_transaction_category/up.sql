@@ -0,0 +1,4 @@
+-- Spending category, e.g. from QIF splits or a mapped CSV column
+ALTER TABLE transactions ADD COLUMN category TEXT;
+
+CREATE INDEX idx_transactions_category ON transactions (category);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_transactions_transfer_fingerprint;
+DROP INDEX IF EXISTS idx_transactions_fingerprint;
+ALTER TABLE transactions DROP COLUMN IF EXISTS transfer_fingerprint;
+ALTER TABLE transactions DROP COLUMN IF EXISTS fingerprint;
@@ -0,0 +1,7 @@
+-- Stable import fingerprint, so re-importing an overlapping statement does not duplicate rows.
+-- transfer_fingerprint is the fingerprint of the other own account's line when a transfer is linked.
+ALTER TABLE transactions ADD COLUMN fingerprint TEXT;
+ALTER TABLE transactions ADD COLUMN transfer_fingerprint TEXT;
+
+CREATE UNIQUE INDEX idx_transactions_fingerprint ON transactions (fingerprint);
+CREATE UNIQUE INDEX idx_transactions_transfer_fingerprint ON transactions (transfer_fingerprint);
@@ -0,0 +1,7 @@
+ALTER TABLE receipts DROP COLUMN IF EXISTS import_batch_id;
+ALTER TABLE accounts DROP COLUMN IF EXISTS import_batch_id;
+ALTER TABLE parties DROP COLUMN IF EXISTS import_batch_id;
+ALTER TABLE transactions DROP COLUMN IF EXISTS transfer_batch_id;
+ALTER TABLE transactions DROP COLUMN IF EXISTS import_batch_id;
+DROP TABLE IF EXISTS import_batch_balances;
+DROP TABLE IF EXISTS import_batches;
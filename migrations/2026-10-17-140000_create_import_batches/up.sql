@@ -0,0 +1,34 @@
+-- One row per import run, so a run can be listed and undone as a whole
+CREATE TABLE import_batches (
+    id SERIAL PRIMARY KEY,
+    file_name TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    format TEXT NOT NULL,
+    imported_at TIMESTAMP NOT NULL DEFAULT now(),
+    rows_created INTEGER NOT NULL DEFAULT 0,
+    rows_skipped INTEGER NOT NULL DEFAULT 0,
+    rows_rejected INTEGER NOT NULL DEFAULT 0,
+    parties_created INTEGER NOT NULL DEFAULT 0,
+    reverted_at TIMESTAMP
+);
+
+-- Net balance change a batch applied to each account, reversed on undo
+CREATE TABLE import_batch_balances (
+    id SERIAL PRIMARY KEY,
+    batch_id INTEGER NOT NULL REFERENCES import_batches(id) ON DELETE CASCADE,
+    account_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    net_change NUMERIC NOT NULL
+);
+
+ALTER TABLE transactions ADD COLUMN import_batch_id INTEGER REFERENCES import_batches(id) ON DELETE SET NULL;
+-- The batch that linked the other side of a transfer to an existing transaction
+ALTER TABLE transactions ADD COLUMN transfer_batch_id INTEGER REFERENCES import_batches(id) ON DELETE SET NULL;
+ALTER TABLE parties ADD COLUMN import_batch_id INTEGER REFERENCES import_batches(id) ON DELETE SET NULL;
+ALTER TABLE accounts ADD COLUMN import_batch_id INTEGER REFERENCES import_batches(id) ON DELETE SET NULL;
+ALTER TABLE receipts ADD COLUMN import_batch_id INTEGER REFERENCES import_batches(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_transactions_import_batch_id ON transactions (import_batch_id);
+CREATE INDEX idx_transactions_transfer_batch_id ON transactions (transfer_batch_id);
+CREATE INDEX idx_parties_import_batch_id ON parties (import_batch_id);
+CREATE INDEX idx_accounts_import_batch_id ON accounts (import_batch_id);
+CREATE INDEX idx_receipts_import_batch_id ON receipts (import_batch_id);
@@ -0,0 +1,8 @@
+-- Counterparty names confirmed to mean an existing party, stored normalised
+CREATE TABLE party_aliases (
+    id SERIAL PRIMARY KEY,
+    party_id INTEGER NOT NULL REFERENCES parties(id) ON DELETE CASCADE,
+    alias TEXT NOT NULL UNIQUE
+);
+
+CREATE INDEX idx_party_aliases_party_id ON party_aliases (party_id);
@@ -0,0 +1,4 @@
+ALTER TABLE transactions DROP COLUMN IF EXISTS funding_party_id;
+ALTER TABLE transactions DROP COLUMN IF EXISTS original_currency;
+ALTER TABLE transactions DROP COLUMN IF EXISTS original_amount;
+ALTER TABLE transactions DROP COLUMN IF EXISTS fee;
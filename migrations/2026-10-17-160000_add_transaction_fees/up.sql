@@ -0,0 +1,7 @@
+-- Payment-provider fee included in the booking, as a positive amount
+ALTER TABLE transactions ADD COLUMN fee NUMERIC;
+-- Amount and currency the payment was made in, when it was converted
+ALTER TABLE transactions ADD COLUMN original_amount NUMERIC;
+ALTER TABLE transactions ADD COLUMN original_currency TEXT;
+-- The provider party a bank booking named before it was linked to the provider account
+ALTER TABLE transactions ADD COLUMN funding_party_id INTEGER REFERENCES parties(id) ON DELETE SET NULL;
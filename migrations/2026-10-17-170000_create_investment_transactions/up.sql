@@ -0,0 +1,26 @@
+-- Trades, income and transfers of a brokerage or exchange account
+CREATE TABLE investment_transactions (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    date TIMESTAMP NOT NULL,
+    -- buy, sell, dividend, interest, fee, tax, deposit or withdrawal
+    kind TEXT NOT NULL,
+    -- Ticker, ISIN or coin; NULL for cash-only rows
+    symbol TEXT,
+    -- Units acquired (positive) or disposed of (negative)
+    quantity NUMERIC,
+    unit_price NUMERIC,
+    -- Currency of unit_price and fee
+    currency TEXT,
+    fee NUMERIC,
+    -- Effect on the account's cash balance, fees included
+    amount NUMERIC NOT NULL,
+    memo TEXT NOT NULL DEFAULT '',
+    external_id TEXT,
+    fingerprint TEXT,
+    import_batch_id INTEGER REFERENCES import_batches(id) ON DELETE SET NULL
+);
+
+CREATE UNIQUE INDEX idx_investment_transactions_fingerprint ON investment_transactions (fingerprint);
+CREATE INDEX idx_investment_transactions_account_symbol ON investment_transactions (account_id, symbol);
+CREATE INDEX idx_investment_transactions_import_batch_id ON investment_transactions (import_batch_id);
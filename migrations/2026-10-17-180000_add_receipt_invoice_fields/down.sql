@@ -0,0 +1,9 @@
+DROP TABLE IF EXISTS receipt_items;
+DROP INDEX IF EXISTS idx_receipts_party_invoice_number;
+ALTER TABLE receipts DROP COLUMN IF EXISTS source;
+ALTER TABLE receipts DROP COLUMN IF EXISTS total;
+ALTER TABLE receipts DROP COLUMN IF EXISTS tax_total;
+ALTER TABLE receipts DROP COLUMN IF EXISTS net_total;
+ALTER TABLE receipts DROP COLUMN IF EXISTS currency;
+ALTER TABLE receipts DROP COLUMN IF EXISTS due_date;
+ALTER TABLE receipts DROP COLUMN IF EXISTS invoice_number;
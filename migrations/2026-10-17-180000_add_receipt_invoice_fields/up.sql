@@ -0,0 +1,29 @@
+-- Invoice details of receipts captured from e-invoices; totals are negative for credit notes
+ALTER TABLE receipts ADD COLUMN invoice_number TEXT;
+ALTER TABLE receipts ADD COLUMN due_date TEXT;
+ALTER TABLE receipts ADD COLUMN currency TEXT;
+ALTER TABLE receipts ADD COLUMN net_total NUMERIC;
+ALTER TABLE receipts ADD COLUMN tax_total NUMERIC;
+ALTER TABLE receipts ADD COLUMN total NUMERIC;
+-- Where the receipt came from, such as ubl or cii; NULL for receipts entered by hand
+ALTER TABLE receipts ADD COLUMN source TEXT;
+
+-- A seller's invoice number identifies the invoice, so it is only stored once
+CREATE UNIQUE INDEX idx_receipts_party_invoice_number ON receipts (party_id, invoice_number);
+
+CREATE TABLE receipt_items (
+    id SERIAL PRIMARY KEY,
+    receipt_id INTEGER NOT NULL REFERENCES receipts(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    quantity NUMERIC,
+    unit_price NUMERIC,
+    -- Line total before VAT
+    net_amount NUMERIC NOT NULL,
+    -- VAT rate in percent
+    vat_rate NUMERIC,
+    -- UNCL5305 VAT category code, e.g. S (standard), Z (zero rated), E (exempt)
+    vat_category TEXT
+);
+
+CREATE INDEX idx_receipt_items_receipt_id ON receipt_items (receipt_id);
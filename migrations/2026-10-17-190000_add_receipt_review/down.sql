@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_receipts_needs_review;
+ALTER TABLE receipts DROP COLUMN IF EXISTS review_reasons;
+ALTER TABLE receipts DROP COLUMN IF EXISTS needs_review;
+ALTER TABLE receipts DROP COLUMN IF EXISTS confidence;
@@ -0,0 +1,7 @@
+-- Confidence (0-1) of the least certain field of a receipt read from OCR text
+ALTER TABLE receipts ADD COLUMN confidence REAL;
+-- Receipts waiting for the user to check them, and which fields are in doubt
+ALTER TABLE receipts ADD COLUMN needs_review BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE receipts ADD COLUMN review_reasons TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_receipts_needs_review ON receipts (needs_review) WHERE needs_review;
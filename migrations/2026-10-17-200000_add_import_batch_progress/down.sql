@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_import_batches_file_hash;
+ALTER TABLE import_batches DROP COLUMN IF EXISTS committed_line;
+ALTER TABLE import_batches DROP COLUMN IF EXISTS in_progress;
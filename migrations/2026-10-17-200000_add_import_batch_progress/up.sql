@@ -0,0 +1,5 @@
+-- Streamed imports commit in chunks; an unfinished one resumes after its last committed line
+ALTER TABLE import_batches ADD COLUMN in_progress BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE import_batches ADD COLUMN committed_line INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_import_batches_file_hash ON import_batches (file_hash);
@@ -0,0 +1,22 @@
+-- Outgoing SEPA credit transfers prepared in FinWise; pending until the bank's booking is imported
+CREATE TABLE payments (
+    id SERIAL PRIMARY KEY,
+    -- The account paying
+    account_id INTEGER NOT NULL REFERENCES accounts(id) ON DELETE CASCADE,
+    -- The party paid, by its IBAN (parties.eban)
+    party_id INTEGER NOT NULL REFERENCES parties(id) ON DELETE CASCADE,
+    amount NUMERIC NOT NULL,
+    execution_date DATE NOT NULL,
+    -- Remittance information shown to the payee
+    reference TEXT NOT NULL DEFAULT '',
+    -- Sent along with the transfer; banks print it on the booking
+    end_to_end_id TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    -- MsgId of the pain.001 file the payment was last written to
+    message_id TEXT,
+    -- The imported booking that settled the payment; undoing its import makes the payment pending again
+    transaction_id INTEGER REFERENCES transactions(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_payments_pending ON payments (account_id) WHERE transaction_id IS NULL;
+CREATE INDEX idx_payments_transaction_id ON payments (transaction_id);
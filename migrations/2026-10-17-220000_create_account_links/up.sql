@@ -0,0 +1,16 @@
+-- Accounts whose bookings `sync` fetches from a bank connector
+CREATE TABLE account_links (
+    id SERIAL PRIMARY KEY,
+    account_id INTEGER NOT NULL UNIQUE REFERENCES accounts(id) ON DELETE CASCADE,
+    -- Registered connector name, such as mock
+    connector TEXT NOT NULL,
+    -- The account's id at the connector
+    remote_account_id TEXT NOT NULL,
+    -- Connector settings as a JSON object of strings
+    settings TEXT NOT NULL DEFAULT '{}',
+    -- Where the next sync continues; only the connector understands it
+    sync_cursor TEXT,
+    synced_at TIMESTAMP,
+    -- Why the last sync failed; cleared by the next successful one
+    last_error TEXT
+);
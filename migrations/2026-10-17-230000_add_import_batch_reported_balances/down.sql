@@ -0,0 +1,4 @@
+ALTER TABLE import_batch_balances DROP COLUMN IF EXISTS reported_closing_date;
+ALTER TABLE import_batch_balances DROP COLUMN IF EXISTS reported_closing;
+ALTER TABLE import_batch_balances DROP COLUMN IF EXISTS reported_opening_date;
+ALTER TABLE import_batch_balances DROP COLUMN IF EXISTS reported_opening;
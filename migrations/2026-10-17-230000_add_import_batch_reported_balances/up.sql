@@ -0,0 +1,5 @@
+-- The opening and closing balances the statement reported, kept for reconciling later
+ALTER TABLE import_batch_balances ADD COLUMN reported_opening NUMERIC;
+ALTER TABLE import_batch_balances ADD COLUMN reported_opening_date TIMESTAMP;
+ALTER TABLE import_batch_balances ADD COLUMN reported_closing NUMERIC;
+ALTER TABLE import_batch_balances ADD COLUMN reported_closing_date TIMESTAMP;
@@ -0,0 +1,2 @@
+-- The account balance before a streamed import began, so a resumed import reports it
+ALTER TABLE import_batches ADD COLUMN opening_balance NUMERIC;
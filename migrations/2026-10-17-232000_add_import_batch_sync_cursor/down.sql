@@ -0,0 +1,2 @@
+ALTER TABLE import_batches DROP COLUMN IF EXISTS previous_sync_cursor;
+ALTER TABLE import_batches DROP COLUMN IF EXISTS sync_link_id;
@@ -0,0 +1,4 @@
+-- The account link a sync batch advanced and its cursor before, so undoing the batch rewinds it.
+-- No foreign key: backups restore import_batches before account_links.
+ALTER TABLE import_batches ADD COLUMN sync_link_id INTEGER;
+ALTER TABLE import_batches ADD COLUMN previous_sync_cursor TEXT;
@@ -0,0 +1,179 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use super::{BackupError, Snapshot, SNAPSHOT_FORMAT};
+
+/// Start of every FinWise backup file.
+const MAGIC: &[u8; 8] = b"FWBACKUP";
+/// Version of the container layout below.
+const CONTAINER_VERSION: u8 = 1;
+
+/// Argon2id cost: 64 MiB of memory and three passes, stored per archive so
+/// later versions can raise it and still open older backups.
+const MEMORY_KIB: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const PARALLELISM: u32 = 1;
+/// Highest cost an archive header may ask for, so a damaged file cannot
+/// exhaust memory or keep the key derivation busy for hours.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+/// Magic, version, three cost parameters, salt and nonce.
+const HEADER_LENGTH: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LENGTH + NONCE_LENGTH;
+
+/// Compresses and encrypts a snapshot with a key derived from `passphrase`.
+///
+/// The file is the header (`FWBACKUP`, container version, Argon2id cost, salt,
+/// nonce) followed by the AES-256-GCM ciphertext of the gzipped JSON snapshot.
+/// The header is authenticated along with the content, so any change to the
+/// file makes it fail to open.
+pub fn seal(snapshot: &Snapshot, passphrase: &str) -> Result<Vec<u8>, BackupError> {
+    let mut compressed = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut compressed, snapshot)?;
+    let plaintext = compressed.finish()?;
+
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut archive = Vec::with_capacity(HEADER_LENGTH + plaintext.len() + 16);
+    archive.extend_from_slice(MAGIC);
+    archive.push(CONTAINER_VERSION);
+    for parameter in [MEMORY_KIB, ITERATIONS, PARALLELISM] {
+        archive.extend_from_slice(&parameter.to_le_bytes());
+    }
+    archive.extend_from_slice(&salt);
+    archive.extend_from_slice(&nonce);
+
+    let cipher = cipher(passphrase, &salt, MEMORY_KIB, ITERATIONS, PARALLELISM)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: &plaintext, aad: &archive })
+        .map_err(|_| BackupError::Format("Encryption failed".to_string()))?;
+    archive.extend_from_slice(&ciphertext);
+    Ok(archive)
+}
+
+/// Decrypts and unpacks an archive written by `seal`, verifying it has not
+/// been changed or damaged.
+pub fn open(archive: &[u8], passphrase: &str) -> Result<Snapshot, BackupError> {
+    if archive.len() < HEADER_LENGTH || &archive[..MAGIC.len()] != MAGIC {
+        return Err(BackupError::Format("Not a FinWise backup".to_string()));
+    }
+    let version = archive[MAGIC.len()];
+    if version != CONTAINER_VERSION {
+        return Err(BackupError::Format(format!("Backup container version {} is not supported; it was written by a newer FinWise", version)));
+    }
+    let (header, ciphertext) = archive.split_at(HEADER_LENGTH);
+    let parameter = |index: usize| {
+        let start = MAGIC.len() + 1 + index * 4;
+        u32::from_le_bytes(header[start..start + 4].try_into().expect("four bytes"))
+    };
+    let salt = &header[HEADER_LENGTH - NONCE_LENGTH - SALT_LENGTH..HEADER_LENGTH - NONCE_LENGTH];
+    let nonce = &header[HEADER_LENGTH - NONCE_LENGTH..];
+
+    if parameter(0) > MAX_MEMORY_KIB || parameter(1) > MAX_ITERATIONS || parameter(2) > MAX_PARALLELISM {
+        return Err(BackupError::Format("Backup header asks for an implausible key derivation cost".to_string()));
+    }
+    let cipher = cipher(passphrase, salt, parameter(0), parameter(1), parameter(2))?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| BackupError::Format("Wrong passphrase, or the backup is damaged or was changed".to_string()))?;
+
+    let mut json = Vec::new();
+    GzDecoder::new(plaintext.as_slice()).read_to_end(&mut json)?;
+    let snapshot: Snapshot = serde_json::from_slice(&json)?;
+    if snapshot.format > SNAPSHOT_FORMAT {
+        return Err(BackupError::Format(format!(
+            "Backup content format {} is newer than this FinWise supports ({})",
+            snapshot.format, SNAPSHOT_FORMAT
+        )));
+    }
+    Ok(snapshot)
+}
+
+/// Reads back an archive written to `path` and checks that it opens with
+/// `passphrase` and holds exactly what `written` does.
+pub fn verify(path: &Path, passphrase: &str, written: &Snapshot) -> Result<(), BackupError> {
+    let read = open(&fs::read(path)?, passphrase)?;
+    let same_tables = read.tables.len() == written.tables.len()
+        && read.tables.iter().zip(&written.tables).all(|(read, written)| {
+            read.name == written.name && read.row_count == written.row_count && read.rows.get() == written.rows.get()
+        });
+    let same_files = read.files.len() == written.files.len()
+        && read.files.iter().zip(&written.files).all(|(read, written)| read.path == written.path && read.content == written.content);
+    if !same_tables || !same_files || read.migrations != written.migrations {
+        return Err(BackupError::Format(format!("Backup {} does not read back as written", path.display())));
+    }
+    Ok(())
+}
+
+fn cipher(passphrase: &str, salt: &[u8], memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Aes256Gcm, BackupError> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(32))
+        .map_err(|e| BackupError::Format(format!("Invalid key derivation parameters: {}", e)))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| BackupError::Format(format!("Key derivation failed: {}", e)))?;
+    let cipher = Aes256Gcm::new_from_slice(&key).expect("key is 32 bytes");
+    key.iter_mut().for_each(|byte| *byte = 0);
+    Ok(cipher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{ArchivedFile, TableRows};
+    use serde_json::value::RawValue;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            format: SNAPSHOT_FORMAT,
+            created_at: chrono::NaiveDate::from_ymd_opt(2026, 10, 17).unwrap().and_hms_opt(12, 0, 0).unwrap(),
+            app_version: "test".to_string(),
+            migrations: vec!["2026-10-17-230000".to_string()],
+            tables: vec![TableRows {
+                name: "parties".to_string(),
+                row_count: 1,
+                rows: RawValue::from_string(r#"[{"id":1,"name":"Me"}]"#.to_string()).unwrap(),
+            }],
+            files: vec![ArchivedFile { path: "config.toml".to_string(), content: "[database]\n".to_string() }],
+        }
+    }
+
+    #[test]
+    fn opens_what_it_sealed_and_nothing_else() {
+        let archive = seal(&snapshot(), "correct horse").unwrap();
+        let read = open(&archive, "correct horse").unwrap();
+        assert_eq!(read.migrations, snapshot().migrations);
+        assert_eq!(read.tables[0].rows.get(), r#"[{"id":1,"name":"Me"}]"#);
+        assert_eq!(read.files[0].content, "[database]\n");
+
+        assert!(open(&archive, "wrong horse").is_err());
+        let mut changed = archive.clone();
+        *changed.last_mut().unwrap() ^= 1;
+        assert!(open(&changed, "correct horse").is_err());
+    }
+
+    #[test]
+    fn refuses_implausible_key_derivation_costs() {
+        let mut archive = seal(&snapshot(), "correct horse").unwrap();
+        // The iteration count follows the memory cost in the header
+        let iterations = MAGIC.len() + 1 + 4;
+        archive[iterations..iterations + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = open(&archive, "correct horse").unwrap_err().to_string();
+        assert!(error.contains("implausible"), "{}", error);
+    }
+}
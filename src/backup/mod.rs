@@ -0,0 +1,112 @@
+pub mod archive;
+pub mod schedule;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::fs;
+use std::path::{Component, Path};
+use thiserror::Error;
+
+/// Version of the snapshot layout inside an archive; restore refuses newer ones.
+pub const SNAPSHOT_FORMAT: u32 = 1;
+
+/// Directory under `~/FinWise/` where backups are written by default.
+pub const BACKUP_DIR: &str = "backups";
+
+/// Database credentials belong to one machine and are never backed up.
+const CREDENTIALS_FILE: &str = ".finwise_db_credentials";
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("backup content error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("database migration error: {0}")]
+    Migration(String),
+    #[error("{0}")]
+    Format(String),
+}
+
+/// Everything a backup holds, before compression and encryption.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub format: u32,
+    pub created_at: NaiveDateTime,
+    /// FinWise version that wrote the backup.
+    pub app_version: String,
+    /// Database migrations applied when the backup was taken, oldest first.
+    pub migrations: Vec<String>,
+    /// Every table, in an order that satisfies foreign keys when restored front to back.
+    pub tables: Vec<TableRows>,
+    /// Profiles, templates, inbox files and other attachments under `~/FinWise/`.
+    pub files: Vec<ArchivedFile>,
+}
+
+/// The rows of a table as a JSON array of objects, kept verbatim so numeric
+/// values keep all their digits.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableRows {
+    pub name: String,
+    pub row_count: usize,
+    pub rows: Box<RawValue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    /// Path relative to `~/FinWise/`, with `/` separators.
+    pub path: String,
+    /// Base64 of the file's bytes.
+    pub content: String,
+}
+
+/// Reads every file under `data_dir` except credentials and the backups in `backup_dir`.
+pub fn collect_files(data_dir: &Path, backup_dir: &Path) -> Result<Vec<ArchivedFile>, BackupError> {
+    // Compare resolved paths, so a backup directory given another way is still left out
+    let data_dir = fs::canonicalize(data_dir)?;
+    let backup_dir = fs::canonicalize(backup_dir).unwrap_or_else(|_| backup_dir.to_path_buf());
+    let mut files = Vec::new();
+    collect_into(&data_dir, &data_dir, &backup_dir, &mut files)?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+fn collect_into(data_dir: &Path, dir: &Path, backup_dir: &Path, files: &mut Vec<ArchivedFile>) -> Result<(), BackupError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let relative = path.strip_prefix(data_dir).expect("walked from data_dir");
+        if relative == Path::new(CREDENTIALS_FILE) || path == backup_dir {
+            continue;
+        }
+        if path.is_dir() {
+            collect_into(data_dir, &path, backup_dir, files)?;
+        } else if path.is_file() {
+            let relative: Vec<String> = relative.components().map(|component| component.as_os_str().to_string_lossy().to_string()).collect();
+            files.push(ArchivedFile { path: relative.join("/"), content: STANDARD.encode(fs::read(&path)?) });
+        }
+    }
+    Ok(())
+}
+
+/// Writes archived files back under `data_dir`, replacing files of the same
+/// name and leaving others alone. Returns how many were written.
+pub fn restore_files(data_dir: &Path, files: &[ArchivedFile]) -> Result<usize, BackupError> {
+    for file in files {
+        let relative = Path::new(&file.path);
+        if file.path.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(BackupError::Format(format!("Refusing to restore file outside ~/FinWise/: '{}'", file.path)));
+        }
+        let content = STANDARD
+            .decode(&file.content)
+            .map_err(|e| BackupError::Format(format!("File '{}' is damaged: {}", file.path, e)))?;
+        let target = data_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, content)?;
+    }
+    Ok(files.len())
+}
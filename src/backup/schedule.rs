@@ -0,0 +1,191 @@
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::BackupError;
+
+/// Backups in the backup directory are named `finwise-<time>.fwbackup`; only
+/// files named so are rotated.
+const FILE_PREFIX: &str = "finwise-";
+const FILE_EXTENSION: &str = ".fwbackup";
+const FILE_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Outcome of the last scheduled run, kept next to the backups.
+const STATUS_FILE: &str = "status.json";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    Daily,
+    Weekly,
+}
+
+impl Schedule {
+    pub fn named(name: &str) -> Result<Self, BackupError> {
+        match name.to_lowercase().as_str() {
+            "daily" => Ok(Schedule::Daily),
+            "weekly" => Ok(Schedule::Weekly),
+            _ => Err(BackupError::Format(format!("Unknown backup schedule '{}': use daily or weekly", name))),
+        }
+    }
+
+    /// When the next backup is due after one taken at `latest`: the start of
+    /// the following day, or of the following Monday.
+    pub fn next_due(self, latest: NaiveDateTime) -> NaiveDateTime {
+        let day = latest.date();
+        let next = match self {
+            Schedule::Daily => day + Days::new(1),
+            Schedule::Weekly => day + Days::new(7 - u64::from(day.weekday().num_days_from_monday())),
+        };
+        next.and_hms_opt(0, 0, 0).expect("midnight exists")
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Schedule::Daily => "daily",
+            Schedule::Weekly => "weekly",
+        })
+    }
+}
+
+/// The day, ISO week or month a date falls in, as a year and its number within the year.
+type PeriodOf = fn(NaiveDate) -> (i32, u32);
+
+/// How many of the most recent days, ISO weeks and months keep their newest backup.
+#[derive(Debug, Clone, Copy)]
+pub struct Retention {
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+}
+
+impl Retention {
+    /// The backups no rule keeps. `backups` must be newest first; the newest is always kept.
+    pub fn expired<'a>(&self, backups: &'a [BackupFile]) -> Vec<&'a BackupFile> {
+        let mut kept: HashSet<&Path> = backups.first().map(|newest| newest.path.as_path()).into_iter().collect();
+        let periods: [(usize, PeriodOf); 3] = [
+            (self.daily, |day| (day.year(), day.ordinal())),
+            (self.weekly, |day| (day.iso_week().year(), day.iso_week().week())),
+            (self.monthly, |day| (day.year(), day.month())),
+        ];
+        for (count, period) in periods {
+            let mut seen = HashSet::new();
+            for backup in backups {
+                if seen.len() == count {
+                    break;
+                }
+                if seen.insert(period(backup.created_at.date())) {
+                    kept.insert(&backup.path);
+                }
+            }
+        }
+        backups.iter().filter(|backup| !kept.contains(backup.path.as_path())).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub created_at: NaiveDateTime,
+    pub size: u64,
+}
+
+/// File name for a backup taken at `created_at`.
+pub fn file_name(created_at: NaiveDateTime) -> String {
+    format!("{}{}{}", FILE_PREFIX, created_at.format(FILE_TIME_FORMAT), FILE_EXTENSION)
+}
+
+/// The backups in `dir`, newest first, dated by their file names.
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupFile>, BackupError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let created_at = name
+            .strip_prefix(FILE_PREFIX)
+            .and_then(|rest| rest.strip_suffix(FILE_EXTENSION))
+            .and_then(|time| NaiveDateTime::parse_from_str(time, FILE_TIME_FORMAT).ok());
+        if let Some(created_at) = created_at {
+            backups.push(BackupFile { path: entry.path(), created_at, size: entry.metadata()?.len() });
+        }
+    }
+    backups.sort_by_key(|backup| Reverse(backup.created_at));
+    Ok(backups)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RunStatus {
+    pub last_run: Option<NaiveDateTime>,
+    pub last_success: Option<NaiveDateTime>,
+    /// Why the last run failed; cleared by the next successful one.
+    pub last_error: Option<String>,
+}
+
+impl RunStatus {
+    pub fn load(dir: &Path) -> Result<Self, BackupError> {
+        let path = dir.join(STATUS_FILE);
+        if !path.exists() {
+            return Ok(RunStatus::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), BackupError> {
+        fs::write(dir.join(STATUS_FILE), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn schedules_the_next_day_or_monday() {
+        // 2024-03-06 is a Wednesday
+        assert_eq!(Schedule::Daily.next_due(at(2024, 3, 6, 23)), at(2024, 3, 7, 0));
+        assert_eq!(Schedule::Weekly.next_due(at(2024, 3, 6, 9)), at(2024, 3, 11, 0));
+        assert_eq!(Schedule::Weekly.next_due(at(2024, 3, 11, 0)), at(2024, 3, 18, 0));
+        assert_eq!(Schedule::named("Weekly").unwrap(), Schedule::Weekly);
+        assert!(Schedule::named("hourly").is_err());
+    }
+
+    #[test]
+    fn keeps_the_newest_backup_of_recent_days_weeks_and_months() {
+        let times = [at(2024, 3, 6, 18), at(2024, 3, 6, 9), at(2024, 3, 5, 9), at(2024, 3, 1, 9), at(2024, 2, 20, 9), at(2024, 1, 10, 9)];
+        let backups: Vec<BackupFile> = times
+            .iter()
+            .map(|created_at| BackupFile { path: PathBuf::from(file_name(*created_at)), created_at: *created_at, size: 0 })
+            .collect();
+        let retention = Retention { daily: 2, weekly: 2, monthly: 2 };
+        let expired: Vec<NaiveDateTime> = retention.expired(&backups).iter().map(|backup| backup.created_at).collect();
+        // Kept: 03-06 18h and 03-05 (days), 03-01 (previous week), 02-20 (previous month)
+        assert_eq!(expired, [at(2024, 3, 6, 9), at(2024, 1, 10, 9)]);
+    }
+
+    #[test]
+    fn lists_only_backup_files_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        for created_at in [at(2024, 3, 5, 9), at(2024, 3, 6, 9)] {
+            fs::write(dir.path().join(file_name(created_at)), b"x").unwrap();
+        }
+        fs::write(dir.path().join("notes.txt"), b"x").unwrap();
+        RunStatus { last_run: Some(at(2024, 3, 6, 9)), ..RunStatus::default() }.save(dir.path()).unwrap();
+
+        let listed: Vec<NaiveDateTime> = list_backups(dir.path()).unwrap().iter().map(|backup| backup.created_at).collect();
+        assert_eq!(listed, [at(2024, 3, 6, 9), at(2024, 3, 5, 9)]);
+        assert_eq!(RunStatus::load(dir.path()).unwrap().last_run, Some(at(2024, 3, 6, 9)));
+    }
+}
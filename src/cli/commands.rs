@@ -1,10 +1,31 @@
-use crate::config::Config;
+use crate::backup::{self, schedule::{self, Retention, RunStatus, Schedule}};
+use crate::config::{BackupConfig, Config, ImportConfig};
 use crate::utils::db::{DatabasePool, get_finwise_data_dir, get_secure_keyfile_path};
 use crate::services::*;
+use crate::import::locale::Locale;
+use crate::import::fingerprint::LineFingerprints;
+use crate::import::{self, Balance, ImportOptions, Statement, StatementLine};
+use crate::export::{self, ExportFilter};
+use crate::import::mail_template::{self, MailTemplate};
+use crate::import::profile::{self, CsvProfile};
+use crate::import::receipt_template::{self, ReceiptTemplate};
+use crate::models::account::Account;
+use crate::models::account_link::{AccountLink, NewAccountLink};
+use crate::models::payment::NewPayment;
+use crate::services::import_service::{AcceptBestMatch, ImportSummary, MatchReview, ReviewDecision, StatementChunk};
+use crate::services::party_match_service::{Candidate, PartyMatcher};
+use crate::sync::{self, ConnectorRegistry, ConnectorSettings};
+use bigdecimal::{BigDecimal, Signed};
+use diesel::{Connection, PgConnection};
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
 use std::fs;
 use std::io::{self, Write};
-use log::{info, error};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use log::{info, warn, error};
 
 pub async fn setup_database() -> Result<(), Box<dyn std::error::Error>> {
     println!("🔐 FinWise Database Security Setup");
@@ -108,36 +129,1718 @@ pub async fn start_server(
 
 pub async fn import_data(
     file_path: &str,
+    profile_name: Option<&str>,
+    locale_name: Option<&str>,
+    account_id: Option<i32>,
+    review: bool,
+    settings: &ImportConfig,
     db_pool: Arc<DatabasePool>
 ) -> Result<(), Box<dyn std::error::Error>> {
-    info!("Importing data from: {}", file_path);
-    
-    let mut _conn = db_pool.get_connection()?;
-    // let transaction_service = TransactionService::new();
-    
-    // TODO: Implement CSV/OFX file parsing
-    // let transactions = parse_file(file_path)?;
-    // for transaction in transactions {
-    //     transaction_service.create_transaction(&conn, transaction)?;
-    // }
-    
-    println!("Import completed for file: {}", file_path);
+    let options = ImportOptions {
+        profile: profile_name.map(profile::load_profile).transpose()?,
+        locale: locale_name.map(Locale::named).transpose()?,
+    };
+    let mut conn = db_pool.get_connection()?;
+    let mut reviewer: Box<dyn MatchReview> = if review { Box::new(PromptReview) } else { Box::new(AcceptBestMatch) };
+    import_file(&mut conn, Path::new(file_path), &options, account_id, settings, reviewer.as_mut(), &mut io::stdout())
+}
+
+/// Asks on the terminal which party a similar-named counterparty is.
+struct PromptReview;
+
+impl MatchReview for PromptReview {
+    fn choose(&mut self, line: &StatementLine, candidates: &[Candidate]) -> ReviewDecision {
+        let name = line.counterparty.as_deref().unwrap_or("?");
+        println!("❓ Line {}: '{}' ({} {}) looks like:", line.line, name, line.date.format("%Y-%m-%d"), line.amount);
+        for (index, candidate) in candidates.iter().enumerate() {
+            println!("   {}) {} (party {}, similarity {:.2})", index + 1, candidate.name, candidate.party_id, candidate.score);
+        }
+        loop {
+            print!("   Use which party? [1-{}, n = new party, Enter = 1] ", candidates.len());
+            let _ = io::stdout().flush();
+            let mut answer = String::new();
+            if io::stdin().read_line(&mut answer).unwrap_or(0) == 0 {
+                return ReviewDecision::CreateNew;
+            }
+            let answer = answer.trim();
+            if answer.eq_ignore_ascii_case("n") {
+                return ReviewDecision::CreateNew;
+            }
+            let choice = if answer.is_empty() { Some(1) } else { answer.parse::<usize>().ok() };
+            if let Some(candidate) = choice.and_then(|choice| choice.checked_sub(1)).and_then(|index| candidates.get(index)) {
+                return ReviewDecision::Use { party_id: candidate.party_id, remember: true };
+            }
+        }
+    }
+}
+
+/// Runs one file through the import pipeline, writing the report to `out`.
+fn import_file(
+    conn: &mut crate::utils::db::DbConnection,
+    path: &Path,
+    options: &ImportOptions,
+    account_id: Option<i32>,
+    settings: &ImportConfig,
+    review: &mut dyn MatchReview,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Importing data from: {}", path.display());
+    if options.profile.is_some() && import::format_of(path) == Some("csv") {
+        return import_csv_in_chunks(conn, path, options, account_id, settings, review, out);
+    }
+
+    let statements = import::parse_file(path, options)?;
+    if account_id.is_some() && statements.len() > 1 {
+        return Err(format!("{} contains {} accounts; --account can only be used for single-account files", path.display(), statements.len()).into());
+    }
+
+    let file_hash = file_hash(path)?;
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+    let format = import::format_of(path).unwrap_or("unknown");
+
+    for earlier in import_batch_service::find_active_by_hash(conn, &file_hash)? {
+        writeln!(out, "ℹ️  This file was already imported as batch {} on {}", earlier.id, earlier.imported_at.format("%Y-%m-%d %H:%M"))?;
+    }
+    // The batch and everything imported under it are committed together, so a
+    // failing statement leaves no empty batch behind
+    let batch = conn.transaction(|conn| -> Result<_, Box<dyn std::error::Error>> {
+        let batch = import_batch_service::create_batch(conn, &file_name, &file_hash, format)?;
+        info!("Recording import as batch {}", batch.id);
+
+        for statement in &statements {
+            for rejected in &statement.rejected {
+                warn!("Skipping line {}: {}", rejected.line, rejected.message);
+                writeln!(out, "⚠️  Line {}: {}", rejected.line, rejected.message)?;
+            }
+            for ignored in &statement.ignored {
+                writeln!(out, "↷ Line {} skipped: {}", ignored.line, ignored.message)?;
+            }
+
+            let account = resolve_import_account(conn, account_id, statement, batch.id, out)?;
+            let summary = import_service::import_statement(conn, &account, statement, batch.id, settings.match_threshold, review)?;
+
+            print_import_summary(account.id, &summary, !statement.trades.is_empty(), statement.rejected.len(), out)?;
+            print_reconciliation(statement, &summary, out)?;
+            if !statement.trades.is_empty() {
+                print_holdings(conn, account.id, out)?;
+            }
+        }
+        Ok(batch)
+    })?;
+
+    writeln!(out, "Import completed for file: {} (batch {}; undo with 'import undo {}')", path.display(), batch.id, batch.id)?;
+    Ok(())
+}
+
+/// Imports a CSV export through its profile while reading it, committing
+/// every `settings.batch_size` rows in their own database transaction.
+///
+/// Memory use stays the same however long the file is. If an earlier run on
+/// the same file was interrupted, its batch is continued after the last
+/// committed line instead of starting over; the lines before it are still read
+/// so that fingerprints of identical lines come out the same.
+fn import_csv_in_chunks(
+    conn: &mut crate::utils::db::DbConnection,
+    path: &Path,
+    options: &ImportOptions,
+    account_id: Option<i32>,
+    settings: &ImportConfig,
+    review: &mut dyn MatchReview,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let profile = options.profile.as_ref().ok_or("CSV import requires a mapping profile (--profile NAME)")?;
+    let named = Statement { account_iban: profile.normalized_account_iban(), ..Statement::default() };
+    let account = find_import_account(conn, account_id, &named)?
+        .ok_or_else(|| format!("The file does not name its account; pass --account ID or set account_iban in profile '{}'", profile.name))?;
+    let file_hash = file_hash(path)?;
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+
+    let batch = match import_batch_service::find_unfinished_by_hash(conn, &file_hash)? {
+        Some(batch) => {
+            if let Some(other) = import_batch_service::batch_account_ids(conn, batch.id)?.into_iter().find(|other| *other != account.id) {
+                return Err(format!("Batch {} of this file was importing into account {}; resume it with --account {} or undo it first", batch.id, other, other).into());
+            }
+            writeln!(out, "↻ Resuming batch {} after line {}", batch.id, batch.committed_line)?;
+            batch
+        }
+        None => {
+            for earlier in import_batch_service::find_active_by_hash(conn, &file_hash)? {
+                writeln!(out, "ℹ️  This file was already imported as batch {} on {}", earlier.id, earlier.imported_at.format("%Y-%m-%d %H:%M"))?;
+            }
+            import_batch_service::start_batch(conn, &file_name, &file_hash, "csv", &account.balance)?
+        }
+    };
+    info!("Recording import as batch {}", batch.id);
+    let resume_after = batch.committed_line as usize;
+
+    let mut reader = import::csv::LineReader::open(path, profile, options.locale.as_ref())?;
+    let mut fingerprints = LineFingerprints::new(account.id);
+    let mut matcher = PartyMatcher::load(conn, settings.match_threshold)?;
+    let progress = ImportProgress::new(fs::metadata(path)?.len());
+    let mut summary = ImportSummary {
+        // On resume the account already holds the committed chunks
+        opening_balance: batch.opening_balance.clone().unwrap_or_else(|| account.balance.clone()),
+        balance: account.balance.clone(),
+        ..ImportSummary::default()
+    };
+    let mut rejected = 0;
+    let mut rows = 0;
+    let mut chunk = StatementChunk::default();
+    while let Some(row) = reader.next_row()? {
+        rows += 1;
+        match row {
+            Ok(line) => {
+                let line_fingerprint = fingerprints.next(&line);
+                if line.line > resume_after {
+                    chunk.last_line = line.line;
+                    chunk.lines.push(line);
+                    chunk.fingerprints.push(line_fingerprint);
+                }
+            }
+            Err(row_error) if row_error.line <= resume_after => {}
+            Err(row_error) => {
+                warn!("Skipping line {}: {}", row_error.line, row_error.message);
+                progress.bar.suspend(|| writeln!(out, "⚠️  Line {}: {}", row_error.line, row_error.message))?;
+                chunk.last_line = row_error.line;
+                chunk.rejected += 1;
+                rejected += 1;
+            }
+        }
+        if chunk.lines.len() + chunk.rejected >= settings.batch_size.get() {
+            summary.absorb(import_service::import_chunk(conn, &account, &chunk, batch.id, &mut matcher, review)?);
+            chunk = StatementChunk::default();
+        }
+        progress.update(reader.bytes_read(), rows);
+    }
+    if chunk.last_line > 0 {
+        summary.absorb(import_service::import_chunk(conn, &account, &chunk, batch.id, &mut matcher, review)?);
+    }
+    import_batch_service::finish_batch(conn, batch.id)?;
+    progress.finish(rows);
+
+    print_import_summary(account.id, &summary, false, rejected, out)?;
+    writeln!(out, "  Balance:              {} → {}", summary.opening_balance, summary.balance)?;
+    writeln!(out, "Import completed for file: {} (batch {}; undo with 'import undo {}')", path.display(), batch.id, batch.id)?;
+    Ok(())
+}
+
+/// A progress bar on the terminal for a file being imported, with the rate in rows per second.
+struct ImportProgress {
+    bar: ProgressBar,
+    started: Instant,
+}
+
+impl ImportProgress {
+    /// Draws to standard error, and not at all when that is not a terminal.
+    fn new(file_size: u64) -> Self {
+        let bar = ProgressBar::new(file_size);
+        bar.set_style(
+            ProgressStyle::with_template("{bar:40} {percent:>3}% {msg} (ETA {eta})")
+                .expect("progress template is valid")
+                .progress_chars("=> "),
+        );
+        ImportProgress { bar, started: Instant::now() }
+    }
+
+    fn update(&self, bytes_read: u64, rows: usize) {
+        self.bar.set_position(bytes_read);
+        if rows.is_multiple_of(256) {
+            self.bar.set_message(format!("{} rows, {:.0} rows/s", rows, self.rate(rows)));
+        }
+    }
+
+    fn finish(&self, rows: usize) {
+        self.bar.finish_and_clear();
+        info!("Read {} rows in {:.1}s ({:.0} rows/s)", rows, self.started.elapsed().as_secs_f64(), self.rate(rows));
+    }
+
+    fn rate(&self, rows: usize) -> f64 {
+        rows as f64 / self.started.elapsed().as_secs_f64().max(0.001)
+    }
+}
+
+/// Hashes a file's content without reading all of it into memory.
+fn file_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// How many duplicate lines an import report lists one by one.
+const SKIPPED_LINES_SHOWN: usize = 20;
+
+fn print_import_summary(account_id: i32, summary: &ImportSummary, trades: bool, rejected: usize, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "Account {}:", account_id)?;
+    writeln!(out, "  Transactions created: {}", summary.transactions_created)?;
+    if trades {
+        writeln!(out, "  Trades created:       {}", summary.trades_created)?;
+    }
+    writeln!(out, "  Duplicates skipped:   {}", summary.duplicates_skipped)?;
+    writeln!(out, "  Transfers linked:     {}", summary.transfers_linked)?;
+    writeln!(out, "  Parties created:      {}", summary.parties_created)?;
+    if summary.payments_settled > 0 {
+        writeln!(out, "  Payments settled:     {}", summary.payments_settled)?;
+    }
+    writeln!(out, "  Rows rejected:        {}", rejected)?;
+    for skipped in summary.skipped.iter().take(SKIPPED_LINES_SHOWN) {
+        writeln!(out, "  ↷ Line {} skipped: already stored as transaction {}", skipped.line, skipped.transaction_id)?;
+    }
+    if summary.skipped.len() > SKIPPED_LINES_SHOWN {
+        writeln!(out, "  ↷ ... and {} more lines already stored", summary.skipped.len() - SKIPPED_LINES_SHOWN)?;
+    }
+    Ok(())
+}
+
+/// Lists the open positions of an investment account.
+fn print_holdings(conn: &mut crate::utils::db::DbConnection, account_id: i32, out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let holdings = investment_service::holdings(conn, account_id)?;
+    if holdings.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "  Holdings:")?;
+    for holding in holdings {
+        match (&holding.last_price, &holding.currency) {
+            (Some(price), Some(currency)) => writeln!(out, "    {} {} (last price {} {})", holding.quantity, holding.symbol, price, currency)?,
+            (Some(price), None) => writeln!(out, "    {} {} (last price {})", holding.quantity, holding.symbol, price)?,
+            _ => writeln!(out, "    {} {}", holding.quantity, holding.symbol)?,
+        }
+    }
+    Ok(())
+}
+
+/// Imports every file dropped into `~/FinWise/inbox/`, polling every `interval_secs` seconds.
+///
+/// The format is detected from the extension, and CSV files are matched against
+/// the saved profiles. E-invoices (UBL or CII XML, ZUGFeRD / Factur-X PDF) are
+/// stored as receipts. Imported files move to `inbox/processed/`, files that
+/// fail to import to `inbox/failed/`, each with a `.log` file holding the report.
+/// A file is only picked up once its size has stopped changing between two polls,
+/// so downloads still being written are left alone. Similar-named counterparties
+/// are matched to the most similar party without asking. Filesystem errors are
+/// logged and the next poll tries again; a file that could not be moved out of
+/// the inbox is not imported again until it changes. Stops on Ctrl-C.
+pub async fn watch_inbox(interval_secs: u64, locale_name: Option<&str>, settings: &ImportConfig, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let locale = locale_name.map(Locale::named).transpose()?;
+    let inbox = get_finwise_data_dir()?.join("inbox");
+    let processed = inbox.join("processed");
+    let failed = inbox.join("failed");
+    fs::create_dir_all(&processed)?;
+    fs::create_dir_all(&failed)?;
+
+    println!("👀 Watching {} (Ctrl-C to stop)", inbox.display());
+    let mut sizes: HashMap<PathBuf, u64> = HashMap::new();
+    // Files already imported that could not be moved away, with their size then
+    let mut stuck: HashMap<PathBuf, u64> = HashMap::new();
+    loop {
+        let entries = match fs::read_dir(&inbox) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok()).collect(),
+            Err(e) => {
+                error!("Cannot read {}: {}", inbox.display(), e);
+                Vec::new()
+            }
+        };
+        for entry in entries {
+            let path = entry.path();
+            let hidden = path.file_name().and_then(|name| name.to_str()).is_none_or(|name| name.starts_with('.'));
+            if !path.is_file() || hidden {
+                continue;
+            }
+            let size = match fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(e) => {
+                    warn!("Cannot read {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if stuck.get(&path) == Some(&size) {
+                continue;
+            }
+            stuck.remove(&path);
+            if sizes.insert(path.clone(), size) != Some(size) {
+                continue;
+            }
+            sizes.remove(&path);
+
+            let mut report: Vec<u8> = Vec::new();
+            let result = import_inbox_file(&db_pool, &path, locale, settings, &mut report);
+            let (target_dir, outcome) = match &result {
+                Ok(()) => (&processed, "imported"),
+                Err(e) => {
+                    error!("Import of {} failed: {}", path.display(), e);
+                    let _ = writeln!(report, "❌ Import failed: {}", e);
+                    (&failed, "failed")
+                }
+            };
+            let target = match move_to(&path, target_dir) {
+                Ok(target) => target,
+                Err(e) => {
+                    error!("Cannot move {} to {}: {}", path.display(), target_dir.display(), e);
+                    println!("⚠️  {} {} but could not be moved to {}: {}", path.display(), outcome, target_dir.display(), e);
+                    stuck.insert(path, size);
+                    continue;
+                }
+            };
+            let mut log = format!("{} {} {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"), outcome, path.display());
+            log.push_str(&String::from_utf8_lossy(&report));
+            let log_path = target.with_file_name(format!("{}.log", target.file_name().unwrap_or_default().to_string_lossy()));
+            if let Err(e) = fs::write(&log_path, log) {
+                error!("Cannot write {}: {}", log_path.display(), e);
+            }
+            println!("{} {} → {}", if result.is_ok() { "✅" } else { "❌" }, path.display(), target.display());
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(interval_secs)) => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!("Stopped watching {}", inbox.display());
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn import_inbox_file(db_pool: &DatabasePool, path: &Path, locale: Option<Locale>, settings: &ImportConfig, out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    if import::einvoice::detect(path)? {
+        writeln!(out, "Detected e-invoice")?;
+        let mut conn = db_pool.get_connection()?;
+        return import_invoice_file(&mut conn, path, settings.match_threshold, out);
+    }
+    let format = import::format_of(path).ok_or_else(|| format!("Unsupported file type: {}", path.display()))?;
+    let mut options = ImportOptions { locale, ..ImportOptions::default() };
+    let provider = if format == "csv" { import::payment_provider::detect(path)? } else { None };
+    let exchange = if format == "csv" && provider.is_none() { import::trades::detect(path)? } else { None };
+    if let Some(provider) = provider {
+        writeln!(out, "Detected {} export", provider.name())?;
+    } else if let Some(exchange) = exchange {
+        writeln!(out, "Detected {} trade history", exchange.name())?;
+    } else if format == "csv" {
+        let profiles = profile::list_profiles()?
+            .iter()
+            .map(|name| profile::load_profile(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let detected = import::csv::detect_profile(path, &profiles).ok_or("No saved CSV profile matches this file")?;
+        writeln!(out, "Using CSV profile '{}'", detected.name)?;
+        options.profile = Some(detected.clone());
+    }
+    writeln!(out, "Format: {}", format)?;
+
+    let mut conn = db_pool.get_connection()?;
+    import_file(&mut conn, path, &options, None, settings, &mut AcceptBestMatch, out)
+}
+
+/// Moves a file into `dir`, prefixing a timestamp if a file of that name is already there.
+fn move_to(path: &Path, dir: &Path) -> io::Result<PathBuf> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let mut target = dir.join(&name);
+    if target.exists() {
+        target = dir.join(format!("{}-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"), name));
+    }
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+pub async fn list_imports(db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let batches = import_batch_service::list_batches(&mut conn)?;
+    if batches.is_empty() {
+        println!("No imports recorded yet.");
+        return Ok(());
+    }
+
+    println!("{:>6}  {:<16}  {:<6}  {:>7}  {:>7}  {:>8}  {:>7}  {:<10}  File", "Batch", "Imported", "Format", "Created", "Skipped", "Rejected", "Parties", "Status");
+    for batch in batches {
+        let status = match batch.reverted_at {
+            Some(reverted) => format!("undone {}", reverted.date()),
+            None if batch.in_progress => format!("stopped at line {}", batch.committed_line),
+            None => "active".to_string(),
+        };
+        // A short content hash tells re-imports of the same file apart from different files of the same name
+        println!(
+            "{:>6}  {:<16}  {:<6}  {:>7}  {:>7}  {:>8}  {:>7}  {:<10}  {} ({})",
+            batch.id,
+            batch.imported_at.format("%Y-%m-%d %H:%M").to_string(),
+            batch.format,
+            batch.rows_created,
+            batch.rows_skipped,
+            batch.rows_rejected,
+            batch.parties_created,
+            status,
+            batch.file_name,
+            &batch.file_hash[..batch.file_hash.len().min(8)]
+        );
+        for balance in import_batch_service::batch_balances(&mut conn, batch.id)? {
+            let reported = [
+                ("opening", &balance.reported_opening, balance.reported_opening_date),
+                ("closing", &balance.reported_closing, balance.reported_closing_date),
+            ];
+            let reported: Vec<String> = reported
+                .into_iter()
+                .filter_map(|(label, amount, date)| Some(format!("{} {} as of {}", label, amount.as_ref()?, date?.date())))
+                .collect();
+            let reported = if reported.is_empty() { String::new() } else { format!("; bank reported {}", reported.join(", ")) };
+            println!("{:>6}  account {}: balance moved by {}{}", "", balance.account_id, balance.net_change, reported);
+        }
+    }
+    Ok(())
+}
+
+pub async fn import_receipts(files: Vec<&str>, match_threshold: f64, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    for file in files {
+        import_invoice_file(&mut conn, Path::new(file), match_threshold, &mut io::stdout())?;
+    }
+    Ok(())
+}
+
+/// Stores an e-invoice as a receipt, recorded as an import batch so it can be undone.
+fn import_invoice_file(
+    conn: &mut crate::utils::db::DbConnection,
+    path: &Path,
+    match_threshold: f64,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Importing e-invoice from: {}", path.display());
+    let invoice = import::einvoice::parse_file(path)?;
+
+    let file_hash = hex::encode(Sha256::digest(fs::read(path)?));
+    let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+    let (batch, imported) = conn.transaction(|conn| -> diesel::QueryResult<_> {
+        let batch = import_batch_service::create_batch(conn, &file_name, &file_hash, invoice.syntax.name())?;
+        let imported = receipt_service::import_invoice(conn, &invoice, Some(batch.id), match_threshold)?;
+        import_batch_service::add_counts(
+            conn,
+            batch.id,
+            if imported.duplicate { 0 } else { 1 },
+            if imported.duplicate { 1 } else { 0 },
+            0,
+            if imported.party_created { 1 } else { 0 },
+        )?;
+        Ok((batch, imported))
+    })?;
+
+    let kind = if invoice.credit_note { "Credit note" } else { "Invoice" };
+    let currency = invoice.currency.as_deref().unwrap_or("");
+    if imported.duplicate {
+        writeln!(out, "↷ {} {} from {} already stored as receipt {}", kind, invoice.number, imported.party.name, imported.receipt_id)?;
+        return Ok(());
+    }
+    writeln!(out, "🧾 {} {} from {} stored as receipt {} (batch {})", kind, invoice.number, imported.party.name, imported.receipt_id, batch.id)?;
+    let source = if invoice.from_pdf { format!("{} in PDF", invoice.syntax.name().to_uppercase()) } else { invoice.syntax.name().to_uppercase() };
+    writeln!(out, "  Format:     {}", source)?;
+    if imported.party_created {
+        writeln!(out, "  New party:  {} (IBAN {})", imported.party.name, invoice.seller_iban.as_deref().unwrap_or("none"))?;
+    }
+    if let Some(vat_id) = &invoice.seller_vat_id {
+        writeln!(out, "  VAT ID:     {}", vat_id)?;
+    }
+    writeln!(out, "  Issued:     {}", invoice.issue_date)?;
+    if let Some(due) = invoice.due_date {
+        writeln!(out, "  Due:        {}", due)?;
+    }
+    writeln!(out, "  Items:      {}", imported.items_created)?;
+    for line in &invoice.lines {
+        let rate = line.vat_rate.as_ref().map(|rate| format!(" ({}% VAT)", rate)).unwrap_or_default();
+        writeln!(out, "  - {} {} {}{}", line.description, line.net_amount, currency, rate)?;
+    }
+    if let Some(net) = &invoice.net_total {
+        writeln!(out, "  Net:        {} {}", net, currency)?;
+    }
+    if let Some(tax) = &invoice.tax_total {
+        writeln!(out, "  VAT:        {} {}", tax, currency)?;
+    }
+    writeln!(out, "  Total:      {} {}", invoice.total, currency)?;
+    Ok(())
+}
+
+/// Stores the order confirmations found under `path` (an `.eml` file, a Maildir
+/// or a directory of `.eml` files) as receipts, in one import batch.
+pub async fn import_mail(path: &str, match_threshold: f64, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let templates = mail_template::list_templates()?
+        .iter()
+        .map(|name| mail_template::load_template(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    if templates.is_empty() {
+        return Err(format!(
+            "No mail templates in {}; create one with 'receipts mail-template init NAME'",
+            mail_template::get_templates_dir()?.display()
+        )
+        .into());
+    }
+
+    let root = Path::new(path);
+    let files = import::mail::mail_files(root)?;
+    info!("Scanning {} messages in {}", files.len(), root.display());
+
+    let mut hasher = Sha256::new();
+    for file in &files {
+        hasher.update(fs::read(file)?);
+    }
+    let file_hash = hex::encode(hasher.finalize());
+    let file_name = root.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| root.display().to_string());
+
+    let mut conn = db_pool.get_connection()?;
+    for earlier in import_batch_service::find_active_by_hash(&mut conn, &file_hash)? {
+        println!("ℹ️  These messages were already imported as batch {} on {}", earlier.id, earlier.imported_at.format("%Y-%m-%d %H:%M"));
+    }
+    let mut matcher = party_match_service::PartyMatcher::load(&mut conn, match_threshold)?;
+
+    // Receipts are only reported once the batch holding them is committed
+    let mut stored = Vec::new();
+    let (mut created, mut skipped, mut rejected, mut parties, mut ignored) = (0, 0, 0, 0, 0);
+    let batch = conn.transaction(|conn| -> diesel::QueryResult<_> {
+        let batch = import_batch_service::create_batch(conn, &file_name, &file_hash, "mail")?;
+        for file in &files {
+            let order = match import::mail::parse_file(file, &templates) {
+                Ok(Some(order)) => order,
+                Ok(None) => {
+                    ignored += 1;
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Skipping {}: {}", file.display(), e);
+                    println!("⚠️  {}: {}", file.display(), e);
+                    rejected += 1;
+                    continue;
+                }
+            };
+
+            let imported = receipt_service::import_order_mail(conn, &mut matcher, &order, Some(batch.id))?;
+            let number = order.order_number.as_deref().map(|number| format!(" {}", number)).unwrap_or_default();
+            let currency = order.currency.as_deref().unwrap_or("");
+            if imported.duplicate {
+                stored.push(format!("↷ Order{} from {} ({}) already stored as receipt {}", number, order.merchant, order.date, imported.receipt_id));
+                skipped += 1;
+                continue;
+            }
+            stored.push(format!(
+                "🧾 Order{} from {} ({}): {} {}, {} item(s) → receipt {} [{}]",
+                number, order.merchant, order.date, order.total, currency, imported.items_created, imported.receipt_id, order.template
+            ));
+            created += 1;
+            if imported.party_created {
+                parties += 1;
+            }
+        }
+        import_batch_service::add_counts(conn, batch.id, created, skipped, rejected, parties)?;
+        Ok(batch)
+    })?;
+    for line in stored {
+        println!("{}", line);
+    }
+
+    println!("Messages scanned:   {}", files.len());
+    println!("Receipts created:   {}", created);
+    println!("Duplicates skipped: {}", skipped);
+    println!("Unreadable orders:  {}", rejected);
+    println!("Other mail:         {}", ignored);
+    println!("Mail import completed (batch {}; undo with 'import undo {}')", batch.id, batch.id);
     Ok(())
 }
 
+pub fn list_mail_templates() -> Result<(), Box<dyn std::error::Error>> {
+    let names = mail_template::list_templates()?;
+    if names.is_empty() {
+        println!("No mail templates in {}", mail_template::get_templates_dir()?.display());
+        println!("Create one with: receipts mail-template init NAME");
+        return Ok(());
+    }
+    for name in names {
+        let template = mail_template::load_template(&name)?;
+        println!("{}  {} ({})", name, template.merchant, template.senders.join(", "));
+    }
+    Ok(())
+}
+
+pub fn init_mail_template(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if mail_template::list_templates()?.iter().any(|existing| existing == name) {
+        return Err(format!("Mail template '{}' already exists", name).into());
+    }
+    let path = mail_template::save_template(&MailTemplate::template(name))?;
+    println!("✅ Created mail template: {}", path.display());
+    println!("Edit the merchant, sender addresses and patterns to match the shop's order confirmations.");
+    Ok(())
+}
+
+/// Stores till receipts from OCR text files, one import batch per file.
+pub async fn import_ocr(files: Vec<&str>, match_threshold: f64, review_threshold: f32, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let templates = receipt_template::list_templates()?
+        .iter()
+        .map(|name| receipt_template::load_template(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut conn = db_pool.get_connection()?;
+    let mut matcher = party_match_service::PartyMatcher::load(&mut conn, match_threshold)?;
+
+    for file in files {
+        let path = Path::new(file);
+        info!("Reading OCR receipt from: {}", path.display());
+        let receipt = import::ocr::parse_file(path, &templates)?;
+
+        let file_hash = hex::encode(Sha256::digest(fs::read(path)?));
+        let file_name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+        let (batch, imported) = conn.transaction(|conn| -> diesel::QueryResult<_> {
+            let batch = import_batch_service::create_batch(conn, &file_name, &file_hash, "ocr")?;
+            let imported = receipt_service::import_ocr_receipt(conn, &mut matcher, &receipt, Some(batch.id), review_threshold)?;
+            import_batch_service::add_counts(
+                conn,
+                batch.id,
+                if imported.duplicate { 0 } else { 1 },
+                if imported.duplicate { 1 } else { 0 },
+                0,
+                if imported.party_created { 1 } else { 0 },
+            )?;
+            Ok((batch, imported))
+        })?;
+
+        if imported.duplicate {
+            println!("↷ {}: already stored as receipt {}", path.display(), imported.receipt_id);
+            continue;
+        }
+        let template = receipt.template.as_deref().map(|name| format!(" [{}]", name)).unwrap_or_default();
+        println!("🧾 {} → receipt {} from {} (batch {}){}", path.display(), imported.receipt_id, imported.party.name, batch.id, template);
+        print_scored("Merchant", &receipt.merchant);
+        print_scored("Date", &receipt.date);
+        print_scored("Time", &receipt.time);
+        print_scored("Payment", &receipt.payment_method);
+        for item in receipt.items.value.iter().flatten() {
+            println!("    - {} {}", item.description, item.price);
+        }
+        let item_count = receipt.items.value.as_ref().map(|items| items.len().to_string());
+        print_field("Items", item_count, receipt.items.confidence, receipt.items.note.as_deref());
+        print_scored("Total", &receipt.total);
+        let reasons = receipt.review_reasons(review_threshold);
+        if !reasons.is_empty() {
+            println!("  ⚠️  Queued for review (confidence {:.2}); see 'receipts review'", receipt.confidence());
+        }
+    }
+    Ok(())
+}
+
+fn print_scored<T: std::fmt::Display>(label: &str, field: &import::ocr::Scored<T>) {
+    print_field(label, field.value.as_ref().map(|value| value.to_string()), field.confidence, field.note.as_deref());
+}
+
+fn print_field(label: &str, value: Option<String>, confidence: f32, note: Option<&str>) {
+    let note = note.map(|note| format!(" - {}", note)).unwrap_or_default();
+    println!("  {:<9} {} ({:.2}){}", format!("{}:", label), value.unwrap_or_else(|| "?".to_string()), confidence, note);
+}
+
+/// Prints a receipt with its line items and its net, tax and gross totals.
+pub async fn show_receipt(receipt_id: i32, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let receipt = receipt_service::get_receipt_by_id(&mut conn, receipt_id).map_err(|e| format!("Receipt {} not found: {}", receipt_id, e))?;
+    let seller = party_service::get_party_by_id(&mut conn, receipt.party_id)?;
+    let currency = receipt.currency.as_deref().unwrap_or("");
+
+    match &receipt.invoice_number {
+        Some(number) => println!("🧾 Receipt {}: invoice {} from {}", receipt.id, number, seller.name),
+        None => println!("🧾 Receipt {}: {}", receipt.id, seller.name),
+    }
+    println!("  Date:       {}", format!("{} {}", receipt.date, receipt.time).trim_end());
+    if let Some(due) = &receipt.due_date {
+        println!("  Due:        {}", due);
+    }
+    if !receipt.payment_method.is_empty() {
+        println!("  Payment:    {}", receipt.payment_method);
+    }
+    if let Some(source) = &receipt.source {
+        println!("  Source:     {}", source);
+    }
+    if let Some(batch_id) = receipt.import_batch_id {
+        println!("  Batch:      {}", batch_id);
+    }
+
+    let items = receipt_service::receipt_items(&mut conn, receipt.id)?;
+    if items.is_empty() {
+        for item in &receipt.items {
+            println!("    - {}", item);
+        }
+    }
+    for item in &items {
+        let quantity = match (&item.quantity, &item.unit_price) {
+            (Some(quantity), Some(price)) => format!("{} × {} = ", quantity, price),
+            (Some(quantity), None) => format!("{} × ", quantity),
+            _ => String::new(),
+        };
+        let vat = match (&item.vat_rate, &item.vat_category) {
+            (Some(rate), Some(category)) => format!(" ({}% VAT, category {})", rate, category),
+            (Some(rate), None) => format!(" ({}% VAT)", rate),
+            (None, Some(category)) => format!(" (VAT category {})", category),
+            (None, None) => String::new(),
+        };
+        println!("  {:>3}. {}: {}{} {}{}", item.position, item.description, quantity, item.net_amount, currency, vat);
+    }
+
+    let amount = |amount: &Option<BigDecimal>| amount.as_ref().map(|amount| format!("{} {}", amount, currency));
+    for (label, value) in [("Net total:", &receipt.net_total), ("Tax total:", &receipt.tax_total), ("Total:", &receipt.total)] {
+        if let Some(value) = amount(value) {
+            println!("  {:<11} {}", label, value.trim_end());
+        }
+    }
+    for reason in &receipt.review_reasons {
+        println!("  ⚠️  {}", reason);
+    }
+    Ok(())
+}
+
+pub async fn review_receipts(db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let queue = receipt_service::review_queue(&mut conn)?;
+    if queue.is_empty() {
+        println!("No receipts waiting for review.");
+        return Ok(());
+    }
+
+    for receipt in queue {
+        let merchant = party_service::get_party_by_id(&mut conn, receipt.party_id)?;
+        let total = receipt.total.map(|total| total.to_string()).unwrap_or_else(|| "?".to_string());
+        let confidence = receipt.confidence.map(|confidence| format!("{:.2}", confidence)).unwrap_or_else(|| "-".to_string());
+        println!("🧾 Receipt {}: {} {} {}, total {} (confidence {})", receipt.id, merchant.name, receipt.date, receipt.time, total, confidence);
+        for item in &receipt.items {
+            println!("    - {}", item);
+        }
+        for reason in &receipt.review_reasons {
+            println!("  ⚠️  {}", reason);
+        }
+    }
+    println!("Approve a receipt with 'receipts approve ID', correcting it with --date and --total if needed.");
+    Ok(())
+}
+
+pub async fn approve_receipt(receipt_id: i32, date: Option<&str>, total: Option<&str>, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(date) = date {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("Invalid date '{}': use YYYY-MM-DD", date))?;
+    }
+    let total = total
+        .map(|total| total.parse::<BigDecimal>().map_err(|_| format!("Invalid total '{}'", total)))
+        .transpose()?;
+
+    let mut conn = db_pool.get_connection()?;
+    let receipt = receipt_service::get_receipt_by_id(&mut conn, receipt_id)
+        .map_err(|e| format!("Receipt {} not found: {}", receipt_id, e))?;
+    if !receipt.needs_review {
+        println!("Receipt {} is not waiting for review.", receipt.id);
+        return Ok(());
+    }
+    receipt_service::approve_receipt(&mut conn, receipt.id, date, total)?;
+    println!("✅ Receipt {} approved", receipt.id);
+    Ok(())
+}
+
+pub fn list_receipt_templates() -> Result<(), Box<dyn std::error::Error>> {
+    let names = receipt_template::list_templates()?;
+    if names.is_empty() {
+        println!("No receipt templates in {}", receipt_template::get_templates_dir()?.display());
+        println!("Create one with: receipts ocr-template init NAME");
+        return Ok(());
+    }
+    for name in names {
+        let template = receipt_template::load_template(&name)?;
+        println!("{}  {} ({})", name, template.merchant, template.keywords.join(", "));
+    }
+    Ok(())
+}
+
+pub fn init_receipt_template(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if receipt_template::list_templates()?.iter().any(|existing| existing == name) {
+        return Err(format!("Receipt template '{}' already exists", name).into());
+    }
+    let path = receipt_template::save_template(&ReceiptTemplate::template(name))?;
+    println!("✅ Created receipt template: {}", path.display());
+    println!("Edit the merchant, keywords and line patterns to match the shop's receipts.");
+    Ok(())
+}
+
+pub async fn list_receipts(db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let stored = receipt_service::list_receipts(&mut conn)?;
+    if stored.is_empty() {
+        println!("No receipts recorded yet.");
+        return Ok(());
+    }
+
+    println!("{:>6}  {:<10}  {:<24}  {:<16}  {:>12}  {:<10}  Status", "ID", "Date", "Seller", "Invoice", "Total", "Due");
+    for receipt in stored {
+        let seller = party_service::get_party_by_id(&mut conn, receipt.party_id)?;
+        let total = match (&receipt.total, &receipt.currency) {
+            (Some(total), Some(currency)) => format!("{} {}", total, currency),
+            (Some(total), None) => total.to_string(),
+            _ => String::new(),
+        };
+        println!(
+            "{:>6}  {:<10}  {:<24}  {:<16}  {:>12}  {:<10}  {}",
+            receipt.id,
+            receipt.date,
+            seller.name,
+            receipt.invoice_number.unwrap_or_default(),
+            total,
+            receipt.due_date.unwrap_or_default(),
+            if receipt.needs_review { "review" } else { "" }
+        );
+    }
+    Ok(())
+}
+
+/// Records an outgoing SEPA transfer to an existing party, to be written to a pain.001 file.
+pub async fn add_payment(
+    account_id: i32,
+    payee: &str,
+    amount: &str,
+    date: Option<&str>,
+    reference: &str,
+    db_pool: Arc<DatabasePool>
+) -> Result<(), Box<dyn std::error::Error>> {
+    let amount = amount.parse::<BigDecimal>().map_err(|_| format!("Invalid amount '{}'", amount))?;
+    if !amount.is_positive() || amount.with_scale(2) != amount {
+        return Err(format!("Amount {} must be positive and in cents", amount).into());
+    }
+    let execution_date = match date {
+        Some(date) => chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("Invalid date '{}': use YYYY-MM-DD", date))?,
+        None => chrono::Local::now().date_naive(),
+    };
+
+    let mut conn = db_pool.get_connection()?;
+    let account = account_service::get_account(&mut conn, account_id)?;
+    let owner = party_service::get_party_by_id(&mut conn, account.party_id)?;
+    if !export::pain001::valid_iban(&owner.eban) {
+        return Err(format!("Account {} ({}) has no valid IBAN to pay from", account.id, owner.name).into());
+    }
+    let party = match payee.parse::<i32>() {
+        Ok(party_id) => party_service::get_party_by_id(&mut conn, party_id)?,
+        Err(_) => party_service::find_party_by_name(&mut conn, payee)?.ok_or_else(|| format!("No party named '{}'", payee))?,
+    };
+    if !export::pain001::valid_iban(&party.eban) {
+        return Err(format!("Party {} ({}) has no valid IBAN ('{}')", party.id, party.name, party.eban).into());
+    }
+
+    let new_payment = NewPayment {
+        account_id: account.id,
+        party_id: party.id,
+        amount,
+        execution_date,
+        reference: reference.to_string(),
+        end_to_end_id: format!("FW{}", uuid::Uuid::new_v4().simple()).to_uppercase(),
+    };
+    let payment = payment_service::create_payment(&mut conn, &new_payment)?;
+    println!(
+        "✅ Payment {}: {} to {} ({}) on {}, pending until exported with 'payments export' and booked",
+        payment.id, payment.amount, party.name, party.eban, payment.execution_date
+    );
+    Ok(())
+}
+
+pub async fn list_payments(db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let stored = payment_service::list_payments(&mut conn)?;
+    if stored.is_empty() {
+        println!("No payments prepared yet. Add one with 'payments add'.");
+        return Ok(());
+    }
+
+    println!("{:>6}  {:<10}  {:>7}  {:<24}  {:>12}  Status", "ID", "Date", "Account", "Payee", "Amount");
+    for payment in stored {
+        let payee = party_service::get_party_by_id(&mut conn, payment.party_id)?;
+        let status = match (payment.transaction_id, &payment.message_id) {
+            (Some(transaction_id), _) => format!("booked as transaction {}", transaction_id),
+            (None, Some(message_id)) => format!("pending, exported in {}", message_id),
+            (None, None) => format!("pending since {}", payment.created_at.format("%Y-%m-%d")),
+        };
+        println!(
+            "{:>6}  {:<10}  {:>7}  {:<24}  {:>12}  {}",
+            payment.id,
+            payment.execution_date.format("%Y-%m-%d"),
+            payment.account_id,
+            payee.name,
+            payment.amount,
+            status
+        );
+    }
+    Ok(())
+}
+
+/// Writes pending payments to a pain.001 file for upload to online banking.
+pub async fn export_payments(output: &str, account_id: Option<i32>, again: bool, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let pending = payment_service::pending_payments(&mut conn, account_id, again)?;
+    if pending.is_empty() {
+        println!("No pending payments to export{}.", if again { "" } else { " (pass --again to include ones already exported)" });
+        return Ok(());
+    }
+
+    let created = chrono::Local::now().naive_local();
+    let message_id = format!("FINWISE-{}", created.format("%Y%m%d%H%M%S"));
+    let transfers = export_service::credit_transfers(&mut conn, &pending)?;
+    let content = export::pain001::write(&message_id, created, &transfers)?;
+    fs::write(output, content)?;
+    let payment_ids: Vec<i32> = pending.iter().map(|payment| payment.id).collect();
+    payment_service::mark_exported(&mut conn, &payment_ids, &message_id)?;
+
+    let total = pending.iter().fold(BigDecimal::from(0), |total, payment| total + &payment.amount);
+    println!("✅ Wrote {} payments over {} EUR to {} (message {})", pending.len(), total, output, message_id);
+    println!("They are settled when their bookings are imported.");
+    Ok(())
+}
+
+pub async fn cancel_payment(payment_id: i32, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    if payment_service::cancel_payment(&mut conn, payment_id)? == 0 {
+        return Err(format!("Payment {} does not exist or is already booked", payment_id).into());
+    }
+    println!("✅ Cancelled payment {}", payment_id);
+    Ok(())
+}
+
+/// Writes an encrypted archive of every table and the files under `~/FinWise/`.
+pub async fn backup_data(output: Option<&str>, config: &BackupConfig, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let passphrase = read_passphrase(config, true)?;
+    let backup_dir = backup_dir(config)?;
+    let path = match output {
+        Some(output) => PathBuf::from(output),
+        None => backup_dir.join(schedule::file_name(chrono::Local::now().naive_local())),
+    };
+    let mut conn = db_pool.get_connection()?;
+    let snapshot = write_backup(&mut conn, &path, &backup_dir, &passphrase)?;
+
+    let rows: usize = snapshot.tables.iter().map(|table| table.row_count).sum();
+    println!("✅ Backed up {} rows from {} tables and {} files to {}", rows, snapshot.tables.len(), snapshot.files.len(), path.display());
+    println!("   Schema version {}, verified by reading it back", snapshot.migrations.last().map(String::as_str).unwrap_or("none"));
+    println!("🔐 Keep the passphrase safe: the backup cannot be restored without it.");
+    Ok(())
+}
+
+/// Writes a backup into the backup directory whenever the schedule says one is
+/// due, then deletes the backups the retention rules no longer keep. Failed
+/// runs are retried every `interval_secs`.
+pub async fn schedule_backups(interval_secs: u64, config: &BackupConfig, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    if config.passphrase.is_none() {
+        return Err("Scheduled backups need their passphrase in BACKUP_PASSPHRASE".into());
+    }
+    let passphrase = read_passphrase(config, true)?;
+    let schedule = Schedule::named(&config.schedule)?;
+    let backup_dir = backup_dir(config)?;
+
+    println!("🕒 Writing {} backups to {} (Ctrl-C to stop)", schedule, backup_dir.display());
+    loop {
+        let now = chrono::Local::now().naive_local();
+        let latest = schedule::list_backups(&backup_dir)?.first().map(|backup| backup.created_at);
+        if latest.is_none_or(|latest| now >= schedule.next_due(latest)) {
+            let path = backup_dir.join(schedule::file_name(now));
+            let mut status = RunStatus::load(&backup_dir).unwrap_or_default();
+            status.last_run = Some(now);
+            match run_scheduled_backup(&db_pool, &path, &backup_dir, &passphrase, retention(config)) {
+                Ok(rotated) => {
+                    info!("Scheduled backup written to {}", path.display());
+                    status.last_success = Some(now);
+                    status.last_error = None;
+                    println!("✅ {}", path.display());
+                    if rotated > 0 {
+                        println!("↻ Deleted {} older backups past retention", rotated);
+                    }
+                }
+                Err(e) => {
+                    error!("Scheduled backup failed: {}", e);
+                    // Never leave a backup behind that did not verify
+                    let _ = fs::remove_file(&path);
+                    status.last_error = Some(e.to_string());
+                    println!("❌ Backup failed: {}", e);
+                }
+            }
+            status.save(&backup_dir)?;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(interval_secs)) => {}
+            _ = tokio::signal::ctrl_c() => {
+                println!("Stopped scheduled backups");
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Writes one scheduled backup and rotates the directory; returns how many backups were deleted.
+fn run_scheduled_backup(
+    db_pool: &DatabasePool,
+    path: &Path,
+    backup_dir: &Path,
+    passphrase: &str,
+    retention: Retention,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    write_backup(&mut conn, path, backup_dir, passphrase)?;
+    let backups = schedule::list_backups(backup_dir)?;
+    let expired = retention.expired(&backups);
+    for backup in &expired {
+        info!("Deleting backup {} past retention", backup.path.display());
+        fs::remove_file(&backup.path)?;
+    }
+    Ok(expired.len())
+}
+
+/// Shows the backups in the backup directory and how the scheduled runs went.
+pub fn backup_status(config: &BackupConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let backup_dir = backup_dir(config)?;
+    let schedule = Schedule::named(&config.schedule)?;
+    let retention = retention(config);
+    let backups = schedule::list_backups(&backup_dir)?;
+    let status = RunStatus::load(&backup_dir)?;
+    let now = chrono::Local::now().naive_local();
+
+    println!("Backups in {}", backup_dir.display());
+    println!(
+        "  Schedule:  {}, keeping {} daily, {} weekly and {} monthly",
+        schedule, retention.daily, retention.weekly, retention.monthly
+    );
+    match (backups.first(), backups.last()) {
+        (Some(latest), Some(oldest)) => {
+            let total: u64 = backups.iter().map(|backup| backup.size).sum();
+            println!("  Backups:   {} ({}), oldest from {}", backups.len(), HumanBytes(total), oldest.created_at.format("%Y-%m-%d %H:%M"));
+            let age = (now - latest.created_at).to_std().unwrap_or_default();
+            println!("  Latest:    {} ({} ago, {})", latest.created_at.format("%Y-%m-%d %H:%M"), HumanDuration(age), HumanBytes(latest.size));
+            let due = schedule.next_due(latest.created_at);
+            if now >= due {
+                println!("  ⚠️  Overdue since {}", due.format("%Y-%m-%d %H:%M"));
+            } else {
+                println!("  Next due:  {}", due.format("%Y-%m-%d %H:%M"));
+            }
+        }
+        _ => println!("  ⚠️  No backups yet: run 'backup', or 'backup --schedule' to keep writing them"),
+    }
+    if let Some(last_run) = status.last_run {
+        match &status.last_error {
+            Some(error) => println!("  ❌ Last scheduled run at {} failed: {}", last_run.format("%Y-%m-%d %H:%M"), error),
+            None => println!("  Last run:  {} ✅", last_run.format("%Y-%m-%d %H:%M")),
+        }
+    }
+    let expired = retention.expired(&backups).len();
+    if expired > 0 {
+        println!("  ℹ️  {} backups are past retention and are deleted at the next scheduled run", expired);
+    }
+    Ok(())
+}
+
+/// The configured backup directory, created if needed.
+fn backup_dir(config: &BackupConfig) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = match &config.dir {
+        Some(dir) => PathBuf::from(dir),
+        None => get_finwise_data_dir()?.join(backup::BACKUP_DIR),
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create backup directory {}: {}", dir.display(), e))?;
+    Ok(dir)
+}
+
+fn retention(config: &BackupConfig) -> Retention {
+    Retention { daily: config.keep_daily, weekly: config.keep_weekly, monthly: config.keep_monthly }
+}
+
+/// Takes a snapshot of the database and data directory, writes it sealed to
+/// `path` and checks that it reads back the same.
+fn write_backup(conn: &mut PgConnection, path: &Path, backup_dir: &Path, passphrase: &str) -> Result<backup::Snapshot, Box<dyn std::error::Error>> {
+    let unknown = backup_service::unknown_tables(conn)?;
+    if !unknown.is_empty() {
+        return Err(format!("The database has tables this FinWise cannot back up: {}", unknown.join(", ")).into());
+    }
+    let snapshot = backup::Snapshot {
+        format: backup::SNAPSHOT_FORMAT,
+        created_at: chrono::Local::now().naive_local(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        migrations: backup_service::applied_migrations(conn)?,
+        tables: backup_service::dump_tables(conn)?,
+        files: backup::collect_files(&get_finwise_data_dir()?, backup_dir)?,
+    };
+    let archive = backup::archive::seal(&snapshot, passphrase)?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(&archive)?;
+    file.sync_all()?;
+    backup::archive::verify(path, passphrase, &snapshot)?;
+    Ok(snapshot)
+}
+
+/// Restores an archive written by `backup_data`.
+///
+/// An empty database is first migrated to the backup's schema version and
+/// brought up to date afterwards; otherwise the database must be at exactly
+/// that version. Existing data is only overwritten with `replace`.
+pub async fn restore_data(path: &str, replace: bool, config: &BackupConfig, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let archive = fs::read(path).map_err(|e| format!("Cannot read backup '{}': {}", path, e))?;
+    let passphrase = read_passphrase(config, false)?;
+    let snapshot = backup::archive::open(&archive, &passphrase)?;
+    let backup_version = snapshot.migrations.last().map(String::as_str).unwrap_or("none");
+    println!(
+        "🔓 Backup of {} by FinWise {} (schema version {})",
+        snapshot.created_at.format("%Y-%m-%d %H:%M"),
+        snapshot.app_version,
+        backup_version
+    );
+
+    let known = backup_service::known_migrations()?;
+    if let Some(unknown) = snapshot.migrations.iter().find(|version| !known.contains(version)) {
+        return Err(format!("The backup needs database migration {}, which this FinWise does not have; update FinWise first", unknown).into());
+    }
+    if let Some(table) = snapshot.tables.iter().find(|table| !backup_service::TABLES.contains(&table.name.as_str())) {
+        return Err(format!("The backup holds unknown table '{}'", table.name).into());
+    }
+
+    let mut conn = db_pool.get_connection()?;
+    let applied = backup_service::applied_migrations(&mut conn)?;
+    let fresh = applied.is_empty();
+    if fresh {
+        let created = backup_service::migrate_to(&mut conn, backup_version)?;
+        println!("ℹ️  Empty database: created the schema with {} migrations", created.len());
+    } else if applied != snapshot.migrations {
+        return Err(format!(
+            "The database is at schema version {}, the backup at {}; restore into an empty database instead",
+            applied.last().map(String::as_str).unwrap_or("none"),
+            backup_version
+        )
+        .into());
+    }
+    if !replace && !backup_service::is_empty(&mut conn)? {
+        return Err("The database already holds data; pass --replace to overwrite it with the backup".into());
+    }
+
+    let rows = backup_service::restore_tables(&mut conn, &snapshot.tables, replace)?;
+    let files = backup::restore_files(&get_finwise_data_dir()?, &snapshot.files)?;
+    println!("✅ Restored {} rows into {} tables and {} files", rows, snapshot.tables.len(), files);
+    if fresh {
+        let upgraded = backup_service::migrate_all(&mut conn)?;
+        if !upgraded.is_empty() {
+            println!("↻ Applied {} newer migrations: {}", upgraded.len(), upgraded.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// The configured backup passphrase, or one typed on the terminal. A new
+/// backup's passphrase is typed twice and must have at least 8 characters.
+fn read_passphrase(config: &BackupConfig, new: bool) -> Result<String, Box<dyn std::error::Error>> {
+    let passphrase = match &config.passphrase {
+        Some(passphrase) => passphrase.clone(),
+        None => {
+            let passphrase = rpassword::prompt_password("Backup passphrase: ")?;
+            if new && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+                return Err("The passphrases do not match".into());
+            }
+            passphrase
+        }
+    };
+    if new && passphrase.chars().count() < 8 {
+        return Err("The backup passphrase must have at least 8 characters".into());
+    }
+    Ok(passphrase)
+}
+
+pub async fn undo_import(batch_id: i32, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let batch = import_batch_service::get_batch(&mut conn, batch_id)
+        .map_err(|e| format!("Import batch {} not found: {}", batch_id, e))?;
+    if let Some(reverted) = batch.reverted_at {
+        return Err(format!("Import batch {} was already undone on {}", batch_id, reverted.format("%Y-%m-%d %H:%M")).into());
+    }
+    let linking = import_batch_service::linking_batches(&mut conn, batch.id)?;
+    if !linking.is_empty() {
+        let ids: Vec<String> = linking.iter().map(|id| id.to_string()).collect();
+        return Err(format!(
+            "Batch {} holds transfers that batch(es) {} matched from the other account; undo those first",
+            batch.id,
+            ids.join(", ")
+        )
+        .into());
+    }
+
+    info!("Undoing import batch {} ({})", batch.id, batch.file_name);
+    let summary = import_batch_service::undo_batch(&mut conn, batch.id)?;
+
+    println!("Undid import batch {} ({}):", batch.id, batch.file_name);
+    println!("  Transactions deleted: {}", summary.transactions_deleted);
+    println!("  Trades deleted:       {}", summary.trades_deleted);
+    println!("  Transfers unlinked:   {}", summary.transfers_unlinked);
+    println!("  Receipts deleted:     {}", summary.receipts_deleted);
+    println!("  Accounts deleted:     {}", summary.accounts_deleted);
+    println!("  Parties deleted:      {}", summary.parties_deleted);
+    if summary.accounts_kept + summary.parties_kept > 0 {
+        println!(
+            "  ⚠️  Kept {} account(s) and {} party(ies) created by this batch that later data still refers to",
+            summary.accounts_kept, summary.parties_kept
+        );
+    }
+    Ok(())
+}
+
+/// Compares the balances the bank reported with the account balance before and after import.
+fn print_reconciliation(statement: &Statement, summary: &import_service::ImportSummary, out: &mut dyn Write) -> io::Result<()> {
+    let currency = statement.currency.as_deref().unwrap_or("");
+    if let Some(reported) = &statement.opening_balance {
+        print_balance_check("Opening", reported, currency, &summary.opening_balance, out)?;
+    }
+    if let Some(reported) = &statement.closing_balance {
+        print_balance_check("Closing", reported, currency, &summary.balance, out)?;
+    }
+    Ok(())
+}
+
+fn print_balance_check(label: &str, reported: &Balance, currency: &str, balance: &BigDecimal, out: &mut dyn Write) -> io::Result<()> {
+    if &reported.amount == balance {
+        writeln!(out, "  ✅ {} balance matches bank balance {} {} as of {}", label, reported.amount, currency, reported.date.date())
+    } else {
+        warn!("{} balance mismatch: bank reports {}, FinWise has {}", label, reported.amount, balance);
+        writeln!(
+            out,
+            "  ⚠️  {} balance mismatch: bank reports {} {} as of {}, FinWise has {} (difference {})",
+            label,
+            reported.amount,
+            currency,
+            reported.date.date(),
+            balance,
+            &reported.amount - balance
+        )
+    }
+}
+
+/// Picks the account an import is booked to: the explicit `--account`, the
+/// account whose owning party has the statement's IBAN, or, for files that
+/// only name their accounts (QIF), the account of the party with that name.
+/// Named accounts that do not exist yet are created with the file's opening balance.
+fn resolve_import_account(
+    conn: &mut crate::utils::db::DbConnection,
+    account_id: Option<i32>,
+    statement: &Statement,
+    batch_id: i32,
+    out: &mut dyn Write,
+) -> Result<Account, Box<dyn std::error::Error>> {
+    if let Some(account) = find_import_account(conn, account_id, statement)? {
+        return Ok(account);
+    }
+
+    let name = statement.account_name.as_deref().ok_or("The file does not name its account; pass --account ID")?;
+    let owner = match party_service::find_party_by_name(conn, name)? {
+        Some(party) => party,
+        None => party_service::create_party(conn, name, "", "", 0, "", Some(batch_id))?,
+    };
+    let initial_balance = statement
+        .opening_balance
+        .as_ref()
+        .map(|balance| balance.amount.clone())
+        .unwrap_or_else(|| BigDecimal::from(0));
+    info!("Creating account '{}' with opening balance {}", name, initial_balance);
+    writeln!(out, "➕ Created account '{}'", name)?;
+    Ok(account_service::create_account(conn, owner.id, initial_balance, Some(batch_id))?)
+}
+
+/// Looks up the account an import is booked to without creating anything.
+///
+/// Returns `None` only for a named account that does not exist yet.
+fn find_import_account(
+    conn: &mut crate::utils::db::DbConnection,
+    account_id: Option<i32>,
+    statement: &Statement,
+) -> Result<Option<Account>, Box<dyn std::error::Error>> {
+    if let Some(account_id) = account_id {
+        return account_service::get_account(conn, account_id)
+            .map(Some)
+            .map_err(|e| format!("Account {} not found: {}", account_id, e).into());
+    }
+
+    if let Some(iban) = statement.account_iban.as_deref() {
+        let owner = party_service::find_party_by_eban(conn, iban)?
+            .ok_or_else(|| format!("No party with IBAN {}; pass --account ID", iban))?;
+        return account_service::get_accounts_by_party(conn, owner.id)?
+            .into_iter()
+            .next()
+            .map(Some)
+            .ok_or_else(|| format!("Party '{}' has no account; pass --account ID", owner.name).into());
+    }
+
+    let name = statement.account_name.as_deref().ok_or("The file does not name its account; pass --account ID")?;
+    match party_service::find_party_by_name(conn, name)? {
+        Some(owner) => Ok(account_service::get_accounts_by_party(conn, owner.id)?.into_iter().next()),
+        None => Ok(None),
+    }
+}
+
+/// Parses a file and reports, per row, what `import_data` would do with it.
+///
+/// Only reads from the database: accounts, parties and transactions are looked up, never created.
+pub async fn preview_import(
+    file_path: &str,
+    profile_name: Option<&str>,
+    locale_name: Option<&str>,
+    account_id: Option<i32>,
+    json: bool,
+    match_threshold: f64,
+    db_pool: Arc<DatabasePool>
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Previewing import of: {}", file_path);
+
+    let options = ImportOptions {
+        profile: profile_name.map(profile::load_profile).transpose()?,
+        locale: locale_name.map(Locale::named).transpose()?,
+    };
+    let statements = import::parse_file(Path::new(file_path), &options)?;
+    if account_id.is_some() && statements.len() > 1 {
+        return Err(format!("{} contains {} accounts; --account can only be used for single-account files", file_path, statements.len()).into());
+    }
+
+    let mut conn = db_pool.get_connection()?;
+    let mut reports = Vec::new();
+    for statement in &statements {
+        let account = find_import_account(&mut conn, account_id, statement)?;
+        let preview = import_service::preview_statement(&mut conn, account.as_ref(), statement, match_threshold)?;
+        let account_label = match (&account, &statement.account_name) {
+            (Some(account), _) => format!("{}", account.id),
+            (None, Some(name)) => format!("new account '{}'", name),
+            (None, None) => "new account".to_string(),
+        };
+        reports.push(serde_json::json!({
+            "account": account_label,
+            "account_id": account.as_ref().map(|account| account.id),
+            "rows": preview.rows,
+            "new_parties": preview.new_parties,
+        }));
+
+        if !json {
+            print_preview(&account_label, &preview);
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else {
+        println!("Dry run for file: {} (nothing was written)", file_path);
+    }
+    Ok(())
+}
+
+fn print_preview(account_label: &str, preview: &import_service::ImportPreview) {
+    use import_service::RowAction;
+
+    println!("Account {}:", account_label);
+    println!("  {:>6}  {:<10}  {:>12}  {:<30}  {:<10}  Action", "Line", "Date", "Amount", "Counterparty", "Party");
+    for row in &preview.rows {
+        let party = match (row.party_id, row.new_party) {
+            (Some(party_id), _) => format!("#{}", party_id),
+            (None, true) => "new".to_string(),
+            (None, false) => String::new(),
+        };
+        let action = match row.action {
+            RowAction::Create => "create",
+            RowAction::Duplicate => "duplicate",
+            RowAction::LinkTransfer => "link transfer",
+            RowAction::Rejected => "rejected",
+            RowAction::Ignored => "ignored",
+        };
+        let details = row.message.as_ref().map(|message| format!(" ({})", message)).unwrap_or_default();
+        println!(
+            "  {:>6}  {:<10}  {:>12}  {:<30}  {:<10}  {}{}",
+            row.line,
+            row.date.map(|date| date.date().to_string()).unwrap_or_default(),
+            row.amount.as_deref().unwrap_or(""),
+            truncate(row.counterparty.as_deref().unwrap_or(""), 30),
+            party,
+            action,
+            details
+        );
+    }
+    println!("  Would create:      {}", preview.count(RowAction::Create));
+    println!("  Duplicates:        {}", preview.count(RowAction::Duplicate));
+    println!("  Transfers linked:  {}", preview.count(RowAction::LinkTransfer));
+    println!("  Rows rejected:     {}", preview.count(RowAction::Rejected));
+    println!("  Rows ignored:      {}", preview.count(RowAction::Ignored));
+    println!("  New parties:       {}", preview.new_parties.len());
+    for name in &preview.new_parties {
+        println!("    + {}", name);
+    }
+}
+
+fn truncate(value: &str, width: usize) -> String {
+    if value.chars().count() <= width {
+        value.to_string()
+    } else {
+        let mut shortened: String = value.chars().take(width - 1).collect();
+        shortened.push('…');
+        shortened
+    }
+}
+
+pub async fn export_data(
+    format: &str,
+    output: Option<&str>,
+    commodity: &str,
+    filter: &ExportFilter,
+    db_pool: Arc<DatabasePool>
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Exporting data as {}", format);
+
+    let dialect = match format {
+        "ledger" => Some(export::journal::Dialect::Ledger),
+        "hledger" => Some(export::journal::Dialect::Hledger),
+        "beancount" => Some(export::journal::Dialect::Beancount),
+        _ => None,
+    };
+    let table_format = match format {
+        "csv" => Some(export::table::Format::Csv),
+        "json" => Some(export::table::Format::Json),
+        "xlsx" => Some(export::table::Format::Xlsx),
+        _ => None,
+    };
+    // A journal's opening balances assume it holds every transaction
+    if dialect.is_some() && !filter.is_empty() {
+        return Err("Filters apply to csv, json, xlsx and ofx exports; journals always hold every transaction".into());
+    }
+    if table_format == Some(export::table::Format::Xlsx) && output.is_none() {
+        return Err("XLSX export needs a file to write; pass --output FILE".into());
+    }
+
+    let mut conn = db_pool.get_connection()?;
+    let (mut accounts, mut transactions) = export_service::load_all(&mut conn, filter)?;
+    if let Some(account_id) = filter.account_id {
+        accounts.retain(|account| account.id == account_id);
+    }
+    // Payments awaiting their booking are listed too, but only tables can mark them pending
+    if table_format.is_some() {
+        transactions.extend(export_service::pending_transactions(&mut conn, filter)?);
+        transactions.sort_by_key(|transaction| transaction.date);
+    }
+    let content = match (dialect, table_format) {
+        (Some(dialect), _) => export::journal::write(dialect, &accounts, &transactions, commodity).into_bytes(),
+        (None, Some(table_format)) => export::table::write(table_format, &transactions)?,
+        (None, None) if format == "ofx" => {
+            for iban in export::ofx::shortened_ibans(&accounts, &transactions) {
+                warn!("IBAN {} is longer than OFX account numbers may be; it is shortened and will not match on import", iban);
+            }
+            export::ofx::write(&accounts, &transactions, commodity, filter).into_bytes()
+        }
+        (None, None) => return Err(format!("Unsupported export format: {}", format).into()),
+    };
+
+    match output {
+        Some(path) => {
+            fs::write(path, content)?;
+            if table_format.is_none() {
+                println!("✅ Exported {} transactions of {} accounts to {}", transactions.len(), accounts.len(), path);
+            } else {
+                println!("✅ Exported {} transactions to {}", transactions.len(), path);
+            }
+        }
+        None => io::stdout().write_all(&content)?,
+    }
+    Ok(())
+}
+
+pub fn list_profiles() -> Result<(), Box<dyn std::error::Error>> {
+    let names = profile::list_profiles()?;
+    if names.is_empty() {
+        println!("No import profiles yet. Create one with 'profile init NAME'.");
+    }
+    for name in names {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+pub fn show_profile(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let profile = profile::load_profile(name)?;
+    println!("{}", serde_json::to_string_pretty(&profile)?);
+    Ok(())
+}
+
+pub fn init_profile(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if profile::list_profiles()?.iter().any(|existing| existing == name) {
+        return Err(format!("Profile '{}' already exists", name).into());
+    }
+    let path = profile::save_profile(&CsvProfile::template(name))?;
+    println!("✅ Created profile template: {}", path.display());
+    println!("Edit the column names, delimiter, encoding and date format to match your bank's export.");
+    Ok(())
+}
+
+/// Fetches new bookings of every linked account from its bank connector.
+///
+/// Each account's bookings are stored like an imported statement, as an
+/// import batch that can be undone, with the same duplicate detection. The
+/// connector's cursor is saved in the same database transaction, so the next
+/// sync continues where this one stopped; `full` fetches everything again and
+/// relies on duplicate detection instead, e.g. after undoing a sync.
 pub async fn sync_accounts(
+    full: bool,
+    match_threshold: f64,
     db_pool: Arc<DatabasePool>
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("Syncing accounts with financial institutions");
-    
-    let mut _conn = db_pool.get_connection()?;
-    // let account_service = AccountService::new();
-    
-    // TODO: Implement account synchronization
+
+    let mut conn = db_pool.get_connection()?;
+    let links = account_link_service::list_links(&mut conn)?;
+    if links.is_empty() {
+        println!("No accounts are linked to a bank yet; link one with 'sync link ACCOUNT --connector NAME --remote ID'");
+        return Ok(());
+    }
+
+    let registry = ConnectorRegistry::builtin();
+    let mut failed = 0;
+    for link in &links {
+        let mut out = io::stdout();
+        if let Err(e) = sync_account(&mut conn, &registry, link, full, match_threshold, &mut out) {
+            error!("Sync of account {} failed: {}", link.account_id, e);
+            println!("❌ Account {} ({} {}): {}", link.account_id, link.connector, link.remote_account_id, e);
+            account_link_service::record_error(&mut conn, link.id, &e.to_string())?;
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} linked accounts failed to sync", failed, links.len()).into());
+    }
     println!("Sync completed");
     Ok(())
 }
 
+fn sync_account(
+    conn: &mut crate::utils::db::DbConnection,
+    registry: &ConnectorRegistry,
+    link: &AccountLink,
+    full: bool,
+    match_threshold: f64,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let settings: ConnectorSettings = serde_json::from_str(&link.settings)?;
+    let mut connector = registry.create(&link.connector, &settings)?;
+    let remote = connector
+        .list_accounts()?
+        .into_iter()
+        .find(|remote| remote.id == link.remote_account_id)
+        .ok_or_else(|| format!("The {} connector no longer lists account '{}'", link.connector, link.remote_account_id))?;
+    let account = account_service::get_account(conn, link.account_id)?;
+
+    let start = if full { None } else { link.sync_cursor.clone() };
+    let (lines, cursor) = sync::fetch_since(connector.as_mut(), &link.remote_account_id, start.as_deref())?;
+    let mut statement = Statement { account_iban: remote.iban, currency: remote.currency, lines, ..Statement::default() };
+    statement.closing_balance = Some(connector.fetch_balance(&link.remote_account_id)?);
+
+    writeln!(out, "↻ Account {} from {} '{}': {} bookings fetched", account.id, link.connector, remote.name, statement.lines.len())?;
+    let synced_at = chrono::Local::now().naive_local();
+    if statement.lines.is_empty() {
+        account_link_service::record_sync(conn, link.id, Some(&cursor), synced_at)?;
+        if let Some(reported) = &statement.closing_balance {
+            print_balance_check("Closing", reported, statement.currency.as_deref().unwrap_or(""), &account.balance, out)?;
+        }
+        return Ok(());
+    }
+
+    let run = format!("{}\u{1f}{}\u{1f}{}\u{1f}{}", link.connector, link.remote_account_id, start.as_deref().unwrap_or(""), cursor);
+    let run_hash = hex::encode(Sha256::digest(run.as_bytes()));
+    let (batch, summary) = conn.transaction(|conn| -> Result<_, Box<dyn std::error::Error>> {
+        let batch = import_batch_service::create_batch(conn, &format!("{} {}", link.connector, link.remote_account_id), &run_hash, "sync")?;
+        import_batch_service::record_sync_start(conn, batch.id, link.id, link.sync_cursor.as_deref())?;
+        let summary = import_service::import_statement(conn, &account, &statement, batch.id, match_threshold, &mut AcceptBestMatch)?;
+        account_link_service::record_sync(conn, link.id, Some(&cursor), synced_at)?;
+        Ok((batch, summary))
+    })?;
+    print_import_summary(account.id, &summary, false, 0, out)?;
+    print_reconciliation(&statement, &summary, out)?;
+    writeln!(out, "  Recorded as batch {} (undo with 'import undo {}'; the next sync fetches its bookings again)", batch.id, batch.id)?;
+    Ok(())
+}
+
+/// Links an account to a bank connector's account for `sync`.
+pub async fn link_account(
+    account_id: i32,
+    connector: &str,
+    remote_account_id: &str,
+    settings: &[&str],
+    db_pool: Arc<DatabasePool>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = parse_connector_settings(settings)?;
+    let remote = ConnectorRegistry::builtin()
+        .create(connector, &settings)?
+        .list_accounts()?
+        .into_iter()
+        .find(|remote| remote.id == remote_account_id)
+        .ok_or_else(|| format!("The {} connector has no account '{}'; see 'sync remote --connector {}'", connector, remote_account_id, connector))?;
+
+    let mut conn = db_pool.get_connection()?;
+    account_service::get_account(&mut conn, account_id).map_err(|e| format!("Account {} not found: {}", account_id, e))?;
+    if let Some(existing) = account_link_service::find_by_account(&mut conn, account_id)? {
+        return Err(format!("Account {} is already linked to {} '{}'; unlink it first", account_id, existing.connector, existing.remote_account_id).into());
+    }
+    let link = NewAccountLink {
+        account_id,
+        connector: connector.to_string(),
+        remote_account_id: remote.id,
+        settings: serde_json::to_string(&settings)?,
+    };
+    account_link_service::create_link(&mut conn, &link)?;
+    println!("✅ Linked account {} to {} '{}'; 'sync' fetches its bookings", account_id, connector, remote.name);
+    Ok(())
+}
+
+pub async fn unlink_account(account_id: i32, db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    if account_link_service::delete_link(&mut conn, account_id)? == 0 {
+        return Err(format!("Account {} is not linked", account_id).into());
+    }
+    println!("✅ Unlinked account {}; its synced transactions are kept", account_id);
+    Ok(())
+}
+
+pub async fn list_account_links(db_pool: Arc<DatabasePool>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut conn = db_pool.get_connection()?;
+    let links = account_link_service::list_links(&mut conn)?;
+    if links.is_empty() {
+        println!("No linked accounts. Available connectors: {}", ConnectorRegistry::builtin().names().join(", "));
+        return Ok(());
+    }
+    println!("{:>7}  {:<10}  {:<20}  {:<16}  Status", "Account", "Connector", "Remote account", "Last sync");
+    for link in links {
+        let synced = link.synced_at.map(|at| at.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_else(|| "never".to_string());
+        let status = match &link.last_error {
+            Some(error) => format!("❌ {}", error),
+            None => "ok".to_string(),
+        };
+        println!("{:>7}  {:<10}  {:<20}  {:<16}  {}", link.account_id, link.connector, truncate(&link.remote_account_id, 20), synced, status);
+    }
+    Ok(())
+}
+
+/// Lists the accounts a connector gives access to, to find the id to link.
+pub fn list_remote_accounts(connector: &str, settings: &[&str]) -> Result<(), Box<dyn std::error::Error>> {
+    let settings = parse_connector_settings(settings)?;
+    let accounts = ConnectorRegistry::builtin().create(connector, &settings)?.list_accounts()?;
+    if accounts.is_empty() {
+        println!("The {} connector lists no accounts.", connector);
+        return Ok(());
+    }
+    println!("{:<20}  {:<34}  {:<8}  Name", "Id", "IBAN", "Currency");
+    for account in accounts {
+        println!(
+            "{:<20}  {:<34}  {:<8}  {}",
+            truncate(&account.id, 20),
+            account.iban.as_deref().unwrap_or("-"),
+            account.currency.as_deref().unwrap_or("-"),
+            account.name
+        );
+    }
+    Ok(())
+}
+
+/// Reads `KEY=VALUE` connector settings from the command line.
+fn parse_connector_settings(settings: &[&str]) -> Result<ConnectorSettings, Box<dyn std::error::Error>> {
+    settings
+        .iter()
+        .map(|setting| {
+            setting
+                .split_once('=')
+                .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+                .ok_or_else(|| format!("Invalid setting '{}': use KEY=VALUE", setting).into())
+        })
+        .collect()
+}
+
 pub async fn generate_report(
     report_type: &str,
     db_pool: Arc<DatabasePool>
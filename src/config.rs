@@ -1,11 +1,14 @@
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::num::NonZeroUsize;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
     pub database: DatabaseConfig,
     pub server: ServerConfig,
     pub logging: LoggingConfig,
+    pub import: ImportConfig,
+    pub backup: BackupConfig,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -27,6 +30,32 @@ pub struct LoggingConfig {
     pub file_path: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ImportConfig {
+    /// Minimum name similarity (0-1) for a counterparty to be matched to an existing party.
+    pub match_threshold: f64,
+    /// Receipts read from OCR text with a field less certain than this (0-1) are queued for review.
+    pub ocr_review_threshold: f32,
+    /// Locale (`de-DE`, `en-US`, ...) for imports whose amounts or dates could be read more than one way.
+    pub locale: Option<String>,
+    /// Rows of a streamed CSV import committed per database transaction.
+    pub batch_size: NonZeroUsize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BackupConfig {
+    /// Passphrase for backup archives; asked for on the terminal when unset.
+    pub passphrase: Option<String>,
+    /// Directory backups are written to and rotated in; `~/FinWise/backups/` when unset.
+    pub dir: Option<String>,
+    /// How often scheduled backups run: `daily` or `weekly`.
+    pub schedule: String,
+    /// Scheduled rotation keeps the newest backup of this many recent days, weeks and months.
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+    pub keep_monthly: usize,
+}
+
 impl Config {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Config {
@@ -55,6 +84,34 @@ impl Config {
                     .unwrap_or_else(|_| "info".to_string()),
                 file_path: env::var("LOG_FILE").ok(),
             },
+            import: ImportConfig {
+                match_threshold: env::var("IMPORT_MATCH_THRESHOLD")
+                    .unwrap_or_else(|_| "0.9".to_string())
+                    .parse()?,
+                ocr_review_threshold: env::var("IMPORT_OCR_REVIEW_THRESHOLD")
+                    .unwrap_or_else(|_| "0.8".to_string())
+                    .parse()?,
+                locale: env::var("IMPORT_LOCALE").ok(),
+                batch_size: env::var("IMPORT_BATCH_SIZE")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse()
+                    .map_err(|e| format!("IMPORT_BATCH_SIZE must be a number of rows of at least 1: {}", e))?,
+            },
+            backup: BackupConfig {
+                passphrase: env::var("BACKUP_PASSPHRASE").ok(),
+                dir: env::var("BACKUP_DIR").ok(),
+                schedule: env::var("BACKUP_SCHEDULE")
+                    .unwrap_or_else(|_| "daily".to_string()),
+                keep_daily: env::var("BACKUP_KEEP_DAILY")
+                    .unwrap_or_else(|_| "7".to_string())
+                    .parse()?,
+                keep_weekly: env::var("BACKUP_KEEP_WEEKLY")
+                    .unwrap_or_else(|_| "4".to_string())
+                    .parse()?,
+                keep_monthly: env::var("BACKUP_KEEP_MONTHLY")
+                    .unwrap_or_else(|_| "12".to_string())
+                    .parse()?,
+            },
         })
     }
 }
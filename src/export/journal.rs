@@ -0,0 +1,240 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Duration, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use super::{ExportAccount, ExportParty, ExportTransaction};
+
+/// Plain-text accounting syntax to write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+/// The five account roots beancount accepts; ledger and hledger use them by convention.
+const ROOTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+const OPENING_BALANCES: &str = "Equity:Opening-Balances";
+
+/// One journal transaction: header data and `(account, amount)` postings.
+struct JournalEntry {
+    date: NaiveDate,
+    payee: String,
+    narration: String,
+    postings: Vec<(String, BigDecimal)>,
+    metadata: Vec<(&'static str, String)>,
+}
+
+/// Writes accounts and transactions as a journal in `commodity`.
+///
+/// Accounts named with a hierarchy (`Assets:Bank:Checking`, e.g. from a journal
+/// import) keep their name; other accounts are placed under `Assets:`. The
+/// other side of each transaction is its category, or `Expenses:`/`Income:Uncategorized`.
+/// Each account gets an opening balance entry for the part of its balance not
+/// explained by transactions, so the journal's balances match FinWise's.
+pub fn write(dialect: Dialect, accounts: &[ExportAccount], transactions: &[ExportTransaction], commodity: &str) -> String {
+    let mut entries: Vec<JournalEntry> = Vec::new();
+    let first_date = transactions.first().map(|transaction| transaction.date.date());
+    let last_date = transactions.last().map(|transaction| transaction.date.date());
+
+    let mut net_by_account: BTreeMap<i32, BigDecimal> = BTreeMap::new();
+    for transaction in transactions {
+        if let Some(account_id) = transaction.to.account_id {
+            *net_by_account.entry(account_id).or_insert_with(BigDecimal::zero) += &transaction.amount;
+        }
+        if let Some(account_id) = transaction.from.account_id {
+            *net_by_account.entry(account_id).or_insert_with(BigDecimal::zero) -= &transaction.amount;
+        }
+    }
+    for account in accounts {
+        let opening = &account.balance - net_by_account.get(&account.id).cloned().unwrap_or_else(BigDecimal::zero);
+        if opening.is_zero() {
+            continue;
+        }
+        entries.push(JournalEntry {
+            date: first_date.unwrap_or_else(|| chrono::Local::now().date_naive()),
+            payee: "Opening balance".to_string(),
+            narration: String::new(),
+            postings: vec![(balance_account(&account.name), opening.clone()), (OPENING_BALANCES.to_string(), -opening)],
+            metadata: Vec::new(),
+        });
+    }
+
+    entries.extend(transactions.iter().map(journal_entry));
+
+    let mut output = String::new();
+    if dialect == Dialect::Beancount {
+        write_beancount_header(&mut output, &entries, commodity);
+    }
+    for entry in &entries {
+        write_entry(&mut output, dialect, entry, commodity);
+    }
+    if dialect == Dialect::Beancount {
+        // Assert FinWise's balances the day after the last transaction
+        if let Some(last_date) = last_date {
+            let assertion_date = last_date + Duration::days(1);
+            for account in accounts {
+                let _ = writeln!(
+                    output,
+                    "{} balance {} {} {}",
+                    assertion_date,
+                    account_name(dialect, &balance_account(&account.name)),
+                    account.balance,
+                    commodity
+                );
+            }
+        }
+    }
+    output
+}
+
+fn journal_entry(transaction: &ExportTransaction) -> JournalEntry {
+    let amount = transaction.amount.clone();
+    let (payee, postings) = match (transaction.from.account_id, transaction.to.account_id) {
+        (Some(_), Some(_)) => (
+            "Transfer".to_string(),
+            vec![(balance_account(&transaction.to.name), amount.clone()), (balance_account(&transaction.from.name), -amount)],
+        ),
+        (Some(_), None) => (
+            transaction.to.name.clone(),
+            vec![(counter_account(transaction.category.as_deref(), true), amount.clone()), (balance_account(&transaction.from.name), -amount)],
+        ),
+        (None, Some(_)) => (
+            transaction.from.name.clone(),
+            vec![(balance_account(&transaction.to.name), amount.clone()), (counter_account(transaction.category.as_deref(), false), -amount)],
+        ),
+        (None, None) => (
+            transaction.to.name.clone(),
+            vec![(party_account("Expenses", &transaction.to), amount.clone()), (party_account("Income", &transaction.from), -amount)],
+        ),
+    };
+
+    let mut metadata = vec![("finwise_id", transaction.id.to_string())];
+    if let Some(external_id) = &transaction.external_id {
+        metadata.push(("bank_id", external_id.clone()));
+    }
+    JournalEntry {
+        date: transaction.date.date(),
+        payee,
+        narration: transaction.memo.clone(),
+        postings,
+        metadata,
+    }
+}
+
+/// The journal account of a FinWise account.
+fn balance_account(name: &str) -> String {
+    if has_root(name) {
+        name.to_string()
+    } else {
+        format!("Assets:{}", name)
+    }
+}
+
+/// The journal account for the other side of a booking, from its category.
+fn counter_account(category: Option<&str>, outflow: bool) -> String {
+    let root = if outflow { "Expenses" } else { "Income" };
+    match category {
+        Some(category) if category.starts_with('[') && category.ends_with(']') => balance_account(&category[1..category.len() - 1]),
+        Some(category) if has_root(category) => category.to_string(),
+        Some(category) => format!("{}:{}", root, category),
+        None => format!("{}:Uncategorized", root),
+    }
+}
+
+fn party_account(root: &str, party: &ExportParty) -> String {
+    format!("{}:{}", root, party.name)
+}
+
+fn has_root(name: &str) -> bool {
+    let root = name.split(':').next().unwrap_or("");
+    ROOTS.iter().any(|known| known.eq_ignore_ascii_case(root))
+}
+
+/// Makes an account name valid for the dialect.
+///
+/// beancount components must start with a capital letter or digit and contain
+/// only letters, digits and dashes; ledger names end at two spaces or a tab.
+fn account_name(dialect: Dialect, name: &str) -> String {
+    match dialect {
+        Dialect::Beancount => name
+            .split(':')
+            .map(|component| {
+                let cleaned: String = component
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' })
+                    .collect();
+                let cleaned = cleaned.trim_matches('-');
+                let mut chars = cleaned.chars();
+                match chars.next() {
+                    Some(first) if first.is_ascii_alphanumeric() => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                    _ => format!("X{}", cleaned),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(":"),
+        Dialect::Ledger | Dialect::Hledger => name.replace(['\t', ';'], " ").split("  ").collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn write_beancount_header(output: &mut String, entries: &[JournalEntry], commodity: &str) {
+    let _ = writeln!(output, "option \"operating_currency\" \"{}\"", commodity);
+    let _ = writeln!(output);
+
+    let mut opened: BTreeSet<String> = BTreeSet::new();
+    let open_date = entries.iter().map(|entry| entry.date).min();
+    for entry in entries {
+        for (account, _) in &entry.postings {
+            opened.insert(account_name(Dialect::Beancount, account));
+        }
+    }
+    if let Some(open_date) = open_date {
+        for account in &opened {
+            let _ = writeln!(output, "{} open {} {}", open_date, account, commodity);
+        }
+        let _ = writeln!(output);
+    }
+}
+
+fn write_entry(output: &mut String, dialect: Dialect, entry: &JournalEntry, commodity: &str) {
+    let payee = single_line(&entry.payee);
+    let narration = single_line(&entry.narration);
+    match dialect {
+        Dialect::Beancount => {
+            let _ = writeln!(output, "{} * \"{}\" \"{}\"", entry.date, quote(&payee), quote(&narration));
+            for (key, value) in &entry.metadata {
+                let _ = writeln!(output, "  {}: \"{}\"", key, quote(value));
+            }
+        }
+        Dialect::Hledger if !narration.is_empty() => {
+            let _ = writeln!(output, "{} * {} | {}", entry.date, payee.replace('|', "/"), narration);
+        }
+        Dialect::Ledger | Dialect::Hledger => {
+            let _ = writeln!(output, "{} * {}", entry.date, payee);
+            if dialect == Dialect::Ledger && !narration.is_empty() {
+                let _ = writeln!(output, "    ; {}", narration);
+            }
+        }
+    }
+    if dialect != Dialect::Beancount {
+        for (key, value) in &entry.metadata {
+            let _ = writeln!(output, "    ; {}: {}", key, value);
+        }
+    }
+
+    let indent = if dialect == Dialect::Beancount { "  " } else { "    " };
+    for (account, amount) in &entry.postings {
+        let _ = writeln!(output, "{}{:<50}  {} {}", indent, account_name(dialect, account), amount, commodity);
+    }
+    let _ = writeln!(output);
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ").replace(';', ",")
+}
+
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
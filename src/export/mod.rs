@@ -0,0 +1,64 @@
+pub mod journal;
+pub mod ofx;
+pub mod pain001;
+pub mod table;
+
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// A party as it appears in exports, with the account it owns if any.
+#[derive(Debug, Clone)]
+pub struct ExportParty {
+    pub name: String,
+    pub iban: String,
+    pub bic: String,
+    pub account_id: Option<i32>,
+}
+
+/// A transaction with its party ids resolved, ready to be written out.
+#[derive(Debug, Clone)]
+pub struct ExportTransaction {
+    pub id: i32,
+    pub date: NaiveDateTime,
+    pub amount: BigDecimal,
+    pub from: ExportParty,
+    pub to: ExportParty,
+    pub memo: String,
+    pub category: Option<String>,
+    pub external_id: Option<String>,
+    /// A prepared payment no imported booking has settled yet; `id` and
+    /// `external_id` are then the payment's id and end-to-end id.
+    pub pending: bool,
+}
+
+/// An account with the name and bank details of its owning party and its balance (as of the
+/// export filter's end date, if any).
+#[derive(Debug, Clone)]
+pub struct ExportAccount {
+    pub id: i32,
+    pub name: String,
+    pub iban: String,
+    pub bic: String,
+    pub balance: BigDecimal,
+}
+
+/// Which transactions to export; unset fields do not restrict.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// First day included.
+    pub from: Option<NaiveDate>,
+    /// Last day included.
+    pub to: Option<NaiveDate>,
+    /// Transactions into or out of this account.
+    pub account_id: Option<i32>,
+    /// Part of the name, or the IBAN, of either party.
+    pub party: Option<String>,
+    /// Category, ignoring case.
+    pub category: Option<String>,
+}
+
+impl ExportFilter {
+    pub fn is_empty(&self) -> bool {
+        self.from.is_none() && self.to.is_none() && self.account_id.is_none() && self.party.is_none() && self.category.is_none()
+    }
+}
@@ -0,0 +1,179 @@
+use bigdecimal::{BigDecimal, Signed};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::fmt::Write;
+
+use super::{ExportAccount, ExportFilter, ExportParty, ExportTransaction};
+
+/// OFX limits NAME to 32, MEMO to 255 and ACCTID to 22 characters.
+const NAME_LENGTH: usize = 32;
+const MEMO_LENGTH: usize = 255;
+const ACCOUNT_ID_LENGTH: usize = 22;
+
+/// Stands in for BANKID, which OFX requires, when a party has no BIC.
+const UNKNOWN_BANK: &str = "FINWISE";
+
+/// Writes an OFX 2.2 file with one bank statement per account, in `currency`.
+///
+/// Each statement lists the transactions into or out of its account, signed
+/// from the account's side, and ends with the account's balance as LEDGERBAL:
+/// as of the end of the filter's last day if it has one, else the current
+/// balance. A transfer between two accounts appears in both statements. The
+/// statement period is the filter's date range, or else the span of the
+/// transactions.
+pub fn write(accounts: &[ExportAccount], transactions: &[ExportTransaction], currency: &str, filter: &ExportFilter) -> String {
+    let now = chrono::Local::now().naive_local();
+    let mut output = String::new();
+    let _ = writeln!(output, r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>"#);
+    let _ = writeln!(output, r#"<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE" OLDFILEUID="NONE" NEWFILEUID="NONE"?>"#);
+    let _ = writeln!(output, "<OFX>");
+    let _ = writeln!(output, "<SIGNONMSGSRSV1><SONRS>");
+    write_status(&mut output);
+    let _ = writeln!(output, "<DTSERVER>{}</DTSERVER><LANGUAGE>ENG</LANGUAGE>", ofx_date(now));
+    let _ = writeln!(output, "</SONRS></SIGNONMSGSRSV1>");
+    let _ = writeln!(output, "<BANKMSGSRSV1>");
+    for account in accounts {
+        write_statement(&mut output, account, transactions, currency, filter, now);
+    }
+    let _ = writeln!(output, "</BANKMSGSRSV1>");
+    let _ = writeln!(output, "</OFX>");
+    output
+}
+
+fn write_statement(
+    output: &mut String,
+    account: &ExportAccount,
+    transactions: &[ExportTransaction],
+    currency: &str,
+    filter: &ExportFilter,
+    now: NaiveDateTime,
+) {
+    let own: Vec<&ExportTransaction> = transactions
+        .iter()
+        .filter(|transaction| transaction.from.account_id == Some(account.id) || transaction.to.account_id == Some(account.id))
+        .collect();
+    let end = filter.to.map(end_of_day).or_else(|| own.last().map(|transaction| transaction.date)).unwrap_or(now);
+    let start = filter.from.map(midnight).or_else(|| own.first().map(|transaction| transaction.date)).unwrap_or(end);
+    let balance_date = filter.to.map(end_of_day).unwrap_or(now);
+
+    let _ = writeln!(output, "<STMTTRNRS>");
+    let _ = writeln!(output, "<TRNUID>{}</TRNUID>", account.id);
+    write_status(output);
+    let _ = writeln!(output, "<STMTRS>");
+    let _ = writeln!(output, "<CURDEF>{}</CURDEF>", escape(currency));
+    let _ = writeln!(output, "<BANKACCTFROM>");
+    write_bank_account(output, &account.bic, &account_number(account));
+    let _ = writeln!(output, "</BANKACCTFROM>");
+    let _ = writeln!(output, "<BANKTRANLIST>");
+    let _ = writeln!(output, "<DTSTART>{}</DTSTART><DTEND>{}</DTEND>", ofx_date(start), ofx_date(end));
+    for transaction in own {
+        write_transaction(output, account, transaction);
+    }
+    let _ = writeln!(output, "</BANKTRANLIST>");
+    let _ = writeln!(output, "<LEDGERBAL><BALAMT>{}</BALAMT><DTASOF>{}</DTASOF></LEDGERBAL>", account.balance, ofx_date(balance_date));
+    let _ = writeln!(output, "</STMTRS>");
+    let _ = writeln!(output, "</STMTTRNRS>");
+}
+
+fn write_transaction(output: &mut String, account: &ExportAccount, transaction: &ExportTransaction) {
+    let outgoing = transaction.from.account_id == Some(account.id);
+    let (amount, other): (BigDecimal, &ExportParty) = if outgoing {
+        (-transaction.amount.clone(), &transaction.to)
+    } else {
+        (transaction.amount.clone(), &transaction.from)
+    };
+    let kind = match (other.account_id, amount.is_negative()) {
+        (Some(_), _) => "XFER",
+        (None, true) => "DEBIT",
+        (None, false) => "CREDIT",
+    };
+
+    let _ = writeln!(output, "<STMTTRN>");
+    let _ = writeln!(output, "<TRNTYPE>{}</TRNTYPE>", kind);
+    let _ = writeln!(output, "<DTPOSTED>{}</DTPOSTED>", ofx_date(transaction.date));
+    let _ = writeln!(output, "<TRNAMT>{}</TRNAMT>", amount);
+    let _ = writeln!(output, "<FITID>{}</FITID>", transaction.id);
+    if !other.name.is_empty() {
+        let _ = writeln!(output, "<NAME>{}</NAME>", escape(&shorten(&other.name, NAME_LENGTH)));
+    }
+    if !other.iban.is_empty() {
+        let _ = writeln!(output, "<BANKACCTTO>");
+        write_bank_account(output, &other.bic, &shorten_iban(&other.iban));
+        let _ = writeln!(output, "</BANKACCTTO>");
+    }
+    if !transaction.memo.is_empty() {
+        let _ = writeln!(output, "<MEMO>{}</MEMO>", escape(&shorten(&transaction.memo, MEMO_LENGTH)));
+    }
+    let _ = writeln!(output, "</STMTTRN>");
+}
+
+fn write_status(output: &mut String) {
+    let _ = writeln!(output, "<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>");
+}
+
+/// BANKID holds at most 9 characters; a BIC's first 8 name the bank.
+fn write_bank_account(output: &mut String, bic: &str, number: &str) {
+    let bank: String = if bic.is_empty() { UNKNOWN_BANK.to_string() } else { bic.chars().take(8).collect() };
+    let _ = writeln!(
+        output,
+        "<BANKID>{}</BANKID><ACCTID>{}</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE>",
+        escape(&bank),
+        escape(number)
+    );
+}
+
+/// The account's IBAN, or its id if it has none. FinWise matches the IBAN
+/// when the file is imported again, so this only round-trips for IBANs that
+/// `shorten_iban` leaves whole.
+fn account_number(account: &ExportAccount) -> String {
+    if account.iban.is_empty() {
+        account.id.to_string()
+    } else {
+        shorten_iban(&account.iban)
+    }
+}
+
+/// IBANs of the accounts and counterparties in an export that do not fit
+/// ACCTID, so the written number cannot be matched back to them.
+pub fn shortened_ibans<'a>(accounts: &'a [ExportAccount], transactions: &'a [ExportTransaction]) -> Vec<&'a str> {
+    let mut ibans: Vec<&str> = accounts
+        .iter()
+        .map(|account| account.iban.as_str())
+        .chain(transactions.iter().flat_map(|transaction| [transaction.from.iban.as_str(), transaction.to.iban.as_str()]))
+        .filter(|iban| iban.chars().count() > ACCOUNT_ID_LENGTH)
+        .collect();
+    ibans.sort_unstable();
+    ibans.dedup();
+    ibans
+}
+
+/// An IBAN that fits ACCTID: German and other IBANs of up to 22 characters
+/// stay whole; longer ones lose the country code and check digits, and if
+/// the BBAN is still too long, the leading bank and branch code, keeping the
+/// account number at its end. Shortened numbers do not lead back to the IBAN.
+fn shorten_iban(iban: &str) -> String {
+    if iban.chars().count() <= ACCOUNT_ID_LENGTH {
+        return iban.to_string();
+    }
+    let bban: Vec<char> = iban.chars().skip(4).collect();
+    bban[bban.len().saturating_sub(ACCOUNT_ID_LENGTH)..].iter().collect()
+}
+
+fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).expect("midnight is a valid time")
+}
+
+fn end_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(23, 59, 59).expect("23:59:59 is a valid time")
+}
+
+fn ofx_date(date: NaiveDateTime) -> String {
+    date.format("%Y%m%d%H%M%S").to_string()
+}
+
+fn shorten(value: &str, length: usize) -> String {
+    value.chars().take(length).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
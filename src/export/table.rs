@@ -0,0 +1,141 @@
+use bigdecimal::ToPrimitive;
+use chrono::Datelike;
+use rust_xlsxwriter::{ExcelDateTime, Format as CellFormat, Workbook, XlsxError};
+use serde::Serialize;
+use std::borrow::Cow;
+
+use super::ExportTransaction;
+
+/// Spreadsheet-style file format with one row per transaction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+    Xlsx,
+}
+
+const COLUMNS: [&str; 11] = ["id", "date", "amount", "from", "from_iban", "to", "to_iban", "category", "memo", "bank_id", "status"];
+
+/// One transaction as written out: party names and IBANs instead of ids.
+#[derive(Serialize)]
+struct Row<'a> {
+    id: i32,
+    date: String,
+    /// Kept as text so no precision is lost on the way through a float.
+    amount: String,
+    from: Cow<'a, str>,
+    from_iban: Cow<'a, str>,
+    to: Cow<'a, str>,
+    to_iban: Cow<'a, str>,
+    category: Option<Cow<'a, str>>,
+    memo: Cow<'a, str>,
+    bank_id: Option<Cow<'a, str>>,
+    /// `booked`, or `pending` for a payment still to be settled by a booking.
+    status: &'static str,
+}
+
+impl<'a> Row<'a> {
+    /// `text` is applied to every text field, so CSV rows can be made safe for spreadsheets.
+    fn new(transaction: &'a ExportTransaction, text: fn(&str) -> Cow<'_, str>) -> Self {
+        Row {
+            id: transaction.id,
+            date: transaction.date.format("%Y-%m-%d").to_string(),
+            amount: transaction.amount.to_string(),
+            from: text(&transaction.from.name),
+            from_iban: text(&transaction.from.iban),
+            to: text(&transaction.to.name),
+            to_iban: text(&transaction.to.iban),
+            category: transaction.category.as_deref().map(text),
+            memo: text(&transaction.memo),
+            bank_id: transaction.external_id.as_deref().map(text),
+            status: status(transaction),
+        }
+    }
+}
+
+fn status(transaction: &ExportTransaction) -> &'static str {
+    if transaction.pending { "pending" } else { "booked" }
+}
+
+fn plain_text(value: &str) -> Cow<'_, str> {
+    Cow::Borrowed(value)
+}
+
+/// Text as a spreadsheet should show it after opening a CSV file: a leading
+/// `=`, `+`, `-` or `@` (or tab or carriage return) would make it run as a
+/// formula, so such text gets a `'` in front. XLSX string cells are never
+/// evaluated and keep their text as is.
+fn spreadsheet_text(value: &str) -> Cow<'_, str> {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", value))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Writes transactions with a header row, or as a JSON array of objects with
+/// the same fields. XLSX output is a single sheet with real dates and numbers.
+pub fn write(format: Format, transactions: &[ExportTransaction]) -> Result<Vec<u8>, String> {
+    match format {
+        Format::Csv => write_csv(transactions).map_err(|e| e.to_string()),
+        Format::Json => {
+            let rows: Vec<Row> = transactions.iter().map(|transaction| Row::new(transaction, plain_text)).collect();
+            let mut content = serde_json::to_vec_pretty(&rows).map_err(|e| e.to_string())?;
+            content.push(b'\n');
+            Ok(content)
+        }
+        Format::Xlsx => write_xlsx(transactions).map_err(|e| e.to_string()),
+    }
+}
+
+fn write_csv(transactions: &[ExportTransaction]) -> Result<Vec<u8>, ::csv::Error> {
+    // The header comes from `Row`'s field names, which match COLUMNS
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    if transactions.is_empty() {
+        writer.write_record(COLUMNS)?;
+    }
+    for transaction in transactions {
+        writer.serialize(Row::new(transaction, spreadsheet_text))?;
+    }
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+fn write_xlsx(transactions: &[ExportTransaction]) -> Result<Vec<u8>, XlsxError> {
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet().set_name("Transactions")?;
+    let header = CellFormat::new().set_bold();
+    let date_format = CellFormat::new().set_num_format("yyyy-mm-dd");
+    let amount_format = CellFormat::new().set_num_format("#,##0.00");
+
+    for (column, name) in COLUMNS.iter().enumerate() {
+        sheet.write_string_with_format(0, column as u16, *name, &header)?;
+    }
+    for (index, transaction) in transactions.iter().enumerate() {
+        let row = index as u32 + 1;
+        let date = transaction.date.date();
+        let excel_date = ExcelDateTime::from_ymd(date.year() as u16, date.month() as u8, date.day() as u8)?;
+        sheet.write_number(row, 0, transaction.id)?;
+        sheet.write_datetime_with_format(row, 1, &excel_date, &date_format)?;
+        let amount = transaction.amount.to_f64().ok_or_else(|| {
+            XlsxError::ParameterError(format!("Amount {} of transaction {} is not a spreadsheet number", transaction.amount, transaction.id))
+        })?;
+        sheet.write_number_with_format(row, 2, amount, &amount_format)?;
+        sheet.write_string(row, 3, &transaction.from.name)?;
+        sheet.write_string(row, 4, &transaction.from.iban)?;
+        sheet.write_string(row, 5, &transaction.to.name)?;
+        sheet.write_string(row, 6, &transaction.to.iban)?;
+        if let Some(category) = &transaction.category {
+            sheet.write_string(row, 7, category)?;
+        }
+        sheet.write_string(row, 8, &transaction.memo)?;
+        if let Some(bank_id) = &transaction.external_id {
+            sheet.write_string(row, 9, bank_id)?;
+        }
+        sheet.write_string(row, 10, status(transaction))?;
+    }
+    sheet.autofilter(0, 0, transactions.len() as u32, COLUMNS.len() as u16 - 1)?;
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+
+    workbook.save_to_buffer()
+}
@@ -0,0 +1,289 @@
+use bigdecimal::{BigDecimal, Signed};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::xml::{self, XmlElement};
+use super::{Balance, ImportError, RowError, Statement, StatementLine};
+
+/// Balance type codes for the start of a statement period.
+const OPENING_BALANCE_CODES: [&str; 3] = ["OPBD", "PRCD", "OPAV"];
+/// Balance type codes for the end of a statement period, in order of preference.
+const CLOSING_BALANCE_CODES: [&str; 3] = ["CLBD", "ITBD", "CLAV"];
+
+pub fn parse_file(path: &Path) -> Result<Vec<Statement>, ImportError> {
+    let content = fs::read_to_string(path)?;
+    parse_str(&content)
+}
+
+/// Parses camt.053 statements, camt.052 account reports and camt.054 notifications.
+pub fn parse_str(content: &str) -> Result<Vec<Statement>, ImportError> {
+    let document = xml::parse(content)?;
+    let root = if document.name == "Document" {
+        document.children.first()
+    } else {
+        Some(&document)
+    }
+    .ok_or_else(|| ImportError::Format("Empty ISO 20022 document".to_string()))?;
+
+    let reports: Vec<&XmlElement> = match root.name.as_str() {
+        "BkToCstmrStmt" => root.children_named("Stmt").collect(),
+        "BkToCstmrAcctRpt" => root.children_named("Rpt").collect(),
+        "BkToCstmrDbtCdtNtfctn" => root.children_named("Ntfctn").collect(),
+        other => return Err(ImportError::Format(format!("Not a camt.052/053/054 document: <{}>", other))),
+    };
+
+    Ok(reports.into_iter().map(parse_report).collect())
+}
+
+fn parse_report(report: &XmlElement) -> Statement {
+    let mut statement = Statement {
+        account_iban: report
+            .text_at(&["Acct", "Id", "IBAN"])
+            .or_else(|| report.text_at(&["Acct", "Id", "Othr", "Id"])),
+        currency: report.text_at(&["Acct", "Ccy"]).or_else(|| {
+            report
+                .path(&["Bal", "Amt"])
+                .and_then(|amount| amount.attribute("Ccy"))
+                .map(|currency| currency.to_string())
+        }),
+        opening_balance: find_balance(report, &OPENING_BALANCE_CODES),
+        closing_balance: find_balance(report, &CLOSING_BALANCE_CODES),
+        ..Statement::default()
+    };
+
+    for (index, entry) in report.children_named("Ntry").enumerate() {
+        let line = index + 1;
+        // Pending entries of intraday reports are booked (and reported) again later
+        if let Some(status) = entry_status(entry).filter(|status| status != "BOOK") {
+            statement.ignored.push(RowError { line, message: format!("entry with status {} is not booked yet", status) });
+            continue;
+        }
+        match parse_entry(entry, line) {
+            Ok(lines) => statement.lines.extend(lines),
+            Err(message) => statement.rejected.push(RowError { line, message }),
+        }
+    }
+
+    statement
+}
+
+fn entry_status(entry: &XmlElement) -> Option<String> {
+    // camt.05x.001.08 and later wrap the status code in <Cd>
+    entry.text_at(&["Sts", "Cd"]).or_else(|| entry.text_at(&["Sts"]))
+}
+
+fn find_balance(report: &XmlElement, codes: &[&str]) -> Option<Balance> {
+    codes.iter().find_map(|code| {
+        report.children_named("Bal").find_map(|balance| {
+            let balance_code = balance
+                .text_at(&["Tp", "CdOrPrtry", "Cd"])
+                .or_else(|| balance.text_at(&["Tp", "CdOrPrtry", "Prtry"]))?;
+            if balance_code != *code {
+                return None;
+            }
+            let amount = signed_amount(balance, balance.child("Amt")?).ok()?;
+            let date = parse_date_choice(balance.child("Dt")?).ok()?;
+            Some(Balance { amount, date })
+        })
+    })
+}
+
+/// Turns an entry into one line per transaction detail; batch bookings list
+/// their individual transfers as several `TxDtls`.
+fn parse_entry(entry: &XmlElement, line: usize) -> Result<Vec<StatementLine>, String> {
+    let entry_amount = signed_amount(entry, entry.child("Amt").ok_or("Entry without <Amt>")?)?;
+    let date = entry
+        .child("BookgDt")
+        .or_else(|| entry.child("ValDt"))
+        .ok_or("Entry without booking or value date")
+        .map(parse_date_choice)??;
+    let entry_reference = entry.text_at(&["AcctSvcrRef"]);
+    let entry_info = entry.text_at(&["AddtlNtryInf"]).unwrap_or_default();
+
+    let mut details = Vec::new();
+    for entry_details in entry.children_named("NtryDtls") {
+        details.extend(entry_details.children_named("TxDtls"));
+    }
+
+    if details.len() <= 1 {
+        let mut statement_line = StatementLine {
+            line,
+            date,
+            amount: entry_amount,
+            counterparty: None,
+            counterparty_iban: None,
+            counterparty_bic: None,
+            memo: entry_info.clone(),
+            bank_id: entry_reference,
+            category: None,
+            fee: None,
+            original_amount: None,
+            original_currency: None,
+            funding: false,
+        };
+        if let Some(transaction) = details.first() {
+            apply_details(&mut statement_line, transaction);
+        }
+        return Ok(vec![statement_line]);
+    }
+
+    details
+        .into_iter()
+        .enumerate()
+        .map(|(position, transaction)| {
+            let amount_element = transaction
+                .path(&["AmtDtls", "TxAmt", "Amt"])
+                .or_else(|| transaction.child("Amt"))
+                .ok_or("Batch transaction without amount")?;
+            // Each transfer of a batch carries its own direction; returns inside a
+            // batch booking go the other way than the entry
+            let amount = if transaction.child("CdtDbtInd").is_some() {
+                signed_amount(transaction, amount_element)?
+            } else {
+                signed_amount(entry, amount_element)?
+            };
+            let mut statement_line = StatementLine {
+                line,
+                date,
+                amount,
+                counterparty: None,
+                counterparty_iban: None,
+                counterparty_bic: None,
+                memo: entry_info.clone(),
+                bank_id: entry_reference.as_ref().map(|reference| format!("{}/{}", reference, position + 1)),
+                category: None,
+                fee: None,
+                original_amount: None,
+                original_currency: None,
+                funding: false,
+            };
+            apply_details(&mut statement_line, transaction);
+            Ok(statement_line)
+        })
+        .collect()
+}
+
+/// Fills counterparty, remittance information and references from `TxDtls`.
+fn apply_details(statement_line: &mut StatementLine, transaction: &XmlElement) {
+    // The counterparty is the debtor for money coming in, the creditor for money going out
+    let (party, account, agent) = if !statement_line.amount.is_negative() {
+        ("Dbtr", "DbtrAcct", "DbtrAgt")
+    } else {
+        ("Cdtr", "CdtrAcct", "CdtrAgt")
+    };
+
+    if let Some(parties) = transaction.child("RltdPties") {
+        statement_line.counterparty = parties
+            .text_at(&[party, "Nm"])
+            .or_else(|| parties.text_at(&[party, "Pty", "Nm"]));
+        statement_line.counterparty_iban = parties
+            .text_at(&[account, "Id", "IBAN"])
+            .map(|iban| iban.replace(' ', "").to_uppercase());
+    }
+    if let Some(agents) = transaction.child("RltdAgts") {
+        statement_line.counterparty_bic = agents
+            .text_at(&[agent, "FinInstnId", "BICFI"])
+            .or_else(|| agents.text_at(&[agent, "FinInstnId", "BIC"]));
+    }
+
+    let remittance: Vec<String> = transaction
+        .child("RmtInf")
+        .map(|info| {
+            info.children_named("Ustrd")
+                .map(|text| text.text.trim().to_string())
+                .filter(|text| !text.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !remittance.is_empty() {
+        statement_line.memo = remittance.join(" ");
+    } else if let Some(info) = transaction.text_at(&["AddtlTxInf"]) {
+        statement_line.memo = info;
+    }
+
+    if statement_line.bank_id.is_none() {
+        statement_line.bank_id = transaction
+            .text_at(&["Refs", "AcctSvcrRef"])
+            .or_else(|| transaction.text_at(&["Refs", "EndToEndId"]).filter(|id| id != "NOTPROVIDED"));
+    }
+}
+
+/// Reads an `<Amt>` and applies the sibling `<CdtDbtInd>` (debits are negative).
+fn signed_amount(parent: &XmlElement, amount: &XmlElement) -> Result<BigDecimal, String> {
+    let value = parse_amount(&amount.text)?;
+    match parent.text_at(&["CdtDbtInd"]).as_deref() {
+        Some("DBIT") => Ok(-value),
+        Some("CRDT") => Ok(value),
+        other => Err(format!("Unknown credit/debit indicator {:?}", other)),
+    }
+}
+
+fn parse_amount(value: &str) -> Result<BigDecimal, String> {
+    BigDecimal::from_str(value.trim()).map_err(|_| format!("Invalid amount '{}'", value.trim()))
+}
+
+/// Reads an ISO 20022 `DateAndDateTimeChoice` (`<Dt>` or `<DtTm>`).
+fn parse_date_choice(choice: &XmlElement) -> Result<NaiveDateTime, String> {
+    if let Some(date) = choice.text_at(&["Dt"]) {
+        return NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+            .map_err(|_| format!("Invalid date '{}'", date));
+    }
+    if let Some(date_time) = choice.text_at(&["DtTm"]) {
+        // Drop fractional seconds and zone offsets; bookings are stored as local times
+        let trimmed: String = date_time.chars().take(19).collect();
+        return NaiveDateTime::parse_from_str(&trimmed, "%Y-%m-%dT%H:%M:%S")
+            .map_err(|_| format!("Invalid date-time '{}'", date_time));
+    }
+    Err("Date without <Dt> or <DtTm>".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document><BkToCstmrStmt><Stmt>
+  <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id><Ccy>EUR</Ccy></Acct>
+  <Bal><Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp><Amt Ccy="EUR">100.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2026-10-01</Dt></Dt></Bal>
+  <Bal><Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp><Amt Ccy="EUR">60.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><Dt><Dt>2026-10-02</Dt></Dt></Bal>
+  <Ntry>
+    <Amt Ccy="EUR">40.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts><Cd>BOOK</Cd></Sts>
+    <BookgDt><Dt>2026-10-02</Dt></BookgDt><AcctSvcrRef>B1</AcctSvcrRef>
+    <NtryDtls>
+      <TxDtls><Amt Ccy="EUR">50.00</Amt><CdtDbtInd>DBIT</CdtDbtInd>
+        <RltdPties><Cdtr><Nm>Landlord</Nm></Cdtr></RltdPties></TxDtls>
+      <TxDtls><Amt Ccy="EUR">10.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <RltdPties><Dbtr><Nm>Returned Ltd</Nm></Dbtr></RltdPties></TxDtls>
+    </NtryDtls>
+  </Ntry>
+  <Ntry>
+    <Amt Ccy="EUR">5.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts><Cd>PDNG</Cd></Sts>
+    <BookgDt><Dt>2026-10-02</Dt></BookgDt>
+  </Ntry>
+</Stmt></BkToCstmrStmt></Document>"#;
+
+    #[test]
+    fn signs_batch_transfers_by_their_own_indicator() {
+        let statement = parse_str(STATEMENT).unwrap().remove(0);
+        let amounts: Vec<String> = statement.lines.iter().map(|line| line.amount.to_string()).collect();
+        assert_eq!(amounts, ["-50.00", "10.00"]);
+        assert_eq!(statement.lines[0].counterparty.as_deref(), Some("Landlord"));
+        assert_eq!(statement.lines[1].counterparty.as_deref(), Some("Returned Ltd"));
+        assert_eq!(statement.lines[1].bank_id.as_deref(), Some("B1/2"));
+    }
+
+    #[test]
+    fn reads_booked_balances_and_reports_pending_entries() {
+        let statement = parse_str(STATEMENT).unwrap().remove(0);
+        assert_eq!(statement.opening_balance.unwrap().amount.to_string(), "100.00");
+        let closing = statement.closing_balance.unwrap();
+        assert_eq!(closing.amount.to_string(), "60.00");
+        assert_eq!(closing.date.date(), NaiveDate::from_ymd_opt(2026, 10, 2).unwrap());
+        assert_eq!(statement.ignored.len(), 1);
+        assert_eq!(statement.ignored[0].line, 2);
+        assert!(statement.rejected.is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+use bigdecimal::BigDecimal;
+use encoding_rs::{Decoder, Encoding};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use super::locale::{self, DateOrder, DecimalSeparator, Locale};
+use super::profile::{Column, CsvProfile};
+use super::{ImportError, RowError, Statement, StatementLine};
+
+/// Column positions resolved against the header of a concrete file.
+struct ResolvedColumns {
+    date: usize,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    counterparty: Option<usize>,
+    iban: Option<usize>,
+    category: Option<usize>,
+    memo: Vec<usize>,
+}
+
+/// How a profile's amounts and dates are read.
+struct Conventions {
+    decimal: DecimalSeparator,
+    date_format: Option<String>,
+    date_order: DateOrder,
+}
+
+/// Reads a CSV export with a profile; `locale` applies if the profile names none.
+pub fn parse_file(path: &Path, profile: &CsvProfile, locale: Option<&Locale>) -> Result<Statement, ImportError> {
+    let mut reader = LineReader::open(path, profile, locale)?;
+    let mut statement = Statement {
+        account_iban: profile.normalized_account_iban(),
+        ..Statement::default()
+    };
+    while let Some(row) = reader.next_row()? {
+        match row {
+            Ok(statement_line) => statement.lines.push(statement_line),
+            Err(rejected) => statement.rejected.push(rejected),
+        }
+    }
+    Ok(statement)
+}
+
+/// Picks the saved profile that reads a file best: the one producing the most
+/// rows, provided at least as many rows parse as are rejected.
+pub fn detect_profile<'a>(path: &Path, profiles: &'a [CsvProfile]) -> Option<&'a CsvProfile> {
+    profiles
+        .iter()
+        .filter_map(|profile| {
+            let statement = parse_file(path, profile, None).ok()?;
+            let parsed = statement.lines.len();
+            (parsed > 0 && parsed >= statement.rejected.len()).then_some((profile, parsed, statement.rejected.len()))
+        })
+        .max_by_key(|(_, parsed, rejected)| (*parsed, usize::MAX - rejected))
+        .map(|(profile, _, _)| profile)
+}
+
+/// Reads the rows of a CSV export one at a time, so files of any size are
+/// read in bounded memory.
+pub struct LineReader<R: Read> {
+    records: ::csv::StringRecordsIntoIter<BufReader<DecodingReader<R>>>,
+    columns: ResolvedColumns,
+    conventions: Conventions,
+    /// Lines dropped before the CSV reader starts counting.
+    skipped_lines: usize,
+}
+
+impl LineReader<File> {
+    pub fn open(path: &Path, profile: &CsvProfile, locale: Option<&Locale>) -> Result<Self, ImportError> {
+        LineReader::new(File::open(path)?, profile, locale)
+    }
+}
+
+impl<R: Read> LineReader<R> {
+    /// Starts reading `source` in the profile's encoding, up to and including its header.
+    pub fn new(source: R, profile: &CsvProfile, locale: Option<&Locale>) -> Result<Self, ImportError> {
+        if !profile.delimiter.is_ascii() {
+            return Err(ImportError::Format(format!("Delimiter '{}' must be an ASCII character", profile.delimiter)));
+        }
+        let encoding = Encoding::for_label(profile.encoding.as_bytes())
+            .ok_or_else(|| ImportError::Format(format!("Unknown encoding '{}'", profile.encoding)))?;
+        let locale = match &profile.locale {
+            Some(name) => Some(Locale::named(name).map_err(ImportError::Format)?),
+            None => locale.copied(),
+        };
+        let conventions = Conventions {
+            decimal: if profile.decimal_comma { DecimalSeparator::Comma } else { locale.map_or(DecimalSeparator::Point, |locale| locale.decimal) },
+            date_format: profile.date_format.clone(),
+            date_order: locale.map_or(DateOrder::YearMonthDay, |locale| locale.dates),
+        };
+
+        // Some banks put account details above the header; drop those lines first
+        let mut text = BufReader::new(DecodingReader::new(source, encoding));
+        let mut skipped = String::new();
+        for _ in 0..profile.skip_rows {
+            skipped.clear();
+            text.read_line(&mut skipped)?;
+        }
+
+        let mut reader = ::csv::ReaderBuilder::new()
+            .delimiter(profile.delimiter as u8)
+            .has_headers(profile.has_header)
+            .flexible(true)
+            .trim(::csv::Trim::All)
+            .from_reader(text);
+
+        let headers: Vec<String> = if profile.has_header {
+            reader.headers()?.iter().map(|h| h.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        let columns = resolve_columns(profile, &headers)?;
+
+        Ok(LineReader {
+            records: reader.into_records(),
+            columns,
+            conventions,
+            skipped_lines: profile.skip_rows,
+        })
+    }
+
+    /// The next row, read or rejected; `None` at the end of the file. Only
+    /// failing to read the file itself is an error.
+    pub fn next_row(&mut self) -> Result<Option<Result<StatementLine, RowError>>, ImportError> {
+        loop {
+            let Some(record) = self.records.next() else { return Ok(None) };
+            // Counted by the reader, since quoted line breaks make records and
+            // file lines drift apart; after blank lines it names the first of them
+            let file_line = |position: Option<&::csv::Position>| self.skipped_lines + position.map_or(0, |p| p.line() as usize);
+            let record = match record {
+                Ok(record) => record,
+                Err(e) if matches!(e.kind(), ::csv::ErrorKind::Io(_)) => return Err(e.into()),
+                Err(e) => return Ok(Some(Err(RowError { line: file_line(e.position()), message: e.to_string() }))),
+            };
+            let line = file_line(record.position());
+            if record.iter().all(|field| field.is_empty()) {
+                continue;
+            }
+            let row = parse_record(&record, &self.columns, &self.conventions, line).map_err(|message| RowError { line, message });
+            return Ok(Some(row));
+        }
+    }
+
+    /// Bytes of the file read so far, for showing progress.
+    pub fn bytes_read(&self) -> u64 {
+        self.records.reader().get_ref().get_ref().bytes_read
+    }
+}
+
+/// Decodes a byte stream from the profile's encoding to UTF-8 as it is read.
+struct DecodingReader<R> {
+    source: R,
+    encoding: &'static Encoding,
+    decoder: Decoder,
+    decoded: Vec<u8>,
+    position: usize,
+    finished: bool,
+    bytes_read: u64,
+}
+
+impl<R: Read> DecodingReader<R> {
+    fn new(source: R, encoding: &'static Encoding) -> Self {
+        DecodingReader {
+            source,
+            encoding,
+            decoder: encoding.new_decoder(),
+            decoded: Vec::new(),
+            position: 0,
+            finished: false,
+            bytes_read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decoded.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            let mut input = [0u8; 8192];
+            let count = self.source.read(&mut input)?;
+            self.bytes_read += count as u64;
+            self.finished = count == 0;
+
+            let mut text = String::with_capacity(self.decoder.max_utf8_buffer_length(count).unwrap_or(4 * count + 16));
+            let (_, _, had_errors) = self.decoder.decode_to_string(&input[..count], &mut text, self.finished);
+            if had_errors {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("File is not valid {}", self.encoding.name())));
+            }
+            self.decoded = text.into_bytes();
+            self.position = 0;
+        }
+        let count = buf.len().min(self.decoded.len() - self.position);
+        buf[..count].copy_from_slice(&self.decoded[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+fn resolve_columns(profile: &CsvProfile, headers: &[String]) -> Result<ResolvedColumns, ImportError> {
+    let resolve = |column: &Column| -> Result<usize, ImportError> {
+        match column {
+            Column::Index(index) => Ok(*index),
+            Column::Name(name) => headers
+                .iter()
+                .position(|header| header.eq_ignore_ascii_case(name))
+                .ok_or_else(|| ImportError::Format(format!("Column '{}' not found in CSV header", name))),
+        }
+    };
+    let resolve_optional = |column: &Option<Column>| column.as_ref().map(resolve).transpose();
+
+    let mapping = &profile.columns;
+    let date = mapping
+        .date
+        .as_ref()
+        .ok_or_else(|| ImportError::Format(format!("Profile '{}' has no date column", profile.name)))?;
+    if mapping.amount.is_none() && mapping.debit.is_none() && mapping.credit.is_none() {
+        return Err(ImportError::Format(format!(
+            "Profile '{}' needs an amount column or debit/credit columns",
+            profile.name
+        )));
+    }
+
+    Ok(ResolvedColumns {
+        date: resolve(date)?,
+        amount: resolve_optional(&mapping.amount)?,
+        debit: resolve_optional(&mapping.debit)?,
+        credit: resolve_optional(&mapping.credit)?,
+        counterparty: resolve_optional(&mapping.counterparty)?,
+        iban: resolve_optional(&mapping.iban)?,
+        category: resolve_optional(&mapping.category)?,
+        memo: mapping.memo.iter().map(resolve).collect::<Result<_, _>>()?,
+    })
+}
+
+fn parse_record(
+    record: &::csv::StringRecord,
+    columns: &ResolvedColumns,
+    conventions: &Conventions,
+    line: usize,
+) -> Result<StatementLine, String> {
+    let field = |index: usize| record.get(index).unwrap_or("");
+    let optional_field = |index: Option<usize>| {
+        index
+            .map(field)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_string())
+    };
+
+    let date = match conventions.date_format.as_deref() {
+        Some(format) => locale::parse_date_as(field(columns.date), format)?,
+        None => locale::parse_date(field(columns.date), conventions.date_order)?,
+    };
+
+    let amount = if let Some(index) = columns.amount {
+        locale::parse_amount(field(index), conventions.decimal)?
+    } else {
+        let debit = match columns.debit.map(field).filter(|value| !value.is_empty()) {
+            Some(value) => locale::parse_amount(value, conventions.decimal)?.abs(),
+            None => BigDecimal::from(0),
+        };
+        let credit = match columns.credit.map(field).filter(|value| !value.is_empty()) {
+            Some(value) => locale::parse_amount(value, conventions.decimal)?.abs(),
+            None => BigDecimal::from(0),
+        };
+        credit - debit
+    };
+
+    let memo = columns
+        .memo
+        .iter()
+        .map(|index| field(*index))
+        .filter(|value| !value.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(StatementLine {
+        line,
+        date,
+        amount,
+        counterparty: optional_field(columns.counterparty),
+        counterparty_iban: optional_field(columns.iban).map(|iban| iban.replace(' ', "").to_uppercase()),
+        counterparty_bic: None,
+        memo,
+        bank_id: None,
+        category: optional_field(columns.category),
+        fee: None,
+        original_amount: None,
+        original_currency: None,
+        funding: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_rows_by_their_line_in_the_file() {
+        let mut profile = CsvProfile::template("test");
+        profile.skip_rows = 1;
+        let file = "Account 123\nDate,Amount,Payee,IBAN,Description\n2024-03-01,-3.50,Cafe,,\"Coffee\nand cake\"\n2024-03-02,oops,Bakery,,Bread\n2024-03-03,10,Shop,,Refund\n";
+        let mut reader = LineReader::new(file.as_bytes(), &profile, None).unwrap();
+
+        let mut lines = Vec::new();
+        while let Some(row) = reader.next_row().unwrap() {
+            lines.push(row.map(|line| line.line).map_err(|error| error.line));
+        }
+        assert_eq!(lines, [Ok(3), Err(5), Ok(6)]);
+    }
+
+    #[test]
+    fn detects_the_profile_that_reads_the_most_rows() {
+        let us = CsvProfile::template("us");
+        let mut de = CsvProfile::template("de");
+        de.delimiter = ';';
+        de.locale = Some("de-DE".to_string());
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, "Date;Amount;Payee;IBAN;Description\n01.03.2024;-3,50;Cafe;;Coffee\n02.03.2024;1.200,00;Employer;;Salary\n".as_bytes()).unwrap();
+
+        let profiles = [us, de];
+        assert_eq!(detect_profile(file.path(), &profiles).map(|profile| profile.name.as_str()), Some("de"));
+        assert!(detect_profile(file.path(), &profiles[..1]).is_none());
+    }
+}
@@ -0,0 +1,233 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+
+use super::{Balance, RowError, Statement, StatementLine};
+
+/// How a double-entry account maps onto FinWise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccountKind {
+    /// Asset and liability accounts, which become FinWise accounts with balances.
+    Balance,
+    /// The equity account that opening balances are booked against.
+    OpeningBalance,
+    /// Income, expense and other equity accounts, which become categories.
+    Category,
+}
+
+/// One posting (split) of a double-entry transaction, valued in `commodity`.
+#[derive(Debug, Clone)]
+pub struct Posting {
+    pub account: String,
+    pub amount: BigDecimal,
+    pub commodity: String,
+}
+
+/// A balanced transaction from a journal or book.
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub line: usize,
+    pub date: NaiveDateTime,
+    pub payee: Option<String>,
+    pub narration: String,
+    pub bank_id: Option<String>,
+    pub postings: Vec<Posting>,
+}
+
+/// Collects double-entry transactions into one statement per balance account,
+/// named by the account's full path.
+///
+/// From each account's point of view a transaction becomes one line per other
+/// posting: the payee is the counterparty and the other posting's account the
+/// category. Postings between two balance accounts are transfers whose
+/// counterparty is the other account, and postings against the opening
+/// balance account set the account's opening balance.
+pub struct StatementBuilder<F: Fn(&str) -> AccountKind> {
+    kind_of: F,
+    statements: Vec<Statement>,
+    index_by_account: HashMap<String, usize>,
+    unassigned: Vec<RowError>,
+}
+
+impl<F: Fn(&str) -> AccountKind> StatementBuilder<F> {
+    pub fn new(kind_of: F) -> Self {
+        StatementBuilder {
+            kind_of,
+            statements: Vec::new(),
+            index_by_account: HashMap::new(),
+            unassigned: Vec::new(),
+        }
+    }
+
+    pub fn is_balance_account(&self, account: &str) -> bool {
+        (self.kind_of)(account) == AccountKind::Balance
+    }
+
+    pub fn statement_for(&mut self, account: &str) -> &mut Statement {
+        let statements = &mut self.statements;
+        let index = *self.index_by_account.entry(account.to_string()).or_insert_with(|| {
+            statements.push(Statement {
+                account_name: Some(account.to_string()),
+                ..Statement::default()
+            });
+            statements.len() - 1
+        });
+        &mut self.statements[index]
+    }
+
+    /// Records a transaction that could not be read, on the statement of `account` if known.
+    pub fn reject(&mut self, account: Option<&str>, error: RowError) {
+        match account {
+            Some(account) => self.statement_for(account).rejected.push(error),
+            None => self.unassigned.push(error),
+        }
+    }
+
+    pub fn add(&mut self, transaction: &Transaction) {
+        for (position, posting) in transaction.postings.iter().enumerate() {
+            if !self.is_balance_account(&posting.account) {
+                continue;
+            }
+            let others: Vec<&Posting> = transaction
+                .postings
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != position)
+                .map(|(_, posting)| posting)
+                .collect();
+            let kinds: Vec<AccountKind> = others.iter().map(|other| (self.kind_of)(&other.account)).collect();
+
+            let statement = self.statement_for(&posting.account);
+            if statement.currency.is_none() && !posting.commodity.is_empty() {
+                statement.currency = Some(posting.commodity.clone());
+            }
+            if let Some(currency) = statement.currency.as_deref().filter(|currency| !posting.commodity.is_empty() && *currency != posting.commodity) {
+                let message = format!("{} posting in {} on a {} account", posting.account, posting.commodity, currency);
+                statement.rejected.push(RowError { line: transaction.line, message });
+                continue;
+            }
+
+            if kinds.contains(&AccountKind::OpeningBalance) {
+                statement.opening_balance = Some(Balance { amount: posting.amount.clone(), date: transaction.date });
+                continue;
+            }
+
+            let base_line = StatementLine {
+                line: transaction.line,
+                date: transaction.date,
+                amount: posting.amount.clone(),
+                counterparty: transaction
+                    .payee
+                    .clone()
+                    .or_else(|| Some(transaction.narration.clone()).filter(|narration| !narration.is_empty())),
+                counterparty_iban: None,
+                counterparty_bic: None,
+                memo: transaction.narration.clone(),
+                bank_id: transaction.bank_id.clone(),
+                category: None,
+                fee: None,
+                original_amount: None,
+                original_currency: None,
+                funding: false,
+            };
+
+            let balance_others = kinds.iter().filter(|kind| **kind == AccountKind::Balance).count();
+            if balance_others == 1 && others.len() == 1 {
+                let transfer_account = others[0].account.clone();
+                statement.lines.push(StatementLine {
+                    counterparty: Some(transfer_account.clone()),
+                    category: Some(format!("[{}]", transfer_account)),
+                    ..base_line
+                });
+            } else if balance_others == 0 {
+                let split = others.len() > 1;
+                for (split_index, other) in others.iter().enumerate() {
+                    statement.lines.push(StatementLine {
+                        amount: -other.amount.clone(),
+                        category: Some(other.account.clone()),
+                        // Split lines need their own bank ids to stay distinct
+                        bank_id: base_line.bank_id.as_ref().map(|id| if split { format!("{}/{}", id, split_index + 1) } else { id.clone() }),
+                        ..base_line.clone()
+                    });
+                }
+            } else {
+                let category = others
+                    .iter()
+                    .zip(&kinds)
+                    .find(|(_, kind)| **kind == AccountKind::Category)
+                    .map(|(other, _)| other.account.clone());
+                statement.lines.push(StatementLine { category, ..base_line });
+            }
+        }
+    }
+
+    /// The statements built so far; rows that belong to no account are reported on the first.
+    pub fn finish(mut self) -> Vec<Statement> {
+        if let Some(first) = self.statements.first_mut() {
+            first.rejected.append(&mut self.unassigned);
+        }
+        self.statements
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+
+    fn posting(account: &str, amount: &str) -> Posting {
+        Posting { account: account.to_string(), amount: BigDecimal::from_str(amount).unwrap(), commodity: "EUR".to_string() }
+    }
+
+    fn transaction(line: usize, postings: Vec<Posting>) -> Transaction {
+        Transaction {
+            line,
+            date: NaiveDate::from_ymd_opt(2024, 1, line as u32).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+            payee: Some("Payee".to_string()),
+            narration: String::new(),
+            bank_id: Some(format!("T{}", line)),
+            postings,
+        }
+    }
+
+    fn kind_of(account: &str) -> AccountKind {
+        match account {
+            "Equity:Opening" => AccountKind::OpeningBalance,
+            _ if account.starts_with("Assets:") => AccountKind::Balance,
+            _ => AccountKind::Category,
+        }
+    }
+
+    #[test]
+    fn splits_transactions_into_account_statements() {
+        let mut builder = StatementBuilder::new(kind_of);
+        builder.add(&transaction(1, vec![posting("Assets:Checking", "100"), posting("Equity:Opening", "-100")]));
+        builder.add(&transaction(2, vec![posting("Assets:Checking", "-30"), posting("Assets:Savings", "30")]));
+        builder.add(&transaction(3, vec![posting("Assets:Checking", "-15"), posting("Expenses:Food", "10"), posting("Expenses:Drinks", "5")]));
+        let statements = builder.finish();
+
+        let checking = &statements[0];
+        assert_eq!(checking.account_name.as_deref(), Some("Assets:Checking"));
+        assert_eq!(checking.opening_balance.as_ref().unwrap().amount.to_string(), "100");
+        let lines: Vec<(String, Option<&str>, Option<&str>)> = checking
+            .lines
+            .iter()
+            .map(|line| (line.amount.to_string(), line.category.as_deref(), line.bank_id.as_deref()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                ("-30".to_string(), Some("[Assets:Savings]"), Some("T2")),
+                ("-10".to_string(), Some("Expenses:Food"), Some("T3/1")),
+                ("-5".to_string(), Some("Expenses:Drinks"), Some("T3/2")),
+            ]
+        );
+        assert_eq!(checking.lines[0].counterparty.as_deref(), Some("Assets:Savings"));
+
+        let savings = &statements[1];
+        assert_eq!(savings.lines.len(), 1);
+        assert_eq!(savings.lines[0].amount.to_string(), "30");
+        assert_eq!(savings.lines[0].category.as_deref(), Some("[Assets:Checking]"));
+    }
+}
@@ -0,0 +1,431 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use flate2::read::ZlibDecoder;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::xml::{self, XmlElement};
+use super::ImportError;
+
+/// UNTDID 1001 document type of a CII credit note.
+const CII_CREDIT_NOTE: &str = "381";
+
+/// The XML syntax an e-invoice is written in. XRechnung uses either; ZUGFeRD
+/// and Factur-X embed CII in a PDF.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Syntax {
+    /// OASIS UBL 2.x `Invoice` or `CreditNote`.
+    Ubl,
+    /// UN/CEFACT Cross Industry Invoice.
+    Cii,
+}
+
+impl Syntax {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Syntax::Ubl => "ubl",
+            Syntax::Cii => "cii",
+        }
+    }
+}
+
+/// One invoice line, with amounts as invoiced.
+#[derive(Debug, Clone)]
+pub struct InvoiceLine {
+    pub description: String,
+    pub quantity: Option<BigDecimal>,
+    pub unit_price: Option<BigDecimal>,
+    /// Line total before VAT.
+    pub net_amount: BigDecimal,
+    /// VAT rate in percent.
+    pub vat_rate: Option<BigDecimal>,
+    /// UNCL5305 category code, e.g. `S` for standard rated.
+    pub vat_category: Option<String>,
+}
+
+/// The parts of an e-invoice FinWise keeps.
+///
+/// Amounts are as written on the document; `credit_note` tells whether the
+/// seller owes them rather than the buyer.
+#[derive(Debug, Clone)]
+pub struct Invoice {
+    pub syntax: Syntax,
+    /// True when the XML came out of a PDF (ZUGFeRD / Factur-X).
+    pub from_pdf: bool,
+    pub number: String,
+    pub credit_note: bool,
+    pub issue_date: NaiveDate,
+    pub due_date: Option<NaiveDate>,
+    pub currency: Option<String>,
+    pub seller_name: String,
+    pub seller_iban: Option<String>,
+    pub seller_bic: Option<String>,
+    pub seller_vat_id: Option<String>,
+    /// UNCL4461 payment means code, e.g. `58` for a SEPA credit transfer.
+    pub payment_means: Option<String>,
+    pub lines: Vec<InvoiceLine>,
+    pub net_total: Option<BigDecimal>,
+    pub tax_total: Option<BigDecimal>,
+    /// The amount due, after prepayments.
+    pub total: BigDecimal,
+}
+
+impl Invoice {
+    /// How the invoice asks to be paid, in the words receipts use for it.
+    pub fn payment_method(&self) -> &'static str {
+        match self.payment_means.as_deref() {
+            Some("30" | "31" | "42" | "58") => "transfer",
+            Some("49" | "59") => "direct debit",
+            Some("48" | "54" | "55") => "card",
+            Some("10") => "cash",
+            _ => "invoice",
+        }
+    }
+}
+
+/// Tells whether a file is an e-invoice: UBL or CII XML, or a PDF carrying one.
+pub fn detect(path: &Path) -> Result<bool, ImportError> {
+    let extension = path.extension().and_then(|ext| ext.to_str()).map(|ext| ext.to_lowercase());
+    match extension.as_deref() {
+        Some("xml") => Ok(root_syntax(&fs::read_to_string(path)?).is_some()),
+        Some("pdf") => Ok(embedded_xml(&fs::read(path)?).is_some()),
+        _ => Ok(false),
+    }
+}
+
+pub fn parse_file(path: &Path) -> Result<Invoice, ImportError> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(b"%PDF") {
+        let content = embedded_xml(&bytes)
+            .ok_or_else(|| ImportError::Format("PDF carries no ZUGFeRD / Factur-X invoice".to_string()))?;
+        let mut invoice = parse_str(&content)?;
+        invoice.from_pdf = true;
+        return Ok(invoice);
+    }
+    parse_str(&String::from_utf8_lossy(&bytes))
+}
+
+/// Parses a UBL or CII invoice or credit note.
+pub fn parse_str(content: &str) -> Result<Invoice, ImportError> {
+    let root = xml::parse(content)?;
+    match root.name.as_str() {
+        "Invoice" | "CreditNote" => parse_ubl(&root),
+        "CrossIndustryInvoice" => parse_cii(&root),
+        other => Err(ImportError::Format(format!("Not a UBL or CII invoice: <{}>", other))),
+    }
+}
+
+/// Guesses the syntax from the root element without parsing the whole document.
+fn root_syntax(content: &str) -> Option<Syntax> {
+    let head = &content[..content.len().min(2048)];
+    if head.contains("CrossIndustryInvoice") {
+        Some(Syntax::Cii)
+    } else if head.contains("urn:oasis:names:specification:ubl:schema:xsd:Invoice-2")
+        || head.contains("urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2")
+    {
+        Some(Syntax::Ubl)
+    } else {
+        None
+    }
+}
+
+/// Finds the invoice XML among the streams of a PDF, inflating compressed ones.
+fn embedded_xml(pdf: &[u8]) -> Option<String> {
+    let mut rest = pdf;
+    while let Some(start) = find(rest, b"stream") {
+        let mut data = &rest[start + b"stream".len()..];
+        data = data.strip_prefix(b"\r").unwrap_or(data);
+        data = data.strip_prefix(b"\n").unwrap_or(data);
+        let Some(end) = find(data, b"endstream") else { break };
+        let stream = &data[..end];
+        rest = &data[end + b"endstream".len()..];
+
+        let mut inflated = Vec::new();
+        let content = match ZlibDecoder::new(stream).read_to_end(&mut inflated) {
+            Ok(_) => String::from_utf8_lossy(&inflated).into_owned(),
+            Err(_) => String::from_utf8_lossy(stream).into_owned(),
+        };
+        if root_syntax(&content).is_some() {
+            return Some(content);
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn parse_ubl(root: &XmlElement) -> Result<Invoice, ImportError> {
+    let credit_note = root.name == "CreditNote";
+    let supplier = root
+        .path(&["AccountingSupplierParty", "Party"])
+        .ok_or_else(|| ImportError::Format("UBL invoice without AccountingSupplierParty".to_string()))?;
+    let payment_means = root.child("PaymentMeans");
+    let totals = root.child("LegalMonetaryTotal");
+
+    let line_name = if credit_note { "CreditNoteLine" } else { "InvoiceLine" };
+    let quantity_name = if credit_note { "CreditedQuantity" } else { "InvoicedQuantity" };
+    let lines = root
+        .children_named(line_name)
+        .map(|line| {
+            Ok(InvoiceLine {
+                description: line
+                    .text_at(&["Item", "Name"])
+                    .or_else(|| line.text_at(&["Item", "Description"]))
+                    .unwrap_or_default(),
+                quantity: optional_amount(line.text_at(&[quantity_name]))?,
+                unit_price: optional_amount(line.text_at(&["Price", "PriceAmount"]))?,
+                net_amount: amount(line.text_at(&["LineExtensionAmount"]), "LineExtensionAmount")?,
+                vat_rate: optional_amount(line.text_at(&["Item", "ClassifiedTaxCategory", "Percent"]))?,
+                vat_category: line.text_at(&["Item", "ClassifiedTaxCategory", "ID"]),
+            })
+        })
+        .collect::<Result<Vec<_>, ImportError>>()?;
+
+    Ok(Invoice {
+        syntax: Syntax::Ubl,
+        from_pdf: false,
+        number: required(root.text_at(&["ID"]), "ID")?,
+        credit_note,
+        issue_date: date(&required(root.text_at(&["IssueDate"]), "IssueDate")?)?,
+        due_date: root
+            .text_at(&["DueDate"])
+            .or_else(|| payment_means.and_then(|means| means.text_at(&["PaymentDueDate"])))
+            .map(|due| date(&due))
+            .transpose()?,
+        currency: root.text_at(&["DocumentCurrencyCode"]),
+        seller_name: supplier
+            .text_at(&["PartyLegalEntity", "RegistrationName"])
+            .or_else(|| supplier.text_at(&["PartyName", "Name"]))
+            .ok_or_else(|| ImportError::Format("UBL invoice without seller name".to_string()))?,
+        seller_iban: payment_means.and_then(|means| means.text_at(&["PayeeFinancialAccount", "ID"])).map(|iban| compact(&iban)),
+        seller_bic: payment_means.and_then(|means| means.text_at(&["PayeeFinancialAccount", "FinancialInstitutionBranch", "ID"])),
+        seller_vat_id: supplier.text_at(&["PartyTaxScheme", "CompanyID"]),
+        payment_means: payment_means.and_then(|means| means.text_at(&["PaymentMeansCode"])),
+        lines,
+        net_total: optional_amount(totals.and_then(|totals| totals.text_at(&["TaxExclusiveAmount"])))?,
+        tax_total: optional_amount(root.text_at(&["TaxTotal", "TaxAmount"]))?,
+        total: amount(
+            totals.and_then(|totals| totals.text_at(&["PayableAmount"]).or_else(|| totals.text_at(&["TaxInclusiveAmount"]))),
+            "PayableAmount",
+        )?,
+    })
+}
+
+fn parse_cii(root: &XmlElement) -> Result<Invoice, ImportError> {
+    let document = root
+        .child("ExchangedDocument")
+        .ok_or_else(|| ImportError::Format("CII invoice without ExchangedDocument".to_string()))?;
+    let transaction = root
+        .child("SupplyChainTradeTransaction")
+        .ok_or_else(|| ImportError::Format("CII invoice without SupplyChainTradeTransaction".to_string()))?;
+    let seller = transaction
+        .path(&["ApplicableHeaderTradeAgreement", "SellerTradeParty"])
+        .ok_or_else(|| ImportError::Format("CII invoice without SellerTradeParty".to_string()))?;
+    let settlement = transaction
+        .child("ApplicableHeaderTradeSettlement")
+        .ok_or_else(|| ImportError::Format("CII invoice without ApplicableHeaderTradeSettlement".to_string()))?;
+    let payment_means = settlement.child("SpecifiedTradeSettlementPaymentMeans");
+    let totals = settlement.child("SpecifiedTradeSettlementHeaderMonetarySummation");
+
+    let lines = transaction
+        .children_named("IncludedSupplyChainTradeLineItem")
+        .map(|line| {
+            let tax = line.path(&["SpecifiedLineTradeSettlement", "ApplicableTradeTax"]);
+            Ok(InvoiceLine {
+                description: line.text_at(&["SpecifiedTradeProduct", "Name"]).unwrap_or_default(),
+                quantity: optional_amount(line.text_at(&["SpecifiedLineTradeDelivery", "BilledQuantity"]))?,
+                unit_price: optional_amount(line.text_at(&["SpecifiedLineTradeAgreement", "NetPriceProductTradePrice", "ChargeAmount"]))?,
+                net_amount: amount(
+                    line.text_at(&["SpecifiedLineTradeSettlement", "SpecifiedTradeSettlementLineMonetarySummation", "LineTotalAmount"]),
+                    "LineTotalAmount",
+                )?,
+                vat_rate: optional_amount(tax.and_then(|tax| tax.text_at(&["RateApplicablePercent"])))?,
+                vat_category: tax.and_then(|tax| tax.text_at(&["CategoryCode"])),
+            })
+        })
+        .collect::<Result<Vec<_>, ImportError>>()?;
+
+    // TaxTotalAmount is repeated when the VAT is also stated in the accounting currency
+    let currency = settlement.text_at(&["InvoiceCurrencyCode"]);
+    let tax_total = totals
+        .and_then(|totals| {
+            totals.children_named("TaxTotalAmount").find(|tax| match (&currency, tax.attribute("currencyID")) {
+                (Some(currency), Some(id)) => id == currency,
+                _ => true,
+            })
+        })
+        .map(|tax| tax.text.trim().to_string());
+
+    Ok(Invoice {
+        syntax: Syntax::Cii,
+        from_pdf: false,
+        number: required(document.text_at(&["ID"]), "ExchangedDocument/ID")?,
+        credit_note: document.text_at(&["TypeCode"]).as_deref() == Some(CII_CREDIT_NOTE),
+        issue_date: cii_date(document.path(&["IssueDateTime", "DateTimeString"]))?
+            .ok_or_else(|| ImportError::Format("CII invoice without IssueDateTime".to_string()))?,
+        due_date: cii_date(settlement.path(&["SpecifiedTradePaymentTerms", "DueDateDateTime", "DateTimeString"]))?,
+        currency,
+        seller_name: required(seller.text_at(&["Name"]), "SellerTradeParty/Name")?,
+        seller_iban: payment_means
+            .and_then(|means| means.text_at(&["PayeePartyCreditorFinancialAccount", "IBANID"]))
+            .map(|iban| compact(&iban)),
+        seller_bic: payment_means.and_then(|means| means.text_at(&["PayeeSpecifiedCreditorFinancialInstitution", "BICID"])),
+        seller_vat_id: seller
+            .children_named("SpecifiedTaxRegistration")
+            .find(|registration| registration.child("ID").and_then(|id| id.attribute("schemeID")) == Some("VA"))
+            .and_then(|registration| registration.text_at(&["ID"])),
+        payment_means: payment_means.and_then(|means| means.text_at(&["TypeCode"])),
+        lines,
+        net_total: optional_amount(totals.and_then(|totals| totals.text_at(&["TaxBasisTotalAmount"])))?,
+        tax_total: optional_amount(tax_total)?,
+        total: amount(
+            totals.and_then(|totals| totals.text_at(&["DuePayableAmount"]).or_else(|| totals.text_at(&["GrandTotalAmount"]))),
+            "DuePayableAmount",
+        )?,
+    })
+}
+
+fn required(value: Option<String>, element: &str) -> Result<String, ImportError> {
+    value.ok_or_else(|| ImportError::Format(format!("Invoice without {}", element)))
+}
+
+fn amount(value: Option<String>, element: &str) -> Result<BigDecimal, ImportError> {
+    let value = required(value, element)?;
+    BigDecimal::from_str(&value).map_err(|_| ImportError::Format(format!("Invalid amount '{}' in {}", value, element)))
+}
+
+fn optional_amount(value: Option<String>) -> Result<Option<BigDecimal>, ImportError> {
+    value
+        .map(|value| BigDecimal::from_str(&value).map_err(|_| ImportError::Format(format!("Invalid amount '{}'", value))))
+        .transpose()
+}
+
+fn date(value: &str) -> Result<NaiveDate, ImportError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| ImportError::Format(format!("Invalid date '{}'", value)))
+}
+
+/// Reads a CII `DateTimeString`; format `102` is `YYYYMMDD`, the only one EN 16931 allows.
+fn cii_date(element: Option<&XmlElement>) -> Result<Option<NaiveDate>, ImportError> {
+    let Some(element) = element else { return Ok(None) };
+    let value = element.text.trim();
+    match element.attribute("format") {
+        Some("102") | None => NaiveDate::parse_from_str(value, "%Y%m%d")
+            .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+            .map(Some)
+            .map_err(|_| ImportError::Format(format!("Invalid date '{}'", value))),
+        Some(format) => Err(ImportError::Format(format!("Unsupported CII date format {}", format))),
+    }
+}
+
+fn compact(iban: &str) -> String {
+    iban.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const UBL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Invoice xmlns="urn:oasis:names:specification:ubl:schema:xsd:Invoice-2"
+         xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2"
+         xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>R-1001</cbc:ID>
+  <cbc:IssueDate>2024-03-01</cbc:IssueDate>
+  <cbc:DueDate>2024-03-15</cbc:DueDate>
+  <cbc:DocumentCurrencyCode>EUR</cbc:DocumentCurrencyCode>
+  <cac:AccountingSupplierParty><cac:Party>
+    <cac:PartyName><cbc:Name>Office Shop</cbc:Name></cac:PartyName>
+    <cac:PartyTaxScheme><cbc:CompanyID>DE123456789</cbc:CompanyID></cac:PartyTaxScheme>
+  </cac:Party></cac:AccountingSupplierParty>
+  <cac:PaymentMeans>
+    <cbc:PaymentMeansCode>58</cbc:PaymentMeansCode>
+    <cac:PayeeFinancialAccount><cbc:ID>DE89 3704 0044 0532 0130 00</cbc:ID></cac:PayeeFinancialAccount>
+  </cac:PaymentMeans>
+  <cac:TaxTotal><cbc:TaxAmount currencyID="EUR">3.80</cbc:TaxAmount></cac:TaxTotal>
+  <cac:LegalMonetaryTotal>
+    <cbc:TaxExclusiveAmount currencyID="EUR">20.00</cbc:TaxExclusiveAmount>
+    <cbc:PayableAmount currencyID="EUR">23.80</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+  <cac:InvoiceLine>
+    <cbc:InvoicedQuantity unitCode="C62">4</cbc:InvoicedQuantity>
+    <cbc:LineExtensionAmount currencyID="EUR">20.00</cbc:LineExtensionAmount>
+    <cac:Item><cbc:Name>Paper</cbc:Name><cac:ClassifiedTaxCategory><cbc:ID>S</cbc:ID><cbc:Percent>19</cbc:Percent></cac:ClassifiedTaxCategory></cac:Item>
+    <cac:Price><cbc:PriceAmount currencyID="EUR">5.00</cbc:PriceAmount></cac:Price>
+  </cac:InvoiceLine>
+</Invoice>"#;
+
+    const CII: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rsm:CrossIndustryInvoice xmlns:rsm="urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100"
+    xmlns:ram="urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100"
+    xmlns:udt="urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100">
+  <rsm:ExchangedDocument>
+    <ram:ID>GS-7</ram:ID><ram:TypeCode>381</ram:TypeCode>
+    <ram:IssueDateTime><udt:DateTimeString format="102">20240305</udt:DateTimeString></ram:IssueDateTime>
+  </rsm:ExchangedDocument>
+  <rsm:SupplyChainTradeTransaction>
+    <ram:ApplicableHeaderTradeAgreement>
+      <ram:SellerTradeParty><ram:Name>Printer GmbH</ram:Name>
+        <ram:SpecifiedTaxRegistration><ram:ID schemeID="VA">DE987654321</ram:ID></ram:SpecifiedTaxRegistration>
+      </ram:SellerTradeParty>
+    </ram:ApplicableHeaderTradeAgreement>
+    <ram:ApplicableHeaderTradeSettlement>
+      <ram:InvoiceCurrencyCode>EUR</ram:InvoiceCurrencyCode>
+      <ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+        <ram:TaxBasisTotalAmount>10.00</ram:TaxBasisTotalAmount>
+        <ram:TaxTotalAmount currencyID="USD">2.05</ram:TaxTotalAmount>
+        <ram:TaxTotalAmount currencyID="EUR">1.90</ram:TaxTotalAmount>
+        <ram:GrandTotalAmount>11.90</ram:GrandTotalAmount>
+        <ram:DuePayableAmount>11.90</ram:DuePayableAmount>
+      </ram:SpecifiedTradeSettlementHeaderMonetarySummation>
+    </ram:ApplicableHeaderTradeSettlement>
+  </rsm:SupplyChainTradeTransaction>
+</rsm:CrossIndustryInvoice>"#;
+
+    #[test]
+    fn reads_a_ubl_invoice_with_its_lines() {
+        let invoice = parse_str(UBL).unwrap();
+        assert_eq!(invoice.syntax, Syntax::Ubl);
+        assert_eq!(invoice.number, "R-1001");
+        assert!(!invoice.credit_note);
+        assert_eq!(invoice.due_date, NaiveDate::from_ymd_opt(2024, 3, 15));
+        assert_eq!(invoice.seller_name, "Office Shop");
+        assert_eq!(invoice.seller_iban.as_deref(), Some("DE89370400440532013000"));
+        assert_eq!(invoice.payment_method(), "transfer");
+        assert_eq!(invoice.total.to_string(), "23.80");
+        assert_eq!(invoice.tax_total.map(|tax| tax.to_string()).as_deref(), Some("3.80"));
+        let line = &invoice.lines[0];
+        assert_eq!(line.description, "Paper");
+        assert_eq!(line.quantity.as_ref().map(|quantity| quantity.to_string()).as_deref(), Some("4"));
+        assert_eq!(line.vat_rate.as_ref().map(|rate| rate.to_string()).as_deref(), Some("19"));
+        assert_eq!(line.vat_category.as_deref(), Some("S"));
+    }
+
+    #[test]
+    fn reads_a_cii_credit_note_embedded_in_a_pdf() {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(CII.as_bytes()).unwrap();
+        let mut pdf = b"%PDF-1.7\n1 0 obj\n<< /Length 5 >>\nstream\nBT ET\nendstream\nendobj\n2 0 obj\n<< /Filter /FlateDecode >>\nstream\n".to_vec();
+        pdf.extend(encoder.finish().unwrap());
+        pdf.extend(b"\nendstream\nendobj\n%%EOF\n");
+        let mut file = tempfile::Builder::new().suffix(".pdf").tempfile().unwrap();
+        file.write_all(&pdf).unwrap();
+
+        assert!(detect(file.path()).unwrap());
+        let invoice = parse_file(file.path()).unwrap();
+        assert_eq!(invoice.syntax, Syntax::Cii);
+        assert!(invoice.from_pdf);
+        assert!(invoice.credit_note);
+        assert_eq!(invoice.issue_date, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+        assert_eq!(invoice.seller_name, "Printer GmbH");
+        assert_eq!(invoice.seller_vat_id.as_deref(), Some("DE987654321"));
+        assert_eq!(invoice.tax_total.map(|tax| tax.to_string()).as_deref(), Some("1.90"));
+        assert_eq!(invoice.total.to_string(), "11.90");
+    }
+}
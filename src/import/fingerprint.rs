@@ -0,0 +1,141 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use super::{StatementLine, TradeLine};
+
+/// Computes the import fingerprint of every line of a statement booked to `account_id`.
+///
+/// Lines with a bank transaction id are identified by it. Otherwise the
+/// fingerprint hashes account, date, amount, counterparty IBAN and memo, plus
+/// how many identical lines came before it in the file, so two equal coffee
+/// purchases on one day stay two rows while a re-import of either is skipped.
+pub fn fingerprints(account_id: i32, lines: &[StatementLine]) -> Vec<String> {
+    let mut fingerprints = LineFingerprints::new(account_id);
+    lines.iter().map(|line| fingerprints.next(line)).collect()
+}
+
+/// Fingerprints the lines of a statement one at a time, as `fingerprints`
+/// does for a whole one, for files read in chunks.
+pub struct LineFingerprints {
+    account_id: i32,
+    occurrences: Occurrences,
+}
+
+impl LineFingerprints {
+    pub fn new(account_id: i32) -> Self {
+        LineFingerprints { account_id, occurrences: Occurrences::default() }
+    }
+
+    /// The fingerprint of the file's next line; every line must be passed, in file order.
+    pub fn next(&mut self, line: &StatementLine) -> String {
+        let key = match &line.bank_id {
+            Some(bank_id) => format!("bank\u{1f}{}\u{1f}{}", self.account_id, bank_id),
+            None => format!(
+                "line\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}",
+                self.account_id,
+                line.date.format("%Y-%m-%d"),
+                line.amount.normalized(),
+                line.counterparty_iban.as_deref().unwrap_or("").replace(' ', "").to_uppercase(),
+                line.memo.trim()
+            ),
+        };
+        self.occurrences.hash(&key)
+    }
+}
+
+/// Computes the import fingerprint of every trade of a statement, the same way
+/// as `fingerprints`: by the exchange's id, or by date, kind, symbol, quantity and amount.
+pub fn trade_fingerprints(account_id: i32, trades: &[TradeLine]) -> Vec<String> {
+    hash_keys(trades.iter().map(|trade| match &trade.bank_id {
+        Some(bank_id) => format!("trade-id\u{1f}{}\u{1f}{}", account_id, bank_id),
+        None => format!(
+            "trade\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}",
+            account_id,
+            trade.date.format("%Y-%m-%d"),
+            trade.kind.as_str(),
+            trade.symbol.as_deref().unwrap_or(""),
+            trade.quantity.as_ref().map(|quantity| quantity.normalized().to_string()).unwrap_or_default(),
+            trade.amount.normalized()
+        ),
+    }))
+}
+
+/// Hashes each key together with how many identical keys came before it.
+fn hash_keys(keys: impl Iterator<Item = String>) -> Vec<String> {
+    let mut occurrences = Occurrences::default();
+    keys.map(|key| occurrences.hash(&key)).collect()
+}
+
+/// How often each key was seen, keyed by its digest so long files stay small in memory.
+#[derive(Default)]
+struct Occurrences(HashMap<[u8; 32], usize>);
+
+impl Occurrences {
+    fn hash(&mut self, key: &str) -> String {
+        let occurrence = self.0.entry(Sha256::digest(key.as_bytes()).into()).or_insert(0);
+        let hash = Sha256::digest(format!("{}\u{1f}{}", key, occurrence).as_bytes());
+        *occurrence += 1;
+        hex::encode(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+
+    fn line(amount: &str, memo: &str, bank_id: Option<&str>) -> StatementLine {
+        StatementLine {
+            line: 1,
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(9, 30, 0).unwrap(),
+            amount: BigDecimal::from_str(amount).unwrap(),
+            counterparty: Some("Café".to_string()),
+            counterparty_iban: None,
+            counterparty_bic: None,
+            memo: memo.to_string(),
+            bank_id: bank_id.map(|id| id.to_string()),
+            category: None,
+            fee: None,
+            original_amount: None,
+            original_currency: None,
+            funding: false,
+        }
+    }
+
+    #[test]
+    fn keeps_identical_lines_apart_and_repeats_on_reimport() {
+        let lines = [line("-3.50", "Coffee", None), line("-3.50", "Coffee", None), line("-3.5", "Coffee ", None)];
+        let first = fingerprints(1, &lines);
+        assert_ne!(first[0], first[1]);
+        // The third line is a third occurrence of the same booking, so it gets its own fingerprint
+        assert_ne!(first[2], first[0]);
+        assert_ne!(first[2], first[1]);
+        assert_eq!(fingerprints(1, &lines[..2]), first[..2]);
+        assert_ne!(fingerprints(2, &lines), first);
+    }
+
+    #[test]
+    fn ignores_how_the_amount_is_written_and_surrounding_spaces() {
+        let plain = fingerprints(1, &[line("-3.50", "Coffee", None)]);
+        let untidy = fingerprints(1, &[line("-3.5", "Coffee ", None)]);
+        assert_eq!(plain, untidy);
+        assert_ne!(plain, fingerprints(1, &[line("-3.51", "Coffee", None)]));
+    }
+
+    #[test]
+    fn identifies_lines_by_bank_id_when_present() {
+        let first = fingerprints(1, &[line("-3.50", "Coffee", Some("T1"))]);
+        let corrected = fingerprints(1, &[line("-3.60", "Coffee and cake", Some("T1"))]);
+        assert_eq!(first, corrected);
+    }
+
+    #[test]
+    fn fingerprints_line_by_line_like_a_whole_statement() {
+        let lines = [line("-3.50", "Coffee", None), line("-3.50", "Coffee", None), line("10", "Refund", Some("R"))];
+        let mut streamed = LineFingerprints::new(7);
+        let one_by_one: Vec<String> = lines.iter().map(|line| streamed.next(line)).collect();
+        assert_eq!(one_by_one, fingerprints(7, &lines));
+    }
+}
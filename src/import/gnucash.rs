@@ -0,0 +1,382 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use flate2::read::GzDecoder;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use super::double_entry::{self, AccountKind, StatementBuilder};
+use super::xml::{self, XmlElement};
+use super::{ImportError, RowError, Statement};
+
+/// GnuCash account types that hold money or debt, and become FinWise accounts.
+const BALANCE_TYPES: [&str; 9] = ["BANK", "CASH", "ASSET", "CREDIT", "LIABILITY", "STOCK", "MUTUAL", "RECEIVABLE", "PAYABLE"];
+
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+struct BookAccount {
+    name: String,
+    account_type: String,
+    parent: Option<String>,
+}
+
+struct BookSplit {
+    account: String,
+    value: BigDecimal,
+    memo: String,
+}
+
+struct BookTransaction {
+    guid: String,
+    date: String,
+    description: String,
+    num: String,
+    currency: String,
+    splits: Vec<BookSplit>,
+}
+
+/// Accounts by GUID and transactions in book order, whichever backend they came from.
+#[derive(Default)]
+struct Book {
+    accounts: HashMap<String, BookAccount>,
+    transactions: Vec<BookTransaction>,
+    /// Root of the scheduled-transaction templates, whose splits are not real bookings.
+    template_root: Option<String>,
+}
+
+/// Reads a GnuCash book saved as compressed XML, plain XML or SQLite.
+pub fn parse_file(path: &Path) -> Result<Vec<Statement>, ImportError> {
+    let bytes = fs::read(path)?;
+    let book = if bytes.starts_with(SQLITE_MAGIC) {
+        read_sqlite(path)?
+    } else if bytes.starts_with(GZIP_MAGIC) {
+        let mut content = String::new();
+        GzDecoder::new(bytes.as_slice()).read_to_string(&mut content)?;
+        read_xml(&content)?
+    } else {
+        read_xml(&String::from_utf8_lossy(&bytes))?
+    };
+    to_statements(&book)
+}
+
+fn read_xml(content: &str) -> Result<Book, ImportError> {
+    let document = xml::parse(content)?;
+    let book_element = if document.name == "book" { Some(&document) } else { document.child("book") }
+        .ok_or_else(|| ImportError::Format("Not a GnuCash XML book (no <gnc:book>)".to_string()))?;
+
+    let mut book = Book::default();
+    for account in book_element.children_named("account") {
+        let guid = account.text_at(&["id"]).ok_or_else(|| ImportError::Format("GnuCash account without id".to_string()))?;
+        book.accounts.insert(
+            guid,
+            BookAccount {
+                name: account.text_at(&["name"]).unwrap_or_default(),
+                account_type: account.text_at(&["type"]).unwrap_or_default(),
+                parent: account.text_at(&["parent"]),
+            },
+        );
+    }
+    for transaction in book_element.children_named("transaction") {
+        book.transactions.push(read_xml_transaction(transaction)?);
+    }
+    Ok(book)
+}
+
+fn read_xml_transaction(transaction: &XmlElement) -> Result<BookTransaction, ImportError> {
+    let splits = transaction
+        .path(&["splits"])
+        .map(|splits| {
+            splits
+                .children_named("split")
+                .map(|split| {
+                    let value = split.text_at(&["value"]).unwrap_or_default();
+                    Ok(BookSplit {
+                        account: split.text_at(&["account"]).unwrap_or_default(),
+                        value: parse_fraction(&value).map_err(ImportError::Format)?,
+                        memo: split.text_at(&["memo"]).unwrap_or_default(),
+                    })
+                })
+                .collect::<Result<Vec<_>, ImportError>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(BookTransaction {
+        guid: transaction.text_at(&["id"]).unwrap_or_default(),
+        date: transaction.text_at(&["date-posted", "date"]).unwrap_or_default(),
+        description: transaction.text_at(&["description"]).unwrap_or_default(),
+        num: transaction.text_at(&["num"]).unwrap_or_default(),
+        currency: transaction.text_at(&["currency", "id"]).unwrap_or_default(),
+        splits,
+    })
+}
+
+fn read_sqlite(path: &Path) -> Result<Book, ImportError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut book = Book {
+        template_root: conn.query_row("SELECT root_template_guid FROM books", [], |row| row.get(0)).ok(),
+        ..Book::default()
+    };
+
+    let mut statement = conn.prepare("SELECT guid, name, account_type, parent_guid FROM accounts")?;
+    let accounts = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            BookAccount {
+                name: row.get(1)?,
+                account_type: row.get(2)?,
+                parent: row.get(3)?,
+            },
+        ))
+    })?;
+    for account in accounts {
+        let (guid, account) = account?;
+        book.accounts.insert(guid, account);
+    }
+
+    let mut splits_by_transaction: HashMap<String, Vec<BookSplit>> = HashMap::new();
+    let mut statement = conn.prepare("SELECT tx_guid, account_guid, memo, value_num, value_denom FROM splits")?;
+    let splits = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+        ))
+    })?;
+    for split in splits {
+        let (transaction, account, memo, num, denom) = split?;
+        splits_by_transaction.entry(transaction).or_default().push(BookSplit {
+            account,
+            value: fraction(num, denom).map_err(ImportError::Format)?,
+            memo: memo.unwrap_or_default(),
+        });
+    }
+
+    let mut statement = conn.prepare(
+        "SELECT t.guid, t.post_date, t.description, t.num, COALESCE(c.mnemonic, '')
+         FROM transactions t LEFT JOIN commodities c ON c.guid = t.currency_guid
+         ORDER BY t.post_date, t.enter_date",
+    )?;
+    let transactions = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, Option<String>>(3)?,
+            row.get::<_, String>(4)?,
+        ))
+    })?;
+    for transaction in transactions {
+        let (guid, date, description, num, currency) = transaction?;
+        book.transactions.push(BookTransaction {
+            splits: splits_by_transaction.remove(&guid).unwrap_or_default(),
+            guid,
+            date: date.unwrap_or_default(),
+            description: description.unwrap_or_default(),
+            num: num.unwrap_or_default(),
+            currency,
+        });
+    }
+    Ok(book)
+}
+
+/// Maps the book onto statements: one per bank, cash, asset, credit card,
+/// liability or investment account, named by its full path without the root
+/// (`Assets:Current Assets:Checking Account`). Transaction descriptions are the
+/// payees and the GUID serves as bank id, so re-importing a book skips what is
+/// already there.
+fn to_statements(book: &Book) -> Result<Vec<Statement>, ImportError> {
+    let mut full_names: HashMap<&str, String> = HashMap::new();
+    let mut kinds: HashMap<String, AccountKind> = HashMap::new();
+    let mut template_accounts: Vec<&str> = Vec::new();
+    for (guid, account) in &book.accounts {
+        let (name, is_template) = full_name(book, guid);
+        if is_template {
+            template_accounts.push(guid);
+            continue;
+        }
+        let kind = if BALANCE_TYPES.contains(&account.account_type.as_str()) {
+            AccountKind::Balance
+        } else if account.account_type == "EQUITY" && name.to_lowercase().contains("opening") {
+            AccountKind::OpeningBalance
+        } else {
+            AccountKind::Category
+        };
+        kinds.insert(name.clone(), kind);
+        full_names.insert(guid, name);
+    }
+
+    let mut builder = StatementBuilder::new(|account: &str| kinds.get(account).copied().unwrap_or(AccountKind::Category));
+    for (index, transaction) in book.transactions.iter().enumerate() {
+        if transaction.splits.iter().any(|split| template_accounts.contains(&split.account.as_str())) {
+            continue;
+        }
+        let line = index + 1;
+        let first_balance_account = transaction
+            .splits
+            .iter()
+            .filter_map(|split| full_names.get(split.account.as_str()))
+            .find(|name| builder.is_balance_account(name))
+            .cloned();
+
+        let date = match parse_date(&transaction.date) {
+            Some(date) => date,
+            None => {
+                let message = format!("'{}': invalid date '{}'", transaction.description, transaction.date);
+                builder.reject(first_balance_account.as_deref(), RowError { line, message });
+                continue;
+            }
+        };
+        let Some(postings) = transaction
+            .splits
+            .iter()
+            .map(|split| {
+                full_names.get(split.account.as_str()).map(|account| double_entry::Posting {
+                    account: account.clone(),
+                    amount: split.value.clone(),
+                    commodity: transaction.currency.clone(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+        else {
+            let message = format!("'{}': split refers to an unknown account", transaction.description);
+            builder.reject(first_balance_account.as_deref(), RowError { line, message });
+            continue;
+        };
+
+        let mut narration: Vec<&str> = transaction.splits.iter().map(|split| split.memo.as_str()).filter(|memo| !memo.is_empty()).collect();
+        narration.dedup();
+        let mut narration = narration.join("; ");
+        if !transaction.num.is_empty() {
+            narration = format!("{} (#{})", narration, transaction.num).trim().to_string();
+        }
+
+        builder.add(&double_entry::Transaction {
+            line,
+            date,
+            payee: Some(transaction.description.clone()).filter(|description| !description.is_empty()),
+            narration,
+            bank_id: Some(transaction.guid.clone()).filter(|guid| !guid.is_empty()),
+            postings,
+        });
+    }
+
+    let statements = builder.finish();
+    if statements.is_empty() {
+        return Err(ImportError::Format("GnuCash book contains no bank, cash, asset or liability transactions".to_string()));
+    }
+    Ok(statements)
+}
+
+/// The `Parent:Child` path of an account below the book root, and whether it
+/// belongs to the scheduled-transaction templates.
+fn full_name(book: &Book, guid: &str) -> (String, bool) {
+    let mut names = Vec::new();
+    let mut current = Some(guid.to_string());
+    let mut is_template = false;
+    while let Some(id) = current {
+        if book.template_root.as_deref() == Some(id.as_str()) {
+            is_template = true;
+        }
+        match book.accounts.get(&id) {
+            Some(account) if account.account_type != "ROOT" => {
+                names.push(account.name.clone());
+                current = account.parent.clone();
+            }
+            _ => break,
+        }
+        // Guard against cycles in a damaged book
+        if names.len() > 64 {
+            break;
+        }
+    }
+    names.reverse();
+    (names.join(":"), is_template)
+}
+
+/// Parses GnuCash's `num/denom` rational amounts.
+fn parse_fraction(value: &str) -> Result<BigDecimal, String> {
+    let invalid = || format!("Invalid amount '{}'", value);
+    let (num, denom) = value.split_once('/').unwrap_or((value, "1"));
+    fraction(num.trim().parse().map_err(|_| invalid())?, denom.trim().parse().map_err(|_| invalid())?)
+}
+
+fn fraction(num: i64, denom: i64) -> Result<BigDecimal, String> {
+    if denom == 0 {
+        return Err(format!("Invalid amount {}/0", num));
+    }
+    // Denominators are powers of ten for currencies; keep those exact
+    let digits = denom.to_string();
+    if digits.starts_with('1') && digits[1..].chars().all(|c| c == '0') {
+        return Ok(BigDecimal::new(num.into(), (digits.len() - 1) as i64));
+    }
+    Ok((BigDecimal::from(num) / BigDecimal::from(denom)).round(8))
+}
+
+/// Parses `2024-01-15 10:59:00 +0000` (XML and newer SQLite books) and `20240115105900` (older SQLite books).
+fn parse_date(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    let date = if value.get(4..5) == Some("-") {
+        NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()?
+    } else {
+        NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?
+    };
+    Some(date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOK: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+<gnc-v2 xmlns:gnc="http://www.gnucash.org/XML/gnc" xmlns:act="http://www.gnucash.org/XML/act" xmlns:trn="http://www.gnucash.org/XML/trn"
+        xmlns:split="http://www.gnucash.org/XML/split" xmlns:ts="http://www.gnucash.org/XML/ts" xmlns:cmdty="http://www.gnucash.org/XML/cmdty">
+<gnc:book version="2.0.0">
+  <gnc:account version="2.0.0"><act:name>Root Account</act:name><act:id type="guid">r</act:id><act:type>ROOT</act:type></gnc:account>
+  <gnc:account version="2.0.0"><act:name>Assets</act:name><act:id type="guid">a</act:id><act:type>ASSET</act:type><act:parent type="guid">r</act:parent></gnc:account>
+  <gnc:account version="2.0.0"><act:name>Checking</act:name><act:id type="guid">c</act:id><act:type>BANK</act:type><act:parent type="guid">a</act:parent></gnc:account>
+  <gnc:account version="2.0.0"><act:name>Expenses</act:name><act:id type="guid">e</act:id><act:type>EXPENSE</act:type><act:parent type="guid">r</act:parent></gnc:account>
+  <gnc:account version="2.0.0"><act:name>Groceries</act:name><act:id type="guid">g</act:id><act:type>EXPENSE</act:type><act:parent type="guid">e</act:parent></gnc:account>
+  <gnc:transaction version="2.0.0">
+    <trn:id type="guid">t1</trn:id>
+    <trn:currency><cmdty:space>CURRENCY</cmdty:space><cmdty:id>EUR</cmdty:id></trn:currency>
+    <trn:num>42</trn:num>
+    <trn:date-posted><ts:date>2024-01-15 10:59:00 +0000</ts:date></trn:date-posted>
+    <trn:description>Corner Shop</trn:description>
+    <trn:splits>
+      <trn:split><split:memo>Milk</split:memo><split:value>-1234/100</split:value><split:account type="guid">c</split:account></trn:split>
+      <trn:split><split:value>1234/100</split:value><split:account type="guid">g</split:account></trn:split>
+    </trn:splits>
+  </gnc:transaction>
+</gnc:book>
+</gnc-v2>"#;
+
+    #[test]
+    fn books_splits_on_the_full_account_path() {
+        let statements = to_statements(&read_xml(BOOK).unwrap()).unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account_name.as_deref(), Some("Assets:Checking"));
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+        let line = &statement.lines[0];
+        assert_eq!(line.amount.to_string(), "-12.34");
+        assert_eq!(line.date.date(), NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+        assert_eq!(line.counterparty.as_deref(), Some("Corner Shop"));
+        assert_eq!(line.category.as_deref(), Some("Expenses:Groceries"));
+        assert_eq!(line.memo, "Milk (#42)");
+        assert_eq!(line.bank_id.as_deref(), Some("t1"));
+    }
+
+    #[test]
+    fn reads_rational_amounts() {
+        assert_eq!(parse_fraction("-1234/100").unwrap().to_string(), "-12.34");
+        assert_eq!(parse_fraction("5").unwrap().to_string(), "5");
+        assert_eq!(parse_fraction("1/3").unwrap().to_string(), "0.33333333");
+        assert!(parse_fraction("1/0").is_err());
+    }
+}
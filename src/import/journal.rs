@@ -0,0 +1,370 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::double_entry::{self, AccountKind, StatementBuilder};
+use super::{Balance, ImportError, RowError, Statement};
+
+/// Plain-text accounting syntaxes. hledger reads ledger journals, so the two share a parser.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dialect {
+    Ledger,
+    Beancount,
+}
+
+/// Metadata keys that carry the bank's transaction id, as written by FinWise's own export.
+const BANK_ID_KEYS: [&str; 3] = ["bank_id", "fitid", "external_id"];
+
+/// One posting of a journal transaction, with its value in the transaction's commodity.
+struct Posting {
+    account: String,
+    /// `None` while the amount is elided; filled in from the other postings.
+    value: Option<(BigDecimal, String)>,
+}
+
+/// A journal transaction with its postings, before it is split per asset account.
+struct Entry {
+    line: usize,
+    date: NaiveDateTime,
+    payee: Option<String>,
+    narration: String,
+    metadata: HashMap<String, String>,
+    postings: Vec<Posting>,
+    error: Option<String>,
+}
+
+pub fn parse_file(path: &Path, dialect: Dialect) -> Result<Vec<Statement>, ImportError> {
+    let content = fs::read_to_string(path)?;
+    parse_str(&content, dialect)
+}
+
+/// Parses a ledger/hledger or beancount journal into one statement per asset
+/// or liability account, named by its full account path (`Assets:Bank:Checking`).
+///
+/// Accounts are classified by their root: `Assets` and `Liabilities` keep
+/// balances and `Equity:Opening…` holds opening balances. See
+/// `StatementBuilder` for how transactions become statement lines.
+pub fn parse_str(content: &str, dialect: Dialect) -> Result<Vec<Statement>, ImportError> {
+    let mut builder = StatementBuilder::new(account_kind);
+    let mut entry: Option<Entry> = None;
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let line = raw_line.trim_end_matches('\r');
+        let trimmed = line.trim();
+
+        if line.starts_with([' ', '\t']) && !trimmed.is_empty() {
+            if let Some(entry) = entry.as_mut() {
+                add_entry_line(entry, trimmed, dialect);
+            }
+            continue;
+        }
+
+        if let Some(finished) = entry.take() {
+            add_entry(&mut builder, finished);
+        }
+        if trimmed.is_empty() || trimmed.starts_with([';', '#', '%', '*']) {
+            continue;
+        }
+
+        let Some((date, rest)) = split_date(trimmed) else { continue };
+        let rest = rest.trim();
+        let keyword = rest.split_whitespace().next().unwrap_or("");
+        match keyword {
+            "balance" if dialect == Dialect::Beancount => {
+                if let Some((account, balance)) = parse_balance_assertion(rest, date) {
+                    let statement = builder.statement_for(&account);
+                    if statement.closing_balance.as_ref().is_none_or(|existing| existing.date <= balance.date) {
+                        statement.closing_balance = Some(balance);
+                    }
+                }
+            }
+            "open" | "close" | "commodity" | "price" | "pad" | "note" | "document" | "event" | "custom" | "query"
+                if dialect == Dialect::Beancount => {}
+            _ => entry = Some(parse_header(line_number, date, rest, dialect)),
+        }
+    }
+    if let Some(finished) = entry.take() {
+        add_entry(&mut builder, finished);
+    }
+
+    let statements = builder.finish();
+    if statements.is_empty() {
+        return Err(ImportError::Format("Journal contains no postings to asset or liability accounts".to_string()));
+    }
+    Ok(statements)
+}
+
+/// Splits a leading `2024-01-31`, `2024/01/31` or `2024.01.31` date (with an optional
+/// ledger `=effective` date) from the rest of the line.
+fn split_date(line: &str) -> Option<(NaiveDateTime, &str)> {
+    let end = line.find(|c: char| c.is_whitespace()).unwrap_or(line.len());
+    let token = line[..end].split('=').next().unwrap_or("");
+    let normalized = token.replace(['/', '.'], "-");
+    let date = NaiveDate::parse_from_str(&normalized, "%Y-%m-%d").ok()?;
+    Some((date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"), &line[end..]))
+}
+
+fn parse_header(line: usize, date: NaiveDateTime, rest: &str, dialect: Dialect) -> Entry {
+    let mut description = rest;
+    // Status flag and beancount's `txn` keyword
+    for prefix in ["txn", "*", "!"] {
+        if let Some(stripped) = description.strip_prefix(prefix) {
+            description = stripped.trim_start();
+        }
+    }
+    // ledger transaction code, e.g. `(1042)`
+    if description.starts_with('(') {
+        if let Some(close) = description.find(')') {
+            description = description[close + 1..].trim_start();
+        }
+    }
+    let description = description.split(" ;").next().unwrap_or(description).trim();
+
+    let (payee, narration) = match dialect {
+        Dialect::Beancount => {
+            let strings = quoted_strings(description);
+            match strings.len() {
+                0 => (None, String::new()),
+                1 => (None, strings[0].clone()),
+                _ => (Some(strings[0].clone()).filter(|payee| !payee.is_empty()), strings[1].clone()),
+            }
+        }
+        // hledger separates payee and note with `|`
+        Dialect::Ledger => match description.split_once('|') {
+            Some((payee, note)) => (Some(payee.trim().to_string()), note.trim().to_string()),
+            None => (Some(description.to_string()).filter(|payee| !payee.is_empty()), String::new()),
+        },
+    };
+
+    Entry {
+        line,
+        date,
+        payee,
+        narration,
+        metadata: HashMap::new(),
+        postings: Vec::new(),
+        error: None,
+    }
+}
+
+/// Reads an indented line of a transaction: a posting, a comment, or metadata.
+fn add_entry_line(entry: &mut Entry, line: &str, dialect: Dialect) {
+    if let Some(comment) = line.strip_prefix(';').or_else(|| line.strip_prefix('#')) {
+        if let Some((key, value)) = split_metadata(comment) {
+            entry.metadata.insert(key, value);
+        } else if entry.narration.is_empty() {
+            entry.narration = comment.trim().to_string();
+        }
+        return;
+    }
+    if dialect == Dialect::Beancount && !line.starts_with(|c: char| c.is_uppercase() || c == '!' || c == '*') {
+        if let Some((key, value)) = split_metadata(line) {
+            entry.metadata.insert(key, value);
+        }
+        return;
+    }
+
+    let (posting, comment) = match line.split_once(';') {
+        Some((posting, comment)) => (posting.trim(), Some(comment)),
+        None => (line, None),
+    };
+    if let Some((key, value)) = comment.and_then(split_metadata) {
+        entry.metadata.insert(key, value);
+    }
+    let posting = posting.trim_start_matches(['!', '*']).trim_start();
+
+    let (account, amount) = match dialect {
+        // beancount account names cannot contain spaces
+        Dialect::Beancount => posting.split_once(char::is_whitespace).unwrap_or((posting, "")),
+        // ledger accounts may, so the amount starts after two spaces or a tab
+        Dialect::Ledger => match posting.find("  ").into_iter().chain(posting.find('\t')).min() {
+            Some(split) => (&posting[..split], &posting[split..]),
+            None => (posting, ""),
+        },
+    };
+    let account = account.trim().trim_matches(['(', ')', '[', ']']).to_string();
+    let amount = amount.trim();
+
+    let value = if amount.is_empty() {
+        None
+    } else {
+        match parse_posting_amount(amount) {
+            Ok(value) => Some(value),
+            Err(message) => {
+                entry.error.get_or_insert(format!("{}: {}", account, message));
+                None
+            }
+        }
+    };
+    entry.postings.push(Posting { account, value });
+}
+
+/// Splits `key: value` metadata; keys are single words, values may be quoted.
+fn split_metadata(text: &str) -> Option<(String, String)> {
+    let (key, value) = text.trim().split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((key.to_lowercase(), value.trim().trim_matches('"').to_string()))
+}
+
+/// Parses a posting amount into its value: the cost for `@ unit`, `@@ total`
+/// and `{unit}` annotations, the amount itself otherwise. Balance assertions are ignored.
+fn parse_posting_amount(text: &str) -> Result<(BigDecimal, String), String> {
+    let text = text.split('=').next().unwrap_or(text).trim();
+
+    if let Some((amount, total)) = text.split_once("@@") {
+        let (quantity, _) = parse_amount(strip_cost(amount))?;
+        let (total, commodity) = parse_amount(total)?;
+        return Ok((if quantity < BigDecimal::zero() { -total.abs() } else { total.abs() }, commodity));
+    }
+    if let Some((amount, price)) = text.split_once('@') {
+        let (quantity, _) = parse_amount(strip_cost(amount))?;
+        let (price, commodity) = parse_amount(price)?;
+        return Ok((quantity * price, commodity));
+    }
+    if let (Some(open), Some(close)) = (text.find('{'), text.rfind('}')) {
+        let cost = text[open + 1..close].split(',').next().unwrap_or("").trim();
+        let (quantity, _) = parse_amount(&text[..open])?;
+        if !cost.is_empty() {
+            let (price, commodity) = parse_amount(cost)?;
+            return Ok((quantity * price, commodity));
+        }
+        return Err("lot without cost".to_string());
+    }
+    parse_amount(text)
+}
+
+fn strip_cost(text: &str) -> &str {
+    text.split('{').next().unwrap_or(text)
+}
+
+/// Parses `-50.00 EUR`, `EUR -50.00`, `$-50.00`, `-$50` and `"1,234.56 USD"` style amounts.
+fn parse_amount(text: &str) -> Result<(BigDecimal, String), String> {
+    let text = text.trim();
+    let mut number = String::new();
+    let mut commodity = String::new();
+    let mut negative = false;
+    let mut in_quotes = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            _ if in_quotes => commodity.push(c),
+            '-' if number.is_empty() => negative = true,
+            '+' if number.is_empty() => {}
+            '0'..='9' | '.' => number.push(c),
+            ',' | '\'' | '_' => {}
+            _ if c.is_whitespace() => {}
+            _ => commodity.push(c),
+        }
+    }
+
+    let mut value = BigDecimal::from_str(&number).map_err(|_| format!("Invalid amount '{}'", text))?;
+    if negative {
+        value = -value;
+    }
+    Ok((value, commodity.trim().to_string()))
+}
+
+/// Parses `balance Assets:Checking 100.00 EUR`.
+fn parse_balance_assertion(rest: &str, date: NaiveDateTime) -> Option<(String, Balance)> {
+    let mut parts = rest.split_whitespace().skip(1);
+    let account = parts.next()?.to_string();
+    let amount: Vec<&str> = parts.take_while(|part| !part.starts_with('~')).collect();
+    let (amount, _) = parse_amount(&amount.join(" ")).ok()?;
+    Some((account, Balance { amount, date }))
+}
+
+fn quoted_strings(text: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut current: Option<String> = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match current.as_mut() {
+            Some(string) if escaped => {
+                string.push(c);
+                escaped = false;
+            }
+            Some(_) if c == '\\' => escaped = true,
+            Some(_) if c == '"' => strings.push(current.take().unwrap_or_default()),
+            Some(string) => string.push(c),
+            None if c == '"' => current = Some(String::new()),
+            None => {}
+        }
+    }
+    strings
+}
+
+/// Asset and liability accounts are the ones FinWise keeps balances for.
+fn account_kind(account: &str) -> AccountKind {
+    let lower = account.to_lowercase();
+    let root = lower.split(':').next().unwrap_or("");
+    match root {
+        "assets" | "asset" | "liabilities" | "liability" => AccountKind::Balance,
+        "equity" if lower.contains("opening") => AccountKind::OpeningBalance,
+        _ => AccountKind::Category,
+    }
+}
+
+/// Fills in an elided amount and hands the entry to the builder.
+fn add_entry<F: Fn(&str) -> AccountKind>(builder: &mut StatementBuilder<F>, mut entry: Entry) {
+    if let Err(message) = complete_postings(&mut entry) {
+        let account = entry.postings.iter().find(|posting| builder.is_balance_account(&posting.account)).map(|posting| posting.account.clone());
+        builder.reject(account.as_deref(), RowError { line: entry.line, message });
+        return;
+    }
+
+    let postings = entry
+        .postings
+        .into_iter()
+        .map(|posting| {
+            let (amount, commodity) = posting.value.expect("postings are complete");
+            double_entry::Posting { account: posting.account, amount, commodity }
+        })
+        .collect();
+    builder.add(&double_entry::Transaction {
+        line: entry.line,
+        date: entry.date,
+        payee: entry.payee,
+        narration: entry.narration,
+        bank_id: BANK_ID_KEYS.iter().find_map(|key| entry.metadata.get(*key).cloned()),
+        postings,
+    });
+}
+
+/// Checks the postings parsed and computes a single elided amount from the others.
+fn complete_postings(entry: &mut Entry) -> Result<(), String> {
+    if let Some(error) = entry.error.take() {
+        return Err(error);
+    }
+    if entry.postings.len() < 2 {
+        return Err("Transaction needs at least two postings".to_string());
+    }
+
+    let elided: Vec<usize> = entry.postings.iter().enumerate().filter(|(_, posting)| posting.value.is_none()).map(|(index, _)| index).collect();
+    match elided.as_slice() {
+        [] => Ok(()),
+        [missing] => {
+            let mut commodity = String::new();
+            let mut total = BigDecimal::zero();
+            for posting in &entry.postings {
+                if let Some((value, posting_commodity)) = &posting.value {
+                    if !commodity.is_empty() && posting_commodity != &commodity {
+                        return Err("Cannot infer an elided amount across commodities".to_string());
+                    }
+                    commodity = posting_commodity.clone();
+                    total += value;
+                }
+            }
+            entry.postings[*missing].value = Some((-total, commodity));
+            Ok(())
+        }
+        _ => Err("More than one posting without amount".to_string()),
+    }
+}
@@ -0,0 +1,435 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use std::str::FromStr;
+
+/// Order of day, month and year in numeric dates such as `03/04/2024`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+impl DateOrder {
+    fn describe(&self) -> &'static str {
+        match self {
+            DateOrder::DayMonthYear => "day first",
+            DateOrder::MonthDayYear => "month first",
+            DateOrder::YearMonthDay => "year first",
+        }
+    }
+}
+
+/// The character that separates the decimals of an amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalSeparator {
+    Point,
+    Comma,
+}
+
+impl DecimalSeparator {
+    fn as_char(&self) -> char {
+        match self {
+            DecimalSeparator::Point => '.',
+            DecimalSeparator::Comma => ',',
+        }
+    }
+
+    /// Characters that may group thousands alongside this separator.
+    fn grouping(&self) -> &'static [char] {
+        match self {
+            DecimalSeparator::Point => &[',', '\'', '’', ' ', '\u{a0}', '\u{202f}'],
+            DecimalSeparator::Comma => &['.', '\'', '’', ' ', '\u{a0}', '\u{202f}'],
+        }
+    }
+}
+
+/// How a country or bank writes amounts and dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    pub name: &'static str,
+    pub decimal: DecimalSeparator,
+    pub dates: DateOrder,
+}
+
+pub const LOCALES: [Locale; 8] = [
+    Locale { name: "en-US", decimal: DecimalSeparator::Point, dates: DateOrder::MonthDayYear },
+    Locale { name: "en-GB", decimal: DecimalSeparator::Point, dates: DateOrder::DayMonthYear },
+    Locale { name: "de-DE", decimal: DecimalSeparator::Comma, dates: DateOrder::DayMonthYear },
+    Locale { name: "de-CH", decimal: DecimalSeparator::Point, dates: DateOrder::DayMonthYear },
+    Locale { name: "fr-FR", decimal: DecimalSeparator::Comma, dates: DateOrder::DayMonthYear },
+    Locale { name: "nl-NL", decimal: DecimalSeparator::Comma, dates: DateOrder::DayMonthYear },
+    Locale { name: "es-ES", decimal: DecimalSeparator::Comma, dates: DateOrder::DayMonthYear },
+    Locale { name: "iso", decimal: DecimalSeparator::Point, dates: DateOrder::YearMonthDay },
+];
+
+impl Locale {
+    /// Looks a locale up by name, ignoring case and accepting `de_DE` for `de-DE`.
+    pub fn named(name: &str) -> Result<Locale, String> {
+        let wanted = name.trim().replace('_', "-");
+        LOCALES.iter().find(|locale| locale.name.eq_ignore_ascii_case(&wanted)).copied().ok_or_else(|| {
+            let known: Vec<&str> = LOCALES.iter().map(|locale| locale.name).collect();
+            format!("Unknown locale '{}' (known: {})", name, known.join(", "))
+        })
+    }
+}
+
+/// Month names and abbreviations in English, German, French and Spanish, matched by prefix.
+const MONTHS: [(&str, u32); 29] = [
+    ("jan", 1), ("ene", 1), ("feb", 2), ("fév", 2), ("fev", 2), ("mär", 3), ("mrz", 3), ("mar", 3),
+    ("apr", 4), ("avr", 4), ("abr", 4), ("may", 5), ("mai", 5), ("juin", 6), ("jun", 6), ("juil", 7),
+    ("jul", 7), ("aug", 8), ("aoû", 8), ("aou", 8), ("ago", 8), ("sep", 9), ("oct", 10), ("okt", 10),
+    ("nov", 11), ("dec", 12), ("dez", 12), ("déc", 12), ("dic", 12),
+];
+
+/// Parses an amount written with `decimal` as decimal separator.
+///
+/// Currency codes and symbols around the number are ignored. Negative amounts
+/// may be written `-12.00`, `12.00-`, `(12.00)` or `12.00 DR`. Thousands
+/// groups must have three digits, so under a decimal point `12,50` is an
+/// error rather than 1250.
+pub fn parse_amount(value: &str, decimal: DecimalSeparator) -> Result<BigDecimal, String> {
+    let invalid = |reason: String| format!("Invalid amount '{}': {}", value.trim(), reason);
+    let is_noise = |c: char| c.is_whitespace() || c.is_alphabetic() || matches!(c, '$' | '€' | '£' | '¥' | '₹' | '₽' | '₺' | '₩' | '¢' | '₿');
+
+    let mut text = value.trim();
+    let mut negative = false;
+    if let Some(inner) = text.strip_prefix('(').and_then(|text| text.strip_suffix(')')) {
+        negative = true;
+        text = inner;
+    }
+    // Debit and credit markers; a trailing `CR` goes with the currency codes below
+    if let Some(rest) = text.len().checked_sub(2).and_then(|start| text.get(start..).filter(|end| end.eq_ignore_ascii_case("dr")).map(|_| &text[..start])) {
+        negative = true;
+        text = rest;
+    }
+    text = text.trim_matches(is_noise);
+    if let Some(rest) = text.strip_prefix(['-', '−']) {
+        negative = true;
+        text = rest.trim_start_matches(is_noise);
+    } else if let Some(rest) = text.strip_prefix('+') {
+        text = rest.trim_start_matches(is_noise);
+    }
+    if let Some(rest) = text.strip_suffix(['-', '−']) {
+        negative = true;
+        text = rest.trim_end_matches(is_noise);
+    }
+
+    if !text.chars().any(|c| c.is_ascii_digit()) {
+        return Err(invalid("no digits".to_string()));
+    }
+    let grouping = decimal.grouping();
+    if let Some(other) = text.chars().find(|c| !c.is_ascii_digit() && *c != decimal.as_char() && !grouping.contains(c)) {
+        return Err(invalid(format!("unexpected character '{}'", other)));
+    }
+
+    let mut parts = text.split(decimal.as_char());
+    let integer = parts.next().unwrap_or("");
+    let fraction = parts.next().unwrap_or("");
+    if parts.next().is_some() {
+        return Err(invalid(format!("more than one decimal separator '{}'", decimal.as_char())));
+    }
+    if let Some(separator) = fraction.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(invalid(format!("'{}' after the decimal separator '{}'", separator, decimal.as_char())));
+    }
+
+    let separators: Vec<char> = integer.chars().filter(|c| !c.is_ascii_digit()).collect();
+    if let Some(&separator) = separators.first() {
+        if separators.iter().any(|other| *other != separator) {
+            return Err(invalid("mixed thousands separators".to_string()));
+        }
+        let groups: Vec<&str> = integer.split(separator).collect();
+        let first_ok = (1..=3).contains(&groups[0].len());
+        if !first_ok || groups[1..].iter().any(|group| group.len() != 3) {
+            return Err(invalid(format!(
+                "'{}' groups thousands when '{}' is the decimal separator, so it must be followed by three digits",
+                separator,
+                decimal.as_char()
+            )));
+        }
+    }
+
+    let digits: String = integer.chars().filter(|c| c.is_ascii_digit()).collect();
+    let normalized = format!(
+        "{}{}.{}",
+        if negative { "-" } else { "" },
+        if digits.is_empty() { "0" } else { &digits },
+        if fraction.is_empty() { "0" } else { fraction }
+    );
+    BigDecimal::from_str(&normalized).map_err(|_| invalid("not a number".to_string()))
+}
+
+/// Parses an amount found on its own, such as one read by OCR, taking the
+/// decimal separator from the value; `1,234` counts as thousands.
+pub fn parse_amount_guess(value: &str) -> Result<BigDecimal, String> {
+    parse_amount(value, decimal_evidence(value).unwrap_or(DecimalSeparator::Point))
+}
+
+/// The decimal separator a single amount proves, if any: `1.234,56` and `12,5`
+/// show a decimal comma, while `1,234` could be read either way.
+fn decimal_evidence(value: &str) -> Option<DecimalSeparator> {
+    let separators: Vec<(usize, char)> = value.char_indices().filter(|(_, c)| matches!(c, '.' | ',')).collect();
+    let &(position, last) = separators.last()?;
+    let digits_after = value[position + 1..].chars().take_while(|c| c.is_ascii_digit()).count();
+    let decimal = if separators.iter().any(|(_, c)| *c != last) || digits_after != 3 {
+        last
+    } else if separators.len() > 1 {
+        // `1.234.567`: the repeated separator groups thousands
+        if last == '.' { ',' } else { '.' }
+    } else {
+        return None;
+    };
+    Some(if decimal == ',' { DecimalSeparator::Comma } else { DecimalSeparator::Point })
+}
+
+/// Decides the decimal separator from the amounts of a whole file, by
+/// majority of the values that show it; `None` if none does.
+pub fn detect_decimal(values: &[&str]) -> Option<DecimalSeparator> {
+    let (mut point, mut comma) = (0, 0);
+    for value in values {
+        match decimal_evidence(value) {
+            Some(DecimalSeparator::Point) => point += 1,
+            Some(DecimalSeparator::Comma) => comma += 1,
+            None => {}
+        }
+    }
+    match (point, comma) {
+        (0, 0) => None,
+        _ if comma > point => Some(DecimalSeparator::Comma),
+        _ => Some(DecimalSeparator::Point),
+    }
+}
+
+/// The first amount whose value depends on the decimal separator, such as `1.234`.
+pub fn ambiguous_amount<'a>(values: &[&'a str]) -> Option<&'a str> {
+    values.iter().copied().find(|value| value.contains(['.', ',']) && decimal_evidence(value).is_none())
+}
+
+/// Parses a date with an optional time of day, such as `2024-03-12T18:42:10Z`,
+/// `12.03.24 18:42` or `Mar 12, 2024 6:42 PM`.
+///
+/// Numeric dates are read in `order` unless they start with a four-digit year;
+/// dates with a month name are read the same in any order. Two-digit years
+/// below 70 are taken as 20xx.
+pub fn parse_date(value: &str, order: DateOrder) -> Result<NaiveDateTime, String> {
+    let invalid = |reason: &str| format!("Invalid date '{}'{}", value.trim(), reason);
+    let text = value.trim();
+
+    // ISO 8601 joins date and time with a `T`
+    let (date_text, time_text) = match text.find('T') {
+        Some(index) if index > 0 && text[..index].ends_with(|c: char| c.is_ascii_digit()) => (&text[..index], &text[index + 1..]),
+        _ => match text.split_whitespace().position(|token| token.contains(':')) {
+            Some(position) => {
+                let start = text.split_whitespace().nth(position).and_then(|token| text.find(token)).unwrap_or(text.len());
+                (&text[..start], &text[start..])
+            }
+            None => (text, ""),
+        },
+    };
+
+    let parts: Vec<&str> = date_text
+        .split(|c: char| matches!(c, '.' | '/' | '-' | ',' | '\'') || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect();
+    let (year, month, day, read_in_order) = match parts[..] {
+        [compact] if compact.len() == 8 && compact.chars().all(|c| c.is_ascii_digit()) => {
+            (number(&compact[..4]), number(&compact[4..6]), number(&compact[6..]), false)
+        }
+        [first, second, third] => match parts.iter().position(|part| part.chars().any(|c| c.is_alphabetic())) {
+            Some(name_index) => {
+                let month = month_number(parts[name_index]).ok_or_else(|| invalid(&format!(" (unknown month '{}')", parts[name_index])))?;
+                let numbers: Vec<&str> = parts.iter().enumerate().filter(|(index, _)| *index != name_index).map(|(_, part)| *part).collect();
+                let (year, day) = if numbers[0].len() == 4 { (numbers[0], numbers[1]) } else { (numbers[1], numbers[0]) };
+                (number(year), Some(month), number(day), false)
+            }
+            None if first.len() == 4 => (number(first), number(second), number(third), false),
+            None => match order {
+                DateOrder::DayMonthYear => (number(third), number(second), number(first), true),
+                DateOrder::MonthDayYear => (number(third), number(first), number(second), true),
+                DateOrder::YearMonthDay => (number(first), number(second), number(third), true),
+            },
+        },
+        _ => return Err(invalid("")),
+    };
+    let (Some(year), Some(month), Some(day)) = (year, month, day) else { return Err(invalid("")) };
+    let year = match year {
+        0..=69 => 2000 + year,
+        70..=99 => 1900 + year,
+        _ => year,
+    };
+    let date = NaiveDate::from_ymd_opt(year as i32, month, day).ok_or_else(|| {
+        if read_in_order {
+            invalid(&format!(" (read {})", order.describe()))
+        } else {
+            invalid("")
+        }
+    })?;
+
+    let time = if time_text.trim().is_empty() {
+        NaiveTime::MIN
+    } else {
+        parse_time(time_text).ok_or_else(|| invalid(" (unreadable time of day)"))?
+    };
+    Ok(date.and_time(time))
+}
+
+/// Parses a date or date-time using a `chrono` format string.
+pub fn parse_date_as(value: &str, format: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+        return Ok(date_time);
+    }
+    NaiveDate::parse_from_str(value, format)
+        .map(|date| date.and_time(NaiveTime::MIN))
+        .map_err(|_| format!("Invalid date '{}' (expected format {})", value, format))
+}
+
+fn number(text: &str) -> Option<u32> {
+    text.parse().ok()
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.trim_end_matches('.').to_lowercase();
+    if name.chars().count() < 3 {
+        return None;
+    }
+    MONTHS.iter().find(|(prefix, _)| name.starts_with(prefix)).map(|(_, month)| *month)
+}
+
+/// Reads `18:42`, `18:42:10.250Z` or `6:42 PM`; a time zone after it is ignored.
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    let mut tokens = text.split_whitespace();
+    let clock: String = tokens.next()?.chars().take_while(|c| c.is_ascii_digit() || matches!(c, ':' | '.')).collect();
+    let mut fields = clock.split(':');
+    let mut hour = number(fields.next()?)?;
+    let minute = number(fields.next()?)?;
+    let second = match fields.next() {
+        Some(second) => number(second.split('.').next()?)?,
+        None => 0,
+    };
+    match tokens.next().map(|token| token.to_uppercase()).as_deref() {
+        Some("PM") if hour < 12 => hour += 12,
+        Some("AM") if hour == 12 => hour = 0,
+        _ => {}
+    }
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+/// The two leading numbers of a numeric date whose meaning depends on the
+/// date order, such as `03/04/2024`; `None` for ISO dates and month names.
+fn order_dependent(date: &str) -> Option<(u32, u32)> {
+    let date = date.split(|c: char| c.is_whitespace() || c == 'T').next()?;
+    let parts: Vec<&str> = date.split(['.', '/', '-']).collect();
+    match parts[..] {
+        [first, second, _] if first.len() <= 2 => Some((number(first)?, number(second)?)),
+        _ => None,
+    }
+}
+
+/// Decides the date order of a file from all its dates: dotted dates are read
+/// day first, and otherwise the order under which more dates are valid wins,
+/// so a single `13/04/2024` settles it. `None` if the dates read validly both
+/// ways; files with only ISO dates or month names report year first.
+pub fn detect_date_order(dates: &[&str]) -> Option<DateOrder> {
+    let numeric: Vec<&str> = dates.iter().copied().filter(|date| order_dependent(date).is_some()).collect();
+    if numeric.is_empty() {
+        return Some(DateOrder::YearMonthDay);
+    }
+    if numeric.iter().any(|date| date.contains('.')) {
+        return Some(DateOrder::DayMonthYear);
+    }
+    let valid = |order: DateOrder| numeric.iter().filter(|date| parse_date(date, order).is_ok()).count();
+    match valid(DateOrder::DayMonthYear).cmp(&valid(DateOrder::MonthDayYear)) {
+        std::cmp::Ordering::Greater => Some(DateOrder::DayMonthYear),
+        std::cmp::Ordering::Less => Some(DateOrder::MonthDayYear),
+        std::cmp::Ordering::Equal => None,
+    }
+}
+
+/// The first date that reads as two different days, such as `03/04/2024`.
+pub fn ambiguous_date<'a>(dates: &[&'a str]) -> Option<&'a str> {
+    dates
+        .iter()
+        .copied()
+        .find(|date| order_dependent(date).is_some_and(|(first, second)| first != second && first <= 12 && second <= 12))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn amount(value: &str, decimal: DecimalSeparator) -> String {
+        parse_amount(value, decimal).unwrap().to_string()
+    }
+
+    fn date(value: &str, order: DateOrder) -> String {
+        parse_date(value, order).unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_amounts_with_grouping_and_negative_markers() {
+        assert_eq!(amount("1,234.56", DecimalSeparator::Point), "1234.56");
+        assert_eq!(amount("EUR 1.234,5", DecimalSeparator::Comma), "1234.5");
+        assert_eq!(amount("1 234,56 €", DecimalSeparator::Comma), "1234.56");
+        assert_eq!(amount("(12.00)", DecimalSeparator::Point), "-12.00");
+        assert_eq!(amount("12.00 DR", DecimalSeparator::Point), "-12.00");
+        assert_eq!(amount("12.00-", DecimalSeparator::Point), "-12.00");
+        assert_eq!(amount("+7", DecimalSeparator::Point), "7.0");
+    }
+
+    #[test]
+    fn rejects_misplaced_grouping() {
+        let error = parse_amount("12,50", DecimalSeparator::Point).unwrap_err();
+        assert!(error.contains("three digits"), "{}", error);
+        assert!(parse_amount("1,23.00", DecimalSeparator::Point).is_err());
+        assert!(parse_amount("1.234.567", DecimalSeparator::Comma).is_ok());
+        assert!(parse_amount("1.234.56", DecimalSeparator::Comma).is_err());
+        assert!(parse_amount("1.2,3", DecimalSeparator::Point).is_err());
+        assert!(parse_amount("1,234'567.00", DecimalSeparator::Point).is_err());
+        assert!(parse_amount("n/a", DecimalSeparator::Point).is_err());
+    }
+
+    #[test]
+    fn parses_dates_in_the_given_order() {
+        assert_eq!(date("03/04/2024", DateOrder::DayMonthYear), "2024-04-03 00:00:00");
+        assert_eq!(date("03/04/2024", DateOrder::MonthDayYear), "2024-03-04 00:00:00");
+        assert_eq!(date("2024-03-12T18:42:10Z", DateOrder::DayMonthYear), "2024-03-12 18:42:10");
+        assert_eq!(date("12.03.24 18:42", DateOrder::DayMonthYear), "2024-03-12 18:42:00");
+        assert_eq!(date("Mar 12, 2024 6:42 PM", DateOrder::DayMonthYear), "2024-03-12 18:42:00");
+        assert_eq!(date("12. März 2024", DateOrder::MonthDayYear), "2024-03-12 00:00:00");
+        assert_eq!(date("20240312", DateOrder::MonthDayYear), "2024-03-12 00:00:00");
+        assert_eq!(date("01/02/69", DateOrder::DayMonthYear), "2069-02-01 00:00:00");
+        assert_eq!(date("01/02/70", DateOrder::DayMonthYear), "1970-02-01 00:00:00");
+    }
+
+    #[test]
+    fn names_the_order_an_impossible_date_was_read_in() {
+        let error = parse_date("13/25/2024", DateOrder::MonthDayYear).unwrap_err();
+        assert!(error.contains("month first"), "{}", error);
+        assert!(parse_date("2024-02-30", DateOrder::YearMonthDay).is_err());
+    }
+
+    #[test]
+    fn detects_the_date_order_of_a_file() {
+        assert_eq!(detect_date_order(&["03/04/2024", "13/04/2024"]), Some(DateOrder::DayMonthYear));
+        assert_eq!(detect_date_order(&["03/04/2024", "04/13/2024"]), Some(DateOrder::MonthDayYear));
+        assert_eq!(detect_date_order(&["03.04.2024"]), Some(DateOrder::DayMonthYear));
+        assert_eq!(detect_date_order(&["2024-04-03", "Apr 3, 2024"]), Some(DateOrder::YearMonthDay));
+        assert_eq!(detect_date_order(&["03/04/2024", "05/06/2024"]), None);
+    }
+
+    #[test]
+    fn finds_dates_that_read_two_ways() {
+        assert_eq!(ambiguous_date(&["13/04/2024", "03/04/2024"]), Some("03/04/2024"));
+        assert_eq!(ambiguous_date(&["04/04/2024", "2024-03-04", "13/04/2024"]), None);
+    }
+
+    #[test]
+    fn detects_the_decimal_separator_of_a_file() {
+        assert_eq!(detect_decimal(&["1.234,56", "12,5", "3.00"]), Some(DecimalSeparator::Comma));
+        assert_eq!(detect_decimal(&["1,234.56", "7"]), Some(DecimalSeparator::Point));
+        assert_eq!(detect_decimal(&["1.234.567", "8"]), Some(DecimalSeparator::Comma));
+        assert_eq!(detect_decimal(&["1,234", "7"]), None);
+        assert_eq!(ambiguous_amount(&["12.50", "1.234"]), Some("1.234"));
+        assert_eq!(ambiguous_amount(&["12.50", "7"]), None);
+    }
+}
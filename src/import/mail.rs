@@ -0,0 +1,155 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use mail_parser::MessageParser;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::locale::{self, DecimalSeparator};
+use super::mail_template::{compile, MailTemplate};
+use super::ImportError;
+
+/// One ordered article as listed in a confirmation mail.
+#[derive(Debug, Clone)]
+pub struct OrderItem {
+    pub description: String,
+    pub quantity: Option<BigDecimal>,
+    /// The price shown for the line.
+    pub price: BigDecimal,
+}
+
+/// An order confirmation read with a mail template.
+#[derive(Debug, Clone)]
+pub struct OrderMail {
+    /// Name of the template that recognised the mail.
+    pub template: String,
+    pub merchant: String,
+    pub order_number: Option<String>,
+    pub date: NaiveDate,
+    pub items: Vec<OrderItem>,
+    pub total: BigDecimal,
+    pub currency: Option<String>,
+    pub payment_method: String,
+}
+
+/// Lists the messages under `path`: the file itself, the `cur/` and `new/`
+/// folders of a Maildir, or every `.eml` file below a directory.
+pub fn mail_files(path: &Path) -> Result<Vec<PathBuf>, ImportError> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    let maildir_folders: Vec<PathBuf> = ["cur", "new"].iter().map(|folder| path.join(folder)).filter(|folder| folder.is_dir()).collect();
+    if maildir_folders.is_empty() {
+        collect_eml_files(path, &mut files)?;
+    } else {
+        for folder in maildir_folders {
+            for entry in fs::read_dir(folder)? {
+                let file = entry?.path();
+                let hidden = file.file_name().and_then(|name| name.to_str()).is_none_or(|name| name.starts_with('.'));
+                if file.is_file() && !hidden {
+                    files.push(file);
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn collect_eml_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ImportError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_eml_files(&path, files)?;
+        } else if path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext.eq_ignore_ascii_case("eml")) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a message file; `None` if no template recognises it as an order confirmation.
+pub fn parse_file(path: &Path, templates: &[MailTemplate]) -> Result<Option<OrderMail>, ImportError> {
+    parse_message(&fs::read(path)?, templates)
+}
+
+pub fn parse_message(raw: &[u8], templates: &[MailTemplate]) -> Result<Option<OrderMail>, ImportError> {
+    let message = MessageParser::default()
+        .parse(raw)
+        .ok_or_else(|| ImportError::Format("Not an e-mail message".to_string()))?;
+    let sender = message
+        .from()
+        .and_then(|from| from.first())
+        .and_then(|address| address.address())
+        .unwrap_or("")
+        .to_string();
+    let subject = message.subject().unwrap_or("").to_string();
+
+    let mut template = None;
+    for candidate in templates {
+        if candidate.matches(&sender, &subject)? {
+            template = Some(candidate);
+            break;
+        }
+    }
+    let Some(template) = template else { return Ok(None) };
+
+    let body = message.body_text(0).map(|text| text.into_owned()).unwrap_or_default();
+    let sent = message
+        .date()
+        .and_then(|sent| NaiveDate::from_ymd_opt(sent.year as i32, sent.month as u32, sent.day as u32));
+    let date = match &template.order_date {
+        Some(pattern) => {
+            let value = capture(pattern, &body)?
+                .ok_or_else(|| ImportError::Format(format!("Template '{}' found no order date", template.name)))?;
+            locale::parse_date_as(&value, &template.date_format).map_err(ImportError::Format)?.date()
+        }
+        None => sent.ok_or_else(|| ImportError::Format("Mail without a valid Date header".to_string()))?,
+    };
+
+    let total = capture(&template.total, &body)?
+        .ok_or_else(|| ImportError::Format(format!("Template '{}' found no order total", template.name)))?;
+    let order_number = match &template.order_number {
+        Some(pattern) => capture(pattern, &body)?,
+        None => None,
+    };
+
+    let mut items = Vec::new();
+    for item in compile(&template.item)?.captures_iter(&body) {
+        let (Some(description), Some(price)) = (item.name("description"), item.name("price")) else {
+            return Err(ImportError::Format(format!("Item pattern of template '{}' needs 'description' and 'price' groups", template.name)));
+        };
+        items.push(OrderItem {
+            description: description.as_str().trim().to_string(),
+            quantity: item.name("quantity").map(|quantity| parse_amount(quantity.as_str(), false)).transpose()?,
+            price: parse_amount(price.as_str(), template.decimal_comma)?,
+        });
+    }
+
+    Ok(Some(OrderMail {
+        template: template.name.clone(),
+        merchant: template.merchant.clone(),
+        order_number,
+        date,
+        items,
+        total: parse_amount(&total, template.decimal_comma)?,
+        currency: template.currency.clone(),
+        payment_method: template.payment_method.clone(),
+    }))
+}
+
+/// The first capture group of `pattern` in `text`, trimmed.
+fn capture(pattern: &str, text: &str) -> Result<Option<String>, ImportError> {
+    Ok(compile(pattern)?
+        .captures(text)
+        .and_then(|captures| captures.get(1))
+        .map(|value| value.as_str().trim().to_string())
+        .filter(|value| !value.is_empty()))
+}
+
+/// Parses `1,234.56`, or `1.234,56` when the template says amounts use a decimal comma.
+fn parse_amount(value: &str, decimal_comma: bool) -> Result<BigDecimal, ImportError> {
+    let decimal = if decimal_comma { DecimalSeparator::Comma } else { DecimalSeparator::Point };
+    locale::parse_amount(value, decimal).map_err(ImportError::Format)
+}
@@ -0,0 +1,142 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::ImportError;
+use crate::utils::db::get_finwise_data_dir;
+
+/// Describes how to read one merchant's order-confirmation mails.
+///
+/// Patterns are regular expressions matched against the plain-text body
+/// (HTML mails are converted to text first). `order_number`, `order_date` and
+/// `total` take their value from the first capture group; `item` is matched
+/// repeatedly and uses the named groups `description`, `price` and, optionally,
+/// `quantity`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MailTemplate {
+    pub name: String,
+    /// Party the receipts are booked to.
+    pub merchant: String,
+    /// Sender addresses, or domains written as `@example.com`, that send the confirmations.
+    pub senders: Vec<String>,
+    /// Only mails whose subject matches are order confirmations.
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub order_number: Option<String>,
+    /// Without it, the order date is the date the mail was sent.
+    #[serde(default)]
+    pub order_date: Option<String>,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    pub item: String,
+    pub total: String,
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// Amounts use `,` as decimal separator and `.` for thousands (e.g. `1.234,56`).
+    #[serde(default)]
+    pub decimal_comma: bool,
+    #[serde(default = "default_payment_method")]
+    pub payment_method: String,
+}
+
+fn default_date_format() -> String {
+    "%d.%m.%Y".to_string()
+}
+
+fn default_payment_method() -> String {
+    "online".to_string()
+}
+
+impl MailTemplate {
+    /// A starting point for a new template that the user edits by hand.
+    pub fn template(name: &str) -> Self {
+        MailTemplate {
+            name: name.to_string(),
+            merchant: "Example Shop".to_string(),
+            senders: vec!["@shop.example".to_string()],
+            subject: Some("(?i)order confirmation|bestellbestätigung".to_string()),
+            order_number: Some(r"(?i)order (?:number|no\.?)[:\s]*([A-Z0-9-]+)".to_string()),
+            order_date: None,
+            date_format: default_date_format(),
+            item: r"(?m)^\s*(?P<quantity>\d+)\s*x\s+(?P<description>.+?)\s+(?P<price>\d[\d.,]*)\s*(?:EUR|€)?\s*$".to_string(),
+            total: r"(?i)(?:order total|total)[^\d\n]*(\d[\d.,]*)".to_string(),
+            currency: Some("EUR".to_string()),
+            decimal_comma: false,
+            payment_method: default_payment_method(),
+        }
+    }
+
+    /// Whether a mail from `sender` with `subject` is one of this template's confirmations.
+    pub fn matches(&self, sender: &str, subject: &str) -> Result<bool, ImportError> {
+        let sender = sender.to_lowercase();
+        let from_sender = self.senders.iter().any(|pattern| {
+            let pattern = pattern.to_lowercase();
+            if pattern.starts_with('@') {
+                sender.ends_with(&pattern)
+            } else {
+                sender == pattern
+            }
+        });
+        if !from_sender {
+            return Ok(false);
+        }
+        match &self.subject {
+            Some(pattern) => Ok(compile(pattern)?.is_match(subject)),
+            None => Ok(true),
+        }
+    }
+}
+
+/// Compiles a template pattern, naming the pattern in the error.
+pub fn compile(pattern: &str) -> Result<Regex, ImportError> {
+    Regex::new(pattern).map_err(|e| ImportError::Format(format!("Invalid mail template pattern '{}': {}", pattern, e)))
+}
+
+/// Gets the directory holding mail templates, creating it if needed
+pub fn get_templates_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let templates_dir = get_finwise_data_dir()?.join("mail_templates");
+    if !templates_dir.exists() {
+        fs::create_dir_all(&templates_dir)
+            .map_err(|e| format!("Failed to create mail template directory: {}", e))?;
+    }
+    Ok(templates_dir)
+}
+
+fn template_path(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid template name '{}': use letters, digits, '-' and '_'", name).into());
+    }
+    Ok(get_templates_dir()?.join(format!("{}.json", name)))
+}
+
+pub fn load_template(name: &str) -> Result<MailTemplate, Box<dyn std::error::Error>> {
+    let path = template_path(name)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read mail template '{}': {}", path.display(), e))?;
+    let template: MailTemplate = serde_json::from_str(&content).map_err(ImportError::from)?;
+    Ok(template)
+}
+
+pub fn save_template(template: &MailTemplate) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = template_path(&template.name)?;
+    fs::write(&path, serde_json::to_string_pretty(template)?)?;
+    Ok(path)
+}
+
+pub fn list_templates() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut names: Vec<String> = fs::read_dir(get_templates_dir()?)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                path.file_stem().and_then(|stem| stem.to_str()).map(|stem| stem.to_string())
+            } else {
+                None
+            }
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
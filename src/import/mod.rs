@@ -0,0 +1,221 @@
+pub mod camt;
+pub mod csv;
+pub mod double_entry;
+pub mod einvoice;
+pub mod fingerprint;
+pub mod gnucash;
+pub mod journal;
+pub mod locale;
+pub mod mail;
+pub mod mail_template;
+pub mod mt940;
+pub mod ocr;
+pub mod ofx;
+pub mod payment_provider;
+pub mod profile;
+pub mod qif;
+pub mod receipt_template;
+pub mod table;
+pub mod trades;
+pub mod xml;
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use std::path::Path;
+use thiserror::Error;
+
+use self::locale::Locale;
+use self::profile::CsvProfile;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("CSV error: {0}")]
+    Csv(#[from] ::csv::Error),
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("profile error: {0}")]
+    Profile(#[from] serde_json::Error),
+    #[error("{0}")]
+    Format(String),
+}
+
+/// A row that could not be turned into a `StatementLine`.
+#[derive(Debug, Clone)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// One booking as read from a bank file, before it is mapped onto parties.
+///
+/// `amount` is signed from the account holder's point of view: negative
+/// amounts leave the account, positive amounts arrive on it.
+#[derive(Debug, Clone)]
+pub struct StatementLine {
+    pub line: usize,
+    pub date: NaiveDateTime,
+    pub amount: BigDecimal,
+    pub counterparty: Option<String>,
+    pub counterparty_iban: Option<String>,
+    pub counterparty_bic: Option<String>,
+    pub memo: String,
+    /// The bank's own transaction id (OFX FITID and similar), when the format has one.
+    pub bank_id: Option<String>,
+    /// Spending category from personal finance exports; `[Name]` denotes a transfer to account `Name`.
+    pub category: Option<String>,
+    /// Fee a payment provider kept, as a positive amount; `amount` is already net of it.
+    pub fee: Option<BigDecimal>,
+    /// The amount in the currency the payment was made in, when it was converted.
+    pub original_amount: Option<BigDecimal>,
+    pub original_currency: Option<String>,
+    /// Money moved between a payment-provider balance and the bank account behind
+    /// it (PayPal top-ups and withdrawals, Stripe payouts).
+    pub funding: bool,
+}
+
+/// What a row of a broker or exchange export did.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TradeKind {
+    Buy,
+    Sell,
+    Dividend,
+    Interest,
+    Fee,
+    Tax,
+    Deposit,
+    Withdrawal,
+}
+
+impl TradeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeKind::Buy => "buy",
+            TradeKind::Sell => "sell",
+            TradeKind::Dividend => "dividend",
+            TradeKind::Interest => "interest",
+            TradeKind::Fee => "fee",
+            TradeKind::Tax => "tax",
+            TradeKind::Deposit => "deposit",
+            TradeKind::Withdrawal => "withdrawal",
+        }
+    }
+}
+
+/// One row of a broker or exchange export.
+///
+/// `quantity` is signed like the holding changes: positive for units bought or
+/// received, negative for units sold or sent. `amount` is the effect on the
+/// account's cash, fees included, and zero for trades settled in another asset.
+#[derive(Debug, Clone)]
+pub struct TradeLine {
+    pub line: usize,
+    pub date: NaiveDateTime,
+    pub kind: TradeKind,
+    pub symbol: Option<String>,
+    pub quantity: Option<BigDecimal>,
+    pub unit_price: Option<BigDecimal>,
+    /// Currency of `unit_price` and `fee`.
+    pub currency: Option<String>,
+    pub fee: Option<BigDecimal>,
+    pub amount: BigDecimal,
+    pub memo: String,
+    pub bank_id: Option<String>,
+}
+
+/// A balance reported by the bank at a point in time.
+#[derive(Debug, Clone)]
+pub struct Balance {
+    pub amount: BigDecimal,
+    pub date: NaiveDateTime,
+}
+
+/// The parsed contents of an import file for a single account.
+#[derive(Debug, Default)]
+pub struct Statement {
+    /// The account as identified by the bank: an IBAN for SEPA formats, the
+    /// bank's account number otherwise.
+    pub account_iban: Option<String>,
+    /// The account's name in the exporting program, for formats without bank identifiers.
+    pub account_name: Option<String>,
+    pub currency: Option<String>,
+    pub opening_balance: Option<Balance>,
+    pub closing_balance: Option<Balance>,
+    /// The payment provider the statement comes from, such as `PayPal`; its
+    /// funding lines are linked to the matching booking on the bank account.
+    pub provider: Option<String>,
+    pub lines: Vec<StatementLine>,
+    /// Trades and investment income, for broker and exchange exports.
+    pub trades: Vec<TradeLine>,
+    pub rejected: Vec<RowError>,
+    /// Rows read but left out on purpose, such as pending bookings that the
+    /// bank reports again once they are booked.
+    pub ignored: Vec<RowError>,
+}
+
+/// Options controlling how a file is parsed.
+#[derive(Debug, Default)]
+pub struct ImportOptions {
+    pub profile: Option<CsvProfile>,
+    /// How amounts and dates are written, for files whose format or profile does not fix it.
+    pub locale: Option<Locale>,
+}
+
+/// Names the import format of a file from its extension, or `None` if it is not supported.
+pub fn format_of(path: &Path) -> Option<&'static str> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "csv" | "txt" => Some("csv"),
+        "ofx" | "qfx" => Some("ofx"),
+        "xml" => Some("camt"),
+        "sta" | "mt940" | "940" | "mt942" | "942" => Some("mt940"),
+        "qif" => Some("qif"),
+        "ledger" | "journal" | "hledger" => Some("ledger"),
+        "beancount" | "bean" => Some("beancount"),
+        "gnucash" | "gnca" => Some("gnucash"),
+        _ => None,
+    }
+}
+
+/// Parses an import file, picking the parser from the file extension.
+///
+/// Formats such as OFX can carry several accounts, so one statement is
+/// returned per account found in the file. CSV files without a profile are
+/// read as PayPal, Stripe, broker or exchange exports if their header matches.
+pub fn parse_file(path: &Path, options: &ImportOptions) -> Result<Vec<Statement>, ImportError> {
+    match format_of(path) {
+        Some("csv") => match &options.profile {
+            Some(profile) => Ok(vec![csv::parse_file(path, profile, options.locale.as_ref())?]),
+            None => {
+                if let Some(provider) = payment_provider::detect(path)? {
+                    payment_provider::parse_file(path, provider, options.locale.as_ref())
+                } else if let Some(exchange) = trades::detect(path)? {
+                    trades::parse_file(path, exchange, options.locale.as_ref())
+                } else {
+                    Err(ImportError::Format(
+                        "CSV import requires a mapping profile (--profile NAME) unless it is a PayPal, Stripe, broker or exchange export"
+                            .to_string(),
+                    ))
+                }
+            }
+        },
+        Some("ofx") => ofx::parse_file(path),
+        Some("camt") if einvoice::detect(path)? => Err(ImportError::Format(format!(
+            "{} is an e-invoice, not a bank statement; store it with 'receipts import'",
+            path.display()
+        ))),
+        Some("camt") => camt::parse_file(path),
+        Some("mt940") => mt940::parse_file(path),
+        Some("qif") => qif::parse_file(path, options.locale.as_ref()),
+        Some("ledger") => journal::parse_file(path, journal::Dialect::Ledger),
+        Some("beancount") => journal::parse_file(path, journal::Dialect::Beancount),
+        Some("gnucash") => gnucash::parse_file(path),
+        _ => Err(ImportError::Format(format!("Unsupported file type: {}", path.display()))),
+    }
+}
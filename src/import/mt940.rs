@@ -0,0 +1,477 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use encoding_rs::WINDOWS_1252;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{Balance, ImportError, RowError, Statement, StatementLine};
+
+/// A `:61:` statement line before its `:86:` information is attached.
+struct BookingLine {
+    date: NaiveDateTime,
+    amount: BigDecimal,
+    reference: Option<String>,
+}
+
+/// Counterparty and remittance data decoded from a `:86:` field.
+#[derive(Debug, Default)]
+struct BookingDetails {
+    gvc: Option<String>,
+    posting_text: Option<String>,
+    counterparty: Option<String>,
+    counterparty_iban: Option<String>,
+    counterparty_bic: Option<String>,
+    remittance: Option<String>,
+    end_to_end_id: Option<String>,
+}
+
+pub fn parse_file(path: &Path) -> Result<Vec<Statement>, ImportError> {
+    let bytes = fs::read(path)?;
+    // SWIFT files are ASCII in theory; German banks ship them in Windows-1252
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => WINDOWS_1252.decode(e.as_bytes()).0.into_owned(),
+    };
+    parse_str(&content)
+}
+
+/// Parses MT940 end-of-day and MT942 interim statements.
+///
+/// A file may hold several messages; each becomes its own statement.
+pub fn parse_str(content: &str) -> Result<Vec<Statement>, ImportError> {
+    let statements: Vec<Statement> = split_messages(content)
+        .into_iter()
+        .map(|fields| parse_message(&fields))
+        .collect();
+
+    if statements.is_empty() {
+        return Err(ImportError::Format("No MT940/MT942 message found (missing :20: tag)".to_string()));
+    }
+    Ok(statements)
+}
+
+/// Splits the file into messages, each a list of `(tag, value)` fields in order.
+/// Continuation lines are joined to their field with a newline.
+fn split_messages(content: &str) -> Vec<Vec<(String, String)>> {
+    let mut messages = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+
+    for raw_line in content.lines() {
+        let line = raw_line.trim_end_matches('\r');
+        // Strip SWIFT envelope blocks such as {1:...}{2:...}{4:
+        let line = match line.rfind("{4:") {
+            Some(position) => &line[position + 3..],
+            None if line.starts_with('{') => continue,
+            None => line,
+        };
+
+        if line.trim() == "-" || line.starts_with("-}") {
+            if !fields.is_empty() {
+                messages.push(std::mem::take(&mut fields));
+            }
+            continue;
+        }
+
+        if let Some((tag, value)) = split_tag(line) {
+            // A new :20: without a closing '-' still starts a new message
+            if tag == "20" && fields.iter().any(|(existing, _)| existing == "20") {
+                messages.push(std::mem::take(&mut fields));
+            }
+            fields.push((tag.to_string(), value.to_string()));
+        } else if let Some((_, value)) = fields.last_mut() {
+            value.push('\n');
+            value.push_str(line);
+        }
+    }
+
+    if !fields.is_empty() {
+        messages.push(fields);
+    }
+    messages.retain(|fields| fields.iter().any(|(tag, _)| tag == "20"));
+    messages
+}
+
+fn split_tag(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix(':')?;
+    let end = rest.find(':')?;
+    let tag = &rest[..end];
+    if tag.is_empty() || tag.len() > 3 || !tag.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some((tag, &rest[end + 1..]))
+}
+
+fn parse_message(fields: &[(String, String)]) -> Statement {
+    let mut statement = Statement::default();
+    let mut pending: Option<(usize, Result<BookingLine, String>)> = None;
+    let mut booking_count = 0;
+
+    for (tag, value) in fields {
+        match tag.as_str() {
+            "25" => statement.account_iban = Some(parse_account(value)),
+            "60F" | "60M" => {
+                if let Ok((balance, currency)) = parse_balance(value) {
+                    statement.opening_balance = Some(balance);
+                    statement.currency = Some(currency);
+                }
+            }
+            "62F" | "62M" => {
+                if let Ok((balance, currency)) = parse_balance(value) {
+                    statement.closing_balance = Some(balance);
+                    statement.currency.get_or_insert(currency);
+                }
+            }
+            "34F" => {
+                // MT942 floor limit starts with the currency
+                statement.currency.get_or_insert(value.chars().take(3).collect());
+            }
+            "61" => {
+                flush_booking(&mut statement, pending.take(), None);
+                booking_count += 1;
+                pending = Some((booking_count, parse_booking(value)));
+            }
+            "86" if pending.is_some() => {
+                flush_booking(&mut statement, pending.take(), Some(parse_details(value)));
+            }
+            _ => {}
+        }
+    }
+    flush_booking(&mut statement, pending, None);
+
+    statement
+}
+
+fn flush_booking(
+    statement: &mut Statement,
+    pending: Option<(usize, Result<BookingLine, String>)>,
+    details: Option<BookingDetails>,
+) {
+    let Some((line, booking)) = pending else { return };
+    let booking = match booking {
+        Ok(booking) => booking,
+        Err(message) => {
+            statement.rejected.push(RowError { line, message });
+            return;
+        }
+    };
+    let details = details.unwrap_or_default();
+
+    let memo = match (&details.posting_text, &details.remittance) {
+        (Some(posting_text), Some(remittance)) => format!("{}: {}", posting_text, remittance),
+        (Some(text), None) | (None, Some(text)) => text.clone(),
+        (None, None) => details.gvc.as_ref().map(|gvc| format!("GVC {}", gvc)).unwrap_or_default(),
+    };
+
+    statement.lines.push(StatementLine {
+        line,
+        date: booking.date,
+        amount: booking.amount,
+        counterparty: details.counterparty,
+        counterparty_iban: details.counterparty_iban,
+        counterparty_bic: details.counterparty_bic,
+        memo,
+        bank_id: booking.reference.or(details.end_to_end_id),
+        category: None,
+        fee: None,
+        original_amount: None,
+        original_currency: None,
+        funding: false,
+    });
+}
+
+/// `:25:` holds either an IBAN or `BLZ/account number`, sometimes with a currency suffix.
+fn parse_account(value: &str) -> String {
+    let account: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let has_currency_suffix = account.len() > 3
+        && account.is_char_boundary(account.len() - 3)
+        && account[account.len() - 3..].chars().all(|c| c.is_ascii_uppercase())
+        && account[..account.len() - 3].chars().last().is_some_and(|c| c.is_ascii_digit());
+    if has_currency_suffix {
+        account[..account.len() - 3].to_string()
+    } else {
+        account
+    }
+}
+
+/// Parses `C240131EUR1234,56`-style balances into the balance and its currency.
+fn parse_balance(value: &str) -> Result<(Balance, String), String> {
+    let value = value.trim();
+    if value.len() < 11 || !value.is_char_boundary(10) || !value[..10].is_ascii() {
+        return Err(format!("Invalid balance '{}'", value));
+    }
+    let mark = &value[..1];
+    let date = parse_date(&value[1..7])?;
+    let currency = value[7..10].to_string();
+    let mut amount = parse_amount(&value[10..])?;
+    match mark {
+        "C" => {}
+        "D" => amount = -amount,
+        _ => return Err(format!("Invalid debit/credit mark in balance '{}'", value)),
+    }
+    Ok((Balance { amount, date }, currency))
+}
+
+/// Parses a `:61:` statement line:
+/// `YYMMDD[MMDD](C|D|RC|RD)[funds code]amount(N|F|S)xxx customer ref[//bank ref]`.
+fn parse_booking(value: &str) -> Result<BookingLine, String> {
+    let first_line = value.lines().next().unwrap_or("").trim();
+    let invalid = || format!("Invalid :61: statement line '{}'", first_line);
+    if first_line.len() < 6 || !first_line.is_char_boundary(6) {
+        return Err(invalid());
+    }
+
+    let date = parse_date(&first_line[..6])?;
+    let mut rest = &first_line[6..];
+    // Optional entry date (MMDD)
+    if rest.get(..4).is_some_and(|entry_date| entry_date.chars().all(|c| c.is_ascii_digit())) {
+        rest = &rest[4..];
+    }
+
+    let (negative, after_mark) = if let Some(after) = rest.strip_prefix("RC") {
+        (true, after)
+    } else if let Some(after) = rest.strip_prefix("RD") {
+        (false, after)
+    } else if let Some(after) = rest.strip_prefix('C') {
+        (false, after)
+    } else if let Some(after) = rest.strip_prefix('D') {
+        (true, after)
+    } else {
+        return Err(invalid());
+    };
+    rest = after_mark;
+    // Optional funds code: the third letter of the currency
+    if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        rest = &rest[1..];
+    }
+
+    let amount_end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ','))
+        .unwrap_or(rest.len());
+    let mut amount = parse_amount(&rest[..amount_end])?;
+    if negative {
+        amount = -amount;
+    }
+    rest = &rest[amount_end..];
+
+    // Transaction type: N, F or S followed by a three character code
+    if rest.len() < 4 || !rest.is_char_boundary(4) {
+        return Err(invalid());
+    }
+    let references = &rest[4..];
+    let (customer_reference, bank_reference) = match references.find("//") {
+        Some(position) => (&references[..position], Some(&references[position + 2..])),
+        None => (references, None),
+    };
+
+    let reference = [bank_reference, Some(customer_reference)]
+        .into_iter()
+        .flatten()
+        .map(|reference| reference.trim())
+        .find(|reference| !reference.is_empty() && *reference != "NONREF")
+        .map(|reference| reference.to_string());
+
+    Ok(BookingLine { date, amount, reference })
+}
+
+/// Decodes a `:86:` field. Understands the German structured layout
+/// (`GVC?00text?20...?30BIC?31IBAN?32name`), the Dutch `/TAG/value/` layout,
+/// and falls back to using the text as remittance information.
+fn parse_details(value: &str) -> BookingDetails {
+    let text: String = value.lines().collect::<Vec<_>>().join("");
+
+    let separator = text.chars().nth(3);
+    let has_gvc = text.len() > 4 && text.chars().take(3).all(|c| c.is_ascii_digit());
+    match separator {
+        Some(separator) if has_gvc && !separator.is_ascii_alphanumeric() && separator != ' ' => {
+            parse_german_details(&text, separator)
+        }
+        _ if text.starts_with('/') => parse_slash_details(&text),
+        _ => BookingDetails {
+            remittance: Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+            ..BookingDetails::default()
+        },
+    }
+}
+
+fn parse_german_details(text: &str, separator: char) -> BookingDetails {
+    let mut details = BookingDetails { gvc: Some(text[..3].to_string()), ..BookingDetails::default() };
+    let mut remittance_lines = Vec::new();
+    let mut name_parts = Vec::new();
+    let mut bank_code = None;
+    let mut account = None;
+
+    for subfield in text[3..].split(separator).filter(|subfield| subfield.len() >= 2 && subfield.is_char_boundary(2)) {
+        let (code, content) = subfield.split_at(2);
+        let content = content.trim();
+        match code {
+            "00" => details.posting_text = Some(content.to_string()).filter(|text| !text.is_empty()),
+            "20" | "21" | "22" | "23" | "24" | "25" | "26" | "27" | "28" | "29" | "60" | "61" | "62" | "63" => {
+                remittance_lines.push(content.to_string())
+            }
+            "30" => bank_code = Some(content.to_string()),
+            "31" => account = Some(content.to_string()),
+            "32" | "33" => name_parts.push(content.to_string()),
+            _ => {}
+        }
+    }
+
+    // Remittance lines are 27-character chunks; SEPA keywords (SVWZ+, EREF+, ...) may span them
+    let remittance = remittance_lines.join("");
+    let sepa_fields = split_sepa_keywords(&remittance);
+    details.remittance = sepa_fields
+        .iter()
+        .find(|(keyword, _)| keyword == "SVWZ")
+        .map(|(_, value)| value.clone())
+        .or_else(|| Some(remittance.trim().to_string()))
+        .filter(|text| !text.is_empty());
+    details.end_to_end_id = sepa_fields
+        .iter()
+        .find(|(keyword, _)| keyword == "EREF")
+        .map(|(_, value)| value.clone())
+        .filter(|reference| reference != "NOTPROVIDED");
+
+    details.counterparty = Some(name_parts.join("")).filter(|name| !name.is_empty());
+    details.counterparty_iban = account.map(|account| account.replace(' ', "").to_uppercase()).filter(|account| is_iban(account));
+    details.counterparty_bic = bank_code.filter(|code| is_bic(code));
+    details
+}
+
+/// Splits SEPA remittance text into `(keyword, value)` pairs, e.g. `EREF+123 SVWZ+Invoice`.
+fn split_sepa_keywords(remittance: &str) -> Vec<(String, String)> {
+    const KEYWORDS: [&str; 9] = ["EREF", "KREF", "MREF", "CRED", "DEBT", "SVWZ", "ABWA", "ABWE", "IBAN"];
+
+    let mut markers: Vec<(usize, &str)> = KEYWORDS
+        .iter()
+        .flat_map(|keyword| {
+            let marker = format!("{}+", keyword);
+            remittance.match_indices(&marker).map(move |(position, _)| (position, *keyword)).collect::<Vec<_>>()
+        })
+        .collect();
+    markers.sort_by_key(|(position, _)| *position);
+
+    markers
+        .iter()
+        .enumerate()
+        .map(|(index, (position, keyword))| {
+            let start = position + keyword.len() + 1;
+            let end = markers.get(index + 1).map(|(next, _)| *next).unwrap_or(remittance.len());
+            (keyword.to_string(), remittance[start..end.max(start)].trim().to_string())
+        })
+        .collect()
+}
+
+/// Parses the Dutch `/TRTP/SEPA OVERBOEKING/IBAN/NL..../BIC/..../NAME/..../REMI/..../` layout.
+fn parse_slash_details(text: &str) -> BookingDetails {
+    const KEYS: [&str; 10] = ["TRTP", "IBAN", "BIC", "NAME", "REMI", "EREF", "MARF", "CSID", "ORDP", "BENM"];
+
+    let parts: Vec<&str> = text.split('/').collect();
+    let mut details = BookingDetails::default();
+    let mut index = 1;
+    while index < parts.len() {
+        let key = parts[index];
+        if !KEYS.contains(&key) {
+            index += 1;
+            continue;
+        }
+        // Values may themselves contain '/', so read until the next known key
+        let mut end = index + 1;
+        while end < parts.len() && !KEYS.contains(&parts[end]) {
+            end += 1;
+        }
+        let value = parts[index + 1..end].join("/").trim().to_string();
+        match key {
+            "TRTP" => details.posting_text = Some(value),
+            "IBAN" => details.counterparty_iban = Some(value.to_uppercase()).filter(|iban| is_iban(iban)),
+            "BIC" => details.counterparty_bic = Some(value).filter(|bic| is_bic(bic)),
+            "NAME" => details.counterparty = Some(value).filter(|name| !name.is_empty()),
+            "REMI" => details.remittance = Some(value).filter(|text| !text.is_empty()),
+            "EREF" => details.end_to_end_id = Some(value).filter(|reference| !reference.is_empty() && reference != "NOTPROVIDED"),
+            _ => {}
+        }
+        index = end;
+    }
+    details
+}
+
+fn is_iban(value: &str) -> bool {
+    value.len() >= 15
+        && value.chars().take(2).all(|c| c.is_ascii_uppercase())
+        && value.chars().skip(2).take(2).all(|c| c.is_ascii_digit())
+        && value.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn is_bic(value: &str) -> bool {
+    (value.len() == 8 || value.len() == 11)
+        && value.chars().take(6).all(|c| c.is_ascii_uppercase())
+        && value.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn parse_amount(value: &str) -> Result<BigDecimal, String> {
+    let normalized = value.trim().replace(',', ".");
+    let normalized = normalized.strip_suffix('.').unwrap_or(&normalized);
+    BigDecimal::from_str(normalized).map_err(|_| format!("Invalid amount '{}'", value.trim()))
+}
+
+fn parse_date(value: &str) -> Result<NaiveDateTime, String> {
+    NaiveDate::parse_from_str(value, "%y%m%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+        .map_err(|_| format!("Invalid date '{}'", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATEMENT: &str = "{1:F01BANKDEFFXXXX0000000000}{2:O940}{4:\r\n\
+:20:STARTUMS\r\n\
+:25:10020030/1234567890EUR\r\n\
+:28C:1/1\r\n\
+:60F:C240131EUR1000,00\r\n\
+:61:2402010201DR42,50NDDTNONREF//B1\r\n\
+:86:105?00LASTSCHRIFT?20EREF+E2E-7 SVWZ+Strom Jan?30COBADEFFXXX?31DE02120300000000202051?32Stadtwerke\r\n\
+:61:240202CR1500,NTRFNONREF\r\n\
+:86:166?00GUTSCHRIFT?20Gehalt Februar?32Employer AG\r\n\
+:61:240203RC10,00NMSCNONREF\r\n\
+:62F:C240203EUR2447,50\r\n\
+-}";
+
+    #[test]
+    fn signs_bookings_by_their_debit_credit_mark() {
+        let statement = parse_str(STATEMENT).unwrap().remove(0);
+        let amounts: Vec<String> = statement.lines.iter().map(|line| line.amount.to_string()).collect();
+        // RC reverses a credit, so it takes money out
+        assert_eq!(amounts, ["-42.50", "1500", "-10.00"]);
+        let dates: Vec<String> = statement.lines.iter().map(|line| line.date.date().to_string()).collect();
+        assert_eq!(dates, ["2024-02-01", "2024-02-02", "2024-02-03"]);
+    }
+
+    #[test]
+    fn reads_account_balances_and_structured_details() {
+        let statement = parse_str(STATEMENT).unwrap().remove(0);
+        assert_eq!(statement.account_iban.as_deref(), Some("10020030/1234567890"));
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+        assert_eq!(statement.opening_balance.unwrap().amount.to_string(), "1000.00");
+        let closing = statement.closing_balance.unwrap();
+        assert_eq!(closing.amount.to_string(), "2447.50");
+        assert_eq!(closing.date.date().to_string(), "2024-02-03");
+
+        let debit = &statement.lines[0];
+        assert_eq!(debit.counterparty.as_deref(), Some("Stadtwerke"));
+        assert_eq!(debit.counterparty_iban.as_deref(), Some("DE02120300000000202051"));
+        assert_eq!(debit.counterparty_bic.as_deref(), Some("COBADEFFXXX"));
+        assert_eq!(debit.memo, "LASTSCHRIFT: Strom Jan");
+        assert_eq!(debit.bank_id.as_deref(), Some("B1"));
+        assert_eq!(statement.lines[2].bank_id, None);
+    }
+
+    #[test]
+    fn rejects_unreadable_bookings() {
+        let content = ":20:X\n:25:DE89370400440532013000\n:61:24XX01D1,00NTRFNONREF\n:61:240202C5,00NTRFNONREF\n-";
+        let statement = parse_str(content).unwrap().remove(0);
+        assert_eq!(statement.rejected.len(), 1);
+        assert_eq!(statement.rejected[0].line, 1);
+        assert_eq!(statement.lines.len(), 1);
+        assert_eq!(statement.lines[0].line, 2);
+    }
+}
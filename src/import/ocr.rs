@@ -0,0 +1,222 @@
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{NaiveDate, NaiveTime};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+use super::locale;
+use super::receipt_template::ReceiptTemplate;
+use super::ImportError;
+
+/// Item line of a till receipt: a description followed by the price and an optional VAT class.
+const ITEM_PATTERN: &str = r"^(?:(?P<quantity>\d+)\s*[xX*]\s+)?(?P<description>.*?\p{L}.*?)\s+(?P<price>-?\d{1,6}[.,]\d{2})(?:\s*(?:€|EUR))?(?:\s+[A-Z0-9]{1,2}\*?)?$";
+const TOTAL_PATTERN: &str = r"(?i)^\s*(?:summe|gesamt(?:betrag)?|total|zu zahlen|to pay|amount due)\b[^\d\n-]*(-?\d+[.,]\d{2})";
+/// Lines that carry an amount but are not purchased items.
+const NON_ITEM_PATTERN: &str = r"(?i)\b(?:zwischensumme|subtotal|mwst|ust|vat|tax|netto|brutto|gegeben|rückgeld|change|bar|cash|ec-karte|girocard|visa|mastercard|kartenzahlung)\b";
+const DATE_PATTERN: &str = r"\b(?:(\d{4})-(\d{2})-(\d{2})|(\d{1,2})[./](\d{1,2})[./](\d{2}|\d{4}))\b";
+const TIME_PATTERN: &str = r"\b([01]?\d|2[0-3]):([0-5]\d)(?::([0-5]\d))?\b";
+
+/// Payment keywords and the method they stand for, most specific first.
+const PAYMENT_METHODS: [(&str, &str); 4] = [
+    (r"(?i)\b(?:ec-karte|girocard|maestro|v pay|debit)\b", "debit card"),
+    (r"(?i)\b(?:visa|mastercard|amex|american express|kreditkarte|credit card)\b", "credit card"),
+    (r"(?i)\b(?:kartenzahlung|card|kontaktlos|contactless|apple pay|google pay)\b", "card"),
+    (r"(?i)\b(?:bar|cash|gegeben|rückgeld)\b", "cash"),
+];
+
+/// A field read from OCR text, with how sure the parser is of it.
+#[derive(Debug, Clone)]
+pub struct Scored<T> {
+    pub value: Option<T>,
+    /// From 0 (not found) to 1 (certain).
+    pub confidence: f32,
+    /// Why the confidence is below 1.
+    pub note: Option<String>,
+}
+
+impl<T> Scored<T> {
+    fn certain(value: T) -> Self {
+        Scored { value: Some(value), confidence: 1.0, note: None }
+    }
+
+    fn likely(value: T, confidence: f32, note: &str) -> Self {
+        Scored { value: Some(value), confidence, note: Some(note.to_string()) }
+    }
+
+    fn missing(note: &str) -> Self {
+        Scored { value: None, confidence: 0.0, note: Some(note.to_string()) }
+    }
+}
+
+/// One purchased item, with the price printed for the line.
+#[derive(Debug, Clone)]
+pub struct ReceiptItem {
+    pub description: String,
+    pub quantity: Option<BigDecimal>,
+    pub price: BigDecimal,
+}
+
+/// A till receipt read from OCR text.
+#[derive(Debug, Clone)]
+pub struct OcrReceipt {
+    /// Name of the layout template used, if one matched.
+    pub template: Option<String>,
+    pub merchant: Scored<String>,
+    pub date: Scored<NaiveDate>,
+    pub time: Scored<NaiveTime>,
+    pub payment_method: Scored<String>,
+    pub items: Scored<Vec<ReceiptItem>>,
+    pub total: Scored<BigDecimal>,
+}
+
+impl OcrReceipt {
+    /// The confidence of the least certain field a receipt needs: merchant, date, items and total.
+    pub fn confidence(&self) -> f32 {
+        [self.merchant.confidence, self.date.confidence, self.items.confidence, self.total.confidence]
+            .into_iter()
+            .fold(1.0, f32::min)
+    }
+
+    /// Describes the needed fields less certain than `threshold`, e.g. `total (0.50): ...`.
+    pub fn review_reasons(&self, threshold: f32) -> Vec<String> {
+        [
+            ("merchant", self.merchant.confidence, &self.merchant.note),
+            ("date", self.date.confidence, &self.date.note),
+            ("items", self.items.confidence, &self.items.note),
+            ("total", self.total.confidence, &self.total.note),
+        ]
+        .into_iter()
+        .filter(|(_, confidence, _)| *confidence < threshold)
+        .map(|(field, confidence, note)| match note {
+            Some(note) => format!("{} ({:.2}): {}", field, confidence, note),
+            None => format!("{} ({:.2})", field, confidence),
+        })
+        .collect()
+    }
+}
+
+pub fn parse_file(path: &Path, templates: &[ReceiptTemplate]) -> Result<OcrReceipt, ImportError> {
+    let bytes = fs::read(path)?;
+    parse_str(&String::from_utf8_lossy(&bytes), templates)
+}
+
+/// Reads a till receipt from OCR text, using the first template whose keywords appear in it.
+pub fn parse_str(text: &str, templates: &[ReceiptTemplate]) -> Result<OcrReceipt, ImportError> {
+    let template = templates.iter().find(|template| template.matches(text));
+    let lines: Vec<&str> = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+
+    let merchant = match template {
+        Some(template) => Scored::certain(template.merchant.clone()),
+        None => match lines.iter().find(|line| line.chars().any(|c| c.is_alphabetic())) {
+            Some(first) => Scored::likely(first.to_string(), 0.4, "guessed from the first line"),
+            None => Scored::missing("no merchant name found"),
+        },
+    };
+
+    let item_pattern = compile(template.and_then(|template| template.item.as_deref()).unwrap_or(ITEM_PATTERN))?;
+    let total_pattern = compile(template.and_then(|template| template.total.as_deref()).unwrap_or(TOTAL_PATTERN))?;
+    let skip_patterns = template
+        .map(|template| template.skip.iter().map(|pattern| compile(pattern)).collect::<Result<Vec<_>, _>>())
+        .transpose()?
+        .unwrap_or_default();
+    let non_item = compile(NON_ITEM_PATTERN)?;
+
+    // Items are listed above the total; anything after it is payment and tax detail
+    let mut items = Vec::new();
+    let mut printed_total = None;
+    for line in &lines {
+        if let Some(total) = total_pattern.captures(line).and_then(|captures| captures.get(1)) {
+            printed_total = Some(parse_amount(total.as_str())?);
+            break;
+        }
+        if non_item.is_match(line) || skip_patterns.iter().any(|pattern| pattern.is_match(line)) {
+            continue;
+        }
+        if let Some(item) = item_pattern.captures(line) {
+            let (Some(description), Some(price)) = (item.name("description"), item.name("price")) else {
+                return Err(ImportError::Format("Item pattern needs 'description' and 'price' groups".to_string()));
+            };
+            items.push(ReceiptItem {
+                description: description.as_str().trim().to_string(),
+                quantity: item.name("quantity").map(|quantity| parse_amount(quantity.as_str())).transpose()?,
+                price: parse_amount(price.as_str())?,
+            });
+        }
+    }
+
+    let item_sum = items.iter().fold(BigDecimal::zero(), |sum, item| sum + &item.price);
+    let no_items = || Scored { value: Some(Vec::new()), confidence: 0.0, note: Some("no item lines found".to_string()) };
+    let (items, total) = match (items.is_empty(), printed_total) {
+        (true, Some(total)) => (no_items(), Scored::likely(total, 0.8, "no items to check it against")),
+        (true, None) => (no_items(), Scored::missing("no total line found")),
+        (false, Some(total)) if total == item_sum => (Scored::certain(items), Scored::certain(total)),
+        (false, Some(total)) => {
+            let note = format!("items add up to {}", item_sum);
+            (Scored::likely(items, 0.6, &note), Scored::likely(total, 0.5, &note))
+        }
+        (false, None) => (
+            Scored::likely(items, 0.7, "no total to check them against"),
+            Scored::likely(item_sum, 0.4, "no total line found; sum of the items"),
+        ),
+    };
+
+    Ok(OcrReceipt {
+        template: template.map(|template| template.name.clone()),
+        merchant,
+        date: find_date(text)?,
+        time: find_time(text)?,
+        payment_method: find_payment_method(text)?,
+        items,
+        total,
+    })
+}
+
+/// The receipt date; several different dates (e.g. a printed best-before) lower the confidence.
+fn find_date(text: &str) -> Result<Scored<NaiveDate>, ImportError> {
+    let dates: Vec<NaiveDate> = compile(DATE_PATTERN)?
+        .captures_iter(text)
+        .filter_map(|captures| {
+            let number = |index: usize| captures.get(index).and_then(|value| value.as_str().parse::<u32>().ok());
+            match (number(1), number(4)) {
+                (Some(year), _) => NaiveDate::from_ymd_opt(year as i32, number(2)?, number(3)?),
+                (None, Some(day)) => {
+                    let year = number(6)?;
+                    let year = if year < 100 { 2000 + year } else { year };
+                    NaiveDate::from_ymd_opt(year as i32, number(5)?, day)
+                }
+                _ => None,
+            }
+        })
+        .collect();
+    let Some(first) = dates.first().copied() else { return Ok(Scored::missing("no date found")) };
+    Ok(if dates.iter().all(|date| *date == first) { Scored::certain(first) } else { Scored::likely(first, 0.6, "several dates on the receipt") })
+}
+
+fn find_time(text: &str) -> Result<Scored<NaiveTime>, ImportError> {
+    let time = compile(TIME_PATTERN)?.captures(text).and_then(|captures| {
+        let number = |index: usize| captures.get(index).and_then(|value| value.as_str().parse::<u32>().ok());
+        NaiveTime::from_hms_opt(number(1)?, number(2)?, number(3).unwrap_or(0))
+    });
+    Ok(match time {
+        Some(time) => Scored::certain(time),
+        None => Scored::missing("no time found"),
+    })
+}
+
+fn find_payment_method(text: &str) -> Result<Scored<String>, ImportError> {
+    for (pattern, method) in PAYMENT_METHODS {
+        if compile(pattern)?.is_match(text) {
+            return Ok(Scored::likely(method.to_string(), 0.9, "recognised from a keyword"));
+        }
+    }
+    Ok(Scored::missing("no payment keyword found"))
+}
+
+fn compile(pattern: &str) -> Result<Regex, ImportError> {
+    Regex::new(pattern).map_err(|e| ImportError::Format(format!("Invalid receipt pattern '{}': {}", pattern, e)))
+}
+
+/// Parses `1,99` and `1.99`; each amount on a receipt shows its own separator.
+fn parse_amount(value: &str) -> Result<BigDecimal, ImportError> {
+    locale::parse_amount_guess(value).map_err(ImportError::Format)
+}
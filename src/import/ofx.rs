@@ -0,0 +1,331 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use super::{Balance, ImportError, RowError, Statement, StatementLine};
+
+/// An OFX element: aggregates have children, leaf elements carry a value.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    value: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn path(&self, path: &[&str]) -> Option<&Element> {
+        path.iter().try_fold(self, |element, name| element.child(name))
+    }
+
+    fn text(&self, path: &[&str]) -> Option<String> {
+        self.path(path)
+            .and_then(|element| element.value.clone())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// All descendants with the given name, depth first.
+    fn find_all<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            } else {
+                child.find_all(name, found);
+            }
+        }
+    }
+}
+
+pub fn parse_file(path: &Path) -> Result<Vec<Statement>, ImportError> {
+    parse_str(&decode(&fs::read(path)?))
+}
+
+/// Decodes an OFX file in the character set its header declares: the SGML
+/// header's `ENCODING`/`CHARSET` for OFX 1.x, the XML declaration for 2.x.
+/// Files that declare nothing usable are read as UTF-8, or as Windows-1252 if
+/// they are not valid UTF-8.
+fn decode(bytes: &[u8]) -> String {
+    let header_end = bytes.windows(5).position(|window| window == b"<OFX>").unwrap_or(bytes.len());
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+    let declared = header_encoding(&header);
+    let encoding = match declared {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        None => WINDOWS_1252,
+    };
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+fn header_encoding(header: &str) -> Option<&'static Encoding> {
+    if let Some(start) = header.find("encoding=") {
+        let value = header[start + "encoding=".len()..].trim_start_matches(['"', '\'']);
+        let label: String = value.chars().take_while(|c| !matches!(c, '"' | '\'' | '?' | ' ')).collect();
+        return Encoding::for_label(label.as_bytes());
+    }
+    let field = |name: &str| {
+        header
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name).and_then(|rest| rest.strip_prefix(':')))
+            .map(|value| value.trim().to_uppercase())
+    };
+    if field("ENCODING").as_deref() == Some("UTF-8") {
+        return Some(UTF_8);
+    }
+    // Latin-1 is read as its Windows superset, as browsers do
+    match field("CHARSET").as_deref() {
+        Some("1252") | Some("ISO-8859-1") | Some("8859-1") | Some("LATIN1") => Some(WINDOWS_1252),
+        Some(charset) if charset != "NONE" => Encoding::for_label(charset.as_bytes()),
+        _ => None,
+    }
+}
+
+/// Parses OFX 1.x (SGML, leaf elements without closing tags) and 2.x (XML).
+pub fn parse_str(content: &str) -> Result<Vec<Statement>, ImportError> {
+    let start = content
+        .find("<OFX>")
+        .ok_or_else(|| ImportError::Format("No <OFX> element found".to_string()))?;
+    let root = parse_elements(&content[start..]);
+
+    let mut statements_found = Vec::new();
+    root.find_all("STMTRS", &mut statements_found);
+    root.find_all("CCSTMTRS", &mut statements_found);
+    if statements_found.is_empty() {
+        return Err(ImportError::Format("OFX file contains no bank or credit card statement".to_string()));
+    }
+
+    Ok(statements_found.into_iter().map(parse_statement).collect())
+}
+
+fn parse_elements(content: &str) -> Element {
+    let mut stack = vec![Element::default()];
+    let mut rest = content;
+
+    while let Some(open) = rest.find('<') {
+        let Some(close) = rest[open..].find('>') else { break };
+        let tag = rest[open + 1..open + close].trim();
+        rest = &rest[open + close + 1..];
+
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_uppercase();
+            // Unwind to the matching aggregate; tolerates stray closing tags
+            if stack.iter().skip(1).any(|element| element.name == name) {
+                while let Some(element) = stack.pop() {
+                    let done = element.name == name;
+                    stack.last_mut().expect("root stays on the stack").children.push(element);
+                    if done {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+
+        let self_closing = tag.ends_with('/');
+        let name = tag.trim_end_matches('/').trim().to_uppercase();
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        let text = rest[..text_end].trim();
+
+        if self_closing {
+            push_child(&mut stack, Element { name, value: Some(String::new()), children: Vec::new() });
+        } else if !text.is_empty() {
+            push_child(&mut stack, Element { name: name.clone(), value: Some(decode_entities(text)), children: Vec::new() });
+            rest = &rest[text_end..];
+            // XML closes leaf elements explicitly; SGML does not
+            let closing = format!("</{}>", name);
+            if rest.get(..closing.len()).is_some_and(|start| start.eq_ignore_ascii_case(&closing)) {
+                rest = &rest[closing.len()..];
+            }
+        } else {
+            stack.push(Element { name, value: None, children: Vec::new() });
+        }
+    }
+
+    while stack.len() > 1 {
+        let element = stack.pop().expect("checked length");
+        push_child(&mut stack, element);
+    }
+    stack.pop().expect("root stays on the stack")
+}
+
+fn push_child(stack: &mut [Element], element: Element) {
+    stack.last_mut().expect("root stays on the stack").children.push(element);
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+fn parse_statement(statement_element: &Element) -> Statement {
+    let mut statement = Statement {
+        account_iban: statement_element
+            .text(&["BANKACCTFROM", "ACCTID"])
+            .or_else(|| statement_element.text(&["CCACCTFROM", "ACCTID"])),
+        currency: statement_element.text(&["CURDEF"]),
+        ..Statement::default()
+    };
+
+    if let (Some(amount), Some(date)) = (
+        statement_element.text(&["LEDGERBAL", "BALAMT"]),
+        statement_element.text(&["LEDGERBAL", "DTASOF"]),
+    ) {
+        if let (Ok(amount), Ok(date)) = (parse_amount(&amount), parse_date(&date)) {
+            statement.closing_balance = Some(Balance { amount, date });
+        }
+    }
+
+    let transactions = statement_element
+        .child("BANKTRANLIST")
+        .map(|list| list.children.iter().filter(|child| child.name == "STMTTRN").collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    for (index, transaction) in transactions.into_iter().enumerate() {
+        let line = index + 1;
+        match parse_transaction(transaction, line) {
+            Ok(statement_line) => statement.lines.push(statement_line),
+            Err(message) => statement.rejected.push(RowError { line, message }),
+        }
+    }
+
+    statement
+}
+
+fn parse_transaction(transaction: &Element, line: usize) -> Result<StatementLine, String> {
+    let posted = transaction.text(&["DTPOSTED"]).ok_or("STMTTRN without DTPOSTED")?;
+    let amount = transaction.text(&["TRNAMT"]).ok_or("STMTTRN without TRNAMT")?;
+
+    let counterparty = transaction
+        .text(&["NAME"])
+        .or_else(|| transaction.text(&["PAYEE", "NAME"]));
+    let counterparty_iban = transaction
+        .text(&["BANKACCTTO", "ACCTID"])
+        .or_else(|| transaction.text(&["CCACCTTO", "ACCTID"]));
+    let memo = [transaction.text(&["MEMO"]), transaction.text(&["CHECKNUM"]).map(|check| format!("Check {}", check))]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Ok(StatementLine {
+        line,
+        date: parse_date(&posted)?,
+        amount: parse_amount(&amount)?,
+        counterparty,
+        counterparty_iban,
+        counterparty_bic: None,
+        memo,
+        bank_id: transaction.text(&["FITID"]),
+        category: None,
+        fee: None,
+        original_amount: None,
+        original_currency: None,
+        funding: false,
+    })
+}
+
+fn parse_amount(value: &str) -> Result<BigDecimal, String> {
+    // Some European OFX producers write a decimal comma
+    BigDecimal::from_str(&value.replace(',', ".")).map_err(|_| format!("Invalid amount '{}'", value))
+}
+
+/// Parses OFX dates of the form `YYYYMMDD[HHMMSS[.XXX]][[offset:TZ]]`, ignoring the zone.
+fn parse_date(value: &str) -> Result<NaiveDateTime, String> {
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let invalid = || format!("Invalid OFX date '{}'", value);
+
+    if digits.len() >= 14 {
+        NaiveDateTime::parse_from_str(&digits[..14], "%Y%m%d%H%M%S").map_err(|_| invalid())
+    } else if digits.len() >= 8 {
+        NaiveDate::parse_from_str(&digits[..8], "%Y%m%d")
+            .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+            .map_err(|_| invalid())
+    } else {
+        Err(invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_declared_windows_1252() {
+        let mut bytes = b"OFXHEADER:100\r\nDATA:OFXSGML\r\nENCODING:USASCII\r\nCHARSET:1252\r\n\r\n<OFX><NAME>Caf".to_vec();
+        bytes.push(0xE9);
+        bytes.extend_from_slice(b"</OFX>");
+        assert!(decode(&bytes).contains("<NAME>Café"));
+    }
+
+    #[test]
+    fn decodes_utf8_and_xml_declarations() {
+        let sgml = "ENCODING:UTF-8\r\nCHARSET:NONE\r\n<OFX><NAME>Bäckerei</OFX>";
+        assert!(decode(sgml.as_bytes()).contains("Bäckerei"));
+
+        let mut xml = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><OFX><NAME>M".to_vec();
+        xml.push(0xFC);
+        xml.extend_from_slice(b"ller</OFX>");
+        assert!(decode(&xml).contains("Müller"));
+    }
+
+    const SGML: &str = "OFXHEADER:100\r\nDATA:OFXSGML\r\nCHARSET:1252\r\n\r\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS>\r\n\
+        <CURDEF>EUR<BANKACCTFROM><BANKID>37040044<ACCTID>0532013000</BANKACCTFROM>\r\n\
+        <BANKTRANLIST>\r\n\
+        <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240201120000.000[-5:EST]<TRNAMT>-42,50<FITID>A1<NAME>Bakery &amp; Co<MEMO>Bread</STMTTRN>\r\n\
+        <STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240202<TRNAMT>1500.00<FITID>A2<NAME>Employer<CHECKNUM>17</STMTTRN>\r\n\
+        <STMTTRN><TRNTYPE>DEBIT<DTPOSTED>2024<TRNAMT>-1.00</STMTTRN>\r\n\
+        </BANKTRANLIST><LEDGERBAL><BALAMT>1457.50<DTASOF>20240202</LEDGERBAL></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+
+    #[test]
+    fn reads_signed_amounts_and_dates() {
+        let statement = parse_str(SGML).unwrap().remove(0);
+        assert_eq!(statement.account_iban.as_deref(), Some("0532013000"));
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+        let amounts: Vec<String> = statement.lines.iter().map(|line| line.amount.to_string()).collect();
+        assert_eq!(amounts, ["-42.50", "1500.00"]);
+        assert_eq!(statement.lines[0].date.to_string(), "2024-02-01 12:00:00");
+        assert_eq!(statement.lines[1].date.to_string(), "2024-02-02 00:00:00");
+        assert_eq!(statement.lines[0].counterparty.as_deref(), Some("Bakery & Co"));
+        assert_eq!(statement.lines[1].memo, "Check 17");
+        assert_eq!(statement.lines[1].bank_id.as_deref(), Some("A2"));
+        assert_eq!(statement.closing_balance.unwrap().amount.to_string(), "1457.50");
+    }
+
+    #[test]
+    fn rejects_transactions_with_unreadable_dates() {
+        let statement = parse_str(SGML).unwrap().remove(0);
+        assert_eq!(statement.rejected.len(), 1);
+        assert_eq!(statement.rejected[0].line, 3);
+        assert!(statement.rejected[0].message.contains("2024"));
+    }
+
+    #[test]
+    fn reads_unclosed_leaves_followed_by_multibyte_text() {
+        // The closing tag check must not cut "é" in half
+        let root = parse_elements("<STMTTRN><FITID>X1<NAME>Aé Cafe</STMTTRN>");
+        let transaction = &root.children[0];
+        assert_eq!(transaction.children[0].value.as_deref(), Some("X1"));
+        assert_eq!(transaction.children[1].value.as_deref(), Some("Aé Cafe"));
+    }
+
+    #[test]
+    fn falls_back_to_windows_1252_for_undeclared_bytes() {
+        let mut bytes = b"<OFX><NAME>Stra".to_vec();
+        bytes.push(0xDF);
+        bytes.extend_from_slice(b"e</OFX>");
+        assert!(decode(&bytes).contains("Straße"));
+    }
+}
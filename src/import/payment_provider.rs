@@ -0,0 +1,434 @@
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::locale::{parse_amount, parse_date, DateOrder, DecimalSeparator, Locale};
+use super::table::{self, field, matches_any, optional_field, CurrencyStatements};
+use super::{Balance, ImportError, RowError, Statement, StatementLine};
+
+/// Payment providers whose activity exports have a dedicated importer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Provider {
+    PayPal,
+    Stripe,
+}
+
+impl Provider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Provider::PayPal => "PayPal",
+            Provider::Stripe => "Stripe",
+        }
+    }
+}
+
+// Column names of the English and German PayPal activity download
+const PAYPAL_DATE: &[&str] = &["Date", "Datum"];
+const PAYPAL_TIME: &[&str] = &["Time", "Uhrzeit"];
+const PAYPAL_NAME: &[&str] = &["Name"];
+const PAYPAL_TYPE: &[&str] = &["Type", "Typ"];
+const PAYPAL_STATUS: &[&str] = &["Status"];
+const PAYPAL_CURRENCY: &[&str] = &["Currency", "Währung"];
+const PAYPAL_GROSS: &[&str] = &["Gross", "Brutto"];
+const PAYPAL_FEE: &[&str] = &["Fee", "Gebühr"];
+const PAYPAL_NET: &[&str] = &["Net", "Netto"];
+const PAYPAL_FROM_EMAIL: &[&str] = &["From Email Address", "Absender E-Mail-Adresse"];
+const PAYPAL_TO_EMAIL: &[&str] = &["To Email Address", "Empfänger E-Mail-Adresse"];
+const PAYPAL_ID: &[&str] = &["Transaction ID", "Transaktionscode"];
+const PAYPAL_REFERENCE: &[&str] = &["Reference Txn ID", "Zugehöriger Transaktionscode"];
+const PAYPAL_BALANCE: &[&str] = &["Balance", "Guthaben"];
+const PAYPAL_BALANCE_IMPACT: &[&str] = &["Balance Impact", "Auswirkung auf Guthaben"];
+const PAYPAL_MEMO: &[&[&str]] = &[&["Item Title", "Artikelbezeichnung"], &["Subject", "Betreff"], &["Note", "Hinweis"]];
+
+// Column names of the Stripe balance history and itemized balance change reports
+const STRIPE_ID: &[&str] = &["id", "balance_transaction_id"];
+const STRIPE_TYPE: &[&str] = &["Type", "reporting_category"];
+const STRIPE_AMOUNT: &[&str] = &["Amount", "gross"];
+const STRIPE_FEE: &[&str] = &["Fee"];
+const STRIPE_NET: &[&str] = &["Net"];
+const STRIPE_CURRENCY: &[&str] = &["Currency"];
+const STRIPE_CREATED: &[&str] = &["Created (UTC)", "Created", "created_utc"];
+const STRIPE_DESCRIPTION: &[&str] = &["Description"];
+const STRIPE_CUSTOMER_NAME: &[&str] = &["Customer Name", "customer_name"];
+const STRIPE_CUSTOMER_EMAIL: &[&str] = &["Customer Email", "customer_email"];
+const STRIPE_CUSTOMER_AMOUNT: &[&str] = &["Customer Facing Amount", "customer_facing_amount"];
+const STRIPE_CUSTOMER_CURRENCY: &[&str] = &["Customer Facing Currency", "customer_facing_currency"];
+
+/// Row types that reserve and release money without spending it.
+const HOLD_TYPES: &[&str] = &["hold", "authorization", "autorisierung", "einbehalt", "reserve"];
+/// Row types that move money between a provider balance and a bank account or card.
+const FUNDING_TYPES: &[&str] = &[
+    "deposit", "withdrawal", "bank transfer", "payout", "topup", "bankgutschrift", "einzahlung", "auszahlung", "abbuchung",
+];
+const CONVERSION_TYPES: &[&str] = &["currency conversion", "währungsumrechnung"];
+
+const PAYPAL_REQUIRED: &[&[&str]] = &[PAYPAL_DATE, PAYPAL_TYPE, PAYPAL_GROSS, PAYPAL_FEE, PAYPAL_NET, PAYPAL_ID];
+const STRIPE_REQUIRED: &[&[&str]] = &[STRIPE_ID, STRIPE_TYPE, STRIPE_AMOUNT, STRIPE_FEE, STRIPE_NET, STRIPE_CURRENCY, STRIPE_CREATED];
+
+/// One PayPal activity row with the fields the importer needs.
+struct PayPalRow {
+    line: usize,
+    date: NaiveDateTime,
+    kind: String,
+    currency: String,
+    gross: BigDecimal,
+    fee: BigDecimal,
+    net: BigDecimal,
+    id: String,
+    reference: String,
+    counterparty: Option<String>,
+    memo: String,
+    balance: Option<BigDecimal>,
+}
+
+/// Names the provider that wrote a CSV export, from its header; `None` for other CSV files.
+pub fn detect(path: &Path) -> Result<Option<Provider>, ImportError> {
+    let content = table::read_text(path)?;
+    let Some(first_line) = content.lines().next() else {
+        return Ok(None);
+    };
+    if table::find_header(first_line, PAYPAL_REQUIRED).is_some() {
+        Ok(Some(Provider::PayPal))
+    } else if table::find_header(first_line, STRIPE_REQUIRED).is_some() {
+        Ok(Some(Provider::Stripe))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Reads a payment-provider export into one statement per currency balance,
+/// named `PayPal EUR`, `Stripe USD` and so on.
+///
+/// Gross, fee and net columns become one line of the net amount with the fee
+/// recorded alongside. Holds, authorizations and their releases are dropped,
+/// and a payment in a foreign currency is merged with the conversion rows
+/// that paid for it into one line in the converted currency that keeps the
+/// original amount. Top-ups, withdrawals and payouts are marked as funding so
+/// the import can link them to the booking on the bank account.
+pub fn parse_file(path: &Path, provider: Provider, locale: Option<&Locale>) -> Result<Vec<Statement>, ImportError> {
+    let content = table::read_text(path)?;
+    let mut statements = match provider {
+        Provider::PayPal => parse_paypal(&content, locale)?,
+        Provider::Stripe => parse_stripe(&content)?,
+    };
+    for statement in &mut statements {
+        statement.provider = Some(provider.name().to_string());
+    }
+    Ok(statements)
+}
+
+fn provider_line(line: usize, date: NaiveDateTime, amount: BigDecimal, counterparty: Option<String>, memo: String, bank_id: &str) -> StatementLine {
+    StatementLine {
+        line,
+        date,
+        amount,
+        counterparty,
+        counterparty_iban: None,
+        counterparty_bic: None,
+        memo,
+        bank_id: Some(bank_id.to_string()).filter(|id| !id.is_empty()),
+        category: None,
+        fee: None,
+        original_amount: None,
+        original_currency: None,
+        funding: false,
+    }
+}
+
+fn nonzero_fee(fee: &BigDecimal) -> Option<BigDecimal> {
+    Some(fee.abs()).filter(|fee| !fee.is_zero())
+}
+
+/// Column positions of a PayPal activity download.
+struct PayPalColumns {
+    date: usize,
+    time: Option<usize>,
+    name: Option<usize>,
+    kind: usize,
+    status: Option<usize>,
+    currency: usize,
+    gross: usize,
+    fee: usize,
+    net: usize,
+    from_email: Option<usize>,
+    to_email: Option<usize>,
+    id: usize,
+    reference: Option<usize>,
+    balance: Option<usize>,
+    balance_impact: Option<usize>,
+    memo: Vec<usize>,
+}
+
+/// Column positions of a Stripe balance export.
+struct StripeColumns {
+    id: usize,
+    kind: usize,
+    amount: usize,
+    fee: usize,
+    net: usize,
+    currency: usize,
+    created: usize,
+    description: Option<usize>,
+    customer_name: Option<usize>,
+    customer_email: Option<usize>,
+    customer_amount: Option<usize>,
+    customer_currency: Option<usize>,
+}
+
+pub fn parse_paypal(content: &str, locale: Option<&Locale>) -> Result<Vec<Statement>, ImportError> {
+    let (header, records) = table::read_records(content, 0)?;
+    let columns = PayPalColumns {
+        date: header.require(PAYPAL_DATE)?,
+        time: header.find(PAYPAL_TIME),
+        name: header.find(PAYPAL_NAME),
+        kind: header.require(PAYPAL_TYPE)?,
+        status: header.find(PAYPAL_STATUS),
+        currency: header.require(PAYPAL_CURRENCY)?,
+        gross: header.require(PAYPAL_GROSS)?,
+        fee: header.require(PAYPAL_FEE)?,
+        net: header.require(PAYPAL_NET)?,
+        from_email: header.find(PAYPAL_FROM_EMAIL),
+        to_email: header.find(PAYPAL_TO_EMAIL),
+        id: header.require(PAYPAL_ID)?,
+        reference: header.find(PAYPAL_REFERENCE),
+        balance: header.find(PAYPAL_BALANCE),
+        balance_impact: header.find(PAYPAL_BALANCE_IMPACT),
+        memo: PAYPAL_MEMO.iter().filter_map(|names| header.find(names)).collect(),
+    };
+
+    let dates: Vec<&str> = records.iter().filter_map(|(_, record)| record.get(columns.date)).collect();
+    let order = table::date_order(&dates, locale)?;
+    let amounts: Vec<&str> = records.iter().filter_map(|(_, record)| record.get(columns.gross)).collect();
+    let decimal = table::decimal_separator(&amounts, locale)?;
+
+    let mut statements = CurrencyStatements::new(Provider::PayPal.name());
+    let mut rows: Vec<PayPalRow> = Vec::new();
+    // Latest reported balance per currency, keyed by the row's date and time
+    let mut balances: HashMap<String, (String, Balance)> = HashMap::new();
+    for (line, record) in &records {
+        let kind = field(record, columns.kind);
+        // Memo rows (pending payments, authorizations) never touched the balance
+        let memo_only = optional_field(record, columns.balance_impact).is_some_and(|impact| impact.eq_ignore_ascii_case("memo"));
+        let pending = columns.balance_impact.is_none()
+            && optional_field(record, columns.status).is_some_and(|status| matches_any(&status, &["pending", "denied", "ausstehend", "abgelehnt"]));
+        if memo_only || pending || matches_any(&kind, HOLD_TYPES) {
+            continue;
+        }
+
+        let row = match paypal_row(record, &columns, *line, order, decimal) {
+            Ok(row) => row,
+            Err(message) => {
+                statements.rejected.push(RowError { line: *line, message });
+                continue;
+            }
+        };
+        if let Some(amount) = &row.balance {
+            let sort_key = format!("{} {}", row.date.format("%Y-%m-%d"), optional_field(record, columns.time).unwrap_or_default());
+            if balances.get(&row.currency).is_none_or(|(latest, _)| *latest <= sort_key) {
+                balances.insert(row.currency.clone(), (sort_key, Balance { amount: amount.clone(), date: row.date }));
+            }
+        }
+        rows.push(row);
+    }
+
+    // Conversion rows are merged into the payment they reference, if that is in the file
+    let payment_ids: Vec<&str> = rows.iter().filter(|row| !matches_any(&row.kind, CONVERSION_TYPES)).map(|row| row.id.as_str()).collect();
+    let mut conversions: HashMap<&str, Vec<&PayPalRow>> = HashMap::new();
+    for row in &rows {
+        if matches_any(&row.kind, CONVERSION_TYPES) && payment_ids.contains(&row.reference.as_str()) {
+            conversions.entry(row.reference.as_str()).or_default().push(row);
+        }
+    }
+
+    for row in &rows {
+        if matches_any(&row.kind, CONVERSION_TYPES) && conversions.contains_key(row.reference.as_str()) {
+            continue;
+        }
+        let mut line = provider_line(row.line, row.date, row.net.clone(), row.counterparty.clone(), row.memo.clone(), &row.id);
+        line.funding = matches_any(&row.kind, FUNDING_TYPES);
+        line.fee = nonzero_fee(&row.fee);
+
+        let settled = conversions.get(row.id.as_str()).and_then(|rows| {
+            rows.iter()
+                .find(|conversion| conversion.currency != row.currency && conversion.gross.is_negative() == row.net.is_negative())
+        });
+        let statement_currency = match settled {
+            Some(conversion) => {
+                // The fee was charged in the payment's currency; convert it at the same rate
+                if let Some(fee) = &line.fee {
+                    if !row.net.is_zero() {
+                        line.fee = Some((fee * conversion.gross.abs() / row.net.abs()).round(2));
+                    }
+                }
+                line.amount = conversion.gross.clone();
+                line.original_amount = Some(row.gross.clone());
+                line.original_currency = Some(row.currency.clone());
+                conversion.currency.clone()
+            }
+            None => row.currency.clone(),
+        };
+        statements.statement_for(&statement_currency).lines.push(line);
+    }
+
+    for (currency, (_, balance)) in balances {
+        if let Some(statement) = statements.statements.iter_mut().find(|statement| statement.currency.as_deref() == Some(currency.as_str())) {
+            statement.closing_balance = Some(balance);
+        }
+    }
+    statements.finish()
+}
+
+fn paypal_row(record: &::csv::StringRecord, columns: &PayPalColumns, line: usize, order: DateOrder, decimal: DecimalSeparator) -> Result<PayPalRow, String> {
+    let kind = field(record, columns.kind);
+    let gross = parse_amount(&field(record, columns.gross), decimal)?;
+    let fee = match optional_field(record, Some(columns.fee)) {
+        Some(value) => parse_amount(&value, decimal)?,
+        None => BigDecimal::zero(),
+    };
+    // The account holder's own address is on the other side of top-ups and withdrawals
+    let email = if matches_any(&kind, FUNDING_TYPES) {
+        None
+    } else if gross.is_negative() {
+        optional_field(record, columns.to_email)
+    } else {
+        optional_field(record, columns.from_email)
+    };
+    let mut memo: Vec<String> = columns.memo.iter().filter_map(|index| optional_field(record, Some(*index))).collect();
+    memo.dedup();
+
+    Ok(PayPalRow {
+        line,
+        date: parse_date(&field(record, columns.date), order)?,
+        counterparty: optional_field(record, columns.name)
+            .or(email)
+            .or_else(|| Some(kind.clone()).filter(|kind| !kind.is_empty())),
+        memo: if memo.is_empty() { kind.clone() } else { memo.join(" ") },
+        kind,
+        currency: field(record, columns.currency).to_uppercase(),
+        gross,
+        fee,
+        net: parse_amount(&field(record, columns.net), decimal)?,
+        id: field(record, columns.id),
+        reference: optional_field(record, columns.reference).unwrap_or_default(),
+        balance: optional_field(record, columns.balance)
+            .map(|value| parse_amount(&value, decimal))
+            .transpose()?,
+    })
+}
+
+pub fn parse_stripe(content: &str) -> Result<Vec<Statement>, ImportError> {
+    let (header, records) = table::read_records(content, 0)?;
+    let columns = StripeColumns {
+        id: header.require(STRIPE_ID)?,
+        kind: header.require(STRIPE_TYPE)?,
+        amount: header.require(STRIPE_AMOUNT)?,
+        fee: header.require(STRIPE_FEE)?,
+        net: header.require(STRIPE_NET)?,
+        currency: header.require(STRIPE_CURRENCY)?,
+        created: header.require(STRIPE_CREATED)?,
+        description: header.find(STRIPE_DESCRIPTION),
+        customer_name: header.find(STRIPE_CUSTOMER_NAME),
+        customer_email: header.find(STRIPE_CUSTOMER_EMAIL),
+        customer_amount: header.find(STRIPE_CUSTOMER_AMOUNT),
+        customer_currency: header.find(STRIPE_CUSTOMER_CURRENCY),
+    };
+
+    let mut statements = CurrencyStatements::new(Provider::Stripe.name());
+    for (line, record) in &records {
+        if matches_any(&field(record, columns.kind), HOLD_TYPES) {
+            continue;
+        }
+        match stripe_line(record, &columns, *line) {
+            Ok((currency, statement_line)) => statements.statement_for(&currency).lines.push(statement_line),
+            Err(message) => statements.rejected.push(RowError { line: *line, message }),
+        }
+    }
+    statements.finish()
+}
+
+/// Reads one Stripe balance transaction, returning its settlement currency with the line.
+fn stripe_line(record: &::csv::StringRecord, columns: &StripeColumns, line: usize) -> Result<(String, StatementLine), String> {
+    let kind = field(record, columns.kind);
+    let created = field(record, columns.created);
+    let date = parse_date(created.get(..10).unwrap_or(&created), DateOrder::YearMonthDay)?;
+    let gross = parse_amount(&field(record, columns.amount), DecimalSeparator::Point)?;
+    let fee = match optional_field(record, Some(columns.fee)) {
+        Some(value) => parse_amount(&value, DecimalSeparator::Point)?,
+        None => BigDecimal::zero(),
+    };
+    let description = optional_field(record, columns.description);
+    let counterparty = optional_field(record, columns.customer_name)
+        .or_else(|| optional_field(record, columns.customer_email))
+        .or_else(|| description.clone())
+        .or_else(|| Some(kind.clone()));
+
+    let net = parse_amount(&field(record, columns.net), DecimalSeparator::Point)?;
+    let mut statement_line = provider_line(line, date, net, counterparty, description.unwrap_or_else(|| kind.clone()), &field(record, columns.id));
+    statement_line.fee = nonzero_fee(&fee);
+    statement_line.funding = matches_any(&kind, FUNDING_TYPES);
+
+    let currency = field(record, columns.currency).to_uppercase();
+    let original_currency = optional_field(record, columns.customer_currency)
+        .map(|value| value.to_uppercase())
+        .filter(|value| *value != currency);
+    if let (Some(original_currency), Some(original)) = (original_currency, optional_field(record, columns.customer_amount)) {
+        let original = parse_amount(&original, DecimalSeparator::Point)?.abs();
+        statement_line.original_amount = Some(if gross.is_negative() { -original } else { original });
+        statement_line.original_currency = Some(original_currency);
+    }
+    Ok((currency, statement_line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYPAL: &str = "\
+Date,Time,Name,Type,Status,Currency,Gross,Fee,Net,Transaction ID,Reference Txn ID,Balance
+13/03/2024,10:00:00,US Shop,Express Checkout Payment,Completed,USD,-10.00,0.00,-10.00,P1,,0.00
+13/03/2024,10:00:01,,General Currency Conversion,Completed,EUR,-9.20,0.00,-9.20,C1,P1,90.80
+13/03/2024,10:00:02,,General Currency Conversion,Completed,USD,10.00,0.00,10.00,C2,P1,0.00
+14/03/2024,09:00:00,,Account Hold for Open Authorization,Completed,EUR,-5.00,0.00,-5.00,H1,,85.80
+15/03/2024,09:00:00,,Bank Deposit to PP Account,Completed,EUR,50.00,0.00,50.00,D1,,140.80
+16/03/2024,09:00:00,Customer,Website Payment,Completed,EUR,20.00,-0.70,19.30,W1,,160.10
+";
+
+    #[test]
+    fn merges_paypal_conversions_and_drops_holds() {
+        let statements = parse_paypal(PAYPAL, None).unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.currency.as_deref(), Some("EUR"));
+
+        let ids: Vec<&str> = statement.lines.iter().filter_map(|line| line.bank_id.as_deref()).collect();
+        assert_eq!(ids, ["P1", "D1", "W1"]);
+        let payment = &statement.lines[0];
+        assert_eq!(payment.amount.to_string(), "-9.20");
+        assert_eq!(payment.original_amount.as_ref().map(|amount| amount.to_string()).as_deref(), Some("-10.00"));
+        assert_eq!(payment.original_currency.as_deref(), Some("USD"));
+        assert!(statement.lines[1].funding);
+        let sale = &statement.lines[2];
+        assert_eq!(sale.amount.to_string(), "19.30");
+        assert_eq!(sale.fee.as_ref().map(|fee| fee.to_string()).as_deref(), Some("0.70"));
+        assert_eq!(statement.closing_balance.as_ref().unwrap().amount.to_string(), "160.10");
+    }
+
+    #[test]
+    fn reads_stripe_net_amounts_fees_and_payouts() {
+        let content = "\
+id,Type,Amount,Fee,Net,Currency,Created (UTC),Description,Customer Email,Customer Facing Amount,Customer Facing Currency
+txn_1,charge,25.00,1.03,23.97,eur,2024-03-01 10:00:00,Order 7,buyer@example.com,27.50,usd
+txn_2,payout,-23.97,0.00,-23.97,eur,2024-03-03 00:00:00,STRIPE PAYOUT,,,
+";
+        let statements = parse_stripe(content).unwrap();
+        let lines = &statements[0].lines;
+        assert_eq!(statements[0].currency.as_deref(), Some("EUR"));
+        assert_eq!(lines[0].amount.to_string(), "23.97");
+        assert_eq!(lines[0].fee.as_ref().map(|fee| fee.to_string()).as_deref(), Some("1.03"));
+        assert_eq!(lines[0].counterparty.as_deref(), Some("buyer@example.com"));
+        assert_eq!(lines[0].original_currency.as_deref(), Some("USD"));
+        assert!(!lines[0].funding);
+        assert!(lines[1].funding);
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::ImportError;
+use crate::utils::db::get_finwise_data_dir;
+
+/// Refers to a CSV column either by header name or by zero-based position.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+/// Which columns of a bank export hold which transaction fields.
+///
+/// Either `amount` (signed) or the `debit`/`credit` pair must be set.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ColumnMapping {
+    pub date: Option<Column>,
+    pub amount: Option<Column>,
+    pub debit: Option<Column>,
+    pub credit: Option<Column>,
+    pub counterparty: Option<Column>,
+    pub iban: Option<Column>,
+    pub category: Option<Column>,
+    #[serde(default)]
+    pub memo: Vec<Column>,
+}
+
+/// A named, per-bank description of a CSV export layout.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CsvProfile {
+    pub name: String,
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    #[serde(default = "default_encoding")]
+    pub encoding: String,
+    #[serde(default = "default_true")]
+    pub has_header: bool,
+    /// Lines to drop before the header, for banks that prepend account details.
+    #[serde(default)]
+    pub skip_rows: usize,
+    /// How amounts and dates are written (`de-DE`, `en-US`, ...); `--locale` applies when unset.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Amounts use `,` as decimal separator and `.` for thousands (e.g. `1.234,56`).
+    #[serde(default)]
+    pub decimal_comma: bool,
+    /// A `chrono` format fixing how dates are written; without it dates are
+    /// read in the locale's order, or as ISO dates if there is no locale.
+    #[serde(default)]
+    pub date_format: Option<String>,
+    /// IBAN of the account this bank's exports belong to, since CSV exports
+    /// rarely name it; imports without `--account` go to that account.
+    #[serde(default)]
+    pub account_iban: Option<String>,
+    pub columns: ColumnMapping,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_encoding() -> String {
+    "utf-8".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl CsvProfile {
+    /// `account_iban` without spaces and in upper case, as IBANs are stored.
+    pub fn normalized_account_iban(&self) -> Option<String> {
+        self.account_iban
+            .as_ref()
+            .map(|iban| iban.replace(' ', "").to_uppercase())
+            .filter(|iban| !iban.is_empty())
+    }
+
+    /// A starting point for a new profile that the user edits by hand.
+    pub fn template(name: &str) -> Self {
+        CsvProfile {
+            name: name.to_string(),
+            delimiter: default_delimiter(),
+            encoding: default_encoding(),
+            has_header: true,
+            skip_rows: 0,
+            locale: Some("en-US".to_string()),
+            decimal_comma: false,
+            date_format: None,
+            account_iban: None,
+            columns: ColumnMapping {
+                date: Some(Column::Name("Date".to_string())),
+                amount: Some(Column::Name("Amount".to_string())),
+                debit: None,
+                credit: None,
+                counterparty: Some(Column::Name("Payee".to_string())),
+                iban: Some(Column::Name("IBAN".to_string())),
+                category: None,
+                memo: vec![Column::Name("Description".to_string())],
+            },
+        }
+    }
+}
+
+/// Gets the directory holding CSV mapping profiles, creating it if needed
+pub fn get_profiles_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let profiles_dir = get_finwise_data_dir()?.join("import_profiles");
+    if !profiles_dir.exists() {
+        fs::create_dir_all(&profiles_dir)
+            .map_err(|e| format!("Failed to create profile directory: {}", e))?;
+    }
+    Ok(profiles_dir)
+}
+
+fn profile_path(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid profile name '{}': use letters, digits, '-' and '_'", name).into());
+    }
+    Ok(get_profiles_dir()?.join(format!("{}.json", name)))
+}
+
+pub fn load_profile(name: &str) -> Result<CsvProfile, Box<dyn std::error::Error>> {
+    let path = profile_path(name)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read profile '{}': {}", path.display(), e))?;
+    let profile: CsvProfile = serde_json::from_str(&content).map_err(ImportError::from)?;
+    Ok(profile)
+}
+
+pub fn save_profile(profile: &CsvProfile) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = profile_path(&profile.name)?;
+    fs::write(&path, serde_json::to_string_pretty(profile)?)?;
+    Ok(path)
+}
+
+pub fn list_profiles() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut names: Vec<String> = fs::read_dir(get_profiles_dir()?)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                path.file_stem().and_then(|stem| stem.to_str()).map(|stem| stem.to_string())
+            } else {
+                None
+            }
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
@@ -0,0 +1,395 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use encoding_rs::WINDOWS_1252;
+use std::fs;
+use std::path::Path;
+
+use super::locale::{self, DateOrder, DecimalSeparator, Locale};
+use super::table;
+use super::{Balance, ImportError, RowError, Statement, StatementLine};
+
+/// Payee Quicken and MS Money use for the record that sets an account's starting balance.
+const OPENING_BALANCE_PAYEE: &str = "Opening Balance";
+
+/// The kind of register a `!Type:` header introduces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Cash,
+    Investment,
+    /// Account lists, category lists, memorized transactions and other non-register data.
+    Other,
+}
+
+/// One `^`-terminated QIF record as raw `(field code, value)` pairs.
+struct Record {
+    line: usize,
+    fields: Vec<(char, String)>,
+}
+
+impl Record {
+    fn get(&self, code: char) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, value)| *field == code && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// How the dates and amounts of a whole file are written.
+struct Notation {
+    dates: DateOrder,
+    decimal: DecimalSeparator,
+}
+
+/// Reads a QIF file; `locale` fixes how dates and amounts are written,
+/// otherwise the file's own dates and amounts decide.
+pub fn parse_file(path: &Path, locale: Option<&Locale>) -> Result<Vec<Statement>, ImportError> {
+    let bytes = fs::read(path)?;
+    // Quicken writes QIF in the Windows code page
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => WINDOWS_1252.decode(e.as_bytes()).0.into_owned(),
+    };
+    parse_str(&content, locale)
+}
+
+/// Parses a QIF export: one statement per `!Account` block, or a single
+/// unnamed statement for files exported from one register.
+///
+/// QIF does not say whether `03/04/24` is day or month first, nor which
+/// decimal separator it uses, so both are settled for the whole file as
+/// `table::date_order` and `table::decimal_separator` do; files that leave
+/// either open are rejected unless `locale` decides.
+pub fn parse_str(content: &str, locale: Option<&Locale>) -> Result<Vec<Statement>, ImportError> {
+    let mut statements: Vec<Statement> = Vec::new();
+    // Register records with the statement they belong to, parsed once the notation is known
+    let mut records: Vec<(usize, Section, Record)> = Vec::new();
+    let mut section = Section::Other;
+    let mut in_account_list = false;
+    let mut auto_switch = false;
+    let mut record = Record { line: 1, fields: Vec::new() };
+
+    for (index, raw_line) in content.lines().enumerate() {
+        let line = raw_line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.trim();
+            let lower = header.to_lowercase();
+            if lower == "option:autoswitch" {
+                auto_switch = true;
+            } else if lower == "clear:autoswitch" {
+                auto_switch = false;
+            } else if lower == "account" {
+                in_account_list = true;
+            } else if let Some(type_name) = lower.strip_prefix("type:") {
+                in_account_list = false;
+                section = match type_name.trim() {
+                    "bank" | "cash" | "ccard" | "oth a" | "oth l" => Section::Cash,
+                    "invst" => Section::Investment,
+                    _ => Section::Other,
+                };
+                if section != Section::Other && statements.is_empty() {
+                    statements.push(Statement::default());
+                }
+            }
+            record = Record { line: index + 2, fields: Vec::new() };
+            continue;
+        }
+
+        if line.starts_with('^') {
+            let finished = std::mem::replace(&mut record, Record { line: index + 2, fields: Vec::new() });
+            if in_account_list {
+                // With AutoSwitch on, the account list is a catalogue, not a switch
+                if !auto_switch {
+                    statements.push(Statement {
+                        account_name: finished.get('N').map(|name| name.trim().to_string()),
+                        ..Statement::default()
+                    });
+                }
+            } else if section != Section::Other {
+                records.push((statements.len() - 1, section, finished));
+            }
+            continue;
+        }
+
+        let mut chars = line.chars();
+        if let Some(code) = chars.next() {
+            record.fields.push((code, chars.as_str().trim().to_string()));
+        }
+    }
+
+    let dates: Vec<String> = records.iter().filter_map(|(_, _, record)| record.get('D')).map(normalize_date).collect();
+    let dates: Vec<&str> = dates.iter().map(|date| date.as_str()).collect();
+    let amounts: Vec<&str> = records
+        .iter()
+        .flat_map(|(_, _, record)| record.fields.iter())
+        .filter(|(code, value)| matches!(code, 'T' | 'U' | '$') && !value.is_empty())
+        .map(|(_, value)| value.as_str())
+        .collect();
+    let notation = Notation {
+        dates: table::date_order(&dates, locale)?,
+        decimal: table::decimal_separator(&amounts, locale)?,
+    };
+    for (index, section, record) in &records {
+        add_record(&mut statements[*index], record, *section, &notation);
+    }
+
+    statements.retain(|statement| !statement.lines.is_empty() || !statement.rejected.is_empty() || statement.opening_balance.is_some());
+    if statements.is_empty() {
+        return Err(ImportError::Format("QIF file contains no bank, card, cash or investment transactions".to_string()));
+    }
+    Ok(statements)
+}
+
+fn add_record(statement: &mut Statement, record: &Record, section: Section, notation: &Notation) {
+    let result = match section {
+        Section::Cash => parse_cash_record(record, statement.account_name.as_deref(), notation),
+        Section::Investment => parse_investment_record(record, notation),
+        Section::Other => return,
+    };
+
+    match result {
+        Ok(RecordResult::Lines(lines)) => statement.lines.extend(lines),
+        Ok(RecordResult::OpeningBalance(balance)) => statement.opening_balance = Some(balance),
+        Ok(RecordResult::Skipped) => {}
+        Err(message) => statement.rejected.push(RowError { line: record.line, message }),
+    }
+}
+
+enum RecordResult {
+    Lines(Vec<StatementLine>),
+    OpeningBalance(Balance),
+    Skipped,
+}
+
+fn parse_cash_record(record: &Record, account_name: Option<&str>, notation: &Notation) -> Result<RecordResult, String> {
+    let date = parse_date(record.get('D').ok_or("Record without date (D)")?, notation)?;
+    let amount = locale::parse_amount(record.get('T').or_else(|| record.get('U')).ok_or("Record without amount (T)")?, notation.decimal)?;
+    let payee = record.get('P').map(|payee| payee.to_string());
+    let memo = record.get('M').unwrap_or("").to_string();
+    let check_number = record.get('N').map(|number| number.to_string());
+    let category = record.get('L');
+
+    if payee.as_deref() == Some(OPENING_BALANCE_PAYEE) && category.and_then(transfer_account) == account_name.map(|name| name.to_string()) {
+        return Ok(RecordResult::OpeningBalance(Balance { amount, date }));
+    }
+
+    let base_line = StatementLine {
+        line: record.line,
+        date,
+        amount: amount.clone(),
+        counterparty: payee,
+        counterparty_iban: None,
+        counterparty_bic: None,
+        memo: memo.clone(),
+        bank_id: None,
+        category: None,
+        fee: None,
+        original_amount: None,
+        original_currency: None,
+        funding: false,
+    };
+
+    let splits = parse_splits(record, notation.decimal)?;
+    if splits.is_empty() {
+        return Ok(RecordResult::Lines(vec![with_category(base_line, category, check_number.as_deref())]));
+    }
+
+    let lines = splits
+        .into_iter()
+        .map(|(split_category, split_memo, split_amount)| {
+            let mut line = base_line.clone();
+            line.amount = split_amount;
+            if !split_memo.is_empty() {
+                line.memo = split_memo;
+            }
+            with_category(line, split_category.as_deref(), check_number.as_deref())
+        })
+        .collect();
+    Ok(RecordResult::Lines(lines))
+}
+
+/// Reads `S`/`E`/`$` split triples in order.
+fn parse_splits(record: &Record, decimal: DecimalSeparator) -> Result<Vec<(Option<String>, String, BigDecimal)>, String> {
+    let mut splits: Vec<(Option<String>, String, Option<BigDecimal>)> = Vec::new();
+    for (code, value) in &record.fields {
+        match code {
+            'S' => splits.push((Some(value.clone()).filter(|category| !category.is_empty()), String::new(), None)),
+            'E' => {
+                if let Some(split) = splits.last_mut() {
+                    split.1 = value.clone();
+                }
+            }
+            '$' => {
+                if let Some(split) = splits.last_mut() {
+                    split.2 = Some(locale::parse_amount(value, decimal)?);
+                }
+            }
+            _ => {}
+        }
+    }
+    splits
+        .into_iter()
+        .map(|(category, memo, amount)| {
+            amount
+                .map(|amount| (category, memo, amount))
+                .ok_or_else(|| "Split without amount ($)".to_string())
+        })
+        .collect()
+}
+
+/// Applies a QIF category: `[Account]` marks a transfer to another account,
+/// which then becomes the counterparty so both registers refer to the same parties.
+fn with_category(mut line: StatementLine, category: Option<&str>, check_number: Option<&str>) -> StatementLine {
+    if let Some(category) = category {
+        match transfer_account(category) {
+            Some(account) => {
+                if let Some(payee) = line.counterparty.replace(account.clone()) {
+                    line.memo = format!("{} {}", payee, line.memo).trim().to_string();
+                }
+                line.category = Some(format!("[{}]", account));
+            }
+            None => line.category = Some(category.to_string()),
+        }
+    }
+    if let Some(check_number) = check_number.filter(|number| number.chars().all(|c| c.is_ascii_digit())) {
+        line.memo = format!("{} (check {})", line.memo, check_number).trim().to_string();
+    }
+    line
+}
+
+/// Returns the account name of a `[Account]` transfer category; class suffixes (`/Class`) are dropped.
+fn transfer_account(category: &str) -> Option<String> {
+    let category = category.split('/').next().unwrap_or(category).trim();
+    category
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .map(|account| account.to_string())
+}
+
+/// Maps an investment record to its cash effect on the account.
+fn parse_investment_record(record: &Record, notation: &Notation) -> Result<RecordResult, String> {
+    let date = parse_date(record.get('D').ok_or("Record without date (D)")?, notation)?;
+    let action = record.get('N').unwrap_or("").to_string();
+    let security = record.get('Y').map(|security| security.to_string());
+    let amount = match record.get('T').or_else(|| record.get('U')) {
+        Some(amount) => locale::parse_amount(amount, notation.decimal)?,
+        None => return Ok(RecordResult::Skipped),
+    };
+
+    let action_key = action.to_lowercase();
+    let signed_amount = match action_key.as_str() {
+        "buy" | "buyx" | "miscexp" | "miscexpx" | "xout" | "margint" | "margintx" | "rtrncap" => -amount.abs(),
+        "sell" | "sellx" | "div" | "divx" | "intinc" | "intincx" | "cglong" | "cglongx" | "cgmid" | "cgmidx" | "cgshort"
+        | "cgshortx" | "miscinc" | "miscincx" | "xin" | "cash" => amount.abs(),
+        // Share movements, splits and reinvestments do not move cash
+        _ => return Ok(RecordResult::Skipped),
+    };
+
+    let mut memo_parts = vec![action.clone()];
+    if let Some(quantity) = record.get('Q') {
+        memo_parts.push(quantity.to_string());
+    }
+    if let Some(security) = &security {
+        memo_parts.push(security.clone());
+    }
+    if let Some(price) = record.get('I') {
+        memo_parts.push(format!("@ {}", price));
+    }
+    if let Some(memo) = record.get('M') {
+        memo_parts.push(format!("- {}", memo));
+    }
+
+    let line = StatementLine {
+        line: record.line,
+        date,
+        amount: signed_amount,
+        counterparty: security.or_else(|| record.get('P').map(|payee| payee.to_string())),
+        counterparty_iban: None,
+        counterparty_bic: None,
+        memo: memo_parts.join(" "),
+        bank_id: None,
+        category: None,
+        fee: None,
+        original_amount: None,
+        original_currency: None,
+        funding: false,
+    };
+    Ok(RecordResult::Lines(vec![with_category(line, record.get('L'), None)]))
+}
+
+/// Writes a QIF date the way `locale::parse_date` reads it: Quicken pads
+/// with spaces (` 1/ 5/98`) and marks years 2000 and later with an
+/// apostrophe (`12/31'23`).
+fn normalize_date(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).map(|c| if c == '\'' { '/' } else { c }).collect()
+}
+
+fn parse_date(value: &str, notation: &Notation) -> Result<NaiveDateTime, String> {
+    locale::parse_date(&normalize_date(value), notation.dates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settles_the_date_order_from_the_whole_file() {
+        let content = "!Type:Bank\nD03/04'24\nT-12.50\nPBakery\n^\nD13/04'24\nT1,200.00\nPEmployer\n^\n";
+        let statement = parse_str(content, None).unwrap().remove(0);
+        let dates: Vec<String> = statement.lines.iter().map(|line| line.date.date().to_string()).collect();
+        assert_eq!(dates, ["2024-04-03", "2024-04-13"]);
+        let amounts: Vec<String> = statement.lines.iter().map(|line| line.amount.to_string()).collect();
+        assert_eq!(amounts, ["-12.50", "1200.00"]);
+    }
+
+    #[test]
+    fn rejects_ambiguous_dates_unless_a_locale_decides() {
+        let content = "!Type:Bank\nD03/04/24\nT-12.50\n^\nD 5/ 6/24\nT-1.00\n^\n";
+        assert!(matches!(parse_str(content, None), Err(ImportError::Format(message)) if message.contains("03/04/24")));
+
+        let us = Locale::named("en-US").unwrap();
+        let statement = parse_str(content, Some(&us)).unwrap().remove(0);
+        assert_eq!(statement.lines[0].date.date().to_string(), "2024-03-04");
+        assert_eq!(statement.lines[1].date.date().to_string(), "2024-05-06");
+    }
+
+    #[test]
+    fn reads_decimal_commas_and_rejects_ambiguous_amounts() {
+        let content = "!Type:Bank\nD31.12.2023\nT-1.234,56\n^\nD30.12.2023\nT12,5\n^\n";
+        let statement = parse_str(content, None).unwrap().remove(0);
+        let amounts: Vec<String> = statement.lines.iter().map(|line| line.amount.to_string()).collect();
+        assert_eq!(amounts, ["-1234.56", "12.5"]);
+
+        let ambiguous = "!Type:Bank\nD2023-12-31\nT1.234\n^\n";
+        assert!(matches!(parse_str(ambiguous, None), Err(ImportError::Format(message)) if message.contains("1.234")));
+    }
+
+    #[test]
+    fn signs_investment_cash_by_action() {
+        let content = "!Type:Invst\nD2024-01-05\nNBuy\nYACME\nQ10\nI5.00\nT50.00\n^\nD2024-02-01\nNDiv\nYACME\nT1.20\n^\nD2024-02-02\nNShrsIn\nYACME\nQ5\n^\n";
+        let statement = parse_str(content, None).unwrap().remove(0);
+        let amounts: Vec<String> = statement.lines.iter().map(|line| line.amount.to_string()).collect();
+        assert_eq!(amounts, ["-50.00", "1.20"]);
+        assert_eq!(statement.lines[0].counterparty.as_deref(), Some("ACME"));
+    }
+
+    #[test]
+    fn splits_records_and_finds_the_opening_balance() {
+        let content = "!Account\nNChecking\nTBank\n^\n!Type:Bank\nD2024-01-01\nT100.00\nPOpening Balance\nL[Checking]\n^\n\
+                       D2024-01-02\nT-30.00\nPMarket\nSFood\n$-20.00\nSHousehold\nEBulbs\n$-10.00\n^\n";
+        let statement = parse_str(content, None).unwrap().remove(0);
+        assert_eq!(statement.account_name.as_deref(), Some("Checking"));
+        assert_eq!(statement.opening_balance.unwrap().amount.to_string(), "100.00");
+        let splits: Vec<(String, String)> = statement
+            .lines
+            .iter()
+            .map(|line| (line.category.clone().unwrap_or_default(), line.amount.to_string()))
+            .collect();
+        assert_eq!(splits, [("Food".to_string(), "-20.00".to_string()), ("Household".to_string(), "-10.00".to_string())]);
+        assert_eq!(statement.lines[1].memo, "Bulbs");
+    }
+}
@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use super::ImportError;
+use crate::utils::db::get_finwise_data_dir;
+
+/// Describes the layout of one merchant's till receipts as they come out of OCR.
+///
+/// A template applies when any of its `keywords` appears in the text. The
+/// patterns are regular expressions matched per line and replace the generic
+/// ones: `item` uses the named groups `description`, `price` and, optionally,
+/// `quantity`; `total` takes the first capture group. Lines matching a `skip`
+/// pattern, such as deposit returns, are never read as items.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReceiptTemplate {
+    pub name: String,
+    /// Party the receipts are booked to.
+    pub merchant: String,
+    pub keywords: Vec<String>,
+    #[serde(default)]
+    pub item: Option<String>,
+    #[serde(default)]
+    pub total: Option<String>,
+    #[serde(default)]
+    pub skip: Vec<String>,
+}
+
+impl ReceiptTemplate {
+    /// A starting point for a new template that the user edits by hand.
+    pub fn template(name: &str) -> Self {
+        ReceiptTemplate {
+            name: name.to_string(),
+            merchant: "Example Market".to_string(),
+            keywords: vec!["EXAMPLE MARKET".to_string()],
+            item: Some(r"^(?P<description>.+?)\s+(?P<price>-?\d+[.,]\d{2})\s+[AB]$".to_string()),
+            total: Some(r"(?i)^summe\s+(?:eur\s+)?(-?\d+[.,]\d{2})".to_string()),
+            skip: vec![r"(?i)^pfand".to_string()],
+        }
+    }
+
+    /// Whether the OCR text names this template's merchant.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.keywords.iter().any(|keyword| text.contains(&keyword.to_lowercase()))
+    }
+}
+
+/// Gets the directory holding receipt layout templates, creating it if needed
+pub fn get_templates_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let templates_dir = get_finwise_data_dir()?.join("receipt_templates");
+    if !templates_dir.exists() {
+        fs::create_dir_all(&templates_dir)
+            .map_err(|e| format!("Failed to create receipt template directory: {}", e))?;
+    }
+    Ok(templates_dir)
+}
+
+fn template_path(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid template name '{}': use letters, digits, '-' and '_'", name).into());
+    }
+    Ok(get_templates_dir()?.join(format!("{}.json", name)))
+}
+
+pub fn load_template(name: &str) -> Result<ReceiptTemplate, Box<dyn std::error::Error>> {
+    let path = template_path(name)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read receipt template '{}': {}", path.display(), e))?;
+    let template: ReceiptTemplate = serde_json::from_str(&content).map_err(ImportError::from)?;
+    Ok(template)
+}
+
+pub fn save_template(template: &ReceiptTemplate) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = template_path(&template.name)?;
+    fs::write(&path, serde_json::to_string_pretty(template)?)?;
+    Ok(path)
+}
+
+pub fn list_templates() -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut names: Vec<String> = fs::read_dir(get_templates_dir()?)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                path.file_stem().and_then(|stem| stem.to_str()).map(|stem| stem.to_string())
+            } else {
+                None
+            }
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
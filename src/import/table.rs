@@ -0,0 +1,210 @@
+use encoding_rs::WINDOWS_1252;
+use std::fs;
+use std::path::Path;
+
+use super::locale::{self, DateOrder, DecimalSeparator, Locale};
+use super::{ImportError, RowError, Statement};
+
+/// How far down an export may start with notes before its header row.
+const MAX_PREAMBLE_LINES: usize = 20;
+
+/// Column positions of a CSV header, looked up by any of their known names, ignoring case.
+pub struct Header(Vec<String>);
+
+impl Header {
+    pub fn find(&self, names: &[&str]) -> Option<usize> {
+        self.0.iter().position(|header| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+    }
+
+    pub fn require(&self, names: &[&str]) -> Result<usize, ImportError> {
+        self.find(names)
+            .ok_or_else(|| ImportError::Format(format!("Column '{}' not found in CSV header", names[0])))
+    }
+
+    pub fn has_all(&self, columns: &[&[&str]]) -> bool {
+        columns.iter().all(|names| self.find(names).is_some())
+    }
+}
+
+/// Reads a CSV export as UTF-8, falling back to Windows-1252, without a byte order mark.
+pub fn read_text(path: &Path) -> Result<String, ImportError> {
+    let bytes = fs::read(path)?;
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(e) => WINDOWS_1252.decode(e.as_bytes()).0.into_owned(),
+    };
+    Ok(content.trim_start_matches('\u{feff}').to_string())
+}
+
+fn delimiter(header_line: &str) -> u8 {
+    if header_line.matches(';').count() > header_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    }
+}
+
+fn parse_header(line: &str) -> Option<Header> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(delimiter(line))
+        .has_headers(false)
+        .from_reader(line.as_bytes());
+    let record = reader.records().next()?.ok()?;
+    Some(Header(record.iter().map(|field| field.trim().to_string()).collect()))
+}
+
+/// Finds the header row holding all `columns` among the first lines of an
+/// export, returning its line index and the header.
+pub fn find_header(content: &str, columns: &[&[&str]]) -> Option<(usize, Header)> {
+    content
+        .lines()
+        .take(MAX_PREAMBLE_LINES)
+        .enumerate()
+        .filter_map(|(index, line)| parse_header(line).map(|header| (index, header)))
+        .find(|(_, header)| header.has_all(columns))
+}
+
+/// Reads the records below the header on line index `header_line`, numbered by
+/// file line and without blank rows.
+pub fn read_records(content: &str, header_line: usize) -> Result<(Header, Vec<(usize, ::csv::StringRecord)>), ImportError> {
+    let body: String = content.lines().skip(header_line).collect::<Vec<_>>().join("\n");
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(delimiter(body.lines().next().unwrap_or("")))
+        .flexible(true)
+        .trim(::csv::Trim::All)
+        .from_reader(body.as_bytes());
+    let header = Header(reader.headers()?.iter().map(|field| field.to_string()).collect());
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        if !record.iter().all(|field| field.is_empty()) {
+            // The reader skips blank lines without counting them, so count the body's
+            let start = record.position().map_or(0, |position| position.byte() as usize);
+            let start = body.len() - body[start..].trim_start_matches(['\r', '\n']).len();
+            records.push((header_line + 1 + body[..start].matches('\n').count(), record));
+        }
+    }
+    Ok((header, records))
+}
+
+pub fn field(record: &::csv::StringRecord, index: usize) -> String {
+    record.get(index).unwrap_or("").to_string()
+}
+
+pub fn optional_field(record: &::csv::StringRecord, index: Option<usize>) -> Option<String> {
+    index.map(|index| field(record, index)).filter(|value| !value.is_empty())
+}
+
+/// Whether a row type such as `Bank Deposit to PP Account` contains any of the lowercase `patterns`.
+pub fn matches_any(kind: &str, patterns: &[&str]) -> bool {
+    let kind = kind.to_lowercase();
+    patterns.iter().any(|pattern| kind.contains(pattern))
+}
+
+/// The date order of an export: the `locale`'s if one was given, otherwise
+/// the one its dates show. Dates that read validly both ways are an error
+/// rather than a guess.
+pub fn date_order(dates: &[&str], locale: Option<&Locale>) -> Result<DateOrder, ImportError> {
+    if let Some(locale) = locale {
+        return Ok(locale.dates);
+    }
+    locale::detect_date_order(dates).ok_or_else(|| {
+        let example = locale::ambiguous_date(dates).or(dates.first().copied()).unwrap_or("");
+        ImportError::Format(format!("Dates such as '{}' could be day or month first; pass --locale to say which", example))
+    })
+}
+
+/// The decimal separator of an export's amounts: the `locale`'s if one was
+/// given, otherwise the one its amounts show. Without either, amounts such as
+/// `1.234` are an error and whole numbers are read with a decimal point.
+pub fn decimal_separator(values: &[&str], locale: Option<&Locale>) -> Result<DecimalSeparator, ImportError> {
+    if let Some(locale) = locale {
+        return Ok(locale.decimal);
+    }
+    if let Some(decimal) = locale::detect_decimal(values) {
+        return Ok(decimal);
+    }
+    match locale::ambiguous_amount(values) {
+        Some(example) => Err(ImportError::Format(format!(
+            "Amounts such as '{}' could use '.' or ',' as decimal separator; pass --locale to say which",
+            example
+        ))),
+        None => Ok(DecimalSeparator::Point),
+    }
+}
+
+/// Collects an export into one statement per currency, in order of first use,
+/// named after the exporting service (`PayPal EUR`, `Kraken USD`).
+pub struct CurrencyStatements {
+    source: String,
+    pub statements: Vec<Statement>,
+    pub rejected: Vec<RowError>,
+}
+
+impl CurrencyStatements {
+    pub fn new(source: &str) -> Self {
+        CurrencyStatements {
+            source: source.to_string(),
+            statements: Vec::new(),
+            rejected: Vec::new(),
+        }
+    }
+
+    pub fn statement_for(&mut self, currency: &str) -> &mut Statement {
+        let index = match self.statements.iter().position(|statement| statement.currency.as_deref() == Some(currency)) {
+            Some(index) => index,
+            None => {
+                self.statements.push(Statement {
+                    account_name: Some(format!("{} {}", self.source, currency)),
+                    currency: Some(currency.to_string()),
+                    ..Statement::default()
+                });
+                self.statements.len() - 1
+            }
+        };
+        &mut self.statements[index]
+    }
+
+    /// The statements built; rows that could not be read are reported on the first.
+    pub fn finish(mut self) -> Result<Vec<Statement>, ImportError> {
+        let Some(first) = self.statements.first_mut() else {
+            let reason = self.rejected.first().map(|error| format!(" (line {}: {})", error.line, error.message)).unwrap_or_default();
+            return Err(ImportError::Format(format!("{} export contains no transactions{}", self.source, reason)));
+        };
+        first.rejected.append(&mut self.rejected);
+        Ok(self.statements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_header_below_notes_and_numbers_rows_by_file_line() {
+        let content = "Exported 2024-03-31\nAccount;123\nDatum;Typ;Betrag\n01.03.2024;Kauf;\"1,50\"\n\n02.03.2024;Verkauf;2,00\n";
+        let (header_line, header) = find_header(content, &[&["Date", "Datum"], &["Type", "Typ"]]).unwrap();
+        assert_eq!(header_line, 2);
+        assert_eq!(header.find(&["Amount", "betrag"]), Some(2));
+        assert!(header.require(&["Currency"]).is_err());
+
+        let (_, records) = read_records(content, header_line).unwrap();
+        let rows: Vec<(usize, String)> = records.iter().map(|(line, record)| (*line, field(record, 2))).collect();
+        assert_eq!(rows, [(4, "1,50".to_string()), (6, "2,00".to_string())]);
+        assert_eq!(optional_field(&records[0].1, Some(5)), None);
+    }
+
+    #[test]
+    fn names_statements_by_source_and_currency() {
+        let mut statements = CurrencyStatements::new("Kraken");
+        statements.statement_for("EUR");
+        statements.statement_for("USD");
+        statements.statement_for("EUR");
+        statements.rejected.push(RowError { line: 3, message: "bad".to_string() });
+        let statements = statements.finish().unwrap();
+        let names: Vec<Option<&str>> = statements.iter().map(|statement| statement.account_name.as_deref()).collect();
+        assert_eq!(names, [Some("Kraken EUR"), Some("Kraken USD")]);
+        assert_eq!(statements[0].rejected.len(), 1);
+        assert!(CurrencyStatements::new("Kraken").finish().is_err());
+    }
+}
@@ -0,0 +1,639 @@
+use bigdecimal::{BigDecimal, Signed, Zero};
+use chrono::NaiveDateTime;
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::locale::{parse_amount, parse_date, DateOrder, DecimalSeparator, Locale};
+use super::table::{self, field, matches_any, optional_field, CurrencyStatements, Header};
+use super::{ImportError, RowError, Statement, TradeKind, TradeLine};
+
+/// Broker and exchange exports with a dedicated importer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exchange {
+    Coinbase,
+    Kraken,
+    /// Any broker export with date, action, symbol, quantity and price or amount columns.
+    Broker,
+}
+
+impl Exchange {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Exchange::Coinbase => "Coinbase",
+            Exchange::Kraken => "Kraken",
+            Exchange::Broker => "Broker",
+        }
+    }
+}
+
+/// Currencies that count as cash on an exchange; everything else is a holding.
+const FIAT: [&str; 8] = ["EUR", "USD", "GBP", "CHF", "CAD", "AUD", "JPY", "SEK"];
+
+// Coinbase transaction history
+const COINBASE_ID: &[&str] = &["ID"];
+const COINBASE_TIMESTAMP: &[&str] = &["Timestamp"];
+const COINBASE_TYPE: &[&str] = &["Transaction Type"];
+const COINBASE_ASSET: &[&str] = &["Asset"];
+const COINBASE_QUANTITY: &[&str] = &["Quantity Transacted"];
+const COINBASE_PRICE_CURRENCY: &[&str] = &["Price Currency", "Spot Price Currency"];
+const COINBASE_PRICE: &[&str] = &["Price at Transaction", "Spot Price at Transaction"];
+const COINBASE_SUBTOTAL: &[&str] = &["Subtotal"];
+const COINBASE_TOTAL: &[&str] = &["Total (inclusive of fees and/or spread)"];
+const COINBASE_FEE: &[&str] = &["Fees and/or Spread"];
+const COINBASE_NOTES: &[&str] = &["Notes"];
+
+// Kraken ledger export
+const KRAKEN_TXID: &[&str] = &["txid"];
+const KRAKEN_REFID: &[&str] = &["refid"];
+const KRAKEN_TIME: &[&str] = &["time"];
+const KRAKEN_TYPE: &[&str] = &["type"];
+const KRAKEN_ASSET: &[&str] = &["asset"];
+const KRAKEN_AMOUNT: &[&str] = &["amount"];
+const KRAKEN_FEE: &[&str] = &["fee"];
+
+// Column names used by common broker exports
+const BROKER_DATE: &[&str] = &["Date", "Trade Date", "Run Date", "Transaction Date", "Datum"];
+const BROKER_ACTION: &[&str] = &["Action", "Type", "Transaction Type", "Activity", "Typ", "Transaktion"];
+const BROKER_SYMBOL: &[&str] = &["Symbol", "Ticker", "ISIN", "Instrument", "Security"];
+const BROKER_QUANTITY: &[&str] = &["Quantity", "Shares", "Qty", "Units", "Anzahl", "Stück"];
+const BROKER_PRICE: &[&str] = &["Price", "Unit Price", "Price ($)", "Kurs"];
+const BROKER_FEE: &[&str] = &["Fees", "Fee", "Commission", "Fees & Comm", "Commission ($)", "Fees ($)", "Gebühren"];
+const BROKER_AMOUNT: &[&str] = &["Amount", "Net Amount", "Total", "Amount ($)", "Betrag"];
+const BROKER_CURRENCY: &[&str] = &["Currency", "Währung"];
+const BROKER_DESCRIPTION: &[&str] = &["Description", "Security Description", "Name", "Product"];
+const BROKER_ID: &[&str] = &["Transaction ID", "Order ID", "ID", "Reference"];
+
+const COINBASE_REQUIRED: &[&[&str]] = &[COINBASE_TIMESTAMP, COINBASE_TYPE, COINBASE_ASSET, COINBASE_QUANTITY];
+const KRAKEN_REQUIRED: &[&[&str]] = &[KRAKEN_TXID, KRAKEN_REFID, KRAKEN_TIME, KRAKEN_TYPE, KRAKEN_ASSET, KRAKEN_AMOUNT, KRAKEN_FEE];
+const BROKER_REQUIRED: &[&[&str]] = &[BROKER_DATE, BROKER_ACTION, BROKER_SYMBOL, BROKER_QUANTITY];
+
+/// Action keywords, checked in order: "Verkauf" must be a sale before "kauf" makes it a purchase.
+const ACTIONS: &[(&[&str], TradeKind)] = &[
+    (&["dividend", "div ", "ausschüttung"], TradeKind::Dividend),
+    (&["sell", "sold", "verkauf"], TradeKind::Sell),
+    (&["buy", "bought", "kauf", "purchase", "reinvest"], TradeKind::Buy),
+    (&["interest", "zins", "reward", "staking", "earn"], TradeKind::Interest),
+    (&["tax", "steuer"], TradeKind::Tax),
+    (&["fee", "commission", "gebühr"], TradeKind::Fee),
+    (&["deposit", "contribution", "transfer in", "receive", "einzahlung", "einlage"], TradeKind::Deposit),
+    (&["withdraw", "transfer out", "send", "auszahlung", "entnahme"], TradeKind::Withdrawal),
+];
+
+/// Names the broker or exchange that wrote a CSV export, from its header; `None` for other CSV files.
+pub fn detect(path: &Path) -> Result<Option<Exchange>, ImportError> {
+    let content = table::read_text(path)?;
+    Ok(if table::find_header(&content, COINBASE_REQUIRED).is_some() {
+        Some(Exchange::Coinbase)
+    } else if table::find_header(&content, KRAKEN_REQUIRED).is_some() {
+        Some(Exchange::Kraken)
+    } else if table::find_header(&content, BROKER_REQUIRED).is_some() {
+        Some(Exchange::Broker)
+    } else {
+        None
+    })
+}
+
+/// Reads a trade history into one statement per cash currency, holding
+/// buys, sells, income, fees and transfers as trades.
+///
+/// Cash amounts are booked against the account of their currency; trades
+/// settled in another coin and coin transfers carry no cash and are kept on
+/// the account of the file's main currency. Generic broker exports do not
+/// name their account, so they need `--account` unless they have a currency column.
+pub fn parse_file(path: &Path, exchange: Exchange, locale: Option<&Locale>) -> Result<Vec<Statement>, ImportError> {
+    let content = table::read_text(path)?;
+    let required = match exchange {
+        Exchange::Coinbase => COINBASE_REQUIRED,
+        Exchange::Kraken => KRAKEN_REQUIRED,
+        Exchange::Broker => BROKER_REQUIRED,
+    };
+    let (header_line, _) = table::find_header(&content, required)
+        .ok_or_else(|| ImportError::Format(format!("Not a {} export", exchange.name())))?;
+    let (header, records) = table::read_records(&content, header_line)?;
+
+    let mut trades = TradeStatements::new(exchange);
+    match exchange {
+        Exchange::Coinbase => parse_coinbase(&header, &records, &mut trades)?,
+        Exchange::Kraken => parse_kraken(&header, &records, &mut trades)?,
+        Exchange::Broker => parse_broker(&header, &records, &mut trades, locale)?,
+    }
+    trades.finish()
+}
+
+/// A trade and the cash currency it settles in, if any.
+struct PendingTrade {
+    cash_currency: Option<String>,
+    trade: TradeLine,
+}
+
+/// Sorts trades onto one statement per cash currency.
+struct TradeStatements {
+    exchange: Exchange,
+    pending: Vec<PendingTrade>,
+    rejected: Vec<RowError>,
+}
+
+impl TradeStatements {
+    fn new(exchange: Exchange) -> Self {
+        TradeStatements { exchange, pending: Vec::new(), rejected: Vec::new() }
+    }
+
+    fn add(&mut self, cash_currency: Option<String>, trade: TradeLine) {
+        self.pending.push(PendingTrade { cash_currency, trade });
+    }
+
+    fn reject(&mut self, line: usize, message: String) {
+        self.rejected.push(RowError { line, message });
+    }
+
+    fn finish(self) -> Result<Vec<Statement>, ImportError> {
+        // The currency most trades settle in holds the trades without cash
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for pending in &self.pending {
+            if let Some(currency) = &pending.cash_currency {
+                *counts.entry(currency.as_str()).or_insert(0) += 1;
+            }
+        }
+        let main_currency = counts.into_iter().max_by_key(|(currency, count)| (*count, std::cmp::Reverse(*currency))).map(|(currency, _)| currency.to_string());
+
+        let Some(main_currency) = main_currency else {
+            // Nothing settled in cash, or a broker export without currencies
+            let mut statement = Statement {
+                account_name: (self.exchange != Exchange::Broker).then(|| self.exchange.name().to_string()),
+                trades: self.pending.into_iter().map(|pending| pending.trade).collect(),
+                rejected: self.rejected,
+                ..Statement::default()
+            };
+            if statement.trades.is_empty() {
+                return Err(ImportError::Format(format!("{} export contains no trades", self.exchange.name())));
+            }
+            statement.trades.sort_by_key(|trade| trade.line);
+            return Ok(vec![statement]);
+        };
+
+        let mut statements = CurrencyStatements::new(self.exchange.name());
+        statements.statement_for(&main_currency);
+        for pending in self.pending {
+            let currency = pending.cash_currency.unwrap_or_else(|| main_currency.clone());
+            statements.statement_for(&currency).trades.push(pending.trade);
+        }
+        statements.rejected = self.rejected;
+        let mut statements = statements.finish()?;
+        for statement in &mut statements {
+            statement.trades.sort_by_key(|trade| trade.line);
+        }
+        Ok(statements)
+    }
+}
+
+fn is_fiat(asset: &str) -> bool {
+    FIAT.contains(&asset)
+}
+
+/// The trade type an action text describes, such as `Reinvest Dividend` or `Kauf`.
+fn classify(action: &str) -> Option<TradeKind> {
+    ACTIONS.iter().find(|(keywords, _)| matches_any(action, keywords)).map(|(_, kind)| *kind)
+}
+
+/// Gives quantities and amounts the sign the trade kind implies, for exports
+/// that write every number as positive.
+fn signed(kind: TradeKind, quantity: Option<BigDecimal>, amount: BigDecimal) -> (Option<BigDecimal>, BigDecimal) {
+    let outflow = matches!(kind, TradeKind::Buy | TradeKind::Fee | TradeKind::Tax | TradeKind::Withdrawal);
+    let disposal = matches!(kind, TradeKind::Sell | TradeKind::Withdrawal);
+    let quantity = quantity.map(|quantity| if disposal { -quantity.abs() } else { quantity.abs() });
+    let amount = if outflow { -amount.abs() } else { amount.abs() };
+    (quantity, amount)
+}
+
+fn trade(line: usize, date: NaiveDateTime, kind: TradeKind, symbol: Option<String>, memo: String) -> TradeLine {
+    TradeLine {
+        line,
+        date,
+        kind,
+        symbol,
+        quantity: None,
+        unit_price: None,
+        currency: None,
+        fee: None,
+        amount: BigDecimal::zero(),
+        memo,
+        bank_id: None,
+    }
+}
+
+fn nonzero(value: BigDecimal) -> Option<BigDecimal> {
+    Some(value.abs()).filter(|value| !value.is_zero())
+}
+
+/// Column positions of a Coinbase transaction history.
+struct CoinbaseColumns {
+    id: Option<usize>,
+    timestamp: usize,
+    kind: usize,
+    asset: usize,
+    quantity: usize,
+    price_currency: Option<usize>,
+    price: Option<usize>,
+    subtotal: Option<usize>,
+    total: Option<usize>,
+    fee: Option<usize>,
+    notes: Option<usize>,
+}
+
+fn parse_coinbase(header: &Header, records: &[(usize, ::csv::StringRecord)], trades: &mut TradeStatements) -> Result<(), ImportError> {
+    let columns = CoinbaseColumns {
+        id: header.find(COINBASE_ID),
+        timestamp: header.require(COINBASE_TIMESTAMP)?,
+        kind: header.require(COINBASE_TYPE)?,
+        asset: header.require(COINBASE_ASSET)?,
+        quantity: header.require(COINBASE_QUANTITY)?,
+        price_currency: header.find(COINBASE_PRICE_CURRENCY),
+        price: header.find(COINBASE_PRICE),
+        subtotal: header.find(COINBASE_SUBTOTAL),
+        total: header.find(COINBASE_TOTAL),
+        fee: header.find(COINBASE_FEE),
+        notes: header.find(COINBASE_NOTES),
+    };
+    for (line, record) in records {
+        // Moves between Coinbase's own trading and staking wallets
+        if matches_any(&field(record, columns.kind), &["staking transfer", "unstaking transfer"]) {
+            continue;
+        }
+        match coinbase_rows(record, &columns, *line) {
+            Ok(rows) => {
+                for (cash_currency, row) in rows {
+                    trades.add(cash_currency, row);
+                }
+            }
+            Err(message) => trades.reject(*line, message),
+        }
+    }
+    Ok(())
+}
+
+fn number(record: &::csv::StringRecord, index: Option<usize>, decimal: DecimalSeparator) -> Result<Option<BigDecimal>, String> {
+    optional_field(record, index)
+        .map(|value| parse_amount(&value, decimal))
+        .transpose()
+}
+
+/// Reads one Coinbase row; a conversion becomes a sale and a purchase.
+fn coinbase_rows(record: &::csv::StringRecord, columns: &CoinbaseColumns, line: usize) -> Result<Vec<(Option<String>, TradeLine)>, String> {
+    let action = field(record, columns.kind);
+    let timestamp = field(record, columns.timestamp);
+    let date = parse_date(timestamp.get(..10).unwrap_or(&timestamp), DateOrder::YearMonthDay)?;
+    let asset = field(record, columns.asset).to_uppercase();
+    let quantity = parse_amount(&field(record, columns.quantity), DecimalSeparator::Point)?;
+    let currency = optional_field(record, columns.price_currency).map(|currency| currency.to_uppercase());
+    let memo = optional_field(record, columns.notes).unwrap_or_else(|| action.clone());
+    let bank_id = optional_field(record, columns.id);
+
+    if action.eq_ignore_ascii_case("convert") {
+        // "Converted 0.01 BTC to 0.15 ETH": a sale of one coin for another, without cash
+        let (target_quantity, target_symbol) = memo
+            .rsplit_once(" to ")
+            .and_then(|(_, target)| target.trim().split_once(' '))
+            .ok_or_else(|| format!("Cannot read conversion target from '{}'", memo))?;
+        let target_quantity = parse_amount(target_quantity, DecimalSeparator::Point)?.abs();
+
+        let mut sold = trade(line, date, TradeKind::Sell, Some(asset), memo.clone());
+        sold.quantity = Some(-quantity.abs());
+        sold.unit_price = number(record, columns.price, DecimalSeparator::Point)?.map(|price| price.abs());
+        sold.currency = currency.clone();
+        sold.fee = number(record, columns.fee, DecimalSeparator::Point)?.and_then(nonzero);
+        sold.bank_id = bank_id.as_ref().map(|id| format!("{}/1", id));
+
+        let mut bought = trade(line, date, TradeKind::Buy, Some(target_symbol.trim().to_uppercase()), memo);
+        if let Some(subtotal) = number(record, columns.subtotal, DecimalSeparator::Point)? {
+            if !target_quantity.is_zero() {
+                bought.unit_price = Some((subtotal.abs() / &target_quantity).round(8));
+            }
+        }
+        bought.quantity = Some(target_quantity);
+        bought.currency = currency;
+        bought.bank_id = bank_id.map(|id| format!("{}/2", id));
+        return Ok(vec![(None, sold), (None, bought)]);
+    }
+
+    let kind = classify(&action).ok_or_else(|| format!("Unknown transaction type '{}'", action))?;
+    // Fiat deposits and withdrawals only move cash
+    if is_fiat(&asset) {
+        let (_, amount) = signed(kind, None, quantity);
+        let mut cash = trade(line, date, kind, None, memo);
+        cash.amount = amount;
+        cash.bank_id = bank_id;
+        return Ok(vec![(Some(asset), cash)]);
+    }
+
+    // Rewards and coin transfers are paid in the coin itself
+    let settles_in_cash = matches!(kind, TradeKind::Buy | TradeKind::Sell);
+    let cash = if settles_in_cash {
+        number(record, columns.total, DecimalSeparator::Point)?.unwrap_or_else(BigDecimal::zero)
+    } else {
+        BigDecimal::zero()
+    };
+    let (signed_quantity, amount) = signed(kind, Some(quantity), cash);
+    let mut holding = trade(line, date, kind, Some(asset), memo);
+    holding.quantity = signed_quantity;
+    holding.unit_price = number(record, columns.price, DecimalSeparator::Point)?.map(|price| price.abs());
+    holding.currency = currency.clone();
+    holding.fee = if settles_in_cash { number(record, columns.fee, DecimalSeparator::Point)?.and_then(nonzero) } else { None };
+    holding.amount = amount;
+    holding.bank_id = bank_id;
+    Ok(vec![(if settles_in_cash { currency } else { None }, holding)])
+}
+
+/// One Kraken ledger entry: a change of one asset's balance.
+struct LedgerEntry {
+    line: usize,
+    txid: String,
+    refid: String,
+    date: NaiveDateTime,
+    kind: String,
+    asset: String,
+    amount: BigDecimal,
+    fee: BigDecimal,
+}
+
+/// Kraken's asset codes without their `X`/`Z` class prefix and wallet suffix (`XXBT` → `BTC`, `DOT.S` → `DOT`).
+fn kraken_asset(code: &str) -> String {
+    let code = code.split('.').next().unwrap_or(code).to_uppercase();
+    let code = if code.len() == 4 && (code.starts_with('X') || code.starts_with('Z')) { code[1..].to_string() } else { code };
+    if code == "XBT" { "BTC".to_string() } else { code }
+}
+
+fn parse_kraken(header: &Header, records: &[(usize, ::csv::StringRecord)], trades: &mut TradeStatements) -> Result<(), ImportError> {
+    let txid = header.require(KRAKEN_TXID)?;
+    let refid = header.require(KRAKEN_REFID)?;
+    let time = header.require(KRAKEN_TIME)?;
+    let kind = header.require(KRAKEN_TYPE)?;
+    let asset = header.require(KRAKEN_ASSET)?;
+    let amount = header.require(KRAKEN_AMOUNT)?;
+    let fee = header.require(KRAKEN_FEE)?;
+
+    let mut entries: Vec<LedgerEntry> = Vec::new();
+    for (line, record) in records {
+        // Kraken lists each transfer once without a txid while it is pending
+        if field(record, txid).is_empty() {
+            continue;
+        }
+        let time_value = field(record, time);
+        let parsed = parse_date(time_value.get(..10).unwrap_or(&time_value), DateOrder::YearMonthDay).and_then(|date| {
+            Ok(LedgerEntry {
+                line: *line,
+                txid: field(record, txid),
+                refid: field(record, refid),
+                date,
+                kind: field(record, kind).to_lowercase(),
+                asset: kraken_asset(&field(record, asset)),
+                amount: parse_amount(&field(record, amount), DecimalSeparator::Point)?,
+                fee: parse_amount(&field(record, fee), DecimalSeparator::Point)?,
+            })
+        });
+        match parsed {
+            Ok(entry) => entries.push(entry),
+            Err(message) => trades.reject(*line, message),
+        }
+    }
+
+    // Both legs of a trade share a refid
+    let mut legs: HashMap<&str, Vec<&LedgerEntry>> = HashMap::new();
+    for entry in &entries {
+        if matches!(entry.kind.as_str(), "trade" | "spend" | "receive") {
+            legs.entry(entry.refid.as_str()).or_default().push(entry);
+        }
+    }
+
+    for entry in &entries {
+        let net = &entry.amount - &entry.fee;
+        match entry.kind.as_str() {
+            "trade" | "spend" | "receive" => {
+                let pair = &legs[entry.refid.as_str()];
+                // A trade is read once, at its first leg
+                if pair[0].txid != entry.txid {
+                    continue;
+                }
+                if pair.len() != 2 {
+                    trades.reject(entry.line, format!("Trade {} has {} ledger entries instead of 2", entry.refid, pair.len()));
+                    continue;
+                }
+                for (cash_currency, row) in kraken_trade(pair[0], pair[1]) {
+                    trades.add(cash_currency, row);
+                }
+            }
+            "deposit" | "withdrawal" => {
+                let row_kind = if entry.kind == "deposit" { TradeKind::Deposit } else { TradeKind::Withdrawal };
+                let mut row = trade(entry.line, entry.date, row_kind, None, entry.kind.clone());
+                row.bank_id = Some(entry.txid.clone());
+                row.fee = nonzero(entry.fee.clone());
+                if is_fiat(&entry.asset) {
+                    row.amount = net;
+                    row.currency = Some(entry.asset.clone());
+                    trades.add(Some(entry.asset.clone()), row);
+                } else {
+                    row.symbol = Some(entry.asset.clone());
+                    row.quantity = Some(net);
+                    row.currency = Some(entry.asset.clone());
+                    trades.add(None, row);
+                }
+            }
+            "staking" | "earn" | "dividend" => {
+                let mut row = trade(entry.line, entry.date, TradeKind::Interest, None, entry.kind.clone());
+                row.bank_id = Some(entry.txid.clone());
+                if is_fiat(&entry.asset) {
+                    row.amount = net;
+                    trades.add(Some(entry.asset.clone()), row);
+                } else {
+                    row.symbol = Some(entry.asset.clone());
+                    row.quantity = Some(net);
+                    trades.add(None, row);
+                }
+            }
+            // Moves between Kraken's own spot, staking and futures wallets
+            "transfer" | "margin" | "rollover" | "settled" => {}
+            other => trades.reject(entry.line, format!("Unknown ledger type '{}'", other)),
+        }
+    }
+    Ok(())
+}
+
+/// Turns the two ledger legs of a Kraken trade into trades: a buy or sell
+/// against cash, or a sale of one coin and a purchase of the other.
+fn kraken_trade(first: &LedgerEntry, second: &LedgerEntry) -> Vec<(Option<String>, TradeLine)> {
+    let bank_id = |leg: &LedgerEntry| Some(leg.txid.clone());
+    let (cash, coin) = match (is_fiat(&first.asset), is_fiat(&second.asset)) {
+        (true, false) => (Some(first), second),
+        (false, true) => (Some(second), first),
+        _ => (None, first),
+    };
+
+    let Some(cash) = cash else {
+        return [first, second]
+            .into_iter()
+            .map(|leg| {
+                let quantity = &leg.amount - &leg.fee;
+                let kind = if quantity.is_negative() { TradeKind::Sell } else { TradeKind::Buy };
+                let mut row = trade(leg.line, leg.date, kind, Some(leg.asset.clone()), format!("trade {}", leg.refid));
+                row.quantity = Some(quantity);
+                row.fee = nonzero(leg.fee.clone());
+                row.currency = Some(leg.asset.clone());
+                row.bank_id = bank_id(leg);
+                (None, row)
+            })
+            .collect();
+    };
+
+    let quantity = &coin.amount - &coin.fee;
+    let kind = if quantity.is_negative() { TradeKind::Sell } else { TradeKind::Buy };
+    let mut row = trade(coin.line.min(cash.line), coin.date, kind, Some(coin.asset.clone()), format!("trade {}", coin.refid));
+    if !quantity.is_zero() {
+        row.unit_price = Some((cash.amount.abs() / quantity.abs()).round(8));
+    }
+    row.quantity = Some(quantity);
+    row.amount = &cash.amount - &cash.fee;
+    row.fee = nonzero(cash.fee.clone());
+    row.currency = Some(cash.asset.clone());
+    row.bank_id = bank_id(coin);
+    vec![(Some(cash.asset.clone()), row)]
+}
+
+/// Column positions of a generic broker export.
+struct BrokerColumns {
+    date: usize,
+    action: usize,
+    symbol: usize,
+    quantity: usize,
+    price: Option<usize>,
+    fee: Option<usize>,
+    amount: Option<usize>,
+    currency: Option<usize>,
+    description: Option<usize>,
+    id: Option<usize>,
+}
+
+fn parse_broker(header: &Header, records: &[(usize, ::csv::StringRecord)], trades: &mut TradeStatements, locale: Option<&Locale>) -> Result<(), ImportError> {
+    let columns = BrokerColumns {
+        date: header.require(BROKER_DATE)?,
+        action: header.require(BROKER_ACTION)?,
+        symbol: header.require(BROKER_SYMBOL)?,
+        quantity: header.require(BROKER_QUANTITY)?,
+        price: header.find(BROKER_PRICE),
+        fee: header.find(BROKER_FEE),
+        amount: header.find(BROKER_AMOUNT),
+        currency: header.find(BROKER_CURRENCY),
+        description: header.find(BROKER_DESCRIPTION),
+        id: header.find(BROKER_ID),
+    };
+    if columns.price.is_none() && columns.amount.is_none() {
+        return Err(ImportError::Format("Broker export needs a price or an amount column".to_string()));
+    }
+
+    let dates: Vec<&str> = records.iter().filter_map(|(_, record)| record.get(columns.date)).collect();
+    let order = table::date_order(&dates, locale)?;
+    let numbers: Vec<&str> = records
+        .iter()
+        .flat_map(|(_, record)| [columns.price, columns.amount].into_iter().flatten().filter_map(|index| record.get(index)))
+        .collect();
+    let decimal = table::decimal_separator(&numbers, locale)?;
+
+    for (line, record) in records {
+        match broker_row(record, &columns, *line, order, decimal) {
+            Ok(row) => trades.add(row.currency.clone(), row),
+            Err(message) => trades.reject(*line, message),
+        }
+    }
+    Ok(())
+}
+
+fn broker_row(record: &::csv::StringRecord, columns: &BrokerColumns, line: usize, order: DateOrder, decimal: DecimalSeparator) -> Result<TradeLine, String> {
+    let action = field(record, columns.action);
+    let kind = classify(&action).ok_or_else(|| format!("Unknown action '{}'", action))?;
+    // Dates may carry a time or an "as of" note after them
+    let date_value = field(record, columns.date);
+    let date = parse_date(date_value.split_whitespace().next().unwrap_or(""), order)?;
+    let symbol = optional_field(record, Some(columns.symbol));
+    let quantity = number(record, Some(columns.quantity), decimal)?.filter(|quantity| !quantity.is_zero());
+    let unit_price = number(record, columns.price, decimal)?.map(|price| price.abs()).filter(|price| !price.is_zero());
+    let fee = number(record, columns.fee, decimal)?.and_then(nonzero);
+
+    let transfers_securities = matches!(kind, TradeKind::Deposit | TradeKind::Withdrawal) && symbol.is_some() && quantity.is_some();
+    let cash = match number(record, columns.amount, decimal)? {
+        // Transfers of securities report their market value, not cash
+        _ if transfers_securities => BigDecimal::zero(),
+        Some(amount) => amount,
+        None => {
+            let value = match (&quantity, &unit_price) {
+                (Some(quantity), Some(price)) => quantity.abs() * price,
+                _ => return Err("Row has neither an amount nor quantity and price".to_string()),
+            };
+            let fee_value = fee.clone().unwrap_or_else(BigDecimal::zero);
+            if kind == TradeKind::Buy {
+                value + fee_value
+            } else {
+                value - fee_value
+            }
+        }
+    };
+    let (signed_quantity, amount) = signed(kind, quantity, cash);
+
+    let mut row = trade(line, date, kind, symbol, optional_field(record, columns.description).unwrap_or(action));
+    row.quantity = signed_quantity;
+    row.unit_price = unit_price;
+    row.fee = fee;
+    row.amount = amount;
+    row.currency = optional_field(record, columns.currency).map(|currency| currency.to_uppercase());
+    row.bank_id = optional_field(record, columns.id);
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn parse(content: &str) -> (Option<Exchange>, Vec<Statement>) {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        let exchange = detect(file.path()).unwrap();
+        let statements = parse_file(file.path(), exchange.unwrap_or(Exchange::Broker), None).unwrap();
+        (exchange, statements)
+    }
+
+    #[test]
+    fn reads_broker_trades_below_a_preamble() {
+        let (exchange, statements) = parse(
+            "Account summary for 12345\n\nDate,Action,Symbol,Quantity,Price,Fees,Amount,Currency\n\
+             2024-03-01,Buy,AAPL,10,170.00,1.00,1701.00,usd\n\
+             2024-03-05,Sell,AAPL,4,180.00,1.00,719.00,USD\n\
+             2024-03-10,Reinvest Dividend,AAPL,,,,2.40,USD\n\
+             2024-03-11,Split,AAPL,1,,,0,USD\n",
+        );
+        assert_eq!(exchange, Some(Exchange::Broker));
+        let statement = &statements[0];
+        assert_eq!(statement.currency.as_deref(), Some("USD"));
+        let trades: Vec<(usize, &str, Option<String>, String)> = statement
+            .trades
+            .iter()
+            .map(|trade| (trade.line, trade.kind.as_str(), trade.quantity.as_ref().map(|quantity| quantity.to_string()), trade.amount.to_string()))
+            .collect();
+        assert_eq!(
+            trades,
+            [
+                (4, "buy", Some("10.0".to_string()), "-1701.00".to_string()),
+                (5, "sell", Some("-4.0".to_string()), "719.00".to_string()),
+                (6, "dividend", None, "2.40".to_string()),
+            ]
+        );
+        assert_eq!(statement.rejected.len(), 1);
+        assert_eq!(statement.rejected[0].line, 7);
+    }
+
+    #[test]
+    fn classifies_actions_in_order() {
+        assert_eq!(classify("Verkauf"), Some(TradeKind::Sell));
+        assert_eq!(classify("Kauf"), Some(TradeKind::Buy));
+        assert_eq!(classify("Cash Dividend"), Some(TradeKind::Dividend));
+        assert_eq!(classify("Staking Reward"), Some(TradeKind::Interest));
+        assert_eq!(classify("Split"), None);
+    }
+}
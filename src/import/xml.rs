@@ -0,0 +1,102 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use super::ImportError;
+
+/// A namespace-stripped XML element tree, small enough for bank statements
+/// and convenient to query by path.
+#[derive(Debug, Default)]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    pub fn path(&self, path: &[&str]) -> Option<&XmlElement> {
+        path.iter().try_fold(self, |element, name| element.child(name))
+    }
+
+    /// Trimmed text of the element at `path`, if present and non-empty.
+    pub fn text_at(&self, path: &[&str]) -> Option<String> {
+        self.path(path)
+            .map(|element| element.text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parses an XML document into its root element.
+pub fn parse(content: &str) -> Result<XmlElement, ImportError> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut stack: Vec<XmlElement> = Vec::new();
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| ImportError::Format(format!("Invalid XML at byte {}: {}", reader.buffer_position(), e)))?;
+        match event {
+            Event::Start(start) => stack.push(element_from(&start)?),
+            Event::Empty(start) => {
+                let element = element_from(&start)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(text) => {
+                if let Some(element) = stack.last_mut() {
+                    let text = text
+                        .unescape()
+                        .map_err(|e| ImportError::Format(format!("Invalid XML text: {}", e)))?;
+                    element.text.push_str(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&String::from_utf8_lossy(&data));
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().expect("quick-xml checks that end tags match");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Event::Eof => return Err(ImportError::Format("XML document has no root element".to_string())),
+            _ => {}
+        }
+    }
+}
+
+fn element_from(start: &BytesStart) -> Result<XmlElement, ImportError> {
+    let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+    let mut attributes = Vec::new();
+    for attribute in start.attributes() {
+        let attribute = attribute.map_err(|e| ImportError::Format(format!("Invalid XML attribute: {}", e)))?;
+        let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+        let value = attribute
+            .unescape_value()
+            .map_err(|e| ImportError::Format(format!("Invalid XML attribute: {}", e)))?
+            .into_owned();
+        attributes.push((key, value));
+    }
+    Ok(XmlElement { name, attributes, text: String::new(), children: Vec::new() })
+}
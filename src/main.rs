@@ -4,8 +4,12 @@ mod services;
 mod utils;
 mod schema;
 mod cli;
+mod import;
+mod export;
+mod backup;
+mod sync;
 
-use clap::{App, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, SubCommand};
 use config::Config;
 use utils::db::DatabasePool;
 use std::sync::Arc;
@@ -29,14 +33,300 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .about("Start the web server"))
         .subcommand(SubCommand::with_name("import")
             .about("Import financial data")
+            .setting(AppSettings::SubcommandsNegateReqs)
+            .subcommand(SubCommand::with_name("list")
+                .about("List recorded import batches"))
+            .subcommand(SubCommand::with_name("undo")
+                .about("Revert everything an import batch created")
+                .arg(Arg::with_name("batch")
+                    .value_name("BATCH")
+                    .required(true)))
             .arg(Arg::with_name("file")
                 .short("f")
                 .long("file")
                 .value_name("FILE")
-                .help("File to import")
-                .required(true)))
+                .help("File to import (CSV, PayPal/Stripe CSV, broker/Coinbase/Kraken trade CSV, OFX/QFX, camt.052/053/054 XML, MT940/MT942, QIF, ledger/hledger journal, beancount, GnuCash book)")
+                .required_unless("watch"))
+            .arg(Arg::with_name("watch")
+                .long("watch")
+                .conflicts_with_all(&["file", "profile", "account", "dry-run", "review"])
+                .help("Keep importing files dropped into ~/FinWise/inbox/"))
+            .arg(Arg::with_name("interval")
+                .long("interval")
+                .value_name("SECONDS")
+                .help("How often --watch checks the inbox")
+                .default_value("10"))
+            .arg(Arg::with_name("profile")
+                .short("p")
+                .long("profile")
+                .value_name("NAME")
+                .help("CSV mapping profile from ~/FinWise/import_profiles/ (not needed for PayPal, Stripe, broker and exchange exports). Only profile CSVs are streamed: they are committed every IMPORT_BATCH_SIZE rows and resume where they stopped when run again, while PayPal, Stripe and trade CSVs are read into memory whole"))
+            .arg(Arg::with_name("locale")
+                .short("l")
+                .long("locale")
+                .value_name("LOCALE")
+                .help("How amounts and dates are written, where the file could be read more than one way (en-US, en-GB, de-DE, de-CH, fr-FR, nl-NL, es-ES, iso)"))
+            .arg(Arg::with_name("account")
+                .short("a")
+                .long("account")
+                .value_name("ID")
+                .help("Account the statement belongs to (default: matched by IBAN)"))
+            .arg(Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Show what would be imported without writing to the database"))
+            .arg(Arg::with_name("review")
+                .long("review")
+                .conflicts_with("dry-run")
+                .help("Ask which party a similar-named counterparty is, and remember the answer"))
+            .arg(Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Dry-run report format")
+                .possible_values(&["table", "json"])
+                .default_value("table")))
+        .subcommand(SubCommand::with_name("export")
+            .about("Export financial data")
+            .arg(Arg::with_name("format")
+                .short("F")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format: a ledger/hledger/beancount journal, an OFX statement per account, or a transaction list as csv, json or xlsx")
+                .possible_values(&["ledger", "hledger", "beancount", "ofx", "csv", "json", "xlsx"])
+                .required(true))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("File to write (default: standard output; required for xlsx)"))
+            .arg(Arg::with_name("commodity")
+                .long("commodity")
+                .value_name("CODE")
+                .help("Commodity amounts are written in (the statement currency for ofx)")
+                .default_value("EUR"))
+            .arg(Arg::with_name("from")
+                .long("from")
+                .value_name("YYYY-MM-DD")
+                .help("Only transactions on or after this day (csv, json, xlsx, ofx)"))
+            .arg(Arg::with_name("to")
+                .long("to")
+                .value_name("YYYY-MM-DD")
+                .help("Only transactions on or before this day (csv, json, xlsx, ofx)"))
+            .arg(Arg::with_name("account")
+                .short("a")
+                .long("account")
+                .value_name("ID")
+                .help("Only transactions into or out of this account (csv, json, xlsx, ofx)"))
+            .arg(Arg::with_name("party")
+                .long("party")
+                .value_name("NAME")
+                .help("Only transactions with a party whose name contains this, or with this IBAN (csv, json, xlsx, ofx)"))
+            .arg(Arg::with_name("category")
+                .short("c")
+                .long("category")
+                .value_name("NAME")
+                .help("Only transactions in this category (csv, json, xlsx, ofx)")))
+        .subcommand(SubCommand::with_name("profile")
+            .about("Manage CSV import mapping profiles")
+            .subcommand(SubCommand::with_name("list")
+                .about("List saved profiles"))
+            .subcommand(SubCommand::with_name("show")
+                .about("Print a profile")
+                .arg(Arg::with_name("name")
+                    .value_name("NAME")
+                    .required(true)))
+            .subcommand(SubCommand::with_name("init")
+                .about("Create a profile template to edit")
+                .arg(Arg::with_name("name")
+                    .value_name("NAME")
+                    .required(true))))
+        .subcommand(SubCommand::with_name("receipts")
+            .about("Manage receipts and invoices")
+            .subcommand(SubCommand::with_name("list")
+                .about("List stored receipts"))
+            .subcommand(SubCommand::with_name("show")
+                .about("Show a receipt with its line items and net, tax and gross totals")
+                .arg(Arg::with_name("id")
+                    .value_name("ID")
+                    .required(true)))
+            .subcommand(SubCommand::with_name("import")
+                .about("Store e-invoices (UBL, CII/XRechnung XML, ZUGFeRD/Factur-X PDF) as receipts")
+                .arg(Arg::with_name("files")
+                    .value_name("FILE")
+                    .multiple(true)
+                    .required(true)))
+            .subcommand(SubCommand::with_name("import-mail")
+                .about("Store order confirmations from .eml files or a Maildir as receipts")
+                .arg(Arg::with_name("path")
+                    .value_name("MAILDIR")
+                    .help("Maildir, directory of .eml files, or a single .eml file")
+                    .required(true)))
+            .subcommand(SubCommand::with_name("import-ocr")
+                .about("Store till receipts from OCR text files; uncertain ones are queued for review")
+                .arg(Arg::with_name("files")
+                    .value_name("FILE")
+                    .multiple(true)
+                    .required(true)))
+            .subcommand(SubCommand::with_name("review")
+                .about("List receipts waiting for review"))
+            .subcommand(SubCommand::with_name("approve")
+                .about("Take a receipt out of the review queue")
+                .arg(Arg::with_name("id")
+                    .value_name("ID")
+                    .required(true))
+                .arg(Arg::with_name("date")
+                    .long("date")
+                    .value_name("YYYY-MM-DD")
+                    .help("Corrected receipt date"))
+                .arg(Arg::with_name("total")
+                    .long("total")
+                    .value_name("AMOUNT")
+                    .help("Corrected total")))
+            .subcommand(SubCommand::with_name("ocr-template")
+                .about("Manage merchant layout templates for OCR receipts")
+                .subcommand(SubCommand::with_name("list")
+                    .about("List saved templates"))
+                .subcommand(SubCommand::with_name("init")
+                    .about("Create a template to edit")
+                    .arg(Arg::with_name("name")
+                        .value_name("NAME")
+                        .required(true))))
+            .subcommand(SubCommand::with_name("mail-template")
+                .about("Manage sender templates for order confirmation mails")
+                .subcommand(SubCommand::with_name("list")
+                    .about("List saved templates"))
+                .subcommand(SubCommand::with_name("init")
+                    .about("Create a template to edit")
+                    .arg(Arg::with_name("name")
+                        .value_name("NAME")
+                        .required(true)))))
+        .subcommand(SubCommand::with_name("payments")
+            .about("Prepare outgoing SEPA transfers and write them to pain.001 files for online banking")
+            .subcommand(SubCommand::with_name("list")
+                .about("List payments and whether their bookings have been imported"))
+            .subcommand(SubCommand::with_name("add")
+                .about("Prepare a transfer to an existing party's IBAN")
+                .arg(Arg::with_name("account")
+                    .short("a")
+                    .long("account")
+                    .value_name("ID")
+                    .help("Account to pay from")
+                    .required(true))
+                .arg(Arg::with_name("party")
+                    .short("p")
+                    .long("party")
+                    .value_name("ID|NAME")
+                    .help("Party to pay")
+                    .required(true))
+                .arg(Arg::with_name("amount")
+                    .long("amount")
+                    .value_name("AMOUNT")
+                    .help("Amount in euro")
+                    .required(true))
+                .arg(Arg::with_name("date")
+                    .long("date")
+                    .value_name("YYYY-MM-DD")
+                    .help("Requested execution date (default: today)"))
+                .arg(Arg::with_name("reference")
+                    .short("r")
+                    .long("reference")
+                    .value_name("TEXT")
+                    .help("Remittance information for the payee")
+                    .default_value("")))
+            .subcommand(SubCommand::with_name("export")
+                .about("Write pending payments to a pain.001.001.09 file")
+                .arg(Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .value_name("FILE")
+                    .required(true))
+                .arg(Arg::with_name("account")
+                    .short("a")
+                    .long("account")
+                    .value_name("ID")
+                    .help("Only payments from this account"))
+                .arg(Arg::with_name("again")
+                    .long("again")
+                    .help("Include pending payments already written to an earlier file")))
+            .subcommand(SubCommand::with_name("cancel")
+                .about("Delete a payment that has not been booked")
+                .arg(Arg::with_name("id")
+                    .value_name("ID")
+                    .required(true))))
+        .subcommand(SubCommand::with_name("backup")
+            .about("Write an encrypted backup of the database and the files in ~/FinWise/")
+            .subcommand(SubCommand::with_name("status")
+                .about("Show the backups in the backup directory and the last scheduled run"))
+            .arg(Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .conflicts_with("schedule")
+                .help("Archive to write (default: finwise-DATE-TIME.fwbackup in the backup directory)"))
+            .arg(Arg::with_name("schedule")
+                .long("schedule")
+                .help("Keep running, writing daily or weekly backups (BACKUP_SCHEDULE) and rotating old ones"))
+            .arg(Arg::with_name("interval")
+                .long("interval")
+                .value_name("SECONDS")
+                .default_value("3600")
+                .help("How often --schedule checks whether a backup is due")))
+        .subcommand(SubCommand::with_name("restore")
+            .about("Restore an encrypted backup into an empty database")
+            .arg(Arg::with_name("file")
+                .value_name("FILE")
+                .required(true))
+            .arg(Arg::with_name("replace")
+                .long("replace")
+                .help("Delete the data in the database and replace it with the backup's")))
         .subcommand(SubCommand::with_name("sync")
-            .about("Sync accounts with banks"))
+            .about("Fetch new bookings of linked accounts from their banks")
+            .arg(Arg::with_name("full")
+                .long("full")
+                .help("Fetch all bookings again instead of continuing after the last sync; duplicates are skipped"))
+            .subcommand(SubCommand::with_name("link")
+                .about("Link an account to an account at a bank connector")
+                .arg(Arg::with_name("account")
+                    .value_name("ACCOUNT")
+                    .required(true))
+                .arg(Arg::with_name("connector")
+                    .short("c")
+                    .long("connector")
+                    .value_name("NAME")
+                    .required(true)
+                    .help("Bank connector (mock)"))
+                .arg(Arg::with_name("remote")
+                    .short("r")
+                    .long("remote")
+                    .value_name("ID")
+                    .required(true)
+                    .help("The account's id at the connector, as shown by 'sync remote'"))
+                .arg(Arg::with_name("setting")
+                    .short("s")
+                    .long("setting")
+                    .value_name("KEY=VALUE")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Connector setting, such as path=bank.json for the mock connector")))
+            .subcommand(SubCommand::with_name("unlink")
+                .about("Stop syncing an account")
+                .arg(Arg::with_name("account")
+                    .value_name("ACCOUNT")
+                    .required(true)))
+            .subcommand(SubCommand::with_name("links")
+                .about("List linked accounts and how their last sync went"))
+            .subcommand(SubCommand::with_name("remote")
+                .about("List the accounts a bank connector gives access to")
+                .arg(Arg::with_name("connector")
+                    .short("c")
+                    .long("connector")
+                    .value_name("NAME")
+                    .required(true))
+                .arg(Arg::with_name("setting")
+                    .short("s")
+                    .long("setting")
+                    .value_name("KEY=VALUE")
+                    .multiple(true)
+                    .number_of_values(1))))
         .subcommand(SubCommand::with_name("report")
             .about("Generate financial reports")
             .arg(Arg::with_name("type")
@@ -59,13 +349,158 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         ("import", Some(sub_m)) => {
             // Initialize database pool only when needed
             let db_pool = Arc::new(DatabasePool::new(&config.database)?);
+            match sub_m.subcommand() {
+                ("list", Some(_)) => return cli::commands::list_imports(db_pool).await,
+                ("undo", Some(undo_m)) => {
+                    let batch_id = undo_m.value_of("batch").unwrap().parse::<i32>()?;
+                    return cli::commands::undo_import(batch_id, db_pool).await;
+                }
+                _ => {}
+            }
+            let locale = sub_m.value_of("locale").or(config.import.locale.as_deref());
+            if sub_m.is_present("watch") {
+                let interval = sub_m.value_of("interval").unwrap().parse::<u64>()?;
+                return cli::commands::watch_inbox(interval, locale, &config.import, db_pool).await;
+            }
             let file_path = sub_m.value_of("file").unwrap();
-            cli::commands::import_data(file_path, db_pool).await?;
+            let profile = sub_m.value_of("profile");
+            let account_id = sub_m.value_of("account").map(|id| id.parse::<i32>()).transpose()?;
+            if sub_m.is_present("dry-run") {
+                let json = sub_m.value_of("format") == Some("json");
+                cli::commands::preview_import(file_path, profile, locale, account_id, json, config.import.match_threshold, db_pool).await?;
+            } else {
+                cli::commands::import_data(file_path, profile, locale, account_id, sub_m.is_present("review"), &config.import, db_pool).await?;
+            }
+        },
+        ("export", Some(sub_m)) => {
+            // Initialize database pool only when needed
+            let db_pool = Arc::new(DatabasePool::new(&config.database)?);
+            let format = sub_m.value_of("format").unwrap();
+            let output = sub_m.value_of("output");
+            let commodity = sub_m.value_of("commodity").unwrap();
+            let day = |name: &str| {
+                sub_m.value_of(name)
+                    .map(|value| chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| format!("Invalid --{} date '{}': use YYYY-MM-DD", name, value)))
+                    .transpose()
+            };
+            let filter = export::ExportFilter {
+                from: day("from")?,
+                to: day("to")?,
+                account_id: sub_m.value_of("account").map(|id| id.parse::<i32>()).transpose()?,
+                party: sub_m.value_of("party").map(str::to_string),
+                category: sub_m.value_of("category").map(str::to_string),
+            };
+            cli::commands::export_data(format, output, commodity, &filter, db_pool).await?;
+        },
+        ("profile", Some(sub_m)) => {
+            match sub_m.subcommand() {
+                ("show", Some(show_m)) => cli::commands::show_profile(show_m.value_of("name").unwrap())?,
+                ("init", Some(init_m)) => cli::commands::init_profile(init_m.value_of("name").unwrap())?,
+                _ => cli::commands::list_profiles()?,
+            }
+        },
+        ("receipts", Some(sub_m)) => {
+            if let ("mail-template", Some(template_m)) = sub_m.subcommand() {
+                match template_m.subcommand() {
+                    ("init", Some(init_m)) => cli::commands::init_mail_template(init_m.value_of("name").unwrap())?,
+                    _ => cli::commands::list_mail_templates()?,
+                }
+                return Ok(());
+            }
+            if let ("ocr-template", Some(template_m)) = sub_m.subcommand() {
+                match template_m.subcommand() {
+                    ("init", Some(init_m)) => cli::commands::init_receipt_template(init_m.value_of("name").unwrap())?,
+                    _ => cli::commands::list_receipt_templates()?,
+                }
+                return Ok(());
+            }
+            // Initialize database pool only when needed
+            let db_pool = Arc::new(DatabasePool::new(&config.database)?);
+            match sub_m.subcommand() {
+                ("import", Some(import_m)) => {
+                    let files = import_m.values_of("files").unwrap().collect();
+                    cli::commands::import_receipts(files, config.import.match_threshold, db_pool).await?;
+                }
+                ("import-ocr", Some(ocr_m)) => {
+                    let files = ocr_m.values_of("files").unwrap().collect();
+                    cli::commands::import_ocr(files, config.import.match_threshold, config.import.ocr_review_threshold, db_pool).await?;
+                }
+                ("show", Some(show_m)) => {
+                    let receipt_id = show_m.value_of("id").unwrap().parse::<i32>()?;
+                    cli::commands::show_receipt(receipt_id, db_pool).await?;
+                }
+                ("review", Some(_)) => cli::commands::review_receipts(db_pool).await?,
+                ("approve", Some(approve_m)) => {
+                    let receipt_id = approve_m.value_of("id").unwrap().parse::<i32>()?;
+                    cli::commands::approve_receipt(receipt_id, approve_m.value_of("date"), approve_m.value_of("total"), db_pool).await?;
+                }
+                ("import-mail", Some(mail_m)) => {
+                    let path = mail_m.value_of("path").unwrap();
+                    cli::commands::import_mail(path, config.import.match_threshold, db_pool).await?;
+                }
+                _ => cli::commands::list_receipts(db_pool).await?,
+            }
+        },
+        ("payments", Some(sub_m)) => {
+            // Initialize database pool only when needed
+            let db_pool = Arc::new(DatabasePool::new(&config.database)?);
+            match sub_m.subcommand() {
+                ("add", Some(add_m)) => {
+                    let account_id = add_m.value_of("account").unwrap().parse::<i32>()?;
+                    let party = add_m.value_of("party").unwrap();
+                    let amount = add_m.value_of("amount").unwrap();
+                    let reference = add_m.value_of("reference").unwrap();
+                    cli::commands::add_payment(account_id, party, amount, add_m.value_of("date"), reference, db_pool).await?;
+                }
+                ("export", Some(export_m)) => {
+                    let account_id = export_m.value_of("account").map(|id| id.parse::<i32>()).transpose()?;
+                    cli::commands::export_payments(export_m.value_of("output").unwrap(), account_id, export_m.is_present("again"), db_pool).await?;
+                }
+                ("cancel", Some(cancel_m)) => {
+                    let payment_id = cancel_m.value_of("id").unwrap().parse::<i32>()?;
+                    cli::commands::cancel_payment(payment_id, db_pool).await?;
+                }
+                _ => cli::commands::list_payments(db_pool).await?,
+            }
+        },
+        ("backup", Some(sub_m)) => {
+            if let ("status", Some(_)) = sub_m.subcommand() {
+                return cli::commands::backup_status(&config.backup);
+            }
+            // Initialize database pool only when needed
+            let db_pool = Arc::new(DatabasePool::new(&config.database)?);
+            if sub_m.is_present("schedule") {
+                let interval = sub_m.value_of("interval").unwrap().parse::<u64>()?;
+                cli::commands::schedule_backups(interval, &config.backup, db_pool).await?;
+            } else {
+                cli::commands::backup_data(sub_m.value_of("output"), &config.backup, db_pool).await?;
+            }
+        },
+        ("restore", Some(sub_m)) => {
+            // Initialize database pool only when needed
+            let db_pool = Arc::new(DatabasePool::new(&config.database)?);
+            cli::commands::restore_data(sub_m.value_of("file").unwrap(), sub_m.is_present("replace"), &config.backup, db_pool).await?;
         },
-        ("sync", Some(_)) => {
+        ("sync", Some(sub_m)) => {
+            if let ("remote", Some(remote_m)) = sub_m.subcommand() {
+                let settings: Vec<&str> = remote_m.values_of("setting").map(|values| values.collect()).unwrap_or_default();
+                return cli::commands::list_remote_accounts(remote_m.value_of("connector").unwrap(), &settings);
+            }
             // Initialize database pool only when needed
             let db_pool = Arc::new(DatabasePool::new(&config.database)?);
-            cli::commands::sync_accounts(db_pool).await?;
+            match sub_m.subcommand() {
+                ("link", Some(link_m)) => {
+                    let account_id = link_m.value_of("account").unwrap().parse::<i32>()?;
+                    let settings: Vec<&str> = link_m.values_of("setting").map(|values| values.collect()).unwrap_or_default();
+                    cli::commands::link_account(account_id, link_m.value_of("connector").unwrap(), link_m.value_of("remote").unwrap(), &settings, db_pool).await?;
+                }
+                ("unlink", Some(unlink_m)) => {
+                    let account_id = unlink_m.value_of("account").unwrap().parse::<i32>()?;
+                    cli::commands::unlink_account(account_id, db_pool).await?;
+                }
+                ("links", Some(_)) => cli::commands::list_account_links(db_pool).await?,
+                _ => cli::commands::sync_accounts(sub_m.is_present("full"), config.import.match_threshold, db_pool).await?,
+            }
         },
         ("report", Some(sub_m)) => {
             // Initialize database pool only when needed
@@ -80,8 +515,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("Available commands:");
             println!("  setup-db        Set up secure database credentials");
             println!("  server          Start the web server");
-            println!("  import -f FILE  Import financial data from file");
-            println!("  sync            Sync accounts with banks");
+            println!("  import -f FILE  Import financial data from file (--dry-run to preview)");
+            println!("  import --watch  Import files dropped into ~/FinWise/inbox/");
+            println!("  import list     List import batches (undo one with 'import undo BATCH')");
+            println!("  export -F FMT   Export data (ledger, hledger, beancount, csv, json, xlsx, ofx)");
+            println!("  profile         Manage CSV import mapping profiles");
+            println!("  receipts        List and show receipts; store e-invoices (import), order mails (import-mail) and OCR text (import-ocr)");
+            println!("  backup          Write an encrypted backup to ~/FinWise/backups/ (restore FILE to restore it)");
+            println!("  backup --schedule  Keep writing daily or weekly backups and rotate old ones ('backup status' to check)");
+            println!("  payments        Prepare SEPA transfers and write them to pain.001 files (payments export)");
+            println!("  sync            Fetch new bookings of linked accounts (link one with 'sync link')");
             println!("  report -t TYPE  Generate reports (summary, transactions, categories)");
             println!();
             println!("🔐 Security Note: Run 'setup-db' first to store database credentials securely in ~/FinWise/");
@@ -2,6 +2,7 @@ use diesel::prelude::*;
 use crate::schema::accounts;
 use bigdecimal::BigDecimal;
 
+/// An account as selected by `account_service::COLUMNS`.
 #[derive(Queryable, Debug)]
 pub struct Account {
     pub id: i32,
@@ -14,4 +15,5 @@ pub struct Account {
 pub struct NewAccount {
     pub party_id: i32,
     pub balance: BigDecimal,
+    pub import_batch_id: Option<i32>,
 }
@@ -0,0 +1,29 @@
+use diesel::prelude::*;
+use crate::schema::account_links;
+use chrono::NaiveDateTime;
+
+#[derive(Queryable, Debug)]
+pub struct AccountLink {
+    pub id: i32,
+    pub account_id: i32,
+    /// Name of the registered connector, such as `mock`.
+    pub connector: String,
+    /// The account's id at the connector.
+    pub remote_account_id: String,
+    /// Connector settings as a JSON object of strings.
+    pub settings: String,
+    /// Where the next sync continues; `None` before the first one.
+    pub sync_cursor: Option<String>,
+    pub synced_at: Option<NaiveDateTime>,
+    /// Why the last sync failed; cleared by the next successful one.
+    pub last_error: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = account_links)]
+pub struct NewAccountLink {
+    pub account_id: i32,
+    pub connector: String,
+    pub remote_account_id: String,
+    pub settings: String,
+}
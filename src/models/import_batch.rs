@@ -0,0 +1,62 @@
+use diesel::prelude::*;
+use crate::schema::{import_batch_balances, import_batches};
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+
+#[derive(Queryable, Debug)]
+pub struct ImportBatch {
+    pub id: i32,
+    pub file_name: String,
+    pub file_hash: String,
+    pub format: String,
+    pub imported_at: NaiveDateTime,
+    pub rows_created: i32,
+    pub rows_skipped: i32,
+    pub rows_rejected: i32,
+    pub parties_created: i32,
+    pub reverted_at: Option<NaiveDateTime>,
+    /// A streamed import that has not reached the end of its file yet.
+    pub in_progress: bool,
+    /// The last file line whose chunk was committed, for resuming a streamed import.
+    pub committed_line: i32,
+    /// The account balance before a streamed import's first chunk.
+    pub opening_balance: Option<BigDecimal>,
+    /// The account link a sync batch advanced, and its cursor before the sync.
+    pub sync_link_id: Option<i32>,
+    pub previous_sync_cursor: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = import_batches)]
+pub struct NewImportBatch {
+    pub file_name: String,
+    pub file_hash: String,
+    pub format: String,
+    pub in_progress: bool,
+    pub opening_balance: Option<BigDecimal>,
+}
+
+/// What a batch did to one account's balance, as loaded by `import_batch_service::batch_balances`.
+#[derive(Queryable, Debug)]
+pub struct ImportBatchBalance {
+    pub account_id: i32,
+    pub net_change: BigDecimal,
+    /// The opening balance the imported statement reported (camt OPBD, MT940 :60F:), if any.
+    pub reported_opening: Option<BigDecimal>,
+    pub reported_opening_date: Option<NaiveDateTime>,
+    /// The closing balance the imported statement reported (camt CLBD, MT940 :62F:), if any.
+    pub reported_closing: Option<BigDecimal>,
+    pub reported_closing_date: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = import_batch_balances)]
+pub struct NewImportBatchBalance {
+    pub batch_id: i32,
+    pub account_id: i32,
+    pub net_change: BigDecimal,
+    pub reported_opening: Option<BigDecimal>,
+    pub reported_opening_date: Option<NaiveDateTime>,
+    pub reported_closing: Option<BigDecimal>,
+    pub reported_closing_date: Option<NaiveDateTime>,
+}
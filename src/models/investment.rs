@@ -0,0 +1,33 @@
+use diesel::prelude::*;
+use crate::schema::investment_transactions;
+use chrono::NaiveDateTime;
+use bigdecimal::BigDecimal;
+
+/// The columns of a stored trade that holdings and duplicate checks need,
+/// as selected by `investment_service::COLUMNS`.
+#[derive(Queryable, Debug)]
+pub struct InvestmentTransaction {
+    pub id: i32,
+    pub symbol: Option<String>,
+    pub quantity: Option<BigDecimal>,
+    pub unit_price: Option<BigDecimal>,
+    pub currency: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = investment_transactions)]
+pub struct NewInvestmentTransaction {
+    pub account_id: i32,
+    pub date: NaiveDateTime,
+    pub kind: String,
+    pub symbol: Option<String>,
+    pub quantity: Option<BigDecimal>,
+    pub unit_price: Option<BigDecimal>,
+    pub currency: Option<String>,
+    pub fee: Option<BigDecimal>,
+    pub amount: BigDecimal,
+    pub memo: String,
+    pub external_id: Option<String>,
+    pub fingerprint: Option<String>,
+    pub import_batch_id: Option<i32>,
+}
@@ -2,3 +2,7 @@ pub mod account;
 pub mod party;
 pub mod transaction;
 pub mod receipt;
+pub mod import_batch;
+pub mod investment;
+pub mod payment;
+pub mod account_link;
@@ -1,6 +1,7 @@
 use diesel::prelude::*;
-use crate::schema::parties;
+use crate::schema::{parties, party_aliases};
 
+/// A party as selected by `party_service::COLUMNS`.
 #[derive(Queryable, Debug)]
 pub struct Party {
     pub id: i32,
@@ -8,6 +9,7 @@ pub struct Party {
     pub phone: String,
     pub eban: String,
     pub address_id: i32,
+    pub bic: String,
 }
 
 #[derive(Insertable, Debug)]
@@ -17,4 +19,20 @@ pub struct NewParty {
     pub phone: String,
     pub eban: String,
     pub address_id: i32,
+    pub bic: String,
+    pub import_batch_id: Option<i32>,
+}
+
+/// A confirmed alternative name of a party, loaded without its row id.
+#[derive(Queryable, Debug)]
+pub struct PartyAlias {
+    pub party_id: i32,
+    pub alias: String,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = party_aliases)]
+pub struct NewPartyAlias {
+    pub party_id: i32,
+    pub alias: String,
 }
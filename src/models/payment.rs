@@ -0,0 +1,31 @@
+use diesel::prelude::*;
+use crate::schema::payments;
+use chrono::{NaiveDate, NaiveDateTime};
+use bigdecimal::BigDecimal;
+
+#[derive(Queryable, Debug)]
+pub struct Payment {
+    pub id: i32,
+    pub account_id: i32,
+    pub party_id: i32,
+    pub amount: BigDecimal,
+    pub execution_date: NaiveDate,
+    pub reference: String,
+    pub end_to_end_id: String,
+    pub created_at: NaiveDateTime,
+    /// MsgId of the pain.001 file the payment was last written to.
+    pub message_id: Option<String>,
+    /// The imported booking that settled the payment; `None` while pending.
+    pub transaction_id: Option<i32>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = payments)]
+pub struct NewPayment {
+    pub account_id: i32,
+    pub party_id: i32,
+    pub amount: BigDecimal,
+    pub execution_date: NaiveDate,
+    pub reference: String,
+    pub end_to_end_id: String,
+}
@@ -1,5 +1,6 @@
 use diesel::prelude::*;
-use crate::schema::receipts;
+use crate::schema::{receipt_items, receipts};
+use bigdecimal::BigDecimal;
 
 #[derive(Queryable, Debug)]
 pub struct Receipt {
@@ -9,6 +10,17 @@ pub struct Receipt {
     pub date: String,
     pub time: String,
     pub items: Vec<String>,
+    pub import_batch_id: Option<i32>,
+    pub invoice_number: Option<String>,
+    pub due_date: Option<String>,
+    pub currency: Option<String>,
+    pub net_total: Option<BigDecimal>,
+    pub tax_total: Option<BigDecimal>,
+    pub total: Option<BigDecimal>,
+    pub source: Option<String>,
+    pub confidence: Option<f32>,
+    pub needs_review: bool,
+    pub review_reasons: Vec<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -19,4 +31,40 @@ pub struct NewReceipt {
     pub date: String,
     pub time: String,
     pub items: Vec<String>,
+    pub import_batch_id: Option<i32>,
+    pub invoice_number: Option<String>,
+    pub due_date: Option<String>,
+    pub currency: Option<String>,
+    pub net_total: Option<BigDecimal>,
+    pub tax_total: Option<BigDecimal>,
+    pub total: Option<BigDecimal>,
+    pub source: Option<String>,
+    pub confidence: Option<f32>,
+    pub needs_review: bool,
+    pub review_reasons: Vec<String>,
+}
+
+/// A line of an invoice, as loaded by `receipt_service::receipt_items`.
+#[derive(Queryable, Debug)]
+pub struct ReceiptItem {
+    pub position: i32,
+    pub description: String,
+    pub quantity: Option<BigDecimal>,
+    pub unit_price: Option<BigDecimal>,
+    pub net_amount: BigDecimal,
+    pub vat_rate: Option<BigDecimal>,
+    pub vat_category: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = receipt_items)]
+pub struct NewReceiptItem {
+    pub receipt_id: i32,
+    pub position: i32,
+    pub description: String,
+    pub quantity: Option<BigDecimal>,
+    pub unit_price: Option<BigDecimal>,
+    pub net_amount: BigDecimal,
+    pub vat_rate: Option<BigDecimal>,
+    pub vat_category: Option<String>,
 }
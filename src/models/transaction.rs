@@ -3,6 +3,7 @@ use crate::schema::transactions;
 use chrono::NaiveDateTime;
 use bigdecimal::BigDecimal;
 
+/// A transaction as selected by `transaction_service::COLUMNS`.
 #[derive(Queryable, Debug)]
 pub struct Transaction {
     pub id: i32,
@@ -10,6 +11,9 @@ pub struct Transaction {
     pub from_party_id: i32,
     pub to_party_id: i32,
     pub date: NaiveDateTime,
+    pub memo: String,
+    pub external_id: Option<String>,
+    pub category: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -19,4 +23,12 @@ pub struct NewTransaction {
     pub from_party_id: i32,
     pub to_party_id: i32,
     pub date: NaiveDateTime,
+    pub memo: String,
+    pub external_id: Option<String>,
+    pub category: Option<String>,
+    pub fingerprint: Option<String>,
+    pub import_batch_id: Option<i32>,
+    pub fee: Option<BigDecimal>,
+    pub original_amount: Option<BigDecimal>,
+    pub original_currency: Option<String>,
 }
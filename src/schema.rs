@@ -1,10 +1,76 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    account_links (id) {
+        id -> Int4,
+        account_id -> Int4,
+        connector -> Text,
+        remote_account_id -> Text,
+        settings -> Text,
+        sync_cursor -> Nullable<Text>,
+        synced_at -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     accounts (id) {
         id -> Int4,
         party_id -> Int4,
         balance -> Numeric,
+        import_batch_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    import_batch_balances (id) {
+        id -> Int4,
+        batch_id -> Int4,
+        account_id -> Int4,
+        net_change -> Numeric,
+        reported_opening -> Nullable<Numeric>,
+        reported_opening_date -> Nullable<Timestamp>,
+        reported_closing -> Nullable<Numeric>,
+        reported_closing_date -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    import_batches (id) {
+        id -> Int4,
+        file_name -> Text,
+        file_hash -> Text,
+        format -> Text,
+        imported_at -> Timestamp,
+        rows_created -> Int4,
+        rows_skipped -> Int4,
+        rows_rejected -> Int4,
+        parties_created -> Int4,
+        reverted_at -> Nullable<Timestamp>,
+        in_progress -> Bool,
+        committed_line -> Int4,
+        opening_balance -> Nullable<Numeric>,
+        sync_link_id -> Nullable<Int4>,
+        previous_sync_cursor -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    investment_transactions (id) {
+        id -> Int4,
+        account_id -> Int4,
+        date -> Timestamp,
+        kind -> Text,
+        symbol -> Nullable<Text>,
+        quantity -> Nullable<Numeric>,
+        unit_price -> Nullable<Numeric>,
+        currency -> Nullable<Text>,
+        fee -> Nullable<Numeric>,
+        amount -> Numeric,
+        memo -> Text,
+        external_id -> Nullable<Text>,
+        fingerprint -> Nullable<Text>,
+        import_batch_id -> Nullable<Int4>,
     }
 }
 
@@ -15,6 +81,31 @@ diesel::table! {
         phone -> Text,
         eban -> Text,
         address_id -> Int4,
+        bic -> Text,
+        import_batch_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    party_aliases (id) {
+        id -> Int4,
+        party_id -> Int4,
+        alias -> Text,
+    }
+}
+
+diesel::table! {
+    payments (id) {
+        id -> Int4,
+        account_id -> Int4,
+        party_id -> Int4,
+        amount -> Numeric,
+        execution_date -> Date,
+        reference -> Text,
+        end_to_end_id -> Text,
+        created_at -> Timestamp,
+        message_id -> Nullable<Text>,
+        transaction_id -> Nullable<Int4>,
     }
 }
 
@@ -26,6 +117,31 @@ diesel::table! {
         date -> Text,
         time -> Text,
         items -> Array<Text>,
+        import_batch_id -> Nullable<Int4>,
+        invoice_number -> Nullable<Text>,
+        due_date -> Nullable<Text>,
+        currency -> Nullable<Text>,
+        net_total -> Nullable<Numeric>,
+        tax_total -> Nullable<Numeric>,
+        total -> Nullable<Numeric>,
+        source -> Nullable<Text>,
+        confidence -> Nullable<Float4>,
+        needs_review -> Bool,
+        review_reasons -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    receipt_items (id) {
+        id -> Int4,
+        receipt_id -> Int4,
+        position -> Int4,
+        description -> Text,
+        quantity -> Nullable<Numeric>,
+        unit_price -> Nullable<Numeric>,
+        net_amount -> Numeric,
+        vat_rate -> Nullable<Numeric>,
+        vat_category -> Nullable<Text>,
     }
 }
 
@@ -36,16 +152,43 @@ diesel::table! {
         from_party_id -> Int4,
         to_party_id -> Int4,
         date -> Timestamp,
+        memo -> Text,
+        external_id -> Nullable<Text>,
+        category -> Nullable<Text>,
+        fingerprint -> Nullable<Text>,
+        transfer_fingerprint -> Nullable<Text>,
+        import_batch_id -> Nullable<Int4>,
+        transfer_batch_id -> Nullable<Int4>,
+        fee -> Nullable<Numeric>,
+        original_amount -> Nullable<Numeric>,
+        original_currency -> Nullable<Text>,
+        funding_party_id -> Nullable<Int4>,
     }
 }
 
+diesel::joinable!(account_links -> accounts (account_id));
 diesel::joinable!(accounts -> parties (party_id));
+diesel::joinable!(import_batch_balances -> accounts (account_id));
+diesel::joinable!(import_batch_balances -> import_batches (batch_id));
+diesel::joinable!(investment_transactions -> accounts (account_id));
+diesel::joinable!(party_aliases -> parties (party_id));
+diesel::joinable!(payments -> accounts (account_id));
+diesel::joinable!(payments -> parties (party_id));
+diesel::joinable!(payments -> transactions (transaction_id));
+diesel::joinable!(receipt_items -> receipts (receipt_id));
 diesel::joinable!(receipts -> parties (party_id));
 diesel::joinable!(transactions -> parties (from_party_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    account_links,
     accounts,
+    import_batch_balances,
+    import_batches,
+    investment_transactions,
     parties,
+    party_aliases,
+    payments,
+    receipt_items,
     receipts,
     transactions,
 );
@@ -0,0 +1,35 @@
+use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use crate::models::account_link::{AccountLink, NewAccountLink};
+use crate::schema::account_links::dsl::*;
+
+pub fn create_link(conn: &mut PgConnection, new_link: &NewAccountLink) -> QueryResult<AccountLink> {
+    diesel::insert_into(account_links)
+        .values(new_link)
+        .get_result(conn)
+}
+
+pub fn list_links(conn: &mut PgConnection) -> QueryResult<Vec<AccountLink>> {
+    account_links.order(account_id.asc()).load(conn)
+}
+
+pub fn find_by_account(conn: &mut PgConnection, linked_account: i32) -> QueryResult<Option<AccountLink>> {
+    account_links.filter(account_id.eq(linked_account)).first(conn).optional()
+}
+
+pub fn delete_link(conn: &mut PgConnection, linked_account: i32) -> QueryResult<usize> {
+    diesel::delete(account_links.filter(account_id.eq(linked_account))).execute(conn)
+}
+
+/// Records a successful sync and where the next one continues.
+pub fn record_sync(conn: &mut PgConnection, link_id: i32, cursor: Option<&str>, at: NaiveDateTime) -> QueryResult<usize> {
+    diesel::update(account_links.filter(id.eq(link_id)))
+        .set((sync_cursor.eq(cursor), synced_at.eq(at), last_error.eq(None::<String>)))
+        .execute(conn)
+}
+
+pub fn record_error(conn: &mut PgConnection, link_id: i32, message: &str) -> QueryResult<usize> {
+    diesel::update(account_links.filter(id.eq(link_id)))
+        .set(last_error.eq(message))
+        .execute(conn)
+}
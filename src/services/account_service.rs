@@ -3,27 +3,32 @@ use crate::models::account::{Account, NewAccount};
 use crate::schema::accounts::dsl::*;
 use bigdecimal::BigDecimal;
 
-pub fn create_account(conn: &mut PgConnection, account_party_id: i32, initial_balance: BigDecimal) -> QueryResult<Account> {
+/// The columns an `Account` is loaded from, in its field order.
+pub const COLUMNS: (id, party_id, balance) = (id, party_id, balance);
+
+pub fn create_account(conn: &mut PgConnection, account_party_id: i32, initial_balance: BigDecimal, new_import_batch_id: Option<i32>) -> QueryResult<Account> {
     let new_account = NewAccount {
         party_id: account_party_id,
         balance: initial_balance,
+        import_batch_id: new_import_batch_id,
     };
 
     diesel::insert_into(accounts)
         .values(&new_account)
+        .returning(COLUMNS)
         .get_result(conn)
 }
 
 pub fn get_account(conn: &mut PgConnection, account_id: i32) -> QueryResult<Account> {
-    accounts.filter(id.eq(account_id)).first(conn)
+    accounts.filter(id.eq(account_id)).select(COLUMNS).first(conn)
 }
 
 pub fn get_accounts_by_party(conn: &mut PgConnection, account_party_id: i32) -> QueryResult<Vec<Account>> {
-    accounts.filter(party_id.eq(account_party_id)).load(conn)
+    accounts.filter(party_id.eq(account_party_id)).select(COLUMNS).load(conn)
 }
 
 pub fn get_all_accounts(conn: &mut PgConnection) -> QueryResult<Vec<Account>> {
-    accounts.load(conn)
+    accounts.select(COLUMNS).load(conn)
 }
 
 pub fn update_balance(conn: &mut PgConnection, account_id: i32, new_balance: BigDecimal) -> QueryResult<usize> {
@@ -0,0 +1,165 @@
+use diesel::migration::MigrationSource;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde_json::value::RawValue;
+use crate::backup::{BackupError, TableRows};
+
+/// The migrations this build was compiled with, for creating the schema of an empty database.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// Every FinWise table, in an order that satisfies their foreign keys when
+/// restored front to back.
+pub const TABLES: [&str; 11] = [
+    "import_batches",
+    "parties",
+    "party_aliases",
+    "accounts",
+    "account_links",
+    "import_batch_balances",
+    "transactions",
+    "investment_transactions",
+    "receipts",
+    "receipt_items",
+    "payments",
+];
+
+#[derive(QueryableByName)]
+struct DumpedTable {
+    #[diesel(sql_type = Text)]
+    rows: String,
+    #[diesel(sql_type = BigInt)]
+    row_count: i64,
+}
+
+#[derive(QueryableByName)]
+struct TableName {
+    #[diesel(sql_type = Text)]
+    table_name: String,
+}
+
+#[derive(QueryableByName)]
+struct Count {
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+}
+
+/// Versions of the migrations applied to the database, oldest first.
+pub fn applied_migrations(conn: &mut PgConnection) -> Result<Vec<String>, BackupError> {
+    let mut versions: Vec<String> = conn.applied_migrations().map_err(migration_error)?.iter().map(|version| version.to_string()).collect();
+    versions.sort();
+    Ok(versions)
+}
+
+/// Versions of the migrations this build knows, oldest first.
+pub fn known_migrations() -> Result<Vec<String>, BackupError> {
+    let mut versions: Vec<String> = MigrationSource::<Pg>::migrations(&MIGRATIONS)
+        .map_err(migration_error)?
+        .iter()
+        .map(|migration| migration.name().version().to_string())
+        .collect();
+    versions.sort();
+    Ok(versions)
+}
+
+/// Applies the pending migrations up to and including `last_version`; returns their versions.
+pub fn migrate_to(conn: &mut PgConnection, last_version: &str) -> Result<Vec<String>, BackupError> {
+    let mut applied = Vec::new();
+    for migration in conn.pending_migrations(MIGRATIONS).map_err(migration_error)? {
+        let version = migration.name().version().to_string();
+        if version.as_str() > last_version {
+            break;
+        }
+        conn.run_migration(&*migration).map_err(migration_error)?;
+        applied.push(version);
+    }
+    Ok(applied)
+}
+
+/// Applies every pending migration; returns their versions.
+pub fn migrate_all(conn: &mut PgConnection) -> Result<Vec<String>, BackupError> {
+    Ok(conn.run_pending_migrations(MIGRATIONS).map_err(migration_error)?.iter().map(|version| version.to_string()).collect())
+}
+
+fn migration_error(error: Box<dyn std::error::Error + Send + Sync>) -> BackupError {
+    BackupError::Migration(error.to_string())
+}
+
+/// Tables in the database, by name.
+fn database_tables(conn: &mut PgConnection) -> QueryResult<Vec<String>> {
+    let tables: Vec<TableName> = diesel::sql_query(
+        "SELECT table_name::text AS table_name FROM information_schema.tables \
+         WHERE table_schema = 'public' AND table_type = 'BASE TABLE' AND table_name <> '__diesel_schema_migrations' \
+         ORDER BY table_name",
+    )
+    .load(conn)?;
+    Ok(tables.into_iter().map(|table| table.table_name).collect())
+}
+
+/// The FinWise tables the database has, in restore order; older schema
+/// versions lack some.
+fn present_tables(conn: &mut PgConnection) -> QueryResult<Vec<&'static str>> {
+    let tables = database_tables(conn)?;
+    Ok(TABLES.into_iter().filter(|table| tables.iter().any(|name| name == table)).collect())
+}
+
+/// Tables in the database that backups do not cover.
+pub fn unknown_tables(conn: &mut PgConnection) -> QueryResult<Vec<String>> {
+    Ok(database_tables(conn)?.into_iter().filter(|name| !TABLES.contains(&name.as_str())).collect())
+}
+
+/// Reads every table as JSON rows, all from the same consistent view of the database.
+pub fn dump_tables(conn: &mut PgConnection) -> QueryResult<Vec<TableRows>> {
+    conn.build_transaction().repeatable_read().read_only().run(|conn| {
+        present_tables(conn)?
+            .into_iter()
+            .map(|table| {
+                let dumped: DumpedTable = diesel::sql_query(format!(
+                    "SELECT COALESCE(json_agg(t ORDER BY t.id), '[]'::json)::text AS rows, COUNT(*) AS row_count FROM {} t",
+                    table
+                ))
+                .get_result(conn)?;
+                let rows = RawValue::from_string(dumped.rows).map_err(|e| diesel::result::Error::DeserializationError(Box::new(e)))?;
+                Ok(TableRows { name: table.to_string(), row_count: dumped.row_count as usize, rows })
+            })
+            .collect()
+    })
+}
+
+/// Whether no FinWise table holds any row.
+pub fn is_empty(conn: &mut PgConnection) -> QueryResult<bool> {
+    for table in present_tables(conn)? {
+        let rows: Count = diesel::sql_query(format!("SELECT COUNT(*) AS count FROM {}", table)).get_result(conn)?;
+        if rows.count > 0 {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Inserts the backed-up rows in one transaction, first emptying every table
+/// if `replace`, and moves each id sequence past the restored ids. Returns
+/// the number of rows restored.
+pub fn restore_tables(conn: &mut PgConnection, tables: &[TableRows], replace: bool) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let present = present_tables(conn)?;
+        if replace {
+            diesel::sql_query(format!("TRUNCATE {} RESTART IDENTITY CASCADE", present.join(", "))).execute(conn)?;
+        }
+        let mut restored = 0;
+        for table in present {
+            let Some(backed_up) = tables.iter().find(|backed_up| backed_up.name == table) else { continue };
+            restored += diesel::sql_query(format!(
+                "INSERT INTO {table} SELECT * FROM json_populate_recordset(NULL::{table}, $1::json)"
+            ))
+            .bind::<Text, _>(backed_up.rows.get())
+            .execute(conn)?;
+            diesel::sql_query(format!(
+                "SELECT setval(pg_get_serial_sequence('{table}', 'id'), COALESCE((SELECT MAX(id) FROM {table}), 0) + 1, false)"
+            ))
+            .execute(conn)?;
+        }
+        Ok(restored)
+    })
+}
@@ -0,0 +1,196 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDateTime, NaiveTime};
+use diesel::dsl::sum;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use crate::export::pain001::CreditTransfer;
+use crate::export::{ExportAccount, ExportFilter, ExportParty, ExportTransaction};
+use crate::models::account::Account;
+use crate::models::party::Party;
+use crate::models::payment::Payment;
+use crate::models::transaction::Transaction;
+use crate::schema::{accounts, investment_transactions, parties, transactions};
+use crate::services::{account_service, party_service, payment_service, transaction_service};
+
+/// Loads all accounts and the transactions `filter` selects, oldest first, with party names resolved.
+///
+/// With an end date in `filter`, account balances are those at the end of
+/// that day rather than the current ones.
+pub fn load_all(conn: &mut PgConnection, filter: &ExportFilter) -> QueryResult<(Vec<ExportAccount>, Vec<ExportTransaction>)> {
+    let all_accounts: Vec<Account> = accounts::table.order(accounts::id.asc()).select(account_service::COLUMNS).load(conn)?;
+    let account_by_party: HashMap<i32, i32> = all_accounts.iter().map(|account| (account.party_id, account.id)).collect();
+    let party_by_id: HashMap<i32, ExportParty> = parties::table
+        .select(party_service::COLUMNS)
+        .load::<Party>(conn)?
+        .into_iter()
+        .map(|party| {
+            let export_party = ExportParty {
+                account_id: account_by_party.get(&party.id).copied(),
+                name: party.name,
+                iban: party.eban,
+                bic: party.bic,
+            };
+            (party.id, export_party)
+        })
+        .collect();
+
+    let export_accounts = all_accounts
+        .iter()
+        .map(|account| {
+            let party = party_by_id.get(&account.party_id);
+            let balance = match filter.to.and_then(|to| to.succ_opt()) {
+                Some(next_day) => balance_before(conn, account, next_day.and_time(NaiveTime::MIN))?,
+                None => account.balance.clone(),
+            };
+            Ok(ExportAccount {
+                id: account.id,
+                name: party.map(|party| party.name.clone()).unwrap_or_default(),
+                iban: party.map(|party| party.iban.clone()).unwrap_or_default(),
+                bic: party.map(|party| party.bic.clone()).unwrap_or_default(),
+                balance,
+            })
+        })
+        .collect::<QueryResult<_>>()?;
+
+    let export_transactions = filtered_transactions(filter)
+        .order((transactions::date.asc(), transactions::id.asc()))
+        .select(transaction_service::COLUMNS)
+        .load::<Transaction>(conn)?
+        .into_iter()
+        .filter_map(|transaction| {
+            Some(ExportTransaction {
+                id: transaction.id,
+                date: transaction.date,
+                amount: transaction.amount,
+                from: party_by_id.get(&transaction.from_party_id)?.clone(),
+                to: party_by_id.get(&transaction.to_party_id)?.clone(),
+                memo: transaction.memo,
+                category: transaction.category,
+                external_id: transaction.external_id,
+                pending: false,
+            })
+        })
+        .collect();
+
+    Ok((export_accounts, export_transactions))
+}
+
+/// Payments not settled by a booking yet that `filter` selects, as pending
+/// transactions dated on their execution date, oldest first.
+pub fn pending_transactions(conn: &mut PgConnection, filter: &ExportFilter) -> QueryResult<Vec<ExportTransaction>> {
+    // Payments carry no category, so none match a category filter
+    if filter.category.is_some() {
+        return Ok(Vec::new());
+    }
+    let pending: Vec<Payment> = payment_service::pending_payments(conn, filter.account_id, true)?
+        .into_iter()
+        .filter(|payment| filter.from.is_none_or(|from| payment.execution_date >= from))
+        .filter(|payment| filter.to.is_none_or(|to| payment.execution_date <= to))
+        .collect();
+    let iban = filter.party.as_ref().map(|party| party.replace(' ', "").to_uppercase());
+    let name = filter.party.as_ref().map(|party| party.to_lowercase());
+    let matches = |party: &ExportParty| match (&iban, &name) {
+        (Some(iban), Some(name)) => party.iban == *iban || party.name.to_lowercase().contains(name),
+        _ => true,
+    };
+
+    Ok(credit_transfers(conn, &pending)?
+        .into_iter()
+        .zip(&pending)
+        .map(|(transfer, payment)| ExportTransaction {
+            id: payment.id,
+            date: payment.execution_date.and_time(NaiveTime::MIN),
+            amount: transfer.amount,
+            from: ExportParty {
+                name: transfer.debtor.name,
+                iban: transfer.debtor.iban,
+                bic: transfer.debtor.bic,
+                account_id: Some(transfer.debtor.id),
+            },
+            to: transfer.creditor,
+            memo: transfer.reference,
+            category: None,
+            external_id: Some(transfer.end_to_end_id),
+            pending: true,
+        })
+        .filter(|transaction| matches(&transaction.from) || matches(&transaction.to))
+        .collect())
+}
+
+/// Resolves the paying account and the payee of each payment for a pain.001 file.
+pub fn credit_transfers(conn: &mut PgConnection, pending: &[Payment]) -> QueryResult<Vec<CreditTransfer>> {
+    pending
+        .iter()
+        .map(|payment| {
+            let account: Account = accounts::table.find(payment.account_id).select(account_service::COLUMNS).first(conn)?;
+            let owner: Party = parties::table.find(account.party_id).select(party_service::COLUMNS).first(conn)?;
+            let payee: Party = parties::table.find(payment.party_id).select(party_service::COLUMNS).first(conn)?;
+            Ok(CreditTransfer {
+                end_to_end_id: payment.end_to_end_id.clone(),
+                amount: payment.amount.clone(),
+                execution_date: payment.execution_date,
+                reference: payment.reference.clone(),
+                debtor: ExportAccount {
+                    id: account.id,
+                    name: owner.name,
+                    iban: owner.eban,
+                    bic: owner.bic,
+                    balance: account.balance,
+                },
+                creditor: ExportParty {
+                    name: payee.name,
+                    iban: payee.eban,
+                    bic: payee.bic,
+                    account_id: None,
+                },
+            })
+        })
+        .collect()
+}
+
+/// The account's balance before `moment`: its current balance without the transactions and trades since.
+fn balance_before(conn: &mut PgConnection, account: &Account, moment: NaiveDateTime) -> QueryResult<BigDecimal> {
+    let since = || transactions::table.filter(transactions::date.ge(moment)).select(sum(transactions::amount));
+    let incoming: Option<BigDecimal> = since().filter(transactions::to_party_id.eq(account.party_id)).first(conn)?;
+    let outgoing: Option<BigDecimal> = since().filter(transactions::from_party_id.eq(account.party_id)).first(conn)?;
+    // Trade amounts are signed cash movements of the account itself
+    let traded: Option<BigDecimal> = investment_transactions::table
+        .filter(investment_transactions::account_id.eq(account.id))
+        .filter(investment_transactions::date.ge(moment))
+        .select(sum(investment_transactions::amount))
+        .first(conn)?;
+    Ok(&account.balance - incoming.unwrap_or_default() + outgoing.unwrap_or_default() - traded.unwrap_or_default())
+}
+
+fn filtered_transactions(filter: &ExportFilter) -> transactions::BoxedQuery<'static, diesel::pg::Pg> {
+    let mut query = transactions::table.into_boxed();
+    if let Some(from) = filter.from {
+        query = query.filter(transactions::date.ge(from.and_time(NaiveTime::MIN)));
+    }
+    if let Some(to) = filter.to.and_then(|to| to.succ_opt()) {
+        query = query.filter(transactions::date.lt(to.and_time(NaiveTime::MIN)));
+    }
+    if let Some(account_id) = filter.account_id {
+        let account_party = || accounts::table.select(accounts::party_id).filter(accounts::id.eq(account_id));
+        query = query.filter(transactions::from_party_id.eq_any(account_party()).or(transactions::to_party_id.eq_any(account_party())));
+    }
+    if let Some(party) = &filter.party {
+        let iban = party.replace(' ', "").to_uppercase();
+        let pattern = format!("%{}%", like_literal(party));
+        let matching = || {
+            parties::table
+                .filter(parties::name.ilike(pattern.clone()).or(parties::eban.eq(iban.clone())))
+                .select(parties::id)
+        };
+        query = query.filter(transactions::from_party_id.eq_any(matching()).or(transactions::to_party_id.eq_any(matching())));
+    }
+    if let Some(category) = &filter.category {
+        query = query.filter(transactions::category.ilike(like_literal(category)));
+    }
+    query
+}
+
+/// Escapes `%`, `_` and `\` so that `value` matches only itself in a LIKE pattern.
+fn like_literal(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
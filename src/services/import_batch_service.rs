@@ -0,0 +1,281 @@
+use diesel::prelude::*;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use crate::import::Balance;
+use crate::models::import_batch::{ImportBatch, ImportBatchBalance, NewImportBatch, NewImportBatchBalance};
+use crate::schema::import_batches::dsl::*;
+use crate::services::party_service;
+
+/// What undoing a batch removed, and what it had to keep because later data refers to it.
+#[derive(Debug, Default)]
+pub struct UndoSummary {
+    pub transactions_deleted: usize,
+    pub trades_deleted: usize,
+    pub transfers_unlinked: usize,
+    pub receipts_deleted: usize,
+    pub accounts_deleted: usize,
+    pub accounts_kept: usize,
+    pub parties_deleted: usize,
+    pub parties_kept: usize,
+}
+
+pub fn create_batch(conn: &mut PgConnection, new_file_name: &str, new_file_hash: &str, new_format: &str) -> QueryResult<ImportBatch> {
+    insert_batch(conn, new_file_name, new_file_hash, new_format, None)
+}
+
+/// Creates the batch of a streamed import, which stays in progress until `finish_batch`.
+/// `opening` is the account balance before the import, reported again on resume.
+pub fn start_batch(conn: &mut PgConnection, new_file_name: &str, new_file_hash: &str, new_format: &str, opening: &BigDecimal) -> QueryResult<ImportBatch> {
+    insert_batch(conn, new_file_name, new_file_hash, new_format, Some(opening))
+}
+
+fn insert_batch(conn: &mut PgConnection, new_file_name: &str, new_file_hash: &str, new_format: &str, opening: Option<&BigDecimal>) -> QueryResult<ImportBatch> {
+    let new_batch = NewImportBatch {
+        file_name: new_file_name.to_string(),
+        file_hash: new_file_hash.to_string(),
+        format: new_format.to_string(),
+        in_progress: opening.is_some(),
+        opening_balance: opening.cloned(),
+    };
+
+    diesel::insert_into(import_batches)
+        .values(&new_batch)
+        .get_result(conn)
+}
+
+pub fn get_batch(conn: &mut PgConnection, batch_id: i32) -> QueryResult<ImportBatch> {
+    import_batches.filter(id.eq(batch_id)).first(conn)
+}
+
+/// All batches, newest first.
+pub fn list_batches(conn: &mut PgConnection) -> QueryResult<Vec<ImportBatch>> {
+    import_batches.order(id.desc()).load(conn)
+}
+
+/// Earlier batches of the same file content that have not been undone.
+pub fn find_active_by_hash(conn: &mut PgConnection, hash: &str) -> QueryResult<Vec<ImportBatch>> {
+    import_batches
+        .filter(file_hash.eq(hash))
+        .filter(reverted_at.is_null())
+        .order(id.asc())
+        .load(conn)
+}
+
+/// The newest streamed import of the same file content that was interrupted before its end.
+pub fn find_unfinished_by_hash(conn: &mut PgConnection, hash: &str) -> QueryResult<Option<ImportBatch>> {
+    import_batches
+        .filter(file_hash.eq(hash))
+        .filter(in_progress.eq(true))
+        .filter(reverted_at.is_null())
+        .order(id.desc())
+        .first(conn)
+        .optional()
+}
+
+/// The accounts whose balance a batch has moved so far.
+pub fn batch_account_ids(conn: &mut PgConnection, batch_id: i32) -> QueryResult<Vec<i32>> {
+    use crate::schema::import_batch_balances;
+
+    import_batch_balances::table
+        .filter(import_batch_balances::batch_id.eq(batch_id))
+        .select(import_batch_balances::account_id)
+        .distinct()
+        .load(conn)
+}
+
+/// The balance changes a batch recorded, one per imported statement or chunk.
+pub fn batch_balances(conn: &mut PgConnection, batch_id: i32) -> QueryResult<Vec<ImportBatchBalance>> {
+    use crate::schema::import_batch_balances;
+
+    import_batch_balances::table
+        .filter(import_batch_balances::batch_id.eq(batch_id))
+        .order(import_batch_balances::id.asc())
+        .select((
+            import_batch_balances::account_id,
+            import_batch_balances::net_change,
+            import_batch_balances::reported_opening,
+            import_batch_balances::reported_opening_date,
+            import_batch_balances::reported_closing,
+            import_batch_balances::reported_closing_date,
+        ))
+        .load(conn)
+}
+
+/// Records that every file line up to `line` is committed; called inside the chunk's transaction.
+pub fn record_progress(conn: &mut PgConnection, batch_id: i32, line: usize) -> QueryResult<usize> {
+    diesel::update(import_batches.filter(id.eq(batch_id)))
+        .set(committed_line.eq(line as i32))
+        .execute(conn)
+}
+
+/// Records which account link a sync batch advances and where its cursor stood,
+/// so that undoing the batch fetches its bookings again on the next sync.
+pub fn record_sync_start(conn: &mut PgConnection, batch_id: i32, link_id: i32, cursor: Option<&str>) -> QueryResult<usize> {
+    diesel::update(import_batches.filter(id.eq(batch_id)))
+        .set((sync_link_id.eq(link_id), previous_sync_cursor.eq(cursor)))
+        .execute(conn)
+}
+
+/// Marks a streamed import as having reached the end of its file.
+pub fn finish_batch(conn: &mut PgConnection, batch_id: i32) -> QueryResult<usize> {
+    diesel::update(import_batches.filter(id.eq(batch_id)))
+        .set(in_progress.eq(false))
+        .execute(conn)
+}
+
+/// Adds the row counts of one imported statement to the batch totals.
+pub fn add_counts(conn: &mut PgConnection, batch_id: i32, created: usize, skipped: usize, rejected: usize, parties: usize) -> QueryResult<usize> {
+    diesel::update(import_batches.filter(id.eq(batch_id)))
+        .set((
+            rows_created.eq(rows_created + created as i32),
+            rows_skipped.eq(rows_skipped + skipped as i32),
+            rows_rejected.eq(rows_rejected + rejected as i32),
+            parties_created.eq(parties_created + parties as i32),
+        ))
+        .execute(conn)
+}
+
+/// Records the net amount a batch moved an account's balance by, so undo can
+/// reverse it, along with the balances the statement reported.
+pub fn record_balance_change(
+    conn: &mut PgConnection,
+    batch_id: i32,
+    account_id: i32,
+    change: BigDecimal,
+    reported_opening: Option<&Balance>,
+    reported_closing: Option<&Balance>,
+) -> QueryResult<usize> {
+    use crate::schema::import_batch_balances;
+
+    let new_balance = NewImportBatchBalance {
+        batch_id,
+        account_id,
+        net_change: change,
+        reported_opening: reported_opening.map(|balance| balance.amount.clone()),
+        reported_opening_date: reported_opening.map(|balance| balance.date),
+        reported_closing: reported_closing.map(|balance| balance.amount.clone()),
+        reported_closing_date: reported_closing.map(|balance| balance.date),
+    };
+
+    diesel::insert_into(import_batch_balances::table)
+        .values(&new_balance)
+        .execute(conn)
+}
+
+/// Later batches that matched one of this batch's transactions as the other
+/// side of a transfer. Their balance changes count those transactions, so
+/// they must be undone first.
+pub fn linking_batches(conn: &mut PgConnection, batch_id: i32) -> QueryResult<Vec<i32>> {
+    use crate::schema::transactions;
+
+    transactions::table
+        .filter(transactions::import_batch_id.eq(batch_id))
+        .filter(transactions::transfer_batch_id.ne(batch_id))
+        .select(transactions::transfer_batch_id.assume_not_null())
+        .distinct()
+        .order(transactions::transfer_batch_id.assume_not_null())
+        .load(conn)
+}
+
+/// Reverts everything a batch wrote, in a single database transaction.
+///
+/// Balances are moved back, linked transfers are unlinked and the batch's
+/// transactions and receipts are deleted. Payments the batch's bookings
+/// settled are pending again, and a sync batch rewinds its account link's
+/// cursor. Accounts and parties the batch created are only deleted when
+/// nothing else refers to them any more, since deleting a party cascades to
+/// everything booked against it. Callers check `linking_batches` first.
+pub fn undo_batch(conn: &mut PgConnection, batch_id: i32) -> QueryResult<UndoSummary> {
+    use crate::schema::{account_links, accounts, import_batch_balances, investment_transactions, parties, payments, receipts, transactions};
+
+    conn.transaction(|conn| {
+        let mut summary = UndoSummary::default();
+
+        let balance_changes: Vec<(i32, BigDecimal)> = import_batch_balances::table
+            .filter(import_batch_balances::batch_id.eq(batch_id))
+            .select((import_batch_balances::account_id, import_batch_balances::net_change))
+            .load(conn)?;
+        for (account_id, change) in balance_changes {
+            diesel::update(accounts::table.filter(accounts::id.eq(account_id)))
+                .set(accounts::balance.eq(accounts::balance - change))
+                .execute(conn)?;
+        }
+
+        // Bank bookings linked to a provider account go back to the provider party they named
+        let batch_parties = accounts::table
+            .filter(accounts::id.eq_any(import_batch_balances::table.filter(import_batch_balances::batch_id.eq(batch_id)).select(import_batch_balances::account_id)))
+            .select(accounts::party_id);
+        let linked = transactions::table.filter(transactions::transfer_batch_id.eq(batch_id)).filter(transactions::funding_party_id.is_not_null());
+        diesel::update(linked.filter(transactions::to_party_id.eq_any(batch_parties)))
+            .set((transactions::to_party_id.eq(transactions::funding_party_id.assume_not_null()), transactions::funding_party_id.eq(None::<i32>)))
+            .execute(conn)?;
+        diesel::update(linked.filter(transactions::from_party_id.eq_any(batch_parties)))
+            .set((transactions::from_party_id.eq(transactions::funding_party_id.assume_not_null()), transactions::funding_party_id.eq(None::<i32>)))
+            .execute(conn)?;
+
+        // A transfer this batch linked from the paying side also settled its payment
+        let batch_accounts = import_batch_balances::table.filter(import_batch_balances::batch_id.eq(batch_id)).select(import_batch_balances::account_id);
+        let linked_here = transactions::table.filter(transactions::transfer_batch_id.eq(batch_id)).select(transactions::id.nullable());
+        diesel::update(payments::table.filter(payments::transaction_id.eq_any(linked_here)).filter(payments::account_id.eq_any(batch_accounts)))
+            .set(payments::transaction_id.eq(None::<i32>))
+            .execute(conn)?;
+
+        summary.transfers_unlinked = diesel::update(transactions::table.filter(transactions::transfer_batch_id.eq(batch_id)))
+            .set((
+                transactions::transfer_fingerprint.eq(None::<String>),
+                transactions::transfer_batch_id.eq(None::<i32>),
+            ))
+            .execute(conn)?;
+        summary.transactions_deleted = diesel::delete(transactions::table.filter(transactions::import_batch_id.eq(batch_id))).execute(conn)?;
+        summary.trades_deleted = diesel::delete(investment_transactions::table.filter(investment_transactions::import_batch_id.eq(batch_id))).execute(conn)?;
+        summary.receipts_deleted = diesel::delete(receipts::table.filter(receipts::import_batch_id.eq(batch_id))).execute(conn)?;
+
+        let batch_accounts: Vec<(i32, i32)> = accounts::table
+            .filter(accounts::import_batch_id.eq(batch_id))
+            .select((accounts::id, accounts::party_id))
+            .load(conn)?;
+        for (account_id, owner_id) in batch_accounts {
+            let booked = diesel::select(diesel::dsl::exists(
+                transactions::table.filter(transactions::from_party_id.eq(owner_id).or(transactions::to_party_id.eq(owner_id))),
+            ))
+            .get_result::<bool>(conn)?;
+            let traded = diesel::select(diesel::dsl::exists(
+                investment_transactions::table.filter(investment_transactions::account_id.eq(account_id)),
+            ))
+            .get_result::<bool>(conn)?;
+            let paying = diesel::select(diesel::dsl::exists(payments::table.filter(payments::account_id.eq(account_id)))).get_result::<bool>(conn)?;
+            let linked = diesel::select(diesel::dsl::exists(account_links::table.filter(account_links::account_id.eq(account_id)))).get_result::<bool>(conn)?;
+            if booked || traded || paying || linked {
+                summary.accounts_kept += 1;
+            } else {
+                diesel::delete(accounts::table.filter(accounts::id.eq(account_id))).execute(conn)?;
+                summary.accounts_deleted += 1;
+            }
+        }
+
+        let batch_parties: Vec<i32> = parties::table
+            .filter(parties::import_batch_id.eq(batch_id))
+            .select(parties::id)
+            .load(conn)?;
+        for party_id in batch_parties {
+            if party_service::party_in_use(conn, party_id)? {
+                summary.parties_kept += 1;
+            } else {
+                party_service::delete_party(conn, party_id)?;
+                summary.parties_deleted += 1;
+            }
+        }
+
+        let batch = get_batch(conn, batch_id)?;
+        if let Some(link_id) = batch.sync_link_id {
+            diesel::update(account_links::table.filter(account_links::id.eq(link_id)))
+                .set(account_links::sync_cursor.eq(batch.previous_sync_cursor))
+                .execute(conn)?;
+        }
+
+        diesel::update(import_batches.filter(id.eq(batch_id)))
+            .set(reverted_at.eq(Some(Utc::now().naive_utc())))
+            .execute(conn)?;
+        Ok(summary)
+    })
+}
@@ -0,0 +1,646 @@
+use diesel::prelude::*;
+use bigdecimal::{BigDecimal, Signed};
+use chrono::NaiveDateTime;
+use log::debug;
+use serde::Serialize;
+use std::collections::HashSet;
+use crate::import::{fingerprint, Statement, StatementLine, TradeLine};
+use crate::models::account::Account;
+use crate::models::investment::{InvestmentTransaction, NewInvestmentTransaction};
+use crate::models::transaction::{NewTransaction, Transaction};
+use crate::models::party::Party;
+use crate::services::party_match_service::{self, Candidate, NameMatch, PartyMatcher};
+use crate::services::{account_service, import_batch_service, investment_service, party_service, payment_service, transaction_service};
+
+/// Name used for bookings whose file carries neither a counterparty name nor an IBAN.
+pub const UNKNOWN_COUNTERPARTY: &str = "Unknown counterparty";
+
+/// A line that was not stored because it is already on record.
+#[derive(Debug)]
+pub struct SkippedLine {
+    pub line: usize,
+    pub transaction_id: i32,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub transactions_created: usize,
+    /// Buys, sells, dividends and other trades stored as investment transactions.
+    pub trades_created: usize,
+    pub parties_created: usize,
+    pub duplicates_skipped: usize,
+    /// The duplicate lines and the stored transactions they matched.
+    pub skipped: Vec<SkippedLine>,
+    /// Transfers from another own account that were already stored from that account's side.
+    pub transfers_linked: usize,
+    /// Pending payments the imported bookings carried out.
+    pub payments_settled: usize,
+    /// The account balance before the import.
+    pub opening_balance: BigDecimal,
+    /// The account balance after the import.
+    pub balance: BigDecimal,
+}
+
+/// What an import would do with one row of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowAction {
+    Create,
+    Duplicate,
+    LinkTransfer,
+    Rejected,
+    /// Read but left out on purpose, such as a pending booking.
+    Ignored,
+}
+
+/// The dry-run outcome for one row of an import file.
+#[derive(Debug, Serialize)]
+pub struct RowPreview {
+    pub line: usize,
+    pub date: Option<NaiveDateTime>,
+    pub amount: Option<String>,
+    pub counterparty: Option<String>,
+    /// The existing party the counterparty was matched to; `None` for new parties.
+    pub party_id: Option<i32>,
+    pub new_party: bool,
+    pub action: RowAction,
+    /// Why a row was rejected or skipped.
+    pub message: Option<String>,
+}
+
+/// The result of previewing a statement against the database without writing to it.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportPreview {
+    pub rows: Vec<RowPreview>,
+    /// Names of the parties the import would create, in order of first use.
+    pub new_parties: Vec<String>,
+}
+
+/// Consecutive lines of a statement read in chunks, committed together.
+#[derive(Debug, Default)]
+pub struct StatementChunk {
+    pub lines: Vec<StatementLine>,
+    /// The lines' fingerprints, computed over the whole file in order.
+    pub fingerprints: Vec<String>,
+    /// Rows of this stretch of the file that could not be read.
+    pub rejected: usize,
+    /// The last file line the chunk covers, read or rejected.
+    pub last_line: usize,
+}
+
+impl ImportSummary {
+    /// Adds a later chunk's summary to this one.
+    pub fn absorb(&mut self, chunk: ImportSummary) {
+        self.transactions_created += chunk.transactions_created;
+        self.trades_created += chunk.trades_created;
+        self.parties_created += chunk.parties_created;
+        self.duplicates_skipped += chunk.duplicates_skipped;
+        self.skipped.extend(chunk.skipped);
+        self.transfers_linked += chunk.transfers_linked;
+        self.payments_settled += chunk.payments_settled;
+        self.balance = chunk.balance;
+    }
+}
+
+impl ImportPreview {
+    pub fn count(&self, action: RowAction) -> usize {
+        self.rows.iter().filter(|row| row.action == action).count()
+    }
+}
+
+/// How a counterparty name was matched against the existing parties.
+#[derive(Debug)]
+pub enum CounterpartyMatch {
+    /// Same IBAN, a confirmed alias or the same normalised name.
+    Found(Party),
+    /// Similar names only; best first.
+    Fuzzy(Vec<Candidate>),
+    None,
+}
+
+/// What to do with a counterparty that only matched existing parties by similarity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewDecision {
+    /// Use this party; `remember` stores the line's name as an alias of it.
+    Use { party_id: i32, remember: bool },
+    CreateNew,
+}
+
+/// Decides fuzzy counterparty matches during an import, e.g. by asking the user.
+pub trait MatchReview {
+    fn choose(&mut self, line: &StatementLine, candidates: &[Candidate]) -> ReviewDecision;
+}
+
+/// Takes the most similar party without asking, for unattended imports.
+pub struct AcceptBestMatch;
+
+impl MatchReview for AcceptBestMatch {
+    fn choose(&mut self, _line: &StatementLine, candidates: &[Candidate]) -> ReviewDecision {
+        match candidates.first() {
+            Some(best) => ReviewDecision::Use { party_id: best.party_id, remember: false },
+            None => ReviewDecision::CreateNew,
+        }
+    }
+}
+
+/// Writes all lines of a parsed statement as transactions of `account`.
+///
+/// Runs in a single database transaction: either every line is stored and the
+/// account balance is moved by the net amount, or nothing is. Lines whose
+/// fingerprint is already on record are skipped, so overlapping statements can
+/// be imported repeatedly, and transfers already stored from the other own
+/// account are only booked against this account's balance. Payment-provider
+/// top-ups and payouts are linked to the bank booking they match, and trades
+/// are stored as investment transactions whose cash effect moves the balance
+/// like any other booking. Everything written
+/// is tagged with `batch_id` so the run can be undone. Counterparties that only
+/// match existing parties by name similarity (at least `match_threshold`) are
+/// settled by `review`.
+pub fn import_statement(
+    conn: &mut PgConnection,
+    account: &Account,
+    statement: &Statement,
+    batch_id: i32,
+    match_threshold: f64,
+    review: &mut dyn MatchReview,
+) -> QueryResult<ImportSummary> {
+    conn.transaction(|conn| {
+        let mut matcher = PartyMatcher::load(conn, match_threshold)?;
+        let mut summary = ImportSummary {
+            opening_balance: account.balance.clone(),
+            ..ImportSummary::default()
+        };
+        let mut net_change = BigDecimal::from(0);
+
+        let mut context = LineContext {
+            account,
+            provider: statement.provider.as_deref(),
+            batch_id,
+            matcher: &mut matcher,
+            review,
+        };
+        let fingerprints = fingerprint::fingerprints(account.id, &statement.lines);
+        for (line, line_fingerprint) in statement.lines.iter().zip(&fingerprints) {
+            net_change += import_line(conn, &mut context, line, line_fingerprint, &mut summary)?;
+        }
+
+        let trade_fingerprints = fingerprint::trade_fingerprints(account.id, &statement.trades);
+        for (trade, trade_fingerprint) in statement.trades.iter().zip(&trade_fingerprints) {
+            if let Some(existing) = find_duplicate_trade(conn, account, trade, trade_fingerprint)? {
+                debug!("Trade on line {} already imported as investment transaction {}", trade.line, existing.id);
+                summary.duplicates_skipped += 1;
+                continue;
+            }
+
+            let new_trade = NewInvestmentTransaction {
+                account_id: account.id,
+                date: trade.date,
+                kind: trade.kind.as_str().to_string(),
+                symbol: trade.symbol.clone(),
+                quantity: trade.quantity.clone(),
+                unit_price: trade.unit_price.clone(),
+                currency: trade.currency.clone(),
+                fee: trade.fee.clone(),
+                amount: trade.amount.clone(),
+                memo: trade.memo.clone(),
+                external_id: trade.bank_id.clone(),
+                fingerprint: Some(trade_fingerprint.clone()),
+                import_batch_id: Some(batch_id),
+            };
+            match investment_service::create_investment_transaction(conn, &new_trade)? {
+                Some(created_trade) => {
+                    debug!("Trade on line {} stored as investment transaction {}", trade.line, created_trade.id);
+                    net_change += &trade.amount;
+                    summary.trades_created += 1;
+                }
+                None => {
+                    debug!("Trade on line {} was stored by a concurrent import", trade.line);
+                    summary.duplicates_skipped += 1;
+                }
+            }
+        }
+
+        summary.balance = &account.balance + &net_change;
+        account_service::update_balance(conn, account.id, summary.balance.clone())?;
+        import_batch_service::record_balance_change(
+            conn,
+            batch_id,
+            account.id,
+            net_change,
+            statement.opening_balance.as_ref(),
+            statement.closing_balance.as_ref(),
+        )?;
+        import_batch_service::add_counts(
+            conn,
+            batch_id,
+            summary.transactions_created + summary.trades_created,
+            summary.duplicates_skipped + statement.ignored.len(),
+            statement.rejected.len(),
+            summary.parties_created,
+        )?;
+        Ok(summary)
+    })
+}
+
+/// Writes one chunk of a statement that is read in chunks, in its own
+/// database transaction.
+///
+/// Lines are stored as `import_statement` does. The account balance moves by
+/// the chunk's net amount and the batch records `chunk.last_line` as
+/// committed, so an interrupted import can resume after the last chunk that
+/// made it to the database. `matcher` is kept across chunks so parties created
+/// by earlier ones are matched.
+pub fn import_chunk(
+    conn: &mut PgConnection,
+    account: &Account,
+    chunk: &StatementChunk,
+    batch_id: i32,
+    matcher: &mut PartyMatcher,
+    review: &mut dyn MatchReview,
+) -> QueryResult<ImportSummary> {
+    conn.transaction(|conn| {
+        let opening_balance = account_service::get_account(conn, account.id)?.balance;
+        let mut summary = ImportSummary {
+            opening_balance: opening_balance.clone(),
+            ..ImportSummary::default()
+        };
+        let mut net_change = BigDecimal::from(0);
+
+        let mut context = LineContext { account, provider: None, batch_id, matcher, review };
+        for (line, line_fingerprint) in chunk.lines.iter().zip(&chunk.fingerprints) {
+            net_change += import_line(conn, &mut context, line, line_fingerprint, &mut summary)?;
+        }
+
+        summary.balance = &opening_balance + &net_change;
+        account_service::update_balance(conn, account.id, summary.balance.clone())?;
+        import_batch_service::record_balance_change(conn, batch_id, account.id, net_change, None, None)?;
+        import_batch_service::add_counts(conn, batch_id, summary.transactions_created, summary.duplicates_skipped, chunk.rejected, summary.parties_created)?;
+        import_batch_service::record_progress(conn, batch_id, chunk.last_line)?;
+        Ok(summary)
+    })
+}
+
+/// What every line of one statement is imported with.
+struct LineContext<'a> {
+    account: &'a Account,
+    /// The payment provider the statement comes from, for linking funding lines.
+    provider: Option<&'a str>,
+    batch_id: i32,
+    matcher: &'a mut PartyMatcher,
+    review: &'a mut dyn MatchReview,
+}
+
+/// Stores one statement line as `import_statement` describes, returning how
+/// much it moves the account balance by.
+fn import_line(conn: &mut PgConnection, context: &mut LineContext, line: &StatementLine, line_fingerprint: &str, summary: &mut ImportSummary) -> QueryResult<BigDecimal> {
+    let account = context.account;
+    let batch_id = context.batch_id;
+    if let Some(existing) = find_duplicate(conn, account, line, line_fingerprint)? {
+        debug!("Line {} already imported as transaction {}", line.line, existing.id);
+        summary.duplicates_skipped += 1;
+        summary.skipped.push(SkippedLine { line: line.line, transaction_id: existing.id });
+        return Ok(BigDecimal::from(0));
+    }
+
+    if let Some(funding) = find_funding(conn, account, context.provider, line)? {
+        debug!("Line {} is the provider side of bank transaction {}", line.line, funding.id);
+        transaction_service::link_funding(conn, &funding, account.party_id, line.amount.is_positive(), line_fingerprint, Some(batch_id))?;
+        summary.transfers_linked += 1;
+        return Ok(line.amount.clone());
+    }
+
+    let (counterparty, created) = resolve_counterparty(conn, context.matcher, context.review, line, Some(batch_id))?;
+    if created {
+        summary.parties_created += 1;
+    }
+
+    let (from_id, to_id) = if line.amount.is_negative() {
+        (account.party_id, counterparty.id)
+    } else {
+        (counterparty.id, account.party_id)
+    };
+    if counterparty_has_account(conn, &counterparty)? {
+        if let Some(existing) = transaction_service::find_transfer(conn, &line.amount.abs(), from_id, to_id, line.date)? {
+            debug!("Line {} is the other side of transfer {}", line.line, existing.id);
+            transaction_service::link_transfer(conn, existing.id, line_fingerprint, Some(batch_id))?;
+            summary.transfers_linked += 1;
+            // The receiving account's statement was imported first; this is the paying side
+            if line.amount.is_negative() {
+                settle(conn, &existing, line, summary)?;
+            }
+            return Ok(line.amount.clone());
+        }
+    }
+
+    let new_transaction = NewTransaction {
+        amount: line.amount.abs(),
+        from_party_id: from_id,
+        to_party_id: to_id,
+        date: line.date,
+        memo: line.memo.clone(),
+        external_id: line.bank_id.clone(),
+        category: line.category.clone(),
+        fingerprint: Some(line_fingerprint.to_string()),
+        import_batch_id: Some(batch_id),
+        fee: line.fee.clone(),
+        original_amount: line.original_amount.as_ref().map(|original| original.abs()),
+        original_currency: line.original_currency.clone(),
+    };
+    match transaction_service::create_transaction(conn, &new_transaction)? {
+        Some(created_transaction) => {
+            debug!("Line {} stored as transaction {}", line.line, created_transaction.id);
+            summary.transactions_created += 1;
+            if line.amount.is_negative() {
+                settle(conn, &created_transaction, line, summary)?;
+            }
+            Ok(line.amount.clone())
+        }
+        None => {
+            debug!("Line {} was stored by a concurrent import", line.line);
+            summary.duplicates_skipped += 1;
+            Ok(BigDecimal::from(0))
+        }
+    }
+
+}
+
+fn settle(conn: &mut PgConnection, booking: &Transaction, line: &StatementLine, summary: &mut ImportSummary) -> QueryResult<()> {
+    if let Some(payment) = payment_service::settle_payment(conn, booking)? {
+        debug!("Line {} settles payment {}", line.line, payment.id);
+        summary.payments_settled += 1;
+    }
+    Ok(())
+}
+
+/// Shows what `import_statement` would do with each line, without writing anything.
+///
+/// `account` is `None` when the import would create the account, in which case
+/// no line can be a duplicate yet. Rows the parser rejected are listed alongside.
+pub fn preview_statement(conn: &mut PgConnection, account: Option<&Account>, statement: &Statement, match_threshold: f64) -> QueryResult<ImportPreview> {
+    let matcher = PartyMatcher::load(conn, match_threshold)?;
+    let mut preview = ImportPreview::default();
+    let mut planned_parties: HashSet<String> = HashSet::new();
+
+    let fingerprints = account.map(|account| fingerprint::fingerprints(account.id, &statement.lines));
+    for (index, line) in statement.lines.iter().enumerate() {
+        let mut row = RowPreview {
+            line: line.line,
+            date: Some(line.date),
+            amount: Some(line.amount.to_string()),
+            counterparty: Some(counterparty_name(line)),
+            party_id: None,
+            new_party: false,
+            action: RowAction::Create,
+            message: None,
+        };
+
+        if let (Some(account), Some(fingerprints)) = (account, &fingerprints) {
+            if let Some(existing) = find_duplicate(conn, account, line, &fingerprints[index])? {
+                row.action = RowAction::Duplicate;
+                row.message = Some(format!("already stored as transaction {}", existing.id));
+                preview.rows.push(row);
+                continue;
+            }
+        }
+
+        if let Some(account) = account {
+            if let Some(funding) = find_funding(conn, account, statement.provider.as_deref(), line)? {
+                row.action = RowAction::LinkTransfer;
+                row.message = Some(format!("funded by bank transaction {}", funding.id));
+                preview.rows.push(row);
+                continue;
+            }
+        }
+
+        let counterparty = match find_counterparty(conn, &matcher, line)? {
+            CounterpartyMatch::Found(party) => Some(party),
+            CounterpartyMatch::Fuzzy(candidates) => {
+                let best = &candidates[0];
+                row.message = Some(format!("similar to '{}' ({:.2})", best.name, best.score));
+                Some(party_service::get_party_by_id(conn, best.party_id)?)
+            }
+            CounterpartyMatch::None => None,
+        };
+        match counterparty {
+            Some(counterparty) => {
+                row.party_id = Some(counterparty.id);
+                if let Some(account) = account {
+                    let (from_id, to_id) = if line.amount.is_negative() {
+                        (account.party_id, counterparty.id)
+                    } else {
+                        (counterparty.id, account.party_id)
+                    };
+                    if counterparty_has_account(conn, &counterparty)? {
+                        if let Some(existing) = transaction_service::find_transfer(conn, &line.amount.abs(), from_id, to_id, line.date)? {
+                            row.action = RowAction::LinkTransfer;
+                            row.message = Some(format!("other side of transfer {}", existing.id));
+                        }
+                    }
+                }
+            }
+            None => {
+                let name = counterparty_name(line);
+                row.new_party = true;
+                if planned_parties.insert(name.clone()) {
+                    preview.new_parties.push(name);
+                }
+            }
+        }
+        preview.rows.push(row);
+    }
+
+    let trade_fingerprints = account.map(|account| fingerprint::trade_fingerprints(account.id, &statement.trades));
+    for (index, trade) in statement.trades.iter().enumerate() {
+        let mut row = RowPreview {
+            line: trade.line,
+            date: Some(trade.date),
+            amount: Some(trade.amount.to_string()),
+            counterparty: Some(trade_description(trade)),
+            party_id: None,
+            new_party: false,
+            action: RowAction::Create,
+            message: None,
+        };
+        if let (Some(account), Some(trade_fingerprints)) = (account, &trade_fingerprints) {
+            if let Some(existing) = find_duplicate_trade(conn, account, trade, &trade_fingerprints[index])? {
+                row.action = RowAction::Duplicate;
+                row.message = Some(format!("already stored as investment transaction {}", existing.id));
+            }
+        }
+        preview.rows.push(row);
+    }
+
+    for rejected in &statement.rejected {
+        preview.rows.push(RowPreview {
+            line: rejected.line,
+            date: None,
+            amount: None,
+            counterparty: None,
+            party_id: None,
+            new_party: false,
+            action: RowAction::Rejected,
+            message: Some(rejected.message.clone()),
+        });
+    }
+    for ignored in &statement.ignored {
+        preview.rows.push(RowPreview {
+            line: ignored.line,
+            date: None,
+            amount: None,
+            counterparty: None,
+            party_id: None,
+            new_party: false,
+            action: RowAction::Ignored,
+            message: Some(ignored.message.clone()),
+        });
+    }
+    preview.rows.sort_by_key(|row| row.line);
+    Ok(preview)
+}
+
+/// Finds the stored transaction a line duplicates: by fingerprint, or by bank
+/// transaction id for rows imported before fingerprints were recorded.
+fn find_duplicate(conn: &mut PgConnection, account: &Account, line: &StatementLine, line_fingerprint: &str) -> QueryResult<Option<Transaction>> {
+    if let Some(existing) = transaction_service::find_by_fingerprint(conn, line_fingerprint)? {
+        return Ok(Some(existing));
+    }
+    match &line.bank_id {
+        Some(bank_id) => transaction_service::find_by_external_id(conn, account.party_id, bank_id),
+        None => Ok(None),
+    }
+}
+
+/// Finds the stored investment transaction a trade duplicates, by fingerprint
+/// or by the exchange's transaction id.
+fn find_duplicate_trade(conn: &mut PgConnection, account: &Account, trade: &TradeLine, trade_fingerprint: &str) -> QueryResult<Option<InvestmentTransaction>> {
+    if let Some(existing) = investment_service::find_by_fingerprint(conn, trade_fingerprint)? {
+        return Ok(Some(existing));
+    }
+    match &trade.bank_id {
+        Some(bank_id) => investment_service::find_by_external_id(conn, account.id, bank_id),
+        None => Ok(None),
+    }
+}
+
+/// Describes a trade for the preview, e.g. `buy 0.5 BTC`.
+fn trade_description(trade: &TradeLine) -> String {
+    match (&trade.quantity, &trade.symbol) {
+        (Some(quantity), Some(symbol)) => format!("{} {} {}", trade.kind.as_str(), quantity.abs(), symbol),
+        (None, Some(symbol)) => format!("{} {}", trade.kind.as_str(), symbol),
+        _ => trade.kind.as_str().to_string(),
+    }
+}
+
+/// Finds the counterparty of a line, creating it if no existing party matches.
+///
+/// Similar-named parties are offered to `review`; a confirmed choice can be
+/// remembered as an alias so the next import matches it directly. A BIC
+/// reported alongside a matched IBAN is recorded on the party if it has none
+/// yet. Returns the party and whether it was newly created.
+pub fn resolve_counterparty(
+    conn: &mut PgConnection,
+    matcher: &mut PartyMatcher,
+    review: &mut dyn MatchReview,
+    line: &StatementLine,
+    batch_id: Option<i32>,
+) -> QueryResult<(Party, bool)> {
+    let line_bic = line.counterparty_bic.as_deref().unwrap_or("");
+    let party_name = counterparty_name(line);
+
+    match find_counterparty(conn, matcher, line)? {
+        CounterpartyMatch::Found(mut party) => {
+            if line.counterparty_iban.as_deref() == Some(party.eban.as_str()) && party.bic.is_empty() && !line_bic.is_empty() {
+                party_service::update_bic(conn, party.id, line_bic)?;
+                party.bic = line_bic.to_string();
+            }
+            return Ok((party, false));
+        }
+        CounterpartyMatch::Fuzzy(candidates) => {
+            if let ReviewDecision::Use { party_id, remember } = review.choose(line, &candidates) {
+                if remember {
+                    party_match_service::save_alias(conn, party_id, &party_name)?;
+                    matcher.add_alias(&party_name, party_id);
+                }
+                let party = party_service::get_party_by_id(conn, party_id)?;
+                debug!("Line {}: '{}' matched to party '{}' by similarity", line.line, party_name, party.name);
+                return Ok((party, false));
+            }
+        }
+        CounterpartyMatch::None => {}
+    }
+
+    let party_eban = line.counterparty_iban.as_deref().unwrap_or("");
+    let party = party_service::create_party(conn, &party_name, "", party_eban, 0, line_bic, batch_id)?;
+    matcher.add_party(&party);
+    Ok((party, true))
+}
+
+/// Looks up the counterparty of a line by IBAN, then by alias or normalised
+/// name, then by name similarity.
+pub fn find_counterparty(conn: &mut PgConnection, matcher: &PartyMatcher, line: &StatementLine) -> QueryResult<CounterpartyMatch> {
+    if let Some(iban) = &line.counterparty_iban {
+        if let Some(party) = party_service::find_party_by_eban(conn, iban)? {
+            return Ok(CounterpartyMatch::Found(party));
+        }
+    }
+    Ok(match matcher.match_name(&counterparty_name(line)) {
+        NameMatch::Exact(party_id) => CounterpartyMatch::Found(party_service::get_party_by_id(conn, party_id)?),
+        NameMatch::Fuzzy(candidates) => CounterpartyMatch::Fuzzy(candidates),
+        NameMatch::None => CounterpartyMatch::None,
+    })
+}
+
+/// The bank booking a payment provider's top-up or payout line corresponds to, if it is stored.
+fn find_funding(conn: &mut PgConnection, account: &Account, provider: Option<&str>, line: &StatementLine) -> QueryResult<Option<Transaction>> {
+    match provider {
+        Some(provider) if line.funding => transaction_service::find_funding(conn, provider, account.party_id, &line.amount, line.date),
+        _ => Ok(None),
+    }
+}
+
+/// The name a new party for this line's counterparty would get.
+fn counterparty_name(line: &StatementLine) -> String {
+    line.counterparty
+        .clone()
+        .or_else(|| line.counterparty_iban.clone())
+        .unwrap_or_else(|| UNKNOWN_COUNTERPARTY.to_string())
+}
+
+fn counterparty_has_account(conn: &mut PgConnection, counterparty: &Party) -> QueryResult<bool> {
+    Ok(!account_service::get_accounts_by_party(conn, counterparty.id)?.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn row(line: usize, action: RowAction) -> RowPreview {
+        RowPreview {
+            line,
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap().and_hms_opt(0, 0, 0),
+            amount: Some("-3.50".to_string()),
+            counterparty: Some("Café".to_string()),
+            party_id: None,
+            new_party: true,
+            action,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn reports_each_row_with_a_snake_case_action() {
+        let preview = ImportPreview {
+            rows: vec![row(2, RowAction::Create), row(3, RowAction::LinkTransfer), row(4, RowAction::Create)],
+            new_parties: vec!["Café".to_string()],
+        };
+        assert_eq!(preview.count(RowAction::Create), 2);
+        assert_eq!(preview.count(RowAction::Duplicate), 0);
+
+        let json = serde_json::to_value(&preview).unwrap();
+        assert_eq!(json["rows"][1]["action"], "link_transfer");
+        assert_eq!(json["rows"][1]["line"], 3);
+        assert_eq!(json["rows"][0]["amount"], "-3.50");
+        assert_eq!(json["new_parties"][0], "Café");
+    }
+}
@@ -0,0 +1,75 @@
+use diesel::prelude::*;
+use bigdecimal::{BigDecimal, Zero};
+use std::collections::BTreeMap;
+use crate::models::investment::{InvestmentTransaction, NewInvestmentTransaction};
+use crate::schema::investment_transactions::dsl::*;
+
+/// The columns an `InvestmentTransaction` is loaded from, in its field order.
+pub const COLUMNS: (id, symbol, quantity, unit_price, currency) = (id, symbol, quantity, unit_price, currency);
+
+/// Units of one symbol held in an account, with the last price paid or received for it.
+#[derive(Debug)]
+pub struct Holding {
+    pub symbol: String,
+    pub quantity: BigDecimal,
+    pub last_price: Option<BigDecimal>,
+    pub currency: Option<String>,
+}
+
+/// Inserts a trade, or returns `None` when one with the same fingerprint is already stored.
+pub fn create_investment_transaction(conn: &mut PgConnection, new_transaction: &NewInvestmentTransaction) -> QueryResult<Option<InvestmentTransaction>> {
+    diesel::insert_into(investment_transactions)
+        .values(new_transaction)
+        .on_conflict(fingerprint)
+        .do_nothing()
+        .returning(COLUMNS)
+        .get_result(conn)
+        .optional()
+}
+
+pub fn find_by_fingerprint(conn: &mut PgConnection, trade_fingerprint: &str) -> QueryResult<Option<InvestmentTransaction>> {
+    investment_transactions
+        .filter(fingerprint.eq(trade_fingerprint))
+        .select(COLUMNS)
+        .first(conn)
+        .optional()
+}
+
+/// Finds a trade of the account carrying the exchange's transaction id.
+pub fn find_by_external_id(conn: &mut PgConnection, trade_account_id: i32, exchange_id: &str) -> QueryResult<Option<InvestmentTransaction>> {
+    investment_transactions
+        .filter(account_id.eq(trade_account_id))
+        .filter(external_id.eq(exchange_id))
+        .select(COLUMNS)
+        .first(conn)
+        .optional()
+}
+
+/// Sums the quantities of every symbol traded in an account, leaving out closed positions.
+pub fn holdings(conn: &mut PgConnection, holding_account_id: i32) -> QueryResult<Vec<Holding>> {
+    let rows: Vec<InvestmentTransaction> = investment_transactions
+        .filter(account_id.eq(holding_account_id))
+        .filter(symbol.is_not_null())
+        .order((date.asc(), id.asc()))
+        .select(COLUMNS)
+        .load(conn)?;
+
+    let mut by_symbol: BTreeMap<String, Holding> = BTreeMap::new();
+    for row in rows {
+        let Some(row_symbol) = row.symbol else { continue };
+        let holding = by_symbol.entry(row_symbol.clone()).or_insert_with(|| Holding {
+            symbol: row_symbol,
+            quantity: BigDecimal::zero(),
+            last_price: None,
+            currency: None,
+        });
+        if let Some(row_quantity) = row.quantity {
+            holding.quantity += row_quantity;
+        }
+        if row.unit_price.is_some() {
+            holding.last_price = row.unit_price;
+            holding.currency = row.currency;
+        }
+    }
+    Ok(by_symbol.into_values().filter(|holding| !holding.quantity.is_zero()).collect())
+}
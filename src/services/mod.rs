@@ -2,3 +2,11 @@ pub mod account_service;
 pub mod party_service;
 pub mod transaction_service;
 pub mod receipt_service;
+pub mod import_service;
+pub mod import_batch_service;
+pub mod export_service;
+pub mod party_match_service;
+pub mod investment_service;
+pub mod payment_service;
+pub mod backup_service;
+pub mod account_link_service;
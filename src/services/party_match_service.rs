@@ -0,0 +1,166 @@
+use diesel::prelude::*;
+use std::collections::HashMap;
+use crate::models::party::{NewPartyAlias, Party, PartyAlias};
+use crate::schema::{parties, party_aliases};
+use crate::services::party_service;
+
+/// Legal-form words dropped before comparing names, so "ACME GmbH" matches "Acme".
+const LEGAL_FORMS: [&str; 24] = [
+    "gmbh", "mbh", "ag", "kg", "ohg", "ug", "ev", "se", "co", "inc", "ltd", "llc", "plc", "corp", "sa", "sas", "sarl", "bv", "nv",
+    "srl", "spa", "ab", "oy", "as",
+];
+
+/// An existing party that might be a line's counterparty.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub party_id: i32,
+    pub name: String,
+    /// Jaro-Winkler similarity of the normalised names, 0 to 1.
+    pub score: f64,
+}
+
+#[derive(Debug)]
+pub enum NameMatch {
+    /// A confirmed alias or the same normalised name.
+    Exact(i32),
+    /// Parties above the similarity threshold, best first.
+    Fuzzy(Vec<Candidate>),
+    None,
+}
+
+/// Lowercases, drops punctuation and legal forms, and collapses whitespace.
+pub fn normalize_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_lowercase().next().unwrap_or(c) } else { ' ' })
+        .collect();
+    cleaned
+        .split_whitespace()
+        .filter(|word| !LEGAL_FORMS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Party names and aliases, loaded once per import so that matching a line
+/// does not need a query per party.
+pub struct PartyMatcher {
+    parties: Vec<(i32, String, String)>,
+    aliases: HashMap<String, i32>,
+    threshold: f64,
+}
+
+impl PartyMatcher {
+    pub fn load(conn: &mut PgConnection, threshold: f64) -> QueryResult<Self> {
+        let mut matcher = PartyMatcher {
+            parties: Vec::new(),
+            aliases: HashMap::new(),
+            threshold,
+        };
+        for party in parties::table.select(party_service::COLUMNS).load::<Party>(conn)? {
+            matcher.add_party(&party);
+        }
+        for party_alias in party_aliases::table.select((party_aliases::party_id, party_aliases::alias)).load::<PartyAlias>(conn)? {
+            matcher.aliases.insert(party_alias.alias, party_alias.party_id);
+        }
+        Ok(matcher)
+    }
+
+    pub fn add_party(&mut self, party: &Party) {
+        self.parties.push((party.id, normalize_name(&party.name), party.name.clone()));
+    }
+
+    pub fn add_alias(&mut self, name: &str, party_id: i32) {
+        self.aliases.insert(normalize_name(name), party_id);
+    }
+
+    /// Matches a counterparty name by alias, then normalised name, then similarity.
+    pub fn match_name(&self, name: &str) -> NameMatch {
+        let normalized = normalize_name(name);
+        if normalized.is_empty() {
+            return NameMatch::None;
+        }
+        if let Some(party_id) = self.aliases.get(&normalized) {
+            return NameMatch::Exact(*party_id);
+        }
+        if let Some((party_id, _, _)) = self.parties.iter().find(|(_, party_name, _)| *party_name == normalized) {
+            return NameMatch::Exact(*party_id);
+        }
+
+        let mut candidates: Vec<Candidate> = self
+            .parties
+            .iter()
+            .filter(|(_, party_name, _)| !party_name.is_empty())
+            .map(|(party_id, party_name, display_name)| Candidate {
+                party_id: *party_id,
+                name: display_name.clone(),
+                score: strsim::jaro_winkler(&normalized, party_name),
+            })
+            .filter(|candidate| candidate.score >= self.threshold)
+            .collect();
+        if candidates.is_empty() {
+            return NameMatch::None;
+        }
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        NameMatch::Fuzzy(candidates)
+    }
+}
+
+/// Remembers that `name` means `party_id`, replacing an earlier alias of the same name.
+pub fn save_alias(conn: &mut PgConnection, alias_party_id: i32, name: &str) -> QueryResult<usize> {
+    let new_alias = NewPartyAlias {
+        party_id: alias_party_id,
+        alias: normalize_name(name),
+    };
+
+    diesel::insert_into(party_aliases::table)
+        .values(&new_alias)
+        .on_conflict(party_aliases::alias)
+        .do_update()
+        .set(party_aliases::party_id.eq(alias_party_id))
+        .execute(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn party(id: i32, name: &str) -> Party {
+        Party {
+            id,
+            name: name.to_string(),
+            phone: String::new(),
+            eban: String::new(),
+            address_id: 0,
+            bic: String::new(),
+        }
+    }
+
+    fn matcher(threshold: f64) -> PartyMatcher {
+        let mut matcher = PartyMatcher { parties: Vec::new(), aliases: HashMap::new(), threshold };
+        matcher.add_party(&party(1, "ACME GmbH"));
+        matcher.add_party(&party(2, "Stadtwerke München"));
+        matcher
+    }
+
+    #[test]
+    fn normalizes_case_punctuation_and_legal_forms() {
+        assert_eq!(normalize_name("  ACME  GmbH & Co. KG "), "acme");
+        assert_eq!(normalize_name("Müller-Lüdenscheidt e.V."), "müller lüdenscheidt e v");
+        assert_eq!(normalize_name("GmbH"), "");
+    }
+
+    #[test]
+    fn matches_by_alias_then_name_then_similarity() {
+        let mut matcher = matcher(0.85);
+        assert!(matches!(matcher.match_name("Acme, Inc."), NameMatch::Exact(1)));
+        assert!(matches!(matcher.match_name("Bakery"), NameMatch::None));
+        assert!(matches!(matcher.match_name("Ltd"), NameMatch::None));
+
+        let NameMatch::Fuzzy(candidates) = matcher.match_name("Stadtwerke Munchen") else { panic!("expected a fuzzy match") };
+        assert_eq!(candidates[0].party_id, 2);
+        assert_eq!(candidates[0].name, "Stadtwerke München");
+
+        matcher.add_alias("SWM Versorgung", 2);
+        assert!(matches!(matcher.match_name("swm versorgung"), NameMatch::Exact(2)));
+    }
+}
@@ -2,7 +2,10 @@ use diesel::prelude::*;
 use crate::models::party::{Party, NewParty};
 use crate::schema::parties::dsl::*;
 
-pub fn create_party(conn: &mut PgConnection, new_name: &str, new_phone: &str, new_eban: &str, new_address_id: i32) -> QueryResult<Party> {
+/// The columns a `Party` is loaded from, in its field order.
+pub const COLUMNS: (id, name, phone, eban, address_id, bic) = (id, name, phone, eban, address_id, bic);
+
+pub fn create_party(conn: &mut PgConnection, new_name: &str, new_phone: &str, new_eban: &str, new_address_id: i32, new_bic: &str, new_import_batch_id: Option<i32>) -> QueryResult<Party> {
     use crate::schema::parties;
 
     let new_party = NewParty {
@@ -10,17 +13,50 @@ pub fn create_party(conn: &mut PgConnection, new_name: &str, new_phone: &str, ne
         phone: new_phone.to_string(),
         eban: new_eban.to_string(),
         address_id: new_address_id,
+        bic: new_bic.to_string(),
+        import_batch_id: new_import_batch_id,
     };
 
     diesel::insert_into(parties::table)
         .values(&new_party)
+        .returning(COLUMNS)
         .get_result(conn)
 }
 
 pub fn get_party_by_id(conn: &mut PgConnection, party_id: i32) -> QueryResult<Party> {
-    parties.filter(id.eq(party_id)).first(conn)
+    parties.filter(id.eq(party_id)).select(COLUMNS).first(conn)
 }
 
 pub fn delete_party(conn: &mut PgConnection, party_id: i32) -> QueryResult<usize> {
     diesel::delete(parties.filter(id.eq(party_id))).execute(conn)
 }
+
+pub fn find_party_by_eban(conn: &mut PgConnection, party_eban: &str) -> QueryResult<Option<Party>> {
+    parties.filter(eban.eq(party_eban)).select(COLUMNS).first(conn).optional()
+}
+
+pub fn find_party_by_name(conn: &mut PgConnection, party_name: &str) -> QueryResult<Option<Party>> {
+    parties.filter(name.eq(party_name)).select(COLUMNS).first(conn).optional()
+}
+
+pub fn update_bic(conn: &mut PgConnection, party_id: i32, new_bic: &str) -> QueryResult<usize> {
+    diesel::update(parties.filter(id.eq(party_id)))
+        .set(bic.eq(new_bic))
+        .execute(conn)
+}
+
+/// Whether any account, transaction, receipt or payment still refers to the party.
+pub fn party_in_use(conn: &mut PgConnection, party_id: i32) -> QueryResult<bool> {
+    use crate::schema::{accounts, payments, receipts, transactions};
+    use diesel::dsl::exists;
+    use diesel::select;
+
+    let has_account = select(exists(accounts::table.filter(accounts::party_id.eq(party_id)))).get_result(conn)?;
+    let has_transaction = select(exists(
+        transactions::table.filter(transactions::from_party_id.eq(party_id).or(transactions::to_party_id.eq(party_id))),
+    ))
+    .get_result(conn)?;
+    let has_receipt = select(exists(receipts::table.filter(receipts::party_id.eq(party_id)))).get_result(conn)?;
+    let has_payment = select(exists(payments::table.filter(payments::party_id.eq(party_id)))).get_result(conn)?;
+    Ok(has_account || has_transaction || has_receipt || has_payment)
+}
@@ -0,0 +1,79 @@
+use diesel::prelude::*;
+use chrono::Duration;
+use crate::models::payment::{NewPayment, Payment};
+use crate::models::transaction::Transaction;
+use crate::schema::payments::dsl::*;
+
+/// How many days before its requested execution date a booking may settle a payment.
+const EARLY_DAYS: i64 = 3;
+/// How many days after its requested execution date a booking may settle a payment.
+const LATE_DAYS: i64 = 10;
+
+pub fn create_payment(conn: &mut PgConnection, new_payment: &NewPayment) -> QueryResult<Payment> {
+    diesel::insert_into(payments)
+        .values(new_payment)
+        .get_result(conn)
+}
+
+pub fn list_payments(conn: &mut PgConnection) -> QueryResult<Vec<Payment>> {
+    payments.order((execution_date.desc(), id.desc())).load(conn)
+}
+
+/// Pending payments, oldest first: of one account if given, and only those not
+/// written to a file yet unless `include_exported`.
+pub fn pending_payments(conn: &mut PgConnection, paying_account: Option<i32>, include_exported: bool) -> QueryResult<Vec<Payment>> {
+    let mut query = payments.filter(transaction_id.is_null()).into_boxed();
+    if let Some(paying_account) = paying_account {
+        query = query.filter(account_id.eq(paying_account));
+    }
+    if !include_exported {
+        query = query.filter(message_id.is_null());
+    }
+    query.order((execution_date.asc(), id.asc())).load(conn)
+}
+
+/// Records the pain.001 message the payments were written to.
+pub fn mark_exported(conn: &mut PgConnection, payment_ids: &[i32], message: &str) -> QueryResult<usize> {
+    diesel::update(payments.filter(id.eq_any(payment_ids)))
+        .set(message_id.eq(message))
+        .execute(conn)
+}
+
+/// Deletes a payment that has not been booked yet; returns 0 if it has.
+pub fn cancel_payment(conn: &mut PgConnection, payment_id: i32) -> QueryResult<usize> {
+    diesel::delete(payments.filter(id.eq(payment_id)).filter(transaction_id.is_null())).execute(conn)
+}
+
+/// Settles the pending payment an imported booking carries out, if any.
+///
+/// The booking must leave the paying account's party towards the payee with
+/// the same amount. A payment whose end-to-end id appears in the booking's memo
+/// wins; otherwise the one with the nearest execution date, within a few days
+/// of it, is taken.
+pub fn settle_payment(conn: &mut PgConnection, booking: &Transaction) -> QueryResult<Option<Payment>> {
+    use crate::schema::accounts;
+
+    let paying_accounts = accounts::table.filter(accounts::party_id.eq(booking.from_party_id)).select(accounts::id);
+    let candidates: Vec<Payment> = payments
+        .filter(transaction_id.is_null())
+        .filter(account_id.eq_any(paying_accounts))
+        .filter(party_id.eq(booking.to_party_id))
+        .filter(amount.eq(&booking.amount))
+        .load(conn)?;
+
+    let booked = booking.date.date();
+    let memo = booking.memo.to_uppercase();
+    let settled = match candidates.iter().find(|payment| memo.contains(&payment.end_to_end_id.to_uppercase())) {
+        Some(payment) => Some(payment),
+        None => candidates
+            .iter()
+            .filter(|payment| booked >= payment.execution_date - Duration::days(EARLY_DAYS) && booked <= payment.execution_date + Duration::days(LATE_DAYS))
+            .min_by_key(|payment| ((booked - payment.execution_date).num_days().abs(), payment.id)),
+    };
+    let Some(settled) = settled else { return Ok(None) };
+
+    diesel::update(payments.filter(id.eq(settled.id)))
+        .set(transaction_id.eq(booking.id))
+        .get_result(conn)
+        .map(Some)
+}
@@ -1,27 +1,33 @@
 use diesel::prelude::*;
-use crate::models::receipt::{Receipt, NewReceipt};
+use bigdecimal::BigDecimal;
+use log::debug;
+use crate::import::einvoice::Invoice;
+use crate::import::mail::OrderMail;
+use crate::import::ocr::OcrReceipt;
+use crate::models::party::Party;
+use crate::models::receipt::{NewReceipt, NewReceiptItem, Receipt, ReceiptItem};
 use crate::schema::receipts::dsl::*;
+use crate::services::party_match_service::{NameMatch, PartyMatcher};
+use crate::services::party_service;
 
-pub fn create_receipt(
-    conn: &mut PgConnection,
-    new_payment_method: &str,
-    new_party_id: i32,
-    new_date: &str,
-    new_time: &str,
-    new_items: Vec<String>,
-) -> QueryResult<Receipt> {
-    use crate::schema::receipts;
-
-    let new_receipt = NewReceipt {
-        payment_method: new_payment_method.to_string(),
-        party_id: new_party_id,
-        date: new_date.to_string(),
-        time: new_time.to_string(),
-        items: new_items,
-    };
+/// Name of the party receipts are booked to when OCR found no merchant name.
+pub const UNKNOWN_MERCHANT: &str = "Unknown merchant";
 
-    diesel::insert_into(receipts::table)
-        .values(&new_receipt)
+/// The outcome of storing an e-invoice or order confirmation as a receipt.
+#[derive(Debug)]
+pub struct ReceiptImport {
+    pub receipt_id: i32,
+    /// The document was already stored; `receipt_id` is the existing receipt.
+    pub duplicate: bool,
+    /// The seller or merchant the receipt is booked to.
+    pub party: Party,
+    pub party_created: bool,
+    pub items_created: usize,
+}
+
+pub fn create_receipt(conn: &mut PgConnection, new_receipt: &NewReceipt) -> QueryResult<Receipt> {
+    diesel::insert_into(receipts)
+        .values(new_receipt)
         .get_result(conn)
 }
 
@@ -29,6 +35,284 @@ pub fn get_receipt_by_id(conn: &mut PgConnection, receipt_id: i32) -> QueryResul
     receipts.filter(id.eq(receipt_id)).first(conn)
 }
 
+/// The line items of a receipt, in invoice order.
+pub fn receipt_items(conn: &mut PgConnection, item_receipt_id: i32) -> QueryResult<Vec<ReceiptItem>> {
+    use crate::schema::receipt_items::dsl as item;
+
+    item::receipt_items
+        .filter(item::receipt_id.eq(item_receipt_id))
+        .order(item::position.asc())
+        .select((item::position, item::description, item::quantity, item::unit_price, item::net_amount, item::vat_rate, item::vat_category))
+        .load(conn)
+}
+
 pub fn delete_receipt(conn: &mut PgConnection, receipt_id: i32) -> QueryResult<usize> {
     diesel::delete(receipts.filter(id.eq(receipt_id))).execute(conn)
 }
+
+pub fn list_receipts(conn: &mut PgConnection) -> QueryResult<Vec<Receipt>> {
+    receipts.order((date.desc(), id.desc())).load(conn)
+}
+
+/// Receipts waiting to be checked, oldest first.
+pub fn review_queue(conn: &mut PgConnection) -> QueryResult<Vec<Receipt>> {
+    receipts.filter(needs_review.eq(true)).order(id.asc()).load(conn)
+}
+
+/// Takes a receipt out of the review queue, applying the user's corrections.
+pub fn approve_receipt(
+    conn: &mut PgConnection,
+    receipt_id: i32,
+    corrected_date: Option<&str>,
+    corrected_total: Option<BigDecimal>,
+) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        if let Some(corrected_date) = corrected_date {
+            diesel::update(receipts.filter(id.eq(receipt_id))).set(date.eq(corrected_date)).execute(conn)?;
+        }
+        if let Some(corrected_total) = corrected_total {
+            diesel::update(receipts.filter(id.eq(receipt_id))).set(total.eq(corrected_total)).execute(conn)?;
+        }
+        diesel::update(receipts.filter(id.eq(receipt_id)))
+            .set((needs_review.eq(false), review_reasons.eq(Vec::<String>::new())))
+            .execute(conn)
+    })
+}
+
+pub fn find_by_invoice_number(conn: &mut PgConnection, seller_id: i32, number: &str) -> QueryResult<Option<Receipt>> {
+    receipts
+        .filter(party_id.eq(seller_id))
+        .filter(invoice_number.eq(number))
+        .first(conn)
+        .optional()
+}
+
+/// Stores an e-invoice as a receipt of its seller, with one item per invoice line.
+///
+/// The seller is looked up by IBAN, then by name as for statement imports
+/// (taking the most similar party at `match_threshold` or above), and
+/// created with its IBAN and BIC if neither finds it. An invoice whose number
+/// is already stored for the seller is left alone. Credit notes are stored
+/// with negative totals.
+pub fn import_invoice(conn: &mut PgConnection, invoice: &Invoice, batch_id: Option<i32>, match_threshold: f64) -> QueryResult<ReceiptImport> {
+    use crate::schema::receipt_items;
+
+    conn.transaction(|conn| {
+        let mut matcher = PartyMatcher::load(conn, match_threshold)?;
+        let (seller, seller_created) = resolve_merchant(
+            conn,
+            &mut matcher,
+            &invoice.seller_name,
+            invoice.seller_iban.as_deref(),
+            invoice.seller_bic.as_deref(),
+            batch_id,
+        )?;
+        if let Some(existing) = find_by_invoice_number(conn, seller.id, &invoice.number)? {
+            debug!("Invoice {} of '{}' already stored as receipt {}", invoice.number, seller.name, existing.id);
+            return Ok(ReceiptImport { receipt_id: existing.id, duplicate: true, party: seller, party_created: seller_created, items_created: 0 });
+        }
+
+        let signed = |value: &BigDecimal| if invoice.credit_note { -value } else { value.clone() };
+        let new_receipt = NewReceipt {
+            payment_method: invoice.payment_method().to_string(),
+            party_id: seller.id,
+            date: invoice.issue_date.format("%Y-%m-%d").to_string(),
+            time: String::new(),
+            items: invoice.lines.iter().map(|line| line.description.clone()).collect(),
+            import_batch_id: batch_id,
+            invoice_number: Some(invoice.number.clone()),
+            due_date: invoice.due_date.map(|due| due.format("%Y-%m-%d").to_string()),
+            currency: invoice.currency.clone(),
+            net_total: invoice.net_total.as_ref().map(signed),
+            tax_total: invoice.tax_total.as_ref().map(signed),
+            total: Some(signed(&invoice.total)),
+            source: Some(invoice.syntax.name().to_string()),
+            confidence: None,
+            needs_review: false,
+            review_reasons: Vec::new(),
+        };
+        let receipt = create_receipt(conn, &new_receipt)?;
+
+        let new_items: Vec<NewReceiptItem> = invoice
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| NewReceiptItem {
+                receipt_id: receipt.id,
+                position: index as i32 + 1,
+                description: line.description.clone(),
+                quantity: line.quantity.clone(),
+                unit_price: line.unit_price.clone(),
+                net_amount: signed(&line.net_amount),
+                vat_rate: line.vat_rate.clone(),
+                vat_category: line.vat_category.clone(),
+            })
+            .collect();
+        let items_created = diesel::insert_into(receipt_items::table).values(&new_items).execute(conn)?;
+
+        debug!("Invoice {} of '{}' stored as receipt {}", invoice.number, seller.name, receipt.id);
+        Ok(ReceiptImport { receipt_id: receipt.id, duplicate: false, party: seller, party_created: seller_created, items_created })
+    })
+}
+
+/// Stores an order confirmation as a receipt of the template's merchant.
+///
+/// Item lines are kept as text on the receipt, since shops show prices with
+/// VAT included. An order is stored once: by order number, or by date and
+/// total for shops whose mails carry none.
+pub fn import_order_mail(conn: &mut PgConnection, matcher: &mut PartyMatcher, order: &OrderMail, batch_id: Option<i32>) -> QueryResult<ReceiptImport> {
+    conn.transaction(|conn| {
+        let (merchant, merchant_created) = resolve_merchant(conn, matcher, &order.merchant, None, None, batch_id)?;
+        let order_date = order.date.format("%Y-%m-%d").to_string();
+        let existing = match &order.order_number {
+            Some(number) => find_by_invoice_number(conn, merchant.id, number)?,
+            None => receipts
+                .filter(party_id.eq(merchant.id))
+                .filter(date.eq(&order_date))
+                .filter(total.eq(&order.total))
+                .first(conn)
+                .optional()?,
+        };
+        if let Some(existing) = existing {
+            debug!("Order from '{}' on {} already stored as receipt {}", merchant.name, order_date, existing.id);
+            return Ok(ReceiptImport { receipt_id: existing.id, duplicate: true, party: merchant, party_created: merchant_created, items_created: 0 });
+        }
+
+        let order_items: Vec<String> = order
+            .items
+            .iter()
+            .map(|item| match &item.quantity {
+                Some(quantity) => format!("{} x {} {}", quantity, item.description, item.price),
+                None => format!("{} {}", item.description, item.price),
+            })
+            .collect();
+        let new_receipt = NewReceipt {
+            payment_method: order.payment_method.clone(),
+            party_id: merchant.id,
+            date: order_date,
+            time: String::new(),
+            items: order_items,
+            import_batch_id: batch_id,
+            invoice_number: order.order_number.clone(),
+            due_date: None,
+            currency: order.currency.clone(),
+            net_total: None,
+            tax_total: None,
+            total: Some(order.total.clone()),
+            source: Some("mail".to_string()),
+            confidence: None,
+            needs_review: false,
+            review_reasons: Vec::new(),
+        };
+        let receipt = create_receipt(conn, &new_receipt)?;
+
+        debug!("Order from '{}' stored as receipt {}", merchant.name, receipt.id);
+        Ok(ReceiptImport { receipt_id: receipt.id, duplicate: false, party: merchant, party_created: merchant_created, items_created: order.items.len() })
+    })
+}
+
+/// Stores a till receipt read from OCR text.
+///
+/// Receipts whose merchant, date, items or total are less certain than
+/// `review_threshold` are stored all the same but queued for review, with the
+/// doubtful fields as reasons. A receipt with the same merchant, date, time
+/// and total as a stored one is taken to be a second scan of it.
+pub fn import_ocr_receipt(
+    conn: &mut PgConnection,
+    matcher: &mut PartyMatcher,
+    receipt: &OcrReceipt,
+    batch_id: Option<i32>,
+    review_threshold: f32,
+) -> QueryResult<ReceiptImport> {
+    conn.transaction(|conn| {
+        let merchant_name = receipt.merchant.value.as_deref().unwrap_or(UNKNOWN_MERCHANT);
+        let (merchant, merchant_created) = resolve_merchant(conn, matcher, merchant_name, None, None, batch_id)?;
+        let receipt_date = receipt.date.value.map(|value| value.format("%Y-%m-%d").to_string()).unwrap_or_default();
+        let receipt_time = receipt.time.value.map(|value| value.format("%H:%M").to_string()).unwrap_or_default();
+
+        let existing: Option<Receipt> = receipts
+            .filter(party_id.eq(merchant.id))
+            .filter(date.eq(&receipt_date))
+            .filter(time.eq(&receipt_time))
+            .filter(total.eq(receipt.total.value.as_ref()))
+            .first(conn)
+            .optional()?;
+        if let Some(existing) = existing {
+            debug!("Receipt from '{}' on {} {} already stored as receipt {}", merchant.name, receipt_date, receipt_time, existing.id);
+            return Ok(ReceiptImport { receipt_id: existing.id, duplicate: true, party: merchant, party_created: merchant_created, items_created: 0 });
+        }
+
+        let item_lines: Vec<String> = receipt
+            .items
+            .value
+            .iter()
+            .flatten()
+            .map(|item| match &item.quantity {
+                Some(quantity) => format!("{} x {} {}", quantity, item.description, item.price),
+                None => format!("{} {}", item.description, item.price),
+            })
+            .collect();
+        let reasons = receipt.review_reasons(review_threshold);
+        let new_receipt = NewReceipt {
+            payment_method: receipt.payment_method.value.clone().unwrap_or_else(|| "unknown".to_string()),
+            party_id: merchant.id,
+            date: receipt_date,
+            time: receipt_time,
+            items: item_lines,
+            import_batch_id: batch_id,
+            invoice_number: None,
+            due_date: None,
+            currency: None,
+            net_total: None,
+            tax_total: None,
+            total: receipt.total.value.clone(),
+            source: Some("ocr".to_string()),
+            confidence: Some(receipt.confidence()),
+            needs_review: !reasons.is_empty(),
+            review_reasons: reasons,
+        };
+        let stored = create_receipt(conn, &new_receipt)?;
+
+        debug!("Receipt from '{}' stored as receipt {} (confidence {:.2})", merchant.name, stored.id, receipt.confidence());
+        Ok(ReceiptImport {
+            receipt_id: stored.id,
+            duplicate: false,
+            party: merchant,
+            party_created: merchant_created,
+            items_created: new_receipt.items.len(),
+        })
+    })
+}
+
+/// Finds the party a receipt belongs to by IBAN, then by name, creating it if none matches.
+fn resolve_merchant(
+    conn: &mut PgConnection,
+    matcher: &mut PartyMatcher,
+    name: &str,
+    merchant_iban: Option<&str>,
+    merchant_bic: Option<&str>,
+    batch_id: Option<i32>,
+) -> QueryResult<(Party, bool)> {
+    let merchant_bic = merchant_bic.unwrap_or("");
+    if let Some(iban) = merchant_iban {
+        if let Some(party) = party_service::find_party_by_eban(conn, iban)? {
+            if party.bic.is_empty() && !merchant_bic.is_empty() {
+                party_service::update_bic(conn, party.id, merchant_bic)?;
+            }
+            return Ok((party, false));
+        }
+    }
+
+    let matched = match matcher.match_name(name) {
+        NameMatch::Exact(matched_id) => Some(matched_id),
+        NameMatch::Fuzzy(candidates) => candidates.first().map(|best| best.party_id),
+        NameMatch::None => None,
+    };
+    if let Some(matched_id) = matched {
+        return Ok((party_service::get_party_by_id(conn, matched_id)?, false));
+    }
+
+    let party = party_service::create_party(conn, name, "", merchant_iban.unwrap_or(""), 0, merchant_bic, batch_id)?;
+    matcher.add_party(&party);
+    Ok((party, true))
+}
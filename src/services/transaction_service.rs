@@ -1,34 +1,145 @@
 use diesel::prelude::*;
 use crate::models::transaction::{Transaction, NewTransaction};
 use crate::schema::transactions::dsl::*;
-use chrono::NaiveDateTime;
-use bigdecimal::BigDecimal;
+use chrono::{Duration, NaiveDateTime};
+use bigdecimal::{BigDecimal, Signed};
 
-pub fn create_transaction(
-    conn: &mut PgConnection,
-    new_amount: BigDecimal,
-    from_id: i32,
-    to_id: i32,
-    transaction_date: NaiveDateTime,
-) -> QueryResult<Transaction> {
-    use crate::schema::transactions;
+/// The columns a `Transaction` is loaded from, in its field order.
+pub const COLUMNS: (id, amount, from_party_id, to_party_id, date, memo, external_id, category) =
+    (id, amount, from_party_id, to_party_id, date, memo, external_id, category);
 
-    let new_transaction = NewTransaction {
-        amount: new_amount,
-        from_party_id: from_id,
-        to_party_id: to_id,
-        date: transaction_date,
-    };
+/// How many days a bank may book a payment-provider top-up or payout before or after the provider.
+const FUNDING_WINDOW_DAYS: i64 = 5;
+
+/// Inserts a transaction, or returns `None` when one with the same fingerprint is already stored.
+pub fn create_transaction(conn: &mut PgConnection, new_transaction: &NewTransaction) -> QueryResult<Option<Transaction>> {
+    use crate::schema::transactions;
 
     diesel::insert_into(transactions::table)
-        .values(&new_transaction)
+        .values(new_transaction)
+        .on_conflict(fingerprint)
+        .do_nothing()
+        .returning(COLUMNS)
         .get_result(conn)
+        .optional()
 }
 
 pub fn get_transaction_by_id(conn: &mut PgConnection, transaction_id: i32) -> QueryResult<Transaction> {
-    transactions.filter(id.eq(transaction_id)).first(conn)
+    transactions.filter(id.eq(transaction_id)).select(COLUMNS).first(conn)
 }
 
 pub fn delete_transaction(conn: &mut PgConnection, transaction_id: i32) -> QueryResult<usize> {
     diesel::delete(transactions.filter(id.eq(transaction_id))).execute(conn)
 }
+
+/// Finds a transaction of `party_id` (on either side) carrying the bank's transaction id.
+pub fn find_by_external_id(conn: &mut PgConnection, party_id: i32, bank_id: &str) -> QueryResult<Option<Transaction>> {
+    transactions
+        .filter(external_id.eq(bank_id))
+        .filter(from_party_id.eq(party_id).or(to_party_id.eq(party_id)))
+        .select(COLUMNS)
+        .first(conn)
+        .optional()
+}
+
+/// Finds the transaction an import fingerprint belongs to, from either side of a transfer.
+pub fn find_by_fingerprint(conn: &mut PgConnection, line_fingerprint: &str) -> QueryResult<Option<Transaction>> {
+    transactions
+        .filter(fingerprint.eq(line_fingerprint).or(transfer_fingerprint.eq(line_fingerprint)))
+        .select(COLUMNS)
+        .first(conn)
+        .optional()
+}
+
+/// Finds a stored transaction between the same two parties with the same amount and date,
+/// i.e. the other side of a transfer between two of the user's own accounts, that has not
+/// been linked to a line of the other account yet.
+pub fn find_transfer(
+    conn: &mut PgConnection,
+    transfer_amount: &BigDecimal,
+    from_id: i32,
+    to_id: i32,
+    transfer_date: NaiveDateTime,
+) -> QueryResult<Option<Transaction>> {
+    transactions
+        .filter(amount.eq(transfer_amount))
+        .filter(from_party_id.eq(from_id))
+        .filter(to_party_id.eq(to_id))
+        .filter(date.eq(transfer_date))
+        .filter(transfer_fingerprint.is_null())
+        .select(COLUMNS)
+        .first(conn)
+        .optional()
+}
+
+/// Records the fingerprint of the other account's line, and the batch that imported it, on a linked transfer.
+pub fn link_transfer(conn: &mut PgConnection, transaction_id: i32, line_fingerprint: &str, batch_id: Option<i32>) -> QueryResult<usize> {
+    diesel::update(transactions.filter(id.eq(transaction_id)))
+        .set((transfer_fingerprint.eq(line_fingerprint), transfer_batch_id.eq(batch_id)))
+        .execute(conn)
+}
+
+/// Finds the bank booking behind a payment-provider funding line: a transaction
+/// between one of the user's other accounts and a party named like `provider`
+/// (e.g. "PayPal Europe S.a.r.l."), over the same amount within a few days, that
+/// has not been linked yet. `funding_amount` is signed from the provider
+/// account's side, so top-ups are positive and payouts negative.
+pub fn find_funding(
+    conn: &mut PgConnection,
+    provider: &str,
+    provider_party_id: i32,
+    funding_amount: &BigDecimal,
+    funding_date: NaiveDateTime,
+) -> QueryResult<Option<Transaction>> {
+    use crate::schema::{accounts, parties};
+
+    let own_parties = || accounts::table.select(accounts::party_id).filter(accounts::party_id.ne(provider_party_id));
+    let provider_parties = || {
+        parties::table
+            .filter(parties::name.ilike(format!("%{}%", provider)))
+            .filter(diesel::dsl::not(parties::id.eq_any(accounts::table.select(accounts::party_id))))
+            .select(parties::id)
+    };
+
+    let window = Duration::days(FUNDING_WINDOW_DAYS);
+    let query = transactions
+        .filter(amount.eq(funding_amount.abs()))
+        .filter(date.between(funding_date - window, funding_date + window))
+        .filter(transfer_fingerprint.is_null())
+        .select(COLUMNS)
+        .into_boxed();
+    let query = if funding_amount.is_negative() {
+        query.filter(from_party_id.eq_any(provider_parties())).filter(to_party_id.eq_any(own_parties()))
+    } else {
+        query.filter(from_party_id.eq_any(own_parties())).filter(to_party_id.eq_any(provider_parties()))
+    };
+
+    let candidates: Vec<Transaction> = query.load(conn)?;
+    Ok(candidates
+        .into_iter()
+        .min_by_key(|candidate| (candidate.date - funding_date).num_seconds().abs()))
+}
+
+/// Moves the provider side of a bank booking found by `find_funding` onto the
+/// provider account's party, turning it into a transfer between own accounts.
+/// The provider party it named is kept in `funding_party_id` for undo.
+pub fn link_funding(
+    conn: &mut PgConnection,
+    funding: &Transaction,
+    provider_party_id: i32,
+    top_up: bool,
+    line_fingerprint: &str,
+    batch_id: Option<i32>,
+) -> QueryResult<usize> {
+    let target = transactions.filter(id.eq(funding.id));
+    let link = (transfer_fingerprint.eq(line_fingerprint), transfer_batch_id.eq(batch_id));
+    if top_up {
+        diesel::update(target)
+            .set((to_party_id.eq(provider_party_id), funding_party_id.eq(funding.to_party_id), link))
+            .execute(conn)
+    } else {
+        diesel::update(target)
+            .set((from_party_id.eq(provider_party_id), funding_party_id.eq(funding.from_party_id), link))
+            .execute(conn)
+    }
+}
@@ -0,0 +1,215 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use super::{BankConnector, ConnectorSettings, RemoteAccount, SyncError, TransactionPage};
+use crate::import::{Balance, StatementLine};
+
+pub const NAME: &str = "mock";
+
+/// Serves accounts from a JSON file, for trying out and testing `sync`
+/// without a bank:
+///
+/// ```json
+/// {"accounts": [{"id": "checking", "name": "Checking", "iban": "DE89370400440532013000",
+///   "currency": "EUR", "balance": "1520.30", "balance_date": "2026-10-17",
+///   "transactions": [{"id": "T1", "date": "2026-10-01", "amount": "-42.00",
+///     "counterparty": "Bakery", "memo": "Bread"}]}]}
+/// ```
+///
+/// The file is read on every request, so bookings appended to an account show
+/// up in the next sync. The cursor is the number of bookings already fetched.
+/// Settings: `path` of the file and, optionally, `page_size`.
+pub struct MockConnector {
+    path: PathBuf,
+    page_size: usize,
+}
+
+#[derive(Deserialize)]
+struct MockFile {
+    accounts: Vec<MockAccount>,
+}
+
+#[derive(Deserialize)]
+struct MockAccount {
+    id: String,
+    name: String,
+    #[serde(default)]
+    iban: Option<String>,
+    #[serde(default)]
+    currency: Option<String>,
+    balance: String,
+    balance_date: String,
+    #[serde(default)]
+    transactions: Vec<MockTransaction>,
+}
+
+#[derive(Deserialize)]
+struct MockTransaction {
+    id: String,
+    date: String,
+    amount: String,
+    #[serde(default)]
+    counterparty: Option<String>,
+    #[serde(default)]
+    counterparty_iban: Option<String>,
+    #[serde(default)]
+    counterparty_bic: Option<String>,
+    #[serde(default)]
+    memo: String,
+}
+
+impl MockConnector {
+    pub fn create(settings: &ConnectorSettings) -> Result<Box<dyn BankConnector>, SyncError> {
+        let path = settings
+            .get("path")
+            .ok_or_else(|| SyncError::Connector("The mock connector needs a 'path' setting".to_string()))?;
+        let page_size = match settings.get("page_size") {
+            Some(size) => size
+                .parse()
+                .ok()
+                .filter(|size| *size > 0)
+                .ok_or_else(|| SyncError::Connector(format!("Invalid page_size '{}'", size)))?,
+            None => usize::MAX,
+        };
+        Ok(Box::new(MockConnector { path: PathBuf::from(path), page_size }))
+    }
+
+    fn read(&self) -> Result<MockFile, SyncError> {
+        let content = fs::read_to_string(&self.path)
+            .map_err(|e| SyncError::Connector(format!("Cannot read mock bank file {}: {}", self.path.display(), e)))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn account(&self, account_id: &str) -> Result<MockAccount, SyncError> {
+        self.read()?
+            .accounts
+            .into_iter()
+            .find(|account| account.id == account_id)
+            .ok_or_else(|| SyncError::Connector(format!("No account '{}' in {}", account_id, self.path.display())))
+    }
+}
+
+impl BankConnector for MockConnector {
+    fn list_accounts(&mut self) -> Result<Vec<RemoteAccount>, SyncError> {
+        Ok(self
+            .read()?
+            .accounts
+            .into_iter()
+            .map(|account| RemoteAccount { id: account.id, name: account.name, iban: account.iban, currency: account.currency })
+            .collect())
+    }
+
+    fn fetch_balance(&mut self, account_id: &str) -> Result<Balance, SyncError> {
+        let account = self.account(account_id)?;
+        Ok(Balance { amount: decimal(&account.balance)?, date: timestamp(&account.balance_date)? })
+    }
+
+    fn fetch_transactions(&mut self, account_id: &str, cursor: Option<&str>) -> Result<TransactionPage, SyncError> {
+        let account = self.account(account_id)?;
+        let total = account.transactions.len();
+        let start = match cursor {
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .ok()
+                .filter(|start| *start <= total)
+                .ok_or_else(|| SyncError::Connector(format!("Cursor '{}' is past the bookings of '{}'; sync again with --full", cursor, account_id)))?,
+            None => 0,
+        };
+        let end = start.saturating_add(self.page_size).min(total);
+        let lines = account.transactions[start..end]
+            .iter()
+            .enumerate()
+            .map(|(index, transaction)| statement_line(start + index + 1, transaction))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(TransactionPage { lines, cursor: end.to_string(), more: end < total })
+    }
+}
+
+fn statement_line(line: usize, transaction: &MockTransaction) -> Result<StatementLine, SyncError> {
+    Ok(StatementLine {
+        line,
+        date: timestamp(&transaction.date)?,
+        amount: decimal(&transaction.amount)?,
+        counterparty: transaction.counterparty.clone(),
+        counterparty_iban: transaction.counterparty_iban.clone(),
+        counterparty_bic: transaction.counterparty_bic.clone(),
+        memo: transaction.memo.clone(),
+        bank_id: Some(transaction.id.clone()),
+        category: None,
+        fee: None,
+        original_amount: None,
+        original_currency: None,
+        funding: false,
+    })
+}
+
+fn decimal(value: &str) -> Result<BigDecimal, SyncError> {
+    BigDecimal::from_str(value.trim()).map_err(|_| SyncError::Connector(format!("Invalid amount '{}'", value)))
+}
+
+fn timestamp(value: &str) -> Result<NaiveDateTime, SyncError> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%Y-%m-%d").map(|day| day.and_hms_opt(0, 0, 0).expect("midnight exists")))
+        .map_err(|_| SyncError::Connector(format!("Invalid date '{}': use YYYY-MM-DD", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::fetch_since;
+    use std::io::Write;
+
+    fn bank_file(bookings: usize) -> tempfile::NamedTempFile {
+        let transactions: Vec<String> = (1..=bookings)
+            .map(|n| format!(r#"{{"id": "T{}", "date": "2026-10-{:02}", "amount": "-{}.00", "counterparty": "Shop"}}"#, n, n, n))
+            .collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"{{"accounts": [{{"id": "checking", "name": "Checking", "balance": "0", "balance_date": "2026-10-17", "transactions": [{}]}}]}}"#,
+            transactions.join(",")
+        )
+        .unwrap();
+        file
+    }
+
+    fn connector(file: &tempfile::NamedTempFile) -> Box<dyn BankConnector> {
+        let settings: ConnectorSettings =
+            [("path".to_string(), file.path().display().to_string()), ("page_size".to_string(), "2".to_string())].into_iter().collect();
+        MockConnector::create(&settings).unwrap()
+    }
+
+    fn bank_ids(lines: &[StatementLine]) -> Vec<String> {
+        lines.iter().filter_map(|line| line.bank_id.clone()).collect()
+    }
+
+    #[test]
+    fn syncing_again_fetches_only_new_bookings() {
+        let file = bank_file(3);
+        let (first, cursor) = fetch_since(connector(&file).as_mut(), "checking", None).unwrap();
+        assert_eq!(bank_ids(&first), ["T1", "T2", "T3"]);
+        assert_eq!(cursor, "3");
+
+        let (nothing_new, same) = fetch_since(connector(&file).as_mut(), "checking", Some(&cursor)).unwrap();
+        assert!(nothing_new.is_empty());
+        assert_eq!(same, "3");
+
+        // The bank books two more; the next sync continues after the first three
+        let file = bank_file(5);
+        let (second, advanced) = fetch_since(connector(&file).as_mut(), "checking", Some(&cursor)).unwrap();
+        assert_eq!(bank_ids(&second), ["T4", "T5"]);
+        assert_eq!(advanced, "5");
+        assert_eq!(second[0].amount.to_string(), "-4.00");
+    }
+
+    #[test]
+    fn rejects_a_cursor_past_the_bookings() {
+        let file = bank_file(1);
+        let error = fetch_since(connector(&file).as_mut(), "checking", Some("4")).unwrap_err();
+        assert!(error.to_string().contains("--full"), "{}", error);
+    }
+}
@@ -0,0 +1,101 @@
+pub mod mock;
+
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+use crate::import::{Balance, StatementLine};
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("connector data error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{0}")]
+    Connector(String),
+}
+
+/// Settings of a connector for one linked account, such as credentials or a file path.
+pub type ConnectorSettings = BTreeMap<String, String>;
+
+/// An account as a connector sees it.
+#[derive(Debug, Clone)]
+pub struct RemoteAccount {
+    /// The connector's id for the account, stored in the account link.
+    pub id: String,
+    pub name: String,
+    pub iban: Option<String>,
+    pub currency: Option<String>,
+}
+
+/// Bookings fetched in one request, oldest first.
+#[derive(Debug)]
+pub struct TransactionPage {
+    /// Signed from the account holder's point of view, as read from files,
+    /// and each with the bank's transaction id so repeated fetches deduplicate.
+    pub lines: Vec<StatementLine>,
+    /// Where the next fetch continues.
+    pub cursor: String,
+    /// Whether more bookings follow this page.
+    pub more: bool,
+}
+
+/// A bank or aggregator that `sync` fetches accounts, balances and bookings from.
+pub trait BankConnector {
+    /// The accounts the connector gives access to.
+    fn list_accounts(&mut self) -> Result<Vec<RemoteAccount>, SyncError>;
+
+    /// The current balance of an account.
+    fn fetch_balance(&mut self, account_id: &str) -> Result<Balance, SyncError>;
+
+    /// The next page of bookings of an account after `cursor`, or from the
+    /// oldest one it has if `cursor` is `None`.
+    fn fetch_transactions(&mut self, account_id: &str, cursor: Option<&str>) -> Result<TransactionPage, SyncError>;
+}
+
+/// Fetches every booking of an account after `cursor`, page by page, with the
+/// cursor the next sync continues from.
+pub fn fetch_since(connector: &mut dyn BankConnector, account_id: &str, cursor: Option<&str>) -> Result<(Vec<StatementLine>, String), SyncError> {
+    let mut lines = Vec::new();
+    let mut cursor = cursor.map(str::to_string);
+    loop {
+        let page = connector.fetch_transactions(account_id, cursor.as_deref())?;
+        lines.extend(page.lines);
+        if !page.more {
+            return Ok((lines, page.cursor));
+        }
+        cursor = Some(page.cursor);
+    }
+}
+
+/// Creates a connector from the settings of an account link.
+pub type ConnectorFactory = fn(&ConnectorSettings) -> Result<Box<dyn BankConnector>, SyncError>;
+
+/// The connectors account links can name.
+pub struct ConnectorRegistry {
+    factories: BTreeMap<&'static str, ConnectorFactory>,
+}
+
+impl ConnectorRegistry {
+    /// A registry of the connectors built into FinWise.
+    pub fn builtin() -> Self {
+        let mut registry = ConnectorRegistry { factories: BTreeMap::new() };
+        registry.register(mock::NAME, mock::MockConnector::create);
+        registry
+    }
+
+    pub fn register(&mut self, name: &'static str, factory: ConnectorFactory) {
+        self.factories.insert(name, factory);
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.factories.keys().copied().collect()
+    }
+
+    pub fn create(&self, name: &str, settings: &ConnectorSettings) -> Result<Box<dyn BankConnector>, SyncError> {
+        let factory = self.factories.get(name).ok_or_else(|| {
+            SyncError::Connector(format!("Unknown bank connector '{}'; available: {}", name, self.names().join(", ")))
+        })?;
+        factory(settings)
+    }
+}